@@ -12,6 +12,7 @@ use std::sync::Arc;
 use buck2_client_ctx::client_ctx::ClientCommandContext;
 use buck2_client_ctx::common::BuckArgMatches;
 use buck2_common::init::ResourceControlConfig;
+use buck2_common::init::SandboxConfig;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::logging::LogConfigurationReloadHandle;
@@ -33,6 +34,9 @@ pub(crate) struct ForkserverCommand {
 
     #[clap(long, value_parser = ResourceControlConfig::deserialize)]
     resource_control: ResourceControlConfig,
+
+    #[clap(long, value_parser = SandboxConfig::deserialize)]
+    sandbox: SandboxConfig,
 }
 
 impl ForkserverCommand {
@@ -59,6 +63,7 @@ impl ForkserverCommand {
                 log_reload_handle,
                 self.state_dir,
                 self.resource_control,
+                self.sandbox,
             ))?)
         }
 
@@ -25,6 +25,7 @@ use buck2_build_api::actions::IncrementalActionExecutable;
 use buck2_build_api::actions::UnregisteredAction;
 use buck2_build_api::artifact_groups::ArtifactGroup;
 use buck2_common::file_ops::FileDigest;
+use buck2_common::file_ops::FileDigestConfig;
 use buck2_common::file_ops::FileMetadata;
 use buck2_common::file_ops::TrackedFileDigest;
 use buck2_common::io::trace::TracingIoProvider;
@@ -37,8 +38,12 @@ use buck2_execute::directory::re_directory_to_re_tree;
 use buck2_execute::directory::re_tree_to_directory;
 use buck2_execute::directory::ActionDirectoryEntry;
 use buck2_execute::directory::INTERNER;
+use buck2_execute::entry::build_entry_from_disk;
 use buck2_execute::execute::command_executor::ActionExecutionTimingData;
 use buck2_execute::materialize::materializer::CasDownloadInfo;
+use buck2_offline_archive::bundle::BundledCasArtifact;
+use buck2_offline_archive::bundle::BundledFetch;
+use buck2_offline_archive::bundle::OfflineBundle;
 use chrono::DateTime;
 use chrono::TimeZone;
 use chrono::Utc;
@@ -161,6 +166,46 @@ impl CasArtifactAction {
             },
         ))
     }
+
+    /// Execute this action by copying the artifact from an offline bundle.
+    async fn execute_from_offline_bundle(
+        &self,
+        ctx: &mut dyn ActionExecutionCtx,
+        bundle: &OfflineBundle,
+    ) -> buck2_error::Result<(ActionOutputs, ActionExecutionMetadata)> {
+        ctx.cleanup_outputs().await?;
+
+        let path = ctx.fs().resolve_build(self.output.get_path());
+        let abs_path = ctx.fs().fs().resolve(&path);
+        bundle.restore_cas_artifact(&self.inner.digest.to_string(), &abs_path)?;
+
+        let (value, _hashing_time) = build_entry_from_disk(
+            abs_path,
+            FileDigestConfig::build(ctx.digest_config().cas_digest_config()),
+            ctx.blocking_executor(),
+            ctx.fs().fs().root(),
+        )
+        .await?;
+        let entry = value
+            .internal_error("Artifact copied from the offline bundle is missing")?
+            .map_dir(|dir| {
+                dir.fingerprint(ctx.digest_config().as_directory_serializer())
+                    .shared(&*INTERNER)
+            });
+        let value = ArtifactValue::from(entry);
+        ctx.materializer()
+            .declare_existing(vec![(path, value.dupe())])
+            .await?;
+
+        Ok((
+            ActionOutputs::from_single(self.output.get_path().dupe(), value),
+            ActionExecutionMetadata {
+                execution_kind: ActionExecutionKind::Simple,
+                timing: ActionExecutionTimingData::default(),
+                input_files_bytes: None,
+            },
+        ))
+    }
 }
 
 #[async_trait]
@@ -192,6 +237,13 @@ impl Action for CasArtifactAction {
     fn identifier(&self) -> Option<&str> {
         Some(self.output.get_path().path().as_str())
     }
+
+    fn offline_bundle_fetch(&self) -> Option<BundledFetch> {
+        Some(BundledFetch::CasArtifact(BundledCasArtifact {
+            digest: self.inner.digest.to_string(),
+            is_directory: matches!(self.inner.kind, ArtifactKind::Directory(_)),
+        }))
+    }
 }
 
 #[async_trait]
@@ -206,6 +258,13 @@ impl IncrementalActionExecutable for CasArtifactAction {
             return self.execute_for_offline(ctx).await.map_err(Into::into);
         }
 
+        if let Some(bundle) = &ctx.run_action_knobs().offline_bundle {
+            return self
+                .execute_from_offline_bundle(ctx, bundle)
+                .await
+                .map_err(Into::into);
+        }
+
         let expiration = ctx
             .re_client()
             .get_digest_expirations(vec![self.inner.digest.to_re()], self.inner.re_use_case)
@@ -34,11 +34,15 @@ use buck2_error::ErrorTag;
 use buck2_execute::artifact_value::ArtifactValue;
 use buck2_execute::digest_config::DigestConfig;
 use buck2_execute::execute::command_executor::ActionExecutionTimingData;
+use buck2_execute::materialize::http::cached_download_size;
 use buck2_execute::materialize::http::http_download;
 use buck2_execute::materialize::http::http_head;
 use buck2_execute::materialize::http::Checksum;
 use buck2_execute::materialize::materializer::HttpDownloadInfo;
+use buck2_http::DownloadCacheConfig;
 use buck2_http::HttpClient;
+use buck2_offline_archive::bundle::BundledDownload;
+use buck2_offline_archive::bundle::BundledFetch;
 use dupe::Dupe;
 use indexmap::IndexSet;
 use starlark::values::OwnedFrozenValue;
@@ -162,6 +166,14 @@ impl DownloadFileAction {
             None => return Ok(None),
         };
 
+        if let Some(length) = cached_download_size(client, &self.inner.checksum) {
+            return Ok(Some(self.file_metadata(digest, length, digest_config)));
+        }
+        if client.download_cache().is_some_and(|cache| cache.offline) {
+            // Can't make a HEAD request; the download will fail with a better error.
+            return Ok(None);
+        }
+
         let url = self.url(client);
         let head = http_head(client, url)
             .await
@@ -189,18 +201,22 @@ impl DownloadFileAction {
                 )
             })?;
 
-        match content_length {
-            Some(length) => {
-                let digest = TrackedFileDigest::new(
-                    FileDigest::new(digest, length),
-                    digest_config.cas_digest_config(),
-                );
-                Ok(Some(FileMetadata {
-                    digest,
-                    is_executable: self.inner.is_executable,
-                }))
-            }
-            None => Ok(None),
+        Ok(content_length.map(|length| self.file_metadata(digest, length, digest_config)))
+    }
+
+    fn file_metadata(
+        &self,
+        digest: RawDigest,
+        length: u64,
+        digest_config: DigestConfig,
+    ) -> FileMetadata {
+        let digest = TrackedFileDigest::new(
+            FileDigest::new(digest, length),
+            digest_config.cas_digest_config(),
+        );
+        FileMetadata {
+            digest,
+            is_executable: self.inner.is_executable,
         }
     }
 
@@ -254,6 +270,14 @@ impl Action for DownloadFileAction {
             .next()
             .map(|o| o.get_path().path().as_str())
     }
+
+    fn offline_bundle_fetch(&self) -> Option<BundledFetch> {
+        Some(BundledFetch::Download(BundledDownload {
+            url: self.inner.url.to_string(),
+            sha1: self.inner.checksum.sha1().map(str::to_owned),
+            sha256: self.inner.checksum.sha256().map(str::to_owned),
+        }))
+    }
 }
 
 #[async_trait]
@@ -271,11 +295,28 @@ impl IncrementalActionExecutable for DownloadFileAction {
             return self.execute_for_offline(ctx).await.map_err(Into::into);
         }
 
-        let client = ctx.http_client();
+        let offline_bundle = ctx.run_action_knobs().offline_bundle;
+        let mut client = ctx.http_client();
+        if let Some(bundle) = &offline_bundle {
+            // The bundle is laid out like a download cache, so serve downloads from it as from an
+            // offline one.
+            client = client.with_download_cache(DownloadCacheConfig {
+                dir: bundle.downloads_dir().into_path_buf(),
+                max_size_bytes: None,
+                offline: true,
+            });
+        }
         let url = self.url(&client);
 
+        // Deferred downloads are done by the materializer, which does not know about the bundle.
+        let declared_metadata = if offline_bundle.is_some() {
+            None
+        } else {
+            self.declared_metadata(&client, ctx.digest_config()).await?
+        };
+
         let (value, execution_kind) = {
-            match self.declared_metadata(&client, ctx.digest_config()).await? {
+            match declared_metadata {
                 Some(metadata) => {
                     let artifact_fs = ctx.fs();
                     let rel_path = artifact_fs.resolve_build(self.output().get_path());
@@ -57,6 +57,7 @@ use buck2_execute::execute::request::CommandExecutionPaths;
 use buck2_execute::execute::request::CommandExecutionRequest;
 use buck2_execute::execute::request::ExecutorPreference;
 use buck2_execute::execute::request::WorkerId;
+use buck2_execute::execute::request::WorkerProtocol;
 use buck2_execute::execute::request::WorkerSpec;
 use buck2_execute::execute::result::CommandExecutionResult;
 use derive_more::Display;
@@ -263,6 +264,7 @@ struct UnpackedWorkerValues<'v> {
     exe: &'v dyn CommandLineArgLike,
     id: WorkerId,
     concurrency: Option<usize>,
+    protocol: WorkerProtocol,
 }
 
 struct UnpackedRunActionValues<'v> {
@@ -319,6 +321,7 @@ impl RunAction {
             exe: worker.exe_command_line(),
             id: WorkerId(worker.id),
             concurrency: worker.concurrency(),
+            protocol: worker.protocol(),
         });
 
         Ok(UnpackedRunActionValues {
@@ -355,6 +358,7 @@ impl RunAction {
                 exe: worker_rendered,
                 id: worker.id,
                 concurrency: worker.concurrency,
+                protocol: worker.protocol,
             })
         } else {
             None
@@ -56,6 +56,7 @@ use buck2_execute::re::manager::ManagedRemoteExecutionClient;
 use buck2_file_watcher::mergebase::Mergebase;
 use buck2_futures::cancellation::CancellationContext;
 use buck2_http::HttpClient;
+use buck2_offline_archive::bundle::BundledFetch;
 use derivative::Derivative;
 use derive_more::Display;
 use indexmap::indexmap;
@@ -161,6 +162,12 @@ pub trait Action: Allocative + Debug + Send + Sync + 'static {
         None
     }
 
+    /// What this action fetches from the network, for network actions (download_file,
+    /// cas_artifact), whose output can be added to an offline bundle.
+    fn offline_bundle_fetch(&self) -> Option<BundledFetch> {
+        None
+    }
+
     // TODO this probably wants more data for execution, like printing a short_name and the target
 }
 
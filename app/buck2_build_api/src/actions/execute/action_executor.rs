@@ -381,7 +381,7 @@ impl ActionExecutionCtx for BuckActionExecutionContext<'_> {
     }
 
     fn run_action_knobs(&self) -> RunActionKnobs {
-        self.executor.run_action_knobs
+        self.executor.run_action_knobs.dupe()
     }
 
     fn cancellation_context(&self) -> &CancellationContext {
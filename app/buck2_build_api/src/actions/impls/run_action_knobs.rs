@@ -7,11 +7,14 @@
  * of this source tree.
  */
 
+use std::sync::Arc;
+
+use buck2_offline_archive::bundle::OfflineBundle;
 use dice::UserComputationData;
 use dupe::Dupe;
 
 /// Knobs controlling how RunAction works.
-#[derive(Copy, Clone, Dupe, Default)]
+#[derive(Clone, Dupe, Default)]
 pub struct RunActionKnobs {
     /// Process dep files as they are generated.
     pub eager_dep_files: bool,
@@ -24,6 +27,10 @@ pub struct RunActionKnobs {
     /// for network actions (download_file, cas_artifact). Used to support offline
     /// builds.
     pub use_network_action_output_cache: bool,
+
+    /// The offline bundle that network actions (download_file, cas_artifact) are served from,
+    /// instead of the network. Git external cells use it too.
+    pub offline_bundle: Option<Arc<OfflineBundle>>,
 }
 
 pub trait HasRunActionKnobs {
@@ -38,9 +45,9 @@ impl HasRunActionKnobs for UserComputationData {
     }
 
     fn get_run_action_knobs(&self) -> RunActionKnobs {
-        *self
-            .data
+        self.data
             .get::<RunActionKnobs>()
             .expect("RunActionKnobs should be set")
+            .dupe()
     }
 }
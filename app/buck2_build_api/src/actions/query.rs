@@ -21,9 +21,12 @@ use buck2_artifact::actions::key::ActionKey;
 use buck2_core::build_file_path::BuildFilePath;
 use buck2_core::cells::cell_path::CellPath;
 use buck2_core::cells::CellResolver;
+use buck2_core::execution_types::executor_config::Executor;
+use buck2_core::execution_types::executor_config::RemoteEnabledExecutor;
 use buck2_core::fs::artifact_path_resolver::ArtifactFs;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_core::global_cfg_options::GlobalCfgOptions;
 use buck2_core::package::PackageLabel;
 use buck2_core::provider::label::ConfiguredProvidersLabel;
@@ -181,6 +184,33 @@ impl ActionQueryNode {
     pub fn key(&self) -> &ActionQueryNodeRef {
         &self.key
     }
+
+    /// The paths of the outputs of this action. Empty for analysis nodes.
+    pub fn output_paths(&self) -> Vec<ProjectRelativePathBuf> {
+        match &self.data {
+            ActionQueryNodeData::Analysis(..) => Vec::new(),
+            ActionQueryNodeData::Action(data) => data
+                .action
+                .outputs()
+                .iter()
+                .map(|output| data.fs.resolve_build(output.get_path()))
+                .collect(),
+        }
+    }
+
+    /// Where this action is configured to run: `local`, `remote` or `hybrid`. A local executor
+    /// that only uses a RE backend for caching is `local`. `None` for analysis nodes.
+    pub fn executor_kind(&self) -> Option<&'static str> {
+        let action = self.action()?;
+        Some(match &action.execution_config().executor {
+            Executor::Local(..) => "local",
+            Executor::RemoteEnabled(options) => match &options.executor {
+                RemoteEnabledExecutor::Local(..) => "local",
+                RemoteEnabledExecutor::Remote(..) => "remote",
+                RemoteEnabledExecutor::Hybrid { .. } => "hybrid",
+            },
+        })
+    }
 }
 
 impl LabeledNode for ActionQueryNode {
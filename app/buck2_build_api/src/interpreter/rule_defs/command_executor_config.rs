@@ -17,6 +17,7 @@ use buck2_core::execution_types::executor_config::Executor;
 use buck2_core::execution_types::executor_config::HybridExecutionLevel;
 use buck2_core::execution_types::executor_config::ImagePackageIdentifier;
 use buck2_core::execution_types::executor_config::LocalExecutorOptions;
+use buck2_core::execution_types::executor_config::LocalResourceLimits;
 use buck2_core::execution_types::executor_config::PathSeparatorKind;
 use buck2_core::execution_types::executor_config::RePlatformFields;
 use buck2_core::execution_types::executor_config::RemoteEnabledExecutor;
@@ -53,6 +54,19 @@ enum CommandExecutorConfigErrors {
         "executor config must specify at least `local_enabled = True` or `remote_enabled = True`"
     )]
     NoExecutor,
+    #[error(
+        "`local_execution_cpu_max_percent` must be between 1 and {1} (100 times the number of CPUs), got {0}"
+    )]
+    InvalidCpuMaxPercent(i32, u64),
+}
+
+/// Check that a CPU limit can be met by this machine, which runs the local actions.
+fn validate_cpu_max_percent(percent: i32, cpus: usize) -> buck2_error::Result<u64> {
+    let max = 100 * cpus as u64;
+    match u64::try_from(percent) {
+        Ok(p) if p > 0 && p <= max => Ok(p),
+        _ => Err(CommandExecutorConfigErrors::InvalidCpuMaxPercent(percent, max).into()),
+    }
 }
 
 #[derive(Debug, Display, NoSerialize, ProvidesStaticType, Allocative)]
@@ -85,6 +99,10 @@ pub fn register_command_executor_config(builder: &mut GlobalsBuilder) {
     /// * `allow_hybrid_fallbacks_on_failure`: Whether to allow fallbacks when the result is failure (i.e. the command failed on the primary, but the infra worked)
     /// * `use_windows_path_separators`: Whether to use Windows path separators in command line arguments
     /// * `use_persistent workers`: Whether to use persistent workers for local execution if they are available
+    /// * `local_execution_memory_max_mebibytes`: The maximum memory a local action may use before it is killed.
+    /// Only enforced when `buck2_resource_control.per_action_cgroups` is enabled
+    /// * `local_execution_cpu_max_percent`: The maximum CPU a local action may use, as a percentage of one CPU.
+    /// Only enforced when `buck2_resource_control.per_action_cgroups` is enabled
     /// * `allow_cache_uploads`: Whether to upload local actions to the RE cache
     /// * `max_cache_upload_mebibytes`: Maximum size to upload in cache uploads
     /// * `experimental_low_pass_filter`: Whether to use the experimental low pass filter
@@ -110,6 +128,10 @@ pub fn register_command_executor_config(builder: &mut GlobalsBuilder) {
         #[starlark(default = false, require = named)] allow_hybrid_fallbacks_on_failure: bool,
         #[starlark(default = false, require = named)] use_windows_path_separators: bool,
         #[starlark(default = false, require = named)] use_persistent_workers: bool,
+        #[starlark(default = NoneOr::None, require = named)]
+        local_execution_memory_max_mebibytes: NoneOr<i32>,
+        #[starlark(default = NoneOr::None, require = named)]
+        local_execution_cpu_max_percent: NoneOr<i32>,
         #[starlark(default = false, require = named)] allow_cache_uploads: bool,
         #[starlark(default = NoneOr::None, require = named)] max_cache_upload_mebibytes: NoneOr<
             i32,
@@ -183,8 +205,24 @@ pub fn register_command_executor_config(builder: &mut GlobalsBuilder) {
             };
 
             let local_options = if local_enabled {
+                let memory_max_bytes = local_execution_memory_max_mebibytes
+                    .into_option()
+                    .map(u64::try_from)
+                    .transpose()
+                    .buck_error_context("local_execution_memory_max_mebibytes is negative")?
+                    .map(|b| b * 1024 * 1024);
+
+                let cpu_max_percent = local_execution_cpu_max_percent
+                    .into_option()
+                    .map(|p| validate_cpu_max_percent(p, num_cpus::get()))
+                    .transpose()?;
+
                 Some(LocalExecutorOptions {
                     use_persistent_workers,
+                    resource_limits: LocalResourceLimits {
+                        memory_max_bytes,
+                        cpu_max_percent,
+                    },
                 })
             } else {
                 None
@@ -411,3 +449,17 @@ pub fn parse_custom_re_image(
         drop_host_mount_globs,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_cpu_max_percent() {
+        assert_eq!(50, validate_cpu_max_percent(50, 4).unwrap());
+        assert_eq!(400, validate_cpu_max_percent(400, 4).unwrap());
+        assert!(validate_cpu_max_percent(0, 4).is_err());
+        assert!(validate_cpu_max_percent(-100, 4).is_err());
+        assert!(validate_cpu_max_percent(401, 4).is_err());
+    }
+}
@@ -14,6 +14,8 @@ use std::sync::atomic::AtomicU64;
 use allocative::Allocative;
 use buck2_build_api_derive::internal_provider;
 use buck2_error::BuckErrorContext;
+use buck2_execute::execute::request::BazelWorkerEncoding;
+use buck2_execute::execute::request::WorkerProtocol;
 use starlark::any::ProvidesStaticType;
 use starlark::coerce::Coerce;
 use starlark::environment::GlobalsBuilder;
@@ -47,6 +49,12 @@ pub struct WorkerInfoGen<V: ValueLifetimeless> {
     pub exe: ValueOfUncheckedGeneric<V, FrozenStarlarkCmdArgs>,
     // Maximum number of concurrent commands to execute on a worker instance without queuing
     pub concurrency: ValueOfUncheckedGeneric<V, NoneOr<usize>>,
+    // Protocol used to talk to the worker: "buck2", "bazel_json" or "bazel_proto"
+    pub protocol: ValueOfUncheckedGeneric<V, String>,
+    // Whether a worker using a Bazel protocol accepts concurrent requests
+    pub multiplex: ValueOfUncheckedGeneric<V, bool>,
+    // Whether a multiplex worker handles cancel requests (Bazel's `supports-worker-cancellation`)
+    pub supports_cancellation: ValueOfUncheckedGeneric<V, bool>,
 
     pub id: u64,
 }
@@ -64,16 +72,23 @@ fn worker_info_creator(globals: &mut GlobalsBuilder) {
         #[starlark(require = named, default = NoneOr::None)] concurrency: NoneOr<
             ValueOf<'v, usize>,
         >,
+        #[starlark(require = named, default = "buck2")] protocol: &str,
+        #[starlark(require = named, default = false)] multiplex: bool,
+        #[starlark(require = named, default = false)] supports_cancellation: bool,
         eval: &mut Evaluator<'v, '_, '_>,
     ) -> starlark::Result<WorkerInfo<'v>> {
         let heap = eval.heap();
         let valid_exe = StarlarkCmdArgs::try_from_value(exe)?;
         let exe = ValueOfUnchecked::new(heap.alloc(valid_exe));
+        parse_protocol(protocol, multiplex, supports_cancellation)?;
         let id = next_id();
         Ok(WorkerInfo {
             exe,
             id,
             concurrency: heap.alloc_typed_unchecked(concurrency).cast(),
+            protocol: ValueOfUnchecked::new(heap.alloc(protocol)),
+            multiplex: ValueOfUnchecked::new(Value::new_bool(multiplex)),
+            supports_cancellation: ValueOfUnchecked::new(Value::new_bool(supports_cancellation)),
         })
     }
 }
@@ -92,6 +107,63 @@ impl<'v, V: ValueLike<'v>> WorkerInfoGen<V> {
             .expect("validated at construction")
             .into_option()
     }
+
+    pub fn protocol(&self) -> WorkerProtocol {
+        parse_protocol(
+            self.protocol
+                .to_value()
+                .get()
+                .unpack_str()
+                .expect("validated at construction"),
+            self.multiplex
+                .to_value()
+                .get()
+                .unpack_bool()
+                .expect("validated at construction"),
+            self.supports_cancellation
+                .to_value()
+                .get()
+                .unpack_bool()
+                .expect("validated at construction"),
+        )
+        .expect("validated at construction")
+    }
+}
+
+fn parse_protocol(
+    protocol: &str,
+    multiplex: bool,
+    supports_cancellation: bool,
+) -> buck2_error::Result<WorkerProtocol> {
+    if supports_cancellation && !multiplex {
+        return Err(buck2_error::buck2_error!(
+            [],
+            "`supports_cancellation` only applies to multiplex workers"
+        ));
+    }
+    let encoding = match protocol {
+        "buck2" if multiplex => {
+            return Err(buck2_error::buck2_error!(
+                [],
+                "`multiplex` only applies to workers using a Bazel protocol"
+            ));
+        }
+        "buck2" => return Ok(WorkerProtocol::Buck2),
+        "bazel_json" => BazelWorkerEncoding::Json,
+        "bazel_proto" => BazelWorkerEncoding::Proto,
+        _ => {
+            return Err(buck2_error::buck2_error!(
+                [],
+                "Value for `protocol` field is not one of `buck2`, `bazel_json` or `bazel_proto`: `{}`",
+                protocol
+            ));
+        }
+    };
+    Ok(WorkerProtocol::Bazel {
+        encoding,
+        multiplex,
+        supports_cancellation,
+    })
 }
 
 fn validate_worker_info<'v, V>(info: &WorkerInfoGen<V>) -> buck2_error::Result<()>
@@ -114,5 +186,25 @@ where
         ));
     }
 
+    let protocol = info
+        .protocol
+        .get()
+        .to_value()
+        .unpack_str()
+        .buck_error_context("Value for `protocol` field is not a string")?;
+    let multiplex = info
+        .multiplex
+        .get()
+        .to_value()
+        .unpack_bool()
+        .buck_error_context("Value for `multiplex` field is not a bool")?;
+    let supports_cancellation = info
+        .supports_cancellation
+        .get()
+        .to_value()
+        .unpack_bool()
+        .buck_error_context("Value for `supports_cancellation` field is not a bool")?;
+    parse_protocol(protocol, multiplex, supports_cancellation)?;
+
     Ok(())
 }
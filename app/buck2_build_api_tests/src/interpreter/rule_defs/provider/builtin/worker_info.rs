@@ -24,8 +24,44 @@ fn run_display() {
         .run_starlark_bzl_test(
             r#"
 def test():
-    assert_eq('WorkerInfo(exe=cmd_args("x"), concurrency=None)', str(WorkerInfo(exe="x")))
+    assert_eq('WorkerInfo(exe=cmd_args("x"), concurrency=None, protocol="buck2", multiplex=False, supports_cancellation=False)', str(WorkerInfo(exe="x")))
 "#,
         )
         .unwrap();
 }
+
+#[test]
+fn protocol() {
+    let mut tester = run_info_tester();
+    tester
+        .run_starlark_bzl_test(
+            r#"
+def test():
+    WorkerInfo(exe="x", protocol="bazel_json")
+    WorkerInfo(exe="x", protocol="bazel_proto", multiplex=True)
+    WorkerInfo(exe="x", protocol="bazel_proto", multiplex=True, supports_cancellation=True)
+"#,
+        )
+        .unwrap();
+    tester.run_starlark_bzl_test_expecting_error(
+        r#"
+def test():
+    WorkerInfo(exe="x", protocol="grpc")
+"#,
+        "is not one of `buck2`, `bazel_json` or `bazel_proto`",
+    );
+    tester.run_starlark_bzl_test_expecting_error(
+        r#"
+def test():
+    WorkerInfo(exe="x", multiplex=True)
+"#,
+        "`multiplex` only applies to workers using a Bazel protocol",
+    );
+    tester.run_starlark_bzl_test_expecting_error(
+        r#"
+def test():
+    WorkerInfo(exe="x", protocol="bazel_proto", supports_cancellation=True)
+"#,
+        "`supports_cancellation` only applies to multiplex workers",
+    );
+}
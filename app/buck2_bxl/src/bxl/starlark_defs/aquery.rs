@@ -276,6 +276,162 @@ fn aquery_methods(builder: &mut MethodsBuilder) {
             .map(StarlarkTargetSet::from)?)
     }
 
+    /// Obtain the actions that produce the direct inputs of the given actions.
+    fn inputs_of<'v>(
+        this: &StarlarkAQueryCtx<'v>,
+        targets: UnpackActionNodes<'v>,
+    ) -> starlark::Result<StarlarkTargetSet<ActionQueryNode>> {
+        Ok(this
+            .ctx
+            .via_dice(|dice, ctx| {
+                dice.via(|dice| {
+                    async {
+                        let targets = unpack_action_nodes(this, dice, targets).await?;
+
+                        get_aquery_env(ctx, &this.global_cfg_options_override)
+                            .await?
+                            .inputs_of(dice, &targets)
+                            .await
+                    }
+                    .boxed_local()
+                })
+            })
+            .map(StarlarkTargetSet::from)?)
+    }
+
+    /// Obtain the actions in `universe` that directly consume an output of any of the given
+    /// actions.
+    ///
+    /// Sample usage:
+    /// ```python
+    /// def _impl_consumers(ctx):
+    ///     aquery = ctx.aquery()
+    ///     universe = aquery.deps("//foo:bar")
+    ///     ctx.output.print(aquery.consumers(universe, aquery.all_actions("//foo:baz")))
+    /// ```
+    fn consumers<'v>(
+        this: &StarlarkAQueryCtx<'v>,
+        universe: UnpackActionNodes<'v>,
+        targets: UnpackActionNodes<'v>,
+    ) -> starlark::Result<StarlarkTargetSet<ActionQueryNode>> {
+        Ok(this
+            .ctx
+            .via_dice(|dice, ctx| {
+                dice.via(|dice| {
+                    async {
+                        let universe = unpack_action_nodes(this, dice, universe).await?;
+                        let targets = unpack_action_nodes(this, dice, targets).await?;
+
+                        get_aquery_env(ctx, &this.global_cfg_options_override)
+                            .await?
+                            .consumers(&universe, &targets)
+                            .await
+                    }
+                    .boxed_local()
+                })
+            })
+            .map(StarlarkTargetSet::from)?)
+    }
+
+    /// Filter actions to those producing an output whose path (relative to the project root)
+    /// matches `regex`.
+    fn producers<'v>(
+        this: &StarlarkAQueryCtx<'v>,
+        regex: &str,
+        targets: UnpackActionNodes<'v>,
+    ) -> starlark::Result<StarlarkTargetSet<ActionQueryNode>> {
+        Ok(this
+            .ctx
+            .via_dice(|dice, ctx| {
+                dice.via(|dice| {
+                    async {
+                        let targets = unpack_action_nodes(this, dice, targets).await?;
+
+                        get_aquery_env(ctx, &this.global_cfg_options_override)
+                            .await?
+                            .producers(regex, &targets)
+                            .await
+                    }
+                    .boxed_local()
+                })
+            })
+            .map(StarlarkTargetSet::from)?)
+    }
+
+    /// Filter actions by category (e.g. `cxx_compile`), using a partial regex match.
+    fn category<'v>(
+        this: &StarlarkAQueryCtx<'v>,
+        regex: &str,
+        targets: UnpackActionNodes<'v>,
+    ) -> starlark::Result<StarlarkTargetSet<ActionQueryNode>> {
+        Ok(this
+            .ctx
+            .via_dice(|dice, ctx| {
+                dice.via(|dice| {
+                    async {
+                        let targets = unpack_action_nodes(this, dice, targets).await?;
+
+                        get_aquery_env(ctx, &this.global_cfg_options_override)
+                            .await?
+                            .category(regex, &targets)
+                            .await
+                    }
+                    .boxed_local()
+                })
+            })
+            .map(StarlarkTargetSet::from)?)
+    }
+
+    /// Filter actions by identifier, using a partial regex match. Actions without an identifier
+    /// never match.
+    fn identifier<'v>(
+        this: &StarlarkAQueryCtx<'v>,
+        regex: &str,
+        targets: UnpackActionNodes<'v>,
+    ) -> starlark::Result<StarlarkTargetSet<ActionQueryNode>> {
+        Ok(this
+            .ctx
+            .via_dice(|dice, ctx| {
+                dice.via(|dice| {
+                    async {
+                        let targets = unpack_action_nodes(this, dice, targets).await?;
+
+                        get_aquery_env(ctx, &this.global_cfg_options_override)
+                            .await?
+                            .identifier(regex, &targets)
+                            .await
+                    }
+                    .boxed_local()
+                })
+            })
+            .map(StarlarkTargetSet::from)?)
+    }
+
+    /// Filter actions by where they are configured to run, using a partial regex match against
+    /// `local`, `remote` or `hybrid`.
+    fn executor<'v>(
+        this: &StarlarkAQueryCtx<'v>,
+        regex: &str,
+        targets: UnpackActionNodes<'v>,
+    ) -> starlark::Result<StarlarkTargetSet<ActionQueryNode>> {
+        Ok(this
+            .ctx
+            .via_dice(|dice, ctx| {
+                dice.via(|dice| {
+                    async {
+                        let targets = unpack_action_nodes(this, dice, targets).await?;
+
+                        get_aquery_env(ctx, &this.global_cfg_options_override)
+                            .await?
+                            .executor(regex, &targets)
+                            .await
+                    }
+                    .boxed_local()
+                })
+            })
+            .map(StarlarkTargetSet::from)?)
+    }
+
     /// The attrfilter query for rule attribute filtering.
     fn attrfilter<'v>(
         this: &StarlarkAQueryCtx<'v>,
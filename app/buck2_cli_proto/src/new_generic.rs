@@ -24,6 +24,8 @@ pub enum NewGenericRequest {
     ExpandExternalCells(ExpandExternalCellsRequest),
     Complete(CompleteRequest),
     Docs(DocsRequest),
+    ExplainRecompute(ExplainRecomputeRequest),
+    OfflineBundle(OfflineBundleRequest),
 }
 
 #[derive(Serialize, Deserialize)]
@@ -34,6 +36,8 @@ pub enum NewGenericResponse {
     ExpandExternalCells(ExpandExternalCellsResponse),
     Complete(CompleteResponse),
     Docs(DocsResponse),
+    ExplainRecompute(ExplainRecomputeResponse),
+    OfflineBundle(OfflineBundleResponse),
 }
 
 #[derive(Serialize, Deserialize)]
@@ -121,3 +125,49 @@ pub struct DocsResponse {
     // Set when requested format is JSON.
     pub json_output: Option<String>,
 }
+
+#[derive(Serialize, Deserialize)]
+pub struct ExplainRecomputeRequest {
+    /// Explain the DICE keys whose display contains this string.
+    pub key: String,
+    /// Only explain the keys of this type, e.g. `AnalysisKey`.
+    pub key_type: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExplainRecomputeResponse {
+    /// The DICE version of the latest committed changes.
+    pub version: usize,
+    /// The matching keys whose value changed at `version`.
+    pub explanations: Vec<RecomputeExplanation>,
+    /// How many more matching keys changed, beyond the ones that were explained.
+    pub omitted: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RecomputeExplanation {
+    pub key: RecomputeKey,
+    /// The chains of deps from each changed key that `key` depends on to `key`.
+    pub chains: Vec<Vec<RecomputeKey>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RecomputeKey {
+    pub key_type: String,
+    pub key: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OfflineBundleRequest {
+    /// The bundle to add to, created if it does not exist.
+    pub bundle_dir: AbsPathBuf,
+    pub target_patterns: Vec<String>,
+    pub target_cfg: TargetCfg,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OfflineBundleResponse {
+    pub downloads: usize,
+    pub cas_artifacts: usize,
+    pub git_cells: usize,
+}
@@ -69,6 +69,12 @@ the specified duration, without killing the daemon",
     #[clap(long = "tracked-only", requires = "stale")]
     tracked_only: bool,
 
+    /// Only delete the disk action cache, which stores the results of locally executed actions.
+    ///
+    /// This still kills the daemon, since it may be using the cache.
+    #[clap(long = "action-cache", conflicts_with_all = &["stale", "keep_since_time"])]
+    action_cache: bool,
+
     /// Command doesn't need these flags, but they are used in mode files, so we need to keep them.
     #[clap(flatten)]
     _target_cfg: TargetCfgUnusedOptions,
@@ -95,9 +101,13 @@ impl CleanCommand {
             |ctx| async move {
                 let buck_out_dir = ctx.paths()?.buck_out_path();
                 let daemon_dir = ctx.paths()?.daemon_dir()?;
+                let action_cache_dir = ctx.paths()?.disk_action_cache_path();
                 let console = &self.common_opts.console_opts.final_console();
 
                 if self.dry_run {
+                    if self.action_cache {
+                        return clean_action_cache(action_cache_dir, console, false).await;
+                    }
                     return clean(buck_out_dir, daemon_dir, console, None).await;
                 }
 
@@ -112,6 +122,9 @@ impl CleanCommand {
 
                 kill_command_impl(&lifecycle_lock, "`buck2 clean` was invoked").await?;
 
+                if self.action_cache {
+                    return clean_action_cache(action_cache_dir, console, true).await;
+                }
                 clean(buck_out_dir, daemon_dir, console, Some(&lifecycle_lock)).await
             },
         )
@@ -154,6 +167,25 @@ async fn clean(
     Ok(())
 }
 
+async fn clean_action_cache(
+    action_cache_dir: AbsNormPathBuf,
+    console: &FinalConsole,
+    // false means "dry run".
+    remove: bool,
+) -> buck2_error::Result<()> {
+    if !action_cache_dir.exists() {
+        return Ok(());
+    }
+    let path = action_cache_dir.display().to_string();
+    if remove {
+        tokio::task::spawn_blocking(move || fs_util::remove_all(&action_cache_dir))
+            .await?
+            .buck_error_context("Failed to remove the disk action cache")?;
+    }
+    console.print_stderr(&path)?;
+    Ok(())
+}
+
 fn collect_paths_to_clean(
     buck_out_path: &AbsNormPathBuf,
 ) -> buck2_error::Result<Vec<AbsNormPathBuf>> {
@@ -27,7 +27,9 @@ use crate::commands::debug::allocative::AllocativeCommand;
 use crate::commands::debug::daemon_dir::DaemonDirCommand;
 use crate::commands::debug::eval::EvalCommand;
 use crate::commands::debug::exe::ExeCommand;
+use crate::commands::debug::explain_recompute::ExplainRecomputeCommand;
 use crate::commands::debug::log_perf::LogPerfCommand;
+use crate::commands::debug::offline_bundle::OfflineBundleCommand;
 use crate::commands::debug::paranoid::ParanoidCommand;
 use crate::commands::debug::persist_event_logs::PersistEventLogsCommand;
 use crate::commands::debug::set_log_filter::SetLogFilterCommand;
@@ -45,12 +47,14 @@ mod daemon_dir;
 mod dice_dump;
 mod eval;
 mod exe;
+mod explain_recompute;
 mod file_status;
 mod flush_dep_files;
 mod heap_dump;
 mod internal_version;
 mod log_perf;
 mod materialize;
+mod offline_bundle;
 mod paranoid;
 mod persist_event_logs;
 mod set_log_filter;
@@ -82,6 +86,8 @@ pub enum DebugCommand {
     UploadReLogs(UploadReLogsCommand),
     /// Validates that Buck2 and disk agree on the state of files.
     FileStatus(FileStatusCommand),
+    /// Explains why DICE keys were recomputed by the latest build.
+    ExplainRecompute(ExplainRecomputeCommand),
     /// Shows the commands that buck ran
     #[clap(alias = "whatran", hide = true)]
     WhatRan(DebugWhatRanCommand),
@@ -95,6 +101,8 @@ pub enum DebugCommand {
     LogPerf(LogPerfCommand),
     /// Interact with I/O tracing of the daemon.
     TraceIo(TraceIoCommand),
+    /// Adds everything fetched from the network to build targets to an offline bundle.
+    OfflineBundle(OfflineBundleCommand),
     #[doc(hidden)]
     PersistEventLogs(PersistEventLogsCommand),
     #[clap(subcommand)]
@@ -123,8 +131,10 @@ impl DebugCommand {
             DebugCommand::Allocative(cmd) => cmd.exec(matches, ctx),
             DebugCommand::SetLogFilter(cmd) => cmd.exec(matches, ctx),
             DebugCommand::FileStatus(cmd) => cmd.exec(matches, ctx),
+            DebugCommand::ExplainRecompute(cmd) => cmd.exec(matches, ctx),
             DebugCommand::LogPerf(cmd) => cmd.exec(matches, ctx),
             DebugCommand::TraceIo(cmd) => cmd.exec(matches, ctx),
+            DebugCommand::OfflineBundle(cmd) => cmd.exec(matches, ctx),
             DebugCommand::PersistEventLogs(cmd) => cmd.exec(matches, ctx),
             DebugCommand::Paranoid(cmd) => cmd.exec(matches, ctx),
             DebugCommand::Eval(cmd) => cmd.exec(matches, ctx),
@@ -0,0 +1,203 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::fmt::Write;
+
+use async_trait::async_trait;
+use buck2_cli_proto::new_generic::ExplainRecomputeRequest;
+use buck2_cli_proto::new_generic::ExplainRecomputeResponse;
+use buck2_cli_proto::new_generic::NewGenericRequest;
+use buck2_cli_proto::new_generic::NewGenericResponse;
+use buck2_cli_proto::new_generic::RecomputeKey;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::ui::CommonConsoleOptions;
+use buck2_client_ctx::common::BuckArgMatches;
+use buck2_client_ctx::common::CommonBuildConfigurationOptions;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_client_ctx::common::CommonEventLogOptions;
+use buck2_client_ctx::common::CommonStarlarkOptions;
+use buck2_client_ctx::daemon::client::BuckdClientConnector;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::streaming::StreamingCommand;
+
+/// Explains why DICE keys were recomputed by the latest build.
+///
+/// For every matching key whose value changed, prints the chains of dependencies from the keys
+/// changed by the latest committed transaction (e.g. files or buckconfigs) to that key. Keys that
+/// were recomputed to the same value don't cause their dependents to recompute, so they are left
+/// out of both.
+#[derive(Debug, clap::Parser)]
+pub struct ExplainRecomputeCommand {
+    /// Explain the keys whose display contains this string, e.g. `root//foo:bar`.
+    #[clap(value_name = "KEY")]
+    key: String,
+
+    /// Only explain the keys of this type, e.g. `AnalysisKey`.
+    #[clap(long, value_name = "TYPE")]
+    key_type: Option<String>,
+
+    /// Print the explanations as JSON.
+    #[clap(long)]
+    json: bool,
+
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+}
+
+#[async_trait]
+impl StreamingCommand for ExplainRecomputeCommand {
+    const COMMAND_NAME: &'static str = "explain-recompute";
+
+    fn existing_only() -> bool {
+        true
+    }
+
+    async fn exec_impl(
+        self,
+        buckd: &mut BuckdClientConnector,
+        matches: BuckArgMatches<'_>,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        let context = ctx.client_context(matches, &self)?;
+        let resp = buckd
+            .with_flushing()
+            .new_generic(
+                context,
+                NewGenericRequest::ExplainRecompute(ExplainRecomputeRequest {
+                    key: self.key,
+                    key_type: self.key_type,
+                }),
+                ctx.console_interaction_stream(&self.common_opts.console_opts),
+            )
+            .await??;
+        let NewGenericResponse::ExplainRecompute(resp) = resp else {
+            return ExitResult::bail("Unexpected response type from generic command");
+        };
+
+        let output = if self.json {
+            let mut output = serde_json::to_string_pretty(&resp)?;
+            output.push('\n');
+            output
+        } else {
+            format_text(&resp)
+        };
+        ExitResult::success().with_stdout(output.into_bytes())
+    }
+
+    fn console_opts(&self) -> &CommonConsoleOptions {
+        &self.common_opts.console_opts
+    }
+
+    fn event_log_opts(&self) -> &CommonEventLogOptions {
+        &self.common_opts.event_log_opts
+    }
+
+    fn build_config_opts(&self) -> &CommonBuildConfigurationOptions {
+        &self.common_opts.config_opts
+    }
+
+    fn starlark_opts(&self) -> &CommonStarlarkOptions {
+        &self.common_opts.starlark_opts
+    }
+}
+
+fn format_key(key: &RecomputeKey) -> String {
+    format!("{}({})", key.key_type, key.key)
+}
+
+fn format_text(resp: &ExplainRecomputeResponse) -> String {
+    let mut output = String::new();
+    if resp.explanations.is_empty() {
+        writeln!(
+            output,
+            "No matching keys changed at DICE version {}",
+            resp.version
+        )
+        .unwrap();
+    }
+    for explanation in &resp.explanations {
+        writeln!(
+            output,
+            "{} changed because of the changes at DICE version {}:",
+            format_key(&explanation.key),
+            resp.version
+        )
+        .unwrap();
+        for chain in &explanation.chains {
+            let chain: Vec<String> = chain.iter().map(format_key).collect();
+            writeln!(output, "  {}", chain.join(" -> ")).unwrap();
+        }
+    }
+    if resp.omitted > 0 {
+        writeln!(
+            output,
+            "{} more matching keys changed, use a more specific KEY or --key-type to explain them",
+            resp.omitted
+        )
+        .unwrap();
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_cli_proto::new_generic::RecomputeExplanation;
+
+    use super::*;
+
+    fn key(key_type: &str, key: &str) -> RecomputeKey {
+        RecomputeKey {
+            key_type: key_type.to_owned(),
+            key: key.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_format_text() {
+        let resp = ExplainRecomputeResponse {
+            version: 3,
+            explanations: vec![
+                RecomputeExplanation {
+                    key: key("AnalysisKey", "root//:bin"),
+                    chains: vec![vec![
+                        key("FileContentsKey", "root//BUCK"),
+                        key("InterpreterResultsKey", "root//"),
+                        key("AnalysisKey", "root//:bin"),
+                    ]],
+                },
+                RecomputeExplanation {
+                    key: key("FileContentsKey", "root//BUCK"),
+                    chains: vec![vec![key("FileContentsKey", "root//BUCK")]],
+                },
+            ],
+            omitted: 2,
+        };
+        assert_eq!(
+            "AnalysisKey(root//:bin) changed because of the changes at DICE version 3:\n\
+             \x20 FileContentsKey(root//BUCK) -> InterpreterResultsKey(root//) -> AnalysisKey(root//:bin)\n\
+             FileContentsKey(root//BUCK) changed because of the changes at DICE version 3:\n\
+             \x20 FileContentsKey(root//BUCK)\n\
+             2 more matching keys changed, use a more specific KEY or --key-type to explain them\n",
+            format_text(&resp)
+        );
+    }
+
+    #[test]
+    fn test_format_text_no_changes() {
+        let resp = ExplainRecomputeResponse {
+            version: 3,
+            explanations: Vec::new(),
+            omitted: 0,
+        };
+        assert_eq!(
+            "No matching keys changed at DICE version 3\n",
+            format_text(&resp)
+        );
+    }
+}
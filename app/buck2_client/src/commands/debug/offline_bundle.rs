@@ -0,0 +1,101 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use async_trait::async_trait;
+use buck2_cli_proto::new_generic::NewGenericRequest;
+use buck2_cli_proto::new_generic::NewGenericResponse;
+use buck2_cli_proto::new_generic::OfflineBundleRequest;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::target_cfg::TargetCfgOptions;
+use buck2_client_ctx::common::ui::CommonConsoleOptions;
+use buck2_client_ctx::common::BuckArgMatches;
+use buck2_client_ctx::common::CommonBuildConfigurationOptions;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_client_ctx::common::CommonEventLogOptions;
+use buck2_client_ctx::common::CommonStarlarkOptions;
+use buck2_client_ctx::daemon::client::BuckdClientConnector;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::path_arg::PathArg;
+use buck2_client_ctx::streaming::StreamingCommand;
+
+/// Adds everything fetched from the network to build the targets to an offline bundle.
+///
+/// That is the outputs of the `download_file` and `cas_artifact` actions of the targets and their
+/// dependencies, whether or not they are cached, and the commits of all git external cells. Build
+/// with `-c buck2.offline_bundle_dir=<BUNDLE_DIR>` to serve these from the bundle instead of the
+/// network.
+#[derive(Debug, clap::Parser)]
+pub struct OfflineBundleCommand {
+    /// The bundle to add to, created if it does not exist.
+    #[clap(value_name = "BUNDLE_DIR")]
+    bundle_dir: PathArg,
+
+    /// Patterns of the targets to build offline.
+    #[clap(value_name = "TARGET_PATTERNS", required = true)]
+    patterns: Vec<String>,
+
+    #[clap(flatten)]
+    target_cfg: TargetCfgOptions,
+
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+}
+
+#[async_trait]
+impl StreamingCommand for OfflineBundleCommand {
+    const COMMAND_NAME: &'static str = "offline-bundle";
+
+    async fn exec_impl(
+        self,
+        buckd: &mut BuckdClientConnector,
+        matches: BuckArgMatches<'_>,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        let context = ctx.client_context(matches, &self)?;
+        let resp = buckd
+            .with_flushing()
+            .new_generic(
+                context,
+                NewGenericRequest::OfflineBundle(OfflineBundleRequest {
+                    bundle_dir: self.bundle_dir.resolve(&ctx.working_dir),
+                    target_patterns: self.patterns,
+                    target_cfg: self.target_cfg.target_cfg(),
+                }),
+                ctx.console_interaction_stream(&self.common_opts.console_opts),
+            )
+            .await??;
+        let NewGenericResponse::OfflineBundle(resp) = resp else {
+            return ExitResult::bail("Unexpected response type from generic command");
+        };
+
+        buck2_client_ctx::eprintln!(
+            "The bundle contains {} downloads, {} CAS artifacts and {} git cells",
+            resp.downloads,
+            resp.cas_artifacts,
+            resp.git_cells
+        )?;
+        ExitResult::success()
+    }
+
+    fn console_opts(&self) -> &CommonConsoleOptions {
+        &self.common_opts.console_opts
+    }
+
+    fn event_log_opts(&self) -> &CommonEventLogOptions {
+        &self.common_opts.event_log_opts
+    }
+
+    fn build_config_opts(&self) -> &CommonBuildConfigurationOptions {
+        &self.common_opts.config_opts
+    }
+
+    fn starlark_opts(&self) -> &CommonStarlarkOptions {
+        &self.common_opts.starlark_opts
+    }
+}
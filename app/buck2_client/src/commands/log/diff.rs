@@ -12,6 +12,8 @@ use buck2_client_ctx::common::BuckArgMatches;
 use buck2_client_ctx::exit_result::ExitResult;
 
 mod action_divergence;
+mod analysis_divergence;
+mod configuration_divergence;
 mod diff_options;
 mod external_config_diff;
 
@@ -19,6 +21,8 @@ mod external_config_diff;
 #[clap(about = "Subcommands for diff'ing two buck2 commands")]
 pub enum DiffCommand {
     ActionDivergence(action_divergence::ActionDivergenceCommand),
+    AnalysisDivergence(analysis_divergence::AnalysisDivergenceCommand),
+    ConfigurationDivergence(configuration_divergence::ConfigurationDivergenceCommand),
     ExternalConfigs(external_config_diff::ExternalConfigDiffCommand),
 }
 
@@ -27,6 +31,8 @@ impl DiffCommand {
         match self {
             Self::ExternalConfigs(cmd) => cmd.exec(matches, ctx),
             Self::ActionDivergence(cmd) => cmd.exec(matches, ctx),
+            Self::AnalysisDivergence(cmd) => cmd.exec(matches, ctx),
+            Self::ConfigurationDivergence(cmd) => cmd.exec(matches, ctx),
         }
     }
 }
@@ -0,0 +1,296 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::BTreeSet;
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::BuckArgMatches;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_event_log::stream_value::StreamValue;
+use buck2_event_observer::display::display_action_name_opt;
+use buck2_event_observer::display::display_action_owner;
+use buck2_event_observer::display::display_anon_target;
+use buck2_event_observer::display::display_configured_target_label;
+use buck2_event_observer::display::TargetDisplayOptions;
+use futures::Stream;
+use futures::TryStreamExt;
+use linked_hash_map::LinkedHashMap;
+
+use crate::commands::log::diff::diff_options::DiffEventLogOptions;
+
+/// Identifies the first configured targets whose analysis differs between two builds.
+/// Analyses differ when a target was analyzed with a different rule, declared a different
+/// number of actions or artifacts, or ran a different set of actions. Only targets analyzed in
+/// both builds are compared, since analyses cached by the daemon do not show up in the event log.
+#[derive(Debug, clap::Parser)]
+pub struct AnalysisDivergenceCommand {
+    #[clap(flatten)]
+    diff_event_log: DiffEventLogOptions,
+
+    /// Maximum number of divergent targets to report.
+    #[clap(long, default_value = "10", value_name = "NUMBER")]
+    limit: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+struct AnalysisData {
+    rule: Option<String>,
+    declared_actions: Option<u64>,
+    declared_artifacts: Option<u64>,
+    /// Names of the actions owned by this target that ran in the build.
+    actions: BTreeSet<String>,
+}
+
+impl AnalysisData {
+    fn diverges_from(&self, other: &AnalysisData) -> bool {
+        self.rule.is_some()
+            && other.rule.is_some()
+            && (self.rule != other.rule
+                || self.declared_actions != other.declared_actions
+                || self.declared_artifacts != other.declared_artifacts
+                || self.actions != other.actions)
+    }
+}
+
+fn process_analysis_data(
+    out: &mut LinkedHashMap<String, AnalysisData>,
+    event: &buck2_data::BuckEvent,
+) -> buck2_error::Result<()> {
+    use buck2_data::analysis_end::Target;
+
+    let opts = TargetDisplayOptions::for_log();
+    let end = match &event.data {
+        Some(buck2_data::buck_event::Data::SpanEnd(end)) => end,
+        _ => return Ok(()),
+    };
+    match &end.data {
+        Some(buck2_data::span_end_event::Data::Analysis(analysis)) => {
+            let target = match &analysis.target {
+                Some(Target::StandardTarget(target)) => {
+                    display_configured_target_label(target, opts)?
+                }
+                Some(Target::AnonTarget(target)) => display_anon_target(target)?,
+                // Dynamic lambdas display as their owner, whose analysis is recorded already.
+                _ => return Ok(()),
+            };
+            let data = out.entry(target).or_default();
+            data.rule = Some(analysis.rule.clone());
+            data.declared_actions = analysis.declared_actions;
+            data.declared_artifacts = analysis.declared_artifacts;
+        }
+        Some(buck2_data::span_end_event::Data::ActionExecution(action)) => {
+            if let Some(owner) = action.key.as_ref().and_then(|key| key.owner.as_ref()) {
+                let target = display_action_owner(owner, opts)?;
+                out.entry(target)
+                    .or_default()
+                    .actions
+                    .insert(display_action_name_opt(action.name.as_ref()));
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+async fn get_analysis_map(
+    mut events: impl Stream<Item = buck2_error::Result<StreamValue>> + Unpin + Send,
+) -> buck2_error::Result<LinkedHashMap<String, AnalysisData>> {
+    let mut out = LinkedHashMap::new();
+    while let Some(event) = events.try_next().await? {
+        match event {
+            StreamValue::Event(event) => process_analysis_data(&mut out, &event)?,
+            _ => {}
+        }
+    }
+    Ok(out)
+}
+
+fn display_opt<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(|| "<none>".to_owned(), |value| value.to_string())
+}
+
+fn print_divergence_msg(
+    target: &str,
+    ad1: &AnalysisData,
+    ad2: &AnalysisData,
+) -> buck2_error::Result<()> {
+    let mut output = vec![
+        format!("{:-^44}", "Divergent Analysis"),
+        target.to_owned(),
+        format!(
+            "rule: first: {} \t second: {}",
+            display_opt(ad1.rule.as_deref()),
+            display_opt(ad2.rule.as_deref()),
+        ),
+        format!(
+            "declared actions: first: {} \t second: {}",
+            display_opt(ad1.declared_actions),
+            display_opt(ad2.declared_actions),
+        ),
+        format!(
+            "declared artifacts: first: {} \t second: {}",
+            display_opt(ad1.declared_artifacts),
+            display_opt(ad2.declared_artifacts),
+        ),
+    ];
+    if ad1.actions != ad2.actions {
+        output.push(format!("{:-^44}", "Actions Run"));
+        output.extend(
+            ad1.actions
+                .difference(&ad2.actions)
+                .map(|action| format!("first only: {}", action)),
+        );
+        output.extend(
+            ad2.actions
+                .difference(&ad1.actions)
+                .map(|action| format!("second only: {}", action)),
+        );
+    }
+    buck2_client_ctx::println!("{}", output.join("\n"))?;
+
+    Ok(())
+}
+
+impl AnalysisDivergenceCommand {
+    pub fn exec(self, _matches: BuckArgMatches<'_>, ctx: ClientCommandContext<'_>) -> ExitResult {
+        ctx.instant_command_no_log("log-diff-analysis-divergence", |ctx| async move {
+            let (log_path1, log_path2) = self.diff_event_log.get(&ctx).await?;
+
+            let (invocation1, events1) = log_path1.unpack_stream().await?;
+            let (invocation2, events2) = log_path2.unpack_stream().await?;
+
+            buck2_client_ctx::println!(
+                "Analyzing divergent analyses between: \n{} and \n{}",
+                invocation1.display_command_line(),
+                invocation2.display_command_line()
+            )?;
+
+            let analysis_map1 = get_analysis_map(events1).await?;
+            let analysis_map2 = get_analysis_map(events2).await?;
+
+            let divergent = analysis_map2.iter().filter_map(|(target, ad2)| {
+                let ad1 = analysis_map1.get(target)?;
+                ad1.diverges_from(ad2).then_some((target, ad1, ad2))
+            });
+
+            let mut divergence_found = false;
+            for (target, ad1, ad2) in divergent.take(self.limit) {
+                divergence_found = true;
+                print_divergence_msg(target, ad1, ad2)?;
+            }
+            if !divergence_found {
+                buck2_client_ctx::println!("No divergent analyses found.")?;
+            }
+            buck2_error::Ok(())
+        })
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analysis(rule: &str, declared_actions: u64, actions: &[&str]) -> AnalysisData {
+        AnalysisData {
+            rule: Some(rule.to_owned()),
+            declared_actions: Some(declared_actions),
+            declared_artifacts: Some(declared_actions),
+            actions: actions.iter().map(|action| (*action).to_owned()).collect(),
+        }
+    }
+
+    fn target_label(name: &str) -> buck2_data::ConfiguredTargetLabel {
+        buck2_data::ConfiguredTargetLabel {
+            label: Some(buck2_data::TargetLabel {
+                package: "root//pkg".to_owned(),
+                name: name.to_owned(),
+            }),
+            configuration: Some(buck2_data::Configuration {
+                full_name: "cfg".to_owned(),
+            }),
+            execution_configuration: None,
+        }
+    }
+
+    fn span_end(data: buck2_data::span_end_event::Data) -> buck2_data::BuckEvent {
+        buck2_data::BuckEvent {
+            data: Some(buck2_data::buck_event::Data::SpanEnd(
+                buck2_data::SpanEndEvent {
+                    data: Some(data),
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_diverges_from() {
+        let base = analysis("cxx_library", 2, &["cxx_compile foo.cpp"]);
+        assert!(!base.diverges_from(&base.clone()));
+        assert!(base.diverges_from(&analysis("cxx_binary", 2, &["cxx_compile foo.cpp"])));
+        assert!(base.diverges_from(&analysis("cxx_library", 3, &["cxx_compile foo.cpp"])));
+        assert!(base.diverges_from(&analysis("cxx_library", 2, &["cxx_compile bar.cpp"])));
+        assert!(base.diverges_from(&analysis("cxx_library", 2, &[])));
+
+        // Targets whose analysis was not in both logs cannot be compared.
+        let unanalyzed = AnalysisData {
+            rule: None,
+            ..analysis("cxx_binary", 3, &[])
+        };
+        assert!(!base.diverges_from(&unanalyzed));
+        assert!(!unanalyzed.diverges_from(&base));
+    }
+
+    #[test]
+    fn test_process_analysis_data() -> buck2_error::Result<()> {
+        let mut out = LinkedHashMap::new();
+        process_analysis_data(
+            &mut out,
+            &span_end(buck2_data::span_end_event::Data::Analysis(
+                buck2_data::AnalysisEnd {
+                    target: Some(buck2_data::analysis_end::Target::StandardTarget(
+                        target_label("foo"),
+                    )),
+                    rule: "cxx_library".to_owned(),
+                    declared_actions: Some(1),
+                    declared_artifacts: Some(2),
+                    ..Default::default()
+                },
+            )),
+        )?;
+        process_analysis_data(
+            &mut out,
+            &span_end(buck2_data::span_end_event::Data::ActionExecution(Box::new(
+                buck2_data::ActionExecutionEnd {
+                    key: Some(buck2_data::ActionKey {
+                        owner: Some(buck2_data::action_key::Owner::TargetLabel(target_label(
+                            "foo",
+                        ))),
+                        ..Default::default()
+                    }),
+                    name: Some(buck2_data::ActionName {
+                        category: "cxx_compile".to_owned(),
+                        identifier: "foo.cpp".to_owned(),
+                    }),
+                    ..Default::default()
+                },
+            ))),
+        )?;
+
+        assert_eq!(1, out.len());
+        let data = out.values().next().unwrap();
+        assert_eq!(Some("cxx_library"), data.rule.as_deref());
+        assert_eq!(Some(1), data.declared_actions);
+        assert_eq!(Some(2), data.declared_artifacts);
+        assert_eq!(1, data.actions.len());
+        Ok(())
+    }
+}
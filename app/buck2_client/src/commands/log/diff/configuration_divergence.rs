@@ -0,0 +1,246 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::BuckArgMatches;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_data::ConfiguredTargetLabel;
+use buck2_event_log::stream_value::StreamValue;
+use futures::Stream;
+use futures::TryStreamExt;
+use linked_hash_map::LinkedHashMap;
+
+use crate::commands::log::diff::diff_options::DiffEventLogOptions;
+use crate::commands::log::diff::external_config_diff::DiffType;
+
+/// Identifies the first targets that were configured differently between two builds.
+/// For each such target, the constraints that differ between its configurations are listed:
+/// these are what `select()` resolves against, so they show which select branches flipped.
+#[derive(Debug, clap::Parser)]
+pub struct ConfigurationDivergenceCommand {
+    #[clap(flatten)]
+    diff_event_log: DiffEventLogOptions,
+
+    /// Maximum number of divergent targets to report.
+    #[clap(long, default_value = "10", value_name = "NUMBER")]
+    limit: usize,
+}
+
+#[derive(Default)]
+struct ConfigurationData {
+    /// Unconfigured target label to the configurations it was used with, in event order.
+    targets: LinkedHashMap<String, BTreeSet<String>>,
+    /// Configuration name to its constraint settings and values.
+    constraints: HashMap<String, BTreeMap<String, String>>,
+}
+
+impl ConfigurationData {
+    fn insert_target(&mut self, target: &ConfiguredTargetLabel) {
+        if let ConfiguredTargetLabel {
+            label: Some(label),
+            configuration: Some(configuration),
+            ..
+        } = target
+        {
+            self.targets
+                .entry(format!("{}:{}", label.package, label.name))
+                .or_default()
+                .insert(configuration.full_name.clone());
+        }
+    }
+
+    fn process_event(&mut self, event: &buck2_data::BuckEvent) {
+        use buck2_data::action_key::Owner;
+        use buck2_data::analysis_start::Target;
+
+        match &event.data {
+            Some(buck2_data::buck_event::Data::SpanStart(start)) => match &start.data {
+                Some(buck2_data::span_start_event::Data::Analysis(analysis)) => {
+                    if let Some(Target::StandardTarget(target)) = &analysis.target {
+                        self.insert_target(target);
+                    }
+                }
+                _ => {}
+            },
+            Some(buck2_data::buck_event::Data::SpanEnd(end)) => match &end.data {
+                Some(buck2_data::span_end_event::Data::ActionExecution(action)) => {
+                    match action.key.as_ref().and_then(|key| key.owner.as_ref()) {
+                        Some(
+                            Owner::TargetLabel(target)
+                            | Owner::TestTargetLabel(target)
+                            | Owner::LocalResourceSetup(target),
+                        ) => self.insert_target(target),
+                        _ => {}
+                    }
+                }
+                _ => {}
+            },
+            Some(buck2_data::buck_event::Data::Instant(instant)) => match &instant.data {
+                Some(buck2_data::instant_event::Data::ConfigurationCreated(created)) => {
+                    if let Some(cfg) = &created.cfg {
+                        self.constraints.insert(
+                            cfg.full_name.clone(),
+                            cfg.constraint
+                                .iter()
+                                .map(|c| (c.setting.clone(), c.value.clone()))
+                                .collect(),
+                        );
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Constraints that differ between two configurations.
+fn diff_constraints<'a>(
+    first: &'a BTreeMap<String, String>,
+    second: &'a BTreeMap<String, String>,
+) -> Vec<DiffType<'a>> {
+    let mut diffs = Vec::new();
+    for (key, value) in first {
+        match second.get(key) {
+            Some(new_value) if new_value != value => diffs.push(DiffType::Changed {
+                key,
+                old_value: value,
+                new_value,
+            }),
+            Some(_) => {}
+            None => diffs.push(DiffType::FirstOnly { key, value }),
+        }
+    }
+    for (key, value) in second {
+        if !first.contains_key(key) {
+            diffs.push(DiffType::SecondOnly { key, value });
+        }
+    }
+    diffs
+}
+
+async fn get_configuration_data(
+    mut events: impl Stream<Item = buck2_error::Result<StreamValue>> + Unpin + Send,
+) -> buck2_error::Result<ConfigurationData> {
+    let mut out = ConfigurationData::default();
+    while let Some(event) = events.try_next().await? {
+        match event {
+            StreamValue::Event(event) => out.process_event(&event),
+            _ => {}
+        }
+    }
+    Ok(out)
+}
+
+fn print_divergence_msg(
+    target: &str,
+    configs1: &BTreeSet<String>,
+    configs2: &BTreeSet<String>,
+    data1: &ConfigurationData,
+    data2: &ConfigurationData,
+) -> buck2_error::Result<()> {
+    let first_only: Vec<&String> = configs1.difference(configs2).collect();
+    let second_only: Vec<&String> = configs2.difference(configs1).collect();
+    let mut output = vec![
+        format!("{:-^44}", "Divergent Target"),
+        target.to_owned(),
+        format!(
+            "first: {} \t second: {}",
+            display_configurations(&first_only),
+            display_configurations(&second_only),
+        ),
+    ];
+    // Configuration names include a hash of their constraints, so either build's record of a
+    // configuration will do.
+    static NO_CONSTRAINTS: BTreeMap<String, String> = BTreeMap::new();
+    let constraints = |cfg: &str| {
+        data1
+            .constraints
+            .get(cfg)
+            .or_else(|| data2.constraints.get(cfg))
+            .unwrap_or(&NO_CONSTRAINTS)
+    };
+    // Pair each configuration only the second build used with the closest one only the first
+    // build used, which is the configuration it most likely replaced.
+    for second in &second_only {
+        let closest = first_only
+            .iter()
+            .map(|first| {
+                (
+                    first,
+                    diff_constraints(constraints(first), constraints(second)),
+                )
+            })
+            .min_by_key(|(_, diffs)| diffs.len());
+        if let Some((first, diffs)) = closest {
+            output.push(format!("{:-^44}", "Flipped Constraints"));
+            output.push(format!("{} -> {}", first, second));
+            output.extend(diffs.iter().map(|diff| diff.to_string()));
+        }
+    }
+    buck2_client_ctx::println!("{}", output.join("\n"))?;
+
+    Ok(())
+}
+
+fn display_configurations(configurations: &[&String]) -> String {
+    if configurations.is_empty() {
+        "<none>".to_owned()
+    } else {
+        configurations
+            .iter()
+            .map(|c| c.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl ConfigurationDivergenceCommand {
+    pub fn exec(self, _matches: BuckArgMatches<'_>, ctx: ClientCommandContext<'_>) -> ExitResult {
+        ctx.instant_command_no_log("log-diff-configuration-divergence", |ctx| async move {
+            let (log_path1, log_path2) = self.diff_event_log.get(&ctx).await?;
+
+            let (invocation1, events1) = log_path1.unpack_stream().await?;
+            let (invocation2, events2) = log_path2.unpack_stream().await?;
+
+            buck2_client_ctx::println!(
+                "Analyzing divergent configurations between: \n{} and \n{}",
+                invocation1.display_command_line(),
+                invocation2.display_command_line()
+            )?;
+
+            let data1 = get_configuration_data(events1).await?;
+            let data2 = get_configuration_data(events2).await?;
+
+            // A build only logs the configured targets it did not have cached, so a target only
+            // diverges when each build used a configuration the other did not.
+            let divergent = data2.targets.iter().filter_map(|(target, configs2)| {
+                let configs1 = data1.targets.get(target)?;
+                let diverges = configs1.difference(configs2).next().is_some()
+                    && configs2.difference(configs1).next().is_some();
+                diverges.then_some((target, configs1, configs2))
+            });
+
+            let mut divergence_found = false;
+            for (target, configs1, configs2) in divergent.take(self.limit) {
+                divergence_found = true;
+                print_divergence_msg(target, configs1, configs2, &data1, &data2)?;
+            }
+            if !divergence_found {
+                buck2_client_ctx::println!("No divergent configurations found.")?;
+            }
+            buck2_error::Ok(())
+        })
+        .into()
+    }
+}
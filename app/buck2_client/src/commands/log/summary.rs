@@ -68,7 +68,9 @@ impl Stats {
                     match ActionExecutionKind::from_i32(data.execution_kind) {
                         Some(ActionExecutionKind::Local) => self.total_local_actions += 1,
                         Some(ActionExecutionKind::Remote) => self.total_remote_actions += 1,
-                        Some(ActionExecutionKind::ActionCache) => self.total_cached_actions += 1,
+                        Some(
+                            ActionExecutionKind::ActionCache | ActionExecutionKind::DiskActionCache,
+                        ) => self.total_cached_actions += 1,
                         _ => self.total_other_actions += 1,
                     }
                 }
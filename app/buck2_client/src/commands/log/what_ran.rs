@@ -339,6 +339,9 @@ impl WhatRanOutputWriter for OutputFormatWithWriter<'_> {
         match &mut self.format {
             LogCommandOutputFormatWithWriter::Tabulated(w) => {
                 w.write_all(format!("{}\n", command.as_tabulated_reproducer()).as_bytes())?;
+                if let Some(WhatRanOutputCommandExtra::SandboxViolation(error)) = command.extra {
+                    writeln!(w, "{}", error)?;
+                }
                 if let Some(std_err) = std_err_formatted {
                     write!(
                         w,
@@ -526,12 +529,17 @@ use json_reproducer::JsonReproducer;
 #[serde(rename_all = "lowercase")]
 enum JsonExtra<'a> {
     TestCases(&'a [String]),
+    #[serde(rename = "sandbox_violation")]
+    SandboxViolation(&'a str),
 }
 
 impl<'a> From<WhatRanOutputCommandExtra<'a>> for JsonExtra<'a> {
     fn from(extra: WhatRanOutputCommandExtra<'a>) -> JsonExtra<'a> {
         match extra {
             WhatRanOutputCommandExtra::TestCases(cases) => JsonExtra::TestCases(cases),
+            WhatRanOutputCommandExtra::SandboxViolation(error) => {
+                JsonExtra::SandboxViolation(error)
+            }
         }
     }
 }
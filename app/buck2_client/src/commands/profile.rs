@@ -7,6 +7,8 @@
  * of this source tree.
  */
 
+mod execution;
+
 use std::time::Duration;
 
 use async_trait::async_trait;
@@ -37,19 +39,25 @@ use buck2_error::BuckErrorContext;
 use dupe::Dupe;
 
 use super::bxl::BxlCommandOptions;
+use crate::commands::profile::execution::ProfileExecutionCommand;
 
 #[derive(Debug, clap::Parser)]
-#[clap(about = "Run starlark profiler")]
+#[clap(about = "Run starlark profiler, or profile the execution of a build")]
 pub enum ProfileCommand {
     Analysis(ProfileAnalysisCommand),
     Loading(ProfileLoadingCommand),
     Bxl(ProfileBxlCommand),
+    Execution(ProfileExecutionCommand),
 }
 
 impl ProfileCommand {
     pub fn exec(self, matches: BuckArgMatches<'_>, ctx: ClientCommandContext<'_>) -> ExitResult {
         let submatches = matches.unwrap_subcommand();
-        ProfileSubcommand { subcommand: self }.exec(submatches, ctx)
+        match self {
+            // Not a Starlark profile, so this is just a build.
+            ProfileCommand::Execution(execution) => execution.exec(submatches, ctx),
+            subcommand => ProfileSubcommand { subcommand }.exec(submatches, ctx),
+        }
     }
 
     pub fn sanitize_argv(&self, argv: Argv) -> SanitizedArgv {
@@ -169,6 +177,7 @@ impl ProfileSubcommand {
             ProfileCommand::Analysis(analysis) => &analysis.profile_common_opts,
             ProfileCommand::Loading(loading) => &loading.profile_common_opts,
             ProfileCommand::Bxl(bxl) => &bxl.profile_common_opts,
+            ProfileCommand::Execution(..) => unreachable!("not a Starlark profile"),
         }
     }
 }
@@ -249,6 +258,7 @@ impl StreamingCommand for ProfileSubcommand {
                     target_cfg: Some(bxl.profile_common_opts.target_cfg.target_cfg.target_cfg()),
                 })
             }
+            ProfileCommand::Execution(..) => unreachable!("not a Starlark profile"),
         };
 
         let request = ProfileRequest {
@@ -0,0 +1,665 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! `buck2 profile execution`: build some targets, and report where the time of every action went.
+//!
+//! Everything in the profile comes from events the daemon sends during any build: the span of
+//! each action, the timings of the command that ran it, its cache lookup and upload spans, and
+//! the critical path computed at the end of the build.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use buck2_cli_proto::build_request::build_providers;
+use buck2_cli_proto::build_request::BuildProviders;
+use buck2_cli_proto::build_request::ResponseOptions;
+use buck2_cli_proto::BuildRequest;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::build::CommonBuildOptions;
+use buck2_client_ctx::common::target_cfg::TargetCfgWithUniverseOptions;
+use buck2_client_ctx::common::ui::CommonConsoleOptions;
+use buck2_client_ctx::common::BuckArgMatches;
+use buck2_client_ctx::common::CommonBuildConfigurationOptions;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_client_ctx::common::CommonEventLogOptions;
+use buck2_client_ctx::common::CommonStarlarkOptions;
+use buck2_client_ctx::daemon::client::BuckdClientConnector;
+use buck2_client_ctx::daemon::client::NoPartialResultHandler;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::path_arg::PathArg;
+use buck2_client_ctx::streaming::StreamingCommand;
+use buck2_client_ctx::subscribers::subscriber::EventSubscriber;
+use buck2_core::fs::fs_util;
+use buck2_data::critical_path_entry2;
+use buck2_data::executor_stage_start;
+use buck2_data::instant_event;
+use buck2_data::span_end_event;
+use buck2_data::span_start_event;
+use buck2_event_observer::display;
+use buck2_event_observer::display::TargetDisplayOptions;
+use buck2_event_observer::unpack_event::unpack_event;
+use buck2_event_observer::unpack_event::UnpackedBuckEvent;
+use buck2_events::span::SpanId;
+use buck2_events::BuckEvent;
+use serde_json::json;
+
+use crate::commands::build::print_build_result;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum ExecutionProfileFormat {
+    /// Folded stacks of target, category and phase, for
+    /// [flamegraph.pl](https://github.com/brendangregg/FlameGraph/blob/master/flamegraph.pl).
+    Flamegraph,
+    /// A trace with a process per target, and a thread for each action of the target that runs
+    /// at the same time as another, for Perfetto or `chrome://tracing`.
+    ChromeTrace,
+}
+
+/// Build targets and profile the execution of their actions.
+///
+/// For every action, this reports the time spent looking it up in the action cache, queued,
+/// materializing inputs, executing, and uploading outputs to the cache, and whether it is on the
+/// critical path of the build.
+#[derive(Debug, clap::Parser)]
+pub struct ProfileExecutionCommand {
+    #[clap(value_name = "TARGET_PATTERNS")]
+    patterns: Vec<String>,
+
+    /// Output file path for the profile.
+    ///
+    /// File will be created if it does not exist, and overwritten if it does.
+    #[clap(long, short = 'o', value_name = "PATH")]
+    output: PathArg,
+
+    #[clap(long, value_enum, default_value = "chrome-trace")]
+    format: ExecutionProfileFormat,
+
+    #[clap(flatten)]
+    target_cfg: TargetCfgWithUniverseOptions,
+
+    #[clap(flatten)]
+    build_opts: CommonBuildOptions,
+
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    /// Filled in from the events of the build.
+    #[clap(skip)]
+    profile: Arc<Mutex<ExecutionProfile>>,
+}
+
+#[async_trait]
+impl StreamingCommand for ProfileExecutionCommand {
+    const COMMAND_NAME: &'static str = "profile";
+
+    async fn exec_impl(
+        self,
+        buckd: &mut BuckdClientConnector,
+        matches: BuckArgMatches<'_>,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        let context = ctx.client_context(matches, &self)?;
+        let output = self.output.resolve(&ctx.working_dir);
+
+        let response = buckd
+            .with_flushing()
+            .build(
+                BuildRequest {
+                    context: Some(context),
+                    target_patterns: self.patterns.clone(),
+                    target_cfg: Some(self.target_cfg.target_cfg.target_cfg()),
+                    build_providers: Some(BuildProviders {
+                        default_info: build_providers::Action::Build as i32,
+                        run_info: build_providers::Action::BuildIfAvailable as i32,
+                        test_info: build_providers::Action::Skip as i32,
+                    }),
+                    response_options: Some(ResponseOptions {
+                        return_outputs: false,
+                        return_default_other_outputs: false,
+                    }),
+                    build_opts: Some(self.build_opts.to_proto()),
+                    target_universe: self.target_cfg.target_universe.clone(),
+                    ..Default::default()
+                },
+                ctx.console_interaction_stream(&self.common_opts.console_opts),
+                &mut NoPartialResultHandler,
+            )
+            .await??;
+
+        let console = self.common_opts.console_opts.final_console();
+        print_build_result(&console, &response.errors)?;
+
+        // Even if the build failed, the actions that ran are worth looking at.
+        let profile = self.profile.lock().unwrap();
+        let contents = match self.format {
+            ExecutionProfileFormat::Flamegraph => profile.flamegraph(),
+            ExecutionProfileFormat::ChromeTrace => profile.chrome_trace()?,
+        };
+        fs_util::write(&output, contents)?;
+
+        buck2_client_ctx::eprintln!(
+            "Execution profile of {} actions has been written to {}",
+            profile.actions.len(),
+            self.output.display(),
+        )?;
+
+        if response.errors.is_empty() {
+            ExitResult::success()
+        } else {
+            ExitResult::from_errors(&response.errors)
+        }
+    }
+
+    fn console_opts(&self) -> &CommonConsoleOptions {
+        &self.common_opts.console_opts
+    }
+
+    fn event_log_opts(&self) -> &CommonEventLogOptions {
+        &self.common_opts.event_log_opts
+    }
+
+    fn build_config_opts(&self) -> &CommonBuildConfigurationOptions {
+        &self.common_opts.config_opts
+    }
+
+    fn starlark_opts(&self) -> &CommonStarlarkOptions {
+        &self.common_opts.starlark_opts
+    }
+
+    fn extra_subscribers(&self) -> Vec<Box<dyn EventSubscriber>> {
+        vec![Box::new(ExecutionProfileSubscriber {
+            profile: self.profile.clone(),
+        })]
+    }
+}
+
+struct ExecutionProfileSubscriber {
+    profile: Arc<Mutex<ExecutionProfile>>,
+}
+
+#[async_trait]
+impl EventSubscriber for ExecutionProfileSubscriber {
+    async fn handle_events(&mut self, events: &[Arc<BuckEvent>]) -> buck2_error::Result<()> {
+        let mut profile = self.profile.lock().unwrap();
+        for event in events {
+            profile.handle_event(event)?;
+        }
+        Ok(())
+    }
+}
+
+/// The parts of an action's execution we break its time down into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Phase {
+    CacheLookup,
+    Queue,
+    InputMaterialization,
+    Execution,
+    Upload,
+}
+
+impl Phase {
+    /// In the order they happen in.
+    const ALL: [Phase; 5] = [
+        Phase::CacheLookup,
+        Phase::Queue,
+        Phase::InputMaterialization,
+        Phase::Execution,
+        Phase::Upload,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Phase::CacheLookup => "cache_lookup",
+            Phase::Queue => "queue",
+            Phase::InputMaterialization => "input_materialization",
+            Phase::Execution => "execution",
+            Phase::Upload => "upload",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ActionProfile {
+    target: String,
+    category: String,
+    identifier: String,
+    start: SystemTime,
+    /// The duration of the whole action span.
+    wall_time: Duration,
+    phases: BTreeMap<Phase, Duration>,
+}
+
+impl ActionProfile {
+    /// The part of the wall time not accounted for by any phase, e.g. preparing the action or
+    /// checking its outputs.
+    fn other(&self) -> Duration {
+        self.wall_time
+            .saturating_sub(self.phases.values().sum::<Duration>())
+    }
+
+    fn name(&self) -> String {
+        if self.identifier.is_empty() {
+            self.category.clone()
+        } else {
+            format!("{} {}", self.category, self.identifier)
+        }
+    }
+
+    fn critical_path_key(&self) -> (String, String, String) {
+        (
+            self.target.clone(),
+            self.category.clone(),
+            self.identifier.clone(),
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct ExecutionProfile {
+    /// Actions that started but didn't finish yet, by span.
+    running: HashMap<SpanId, ActionProfile>,
+    /// Cache lookup and upload spans of running actions, with the action span they belong to.
+    phase_spans: HashMap<SpanId, (SpanId, Phase)>,
+    actions: Vec<ActionProfile>,
+    /// Actions on the critical path, as target, category and identifier.
+    critical_path: HashSet<(String, String, String)>,
+}
+
+impl ExecutionProfile {
+    fn handle_event(&mut self, event: &BuckEvent) -> buck2_error::Result<()> {
+        let opts = TargetDisplayOptions::for_log();
+        match unpack_event(event)? {
+            UnpackedBuckEvent::SpanStart(event, _, data) => {
+                let Some(span_id) = event.span_id() else {
+                    return Ok(());
+                };
+                let phase = match data {
+                    span_start_event::Data::ActionExecution(start) => {
+                        let target = match &start.key {
+                            Some(key) => display::display_action_key(key, opts)?,
+                            None => return Ok(()),
+                        };
+                        let (category, identifier) = match &start.name {
+                            Some(name) => (name.category.clone(), name.identifier.clone()),
+                            None => Default::default(),
+                        };
+                        self.running.insert(
+                            span_id,
+                            ActionProfile {
+                                target,
+                                category,
+                                identifier,
+                                start: event.timestamp(),
+                                wall_time: Duration::ZERO,
+                                phases: BTreeMap::new(),
+                            },
+                        );
+                        return Ok(());
+                    }
+                    span_start_event::Data::ExecutorStage(stage) => match &stage.stage {
+                        Some(executor_stage_start::Stage::CacheQuery(_)) => Phase::CacheLookup,
+                        _ => return Ok(()),
+                    },
+                    span_start_event::Data::CacheUpload(_) => Phase::Upload,
+                    _ => return Ok(()),
+                };
+                if let Some(parent_id) = event.parent_id() {
+                    if self.running.contains_key(&parent_id) {
+                        self.phase_spans.insert(span_id, (parent_id, phase));
+                    }
+                }
+            }
+            UnpackedBuckEvent::SpanEnd(event, end, data) => {
+                let Some(span_id) = event.span_id() else {
+                    return Ok(());
+                };
+                let duration = to_duration(&end.duration);
+
+                if let Some((action_span_id, phase)) = self.phase_spans.remove(&span_id) {
+                    if let Some(action) = self.running.get_mut(&action_span_id) {
+                        *action.phases.entry(phase).or_default() += duration;
+                    }
+                }
+
+                if let span_end_event::Data::ActionExecution(end) = data {
+                    if let Some(mut action) = self.running.remove(&span_id) {
+                        action.wall_time = duration;
+                        // The last command is the one that produced the outputs.
+                        let metadata = end
+                            .commands
+                            .last()
+                            .and_then(|command| command.details.as_ref())
+                            .and_then(|details| details.metadata.as_ref());
+                        match metadata {
+                            // For cache hits, the command's timings are those of the execution
+                            // that populated the cache, not of this build.
+                            Some(_) if is_cache_hit(end.execution_kind) => {}
+                            Some(metadata) => {
+                                for (phase, duration) in [
+                                    (Phase::Queue, &metadata.queue_duration),
+                                    (
+                                        Phase::InputMaterialization,
+                                        &metadata.input_materialization_duration,
+                                    ),
+                                    (Phase::Execution, &metadata.execution_time),
+                                ] {
+                                    *action.phases.entry(phase).or_default() +=
+                                        to_duration(duration);
+                                }
+                            }
+                            // Actions without a command (e.g. writes and copies) run in the
+                            // daemon.
+                            None => {
+                                action.phases.insert(Phase::Execution, duration);
+                            }
+                        }
+                        self.actions.push(action);
+                    }
+                }
+            }
+            UnpackedBuckEvent::Instant(_, _, instant_event::Data::BuildGraphInfo(info)) => {
+                for entry in &info.critical_path2 {
+                    if let Some(critical_path_entry2::Entry::ActionExecution(action)) = &entry.entry
+                    {
+                        use critical_path_entry2::action_execution::Owner;
+                        let target = match &action.owner {
+                            Some(Owner::TargetLabel(t)) => {
+                                display::display_configured_target_label(t, opts)?
+                            }
+                            Some(Owner::BxlKey(t)) => display::display_bxl_key(t)?,
+                            Some(Owner::AnonTarget(t)) => display::display_anon_target(t)?,
+                            None => continue,
+                        };
+                        let (category, identifier) = match &action.name {
+                            Some(name) => (name.category.clone(), name.identifier.clone()),
+                            None => Default::default(),
+                        };
+                        self.critical_path.insert((target, category, identifier));
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Folded stacks of `target;category;phase`, weighted by microseconds.
+    fn flamegraph(&self) -> String {
+        let mut stacks = BTreeMap::<String, u128>::new();
+        for action in &self.actions {
+            let phases = action
+                .phases
+                .iter()
+                .map(|(phase, duration)| (phase.name(), *duration))
+                .chain([("other", action.other())]);
+            for (phase, duration) in phases {
+                if duration.is_zero() {
+                    continue;
+                }
+                let stack = format!("{};{};{}", action.target, action.category, phase);
+                *stacks.entry(stack).or_default() += duration.as_micros();
+            }
+        }
+
+        let mut out = String::new();
+        for (stack, micros) in stacks {
+            out.push_str(&format!("{} {}\n", stack, micros));
+        }
+        out
+    }
+
+    /// A Chrome trace with a process per target. Slices on a thread must nest, so actions of a
+    /// target that overlap in time are put on different threads, reusing a thread once its last
+    /// action finished. Every action has a slice for each of its phases. Commands don't report
+    /// when each phase started, so the phases are laid out one after the other from the start of
+    /// the action.
+    fn chrome_trace(&self) -> buck2_error::Result<String> {
+        let Some(first_start) = self.actions.iter().map(|a| a.start).min() else {
+            return Ok(serde_json::to_string(&json!({ "traceEvents": [] }))?);
+        };
+        let micros_since_start = |time: SystemTime| {
+            time.duration_since(first_start)
+                .unwrap_or_default()
+                .as_micros()
+        };
+
+        let mut pids = BTreeMap::new();
+        for action in &self.actions {
+            pids.entry(action.target.as_str()).or_insert(0);
+        }
+        for (i, pid) in pids.values_mut().enumerate() {
+            *pid = i + 1;
+        }
+
+        // For every target, when the last action on each of its threads ends.
+        let mut thread_ends = BTreeMap::<&str, Vec<SystemTime>>::new();
+        let mut tids = vec![0; self.actions.len()];
+        let mut by_start: Vec<usize> = (0..self.actions.len()).collect();
+        by_start.sort_by_key(|&i| self.actions[i].start);
+        for i in by_start {
+            let action = &self.actions[i];
+            let ends = thread_ends.entry(action.target.as_str()).or_default();
+            let end = action.start + action.wall_time;
+            let thread = match ends.iter().position(|&e| e <= action.start) {
+                Some(thread) => {
+                    ends[thread] = end;
+                    thread
+                }
+                None => {
+                    ends.push(end);
+                    ends.len() - 1
+                }
+            };
+            tids[i] = thread + 1;
+        }
+
+        let mut events = Vec::new();
+        for (target, pid) in &pids {
+            events.push(json!({
+                "name": "process_name",
+                "ph": "M",
+                "pid": pid,
+                "args": { "name": target },
+            }));
+            for tid in 1..=thread_ends[target].len() {
+                events.push(json!({
+                    "name": "thread_name",
+                    "ph": "M",
+                    "pid": pid,
+                    "tid": tid,
+                    "args": { "name": format!("actions {}", tid) },
+                }));
+            }
+        }
+
+        for (action, &tid) in self.actions.iter().zip(&tids) {
+            let pid = pids[action.target.as_str()];
+            let on_critical_path = self.critical_path.contains(&action.critical_path_key());
+            let start = micros_since_start(action.start);
+
+            let mut args = serde_json::Map::new();
+            for (phase, duration) in &action.phases {
+                args.insert(phase.name().to_owned(), json!(duration.as_micros()));
+            }
+            args.insert("other".to_owned(), json!(action.other().as_micros()));
+            args.insert("critical_path".to_owned(), json!(on_critical_path));
+
+            events.push(json!({
+                "name": action.name(),
+                "cat": if on_critical_path { "critical_path" } else { "action" },
+                "ph": "X",
+                "ts": start,
+                "dur": action.wall_time.as_micros(),
+                "pid": pid,
+                "tid": tid,
+                "args": args,
+            }));
+
+            let mut phase_start = start;
+            for phase in Phase::ALL {
+                let Some(duration) = action.phases.get(&phase) else {
+                    continue;
+                };
+                if duration.is_zero() {
+                    continue;
+                }
+                events.push(json!({
+                    "name": phase.name(),
+                    "cat": "phase",
+                    "ph": "X",
+                    "ts": phase_start,
+                    "dur": duration.as_micros(),
+                    "pid": pid,
+                    "tid": tid,
+                }));
+                phase_start += duration.as_micros();
+            }
+        }
+
+        Ok(serde_json::to_string(&json!({ "traceEvents": events }))?)
+    }
+}
+
+fn is_cache_hit(execution_kind: i32) -> bool {
+    use buck2_data::ActionExecutionKind;
+    matches!(
+        ActionExecutionKind::from_i32(execution_kind),
+        Some(
+            ActionExecutionKind::ActionCache
+                | ActionExecutionKind::RemoteDepFileCache
+                | ActionExecutionKind::LocalActionCache
+                | ActionExecutionKind::DiskActionCache
+                | ActionExecutionKind::LocalDepFile
+        )
+    )
+}
+
+fn to_duration(duration: &Option<prost_types::Duration>) -> Duration {
+    duration
+        .clone()
+        .and_then(|d| Duration::try_from(d).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action(target: &str, category: &str, phases: &[(Phase, u64)]) -> ActionProfile {
+        let phases: BTreeMap<_, _> = phases
+            .iter()
+            .map(|(phase, millis)| (*phase, Duration::from_millis(*millis)))
+            .collect();
+        ActionProfile {
+            target: target.to_owned(),
+            category: category.to_owned(),
+            identifier: String::new(),
+            start: SystemTime::UNIX_EPOCH,
+            wall_time: phases.values().sum::<Duration>() + Duration::from_millis(1),
+            phases,
+        }
+    }
+
+    #[test]
+    fn test_flamegraph_groups_by_target_and_category() {
+        let profile = ExecutionProfile {
+            actions: vec![
+                action(
+                    "root//:a",
+                    "cxx_compile",
+                    &[(Phase::Queue, 2), (Phase::Execution, 10)],
+                ),
+                action("root//:a", "cxx_compile", &[(Phase::Execution, 5)]),
+                action("root//:b", "cxx_link", &[(Phase::Upload, 3)]),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            "root//:a;cxx_compile;execution 15000\n\
+             root//:a;cxx_compile;other 2000\n\
+             root//:a;cxx_compile;queue 2000\n\
+             root//:b;cxx_link;other 1000\n\
+             root//:b;cxx_link;upload 3000\n",
+            profile.flamegraph()
+        );
+    }
+
+    #[test]
+    fn test_chrome_trace_marks_critical_path() {
+        let a = action("root//:a", "cxx_compile", &[(Phase::Execution, 10)]);
+        let profile = ExecutionProfile {
+            critical_path: HashSet::from([a.critical_path_key()]),
+            actions: vec![a],
+            ..Default::default()
+        };
+        let trace: serde_json::Value =
+            serde_json::from_str(&profile.chrome_trace().unwrap()).unwrap();
+        let actions: Vec<_> = trace["traceEvents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|e| e["ph"] == "X")
+            .collect();
+        assert_eq!(2, actions.len());
+        assert_eq!("critical_path", actions[0]["cat"]);
+        assert_eq!(11000, actions[0]["dur"]);
+        assert_eq!("execution", actions[1]["name"]);
+    }
+
+    #[test]
+    fn test_chrome_trace_puts_overlapping_actions_on_different_threads() {
+        let at = |millis| SystemTime::UNIX_EPOCH + Duration::from_millis(millis);
+        // Each action takes 11ms.
+        let profile = ExecutionProfile {
+            actions: vec![
+                ActionProfile {
+                    start: at(0),
+                    ..action("root//:a", "cxx_compile", &[(Phase::Execution, 10)])
+                },
+                ActionProfile {
+                    start: at(5),
+                    ..action("root//:a", "cxx_compile", &[(Phase::Execution, 10)])
+                },
+                ActionProfile {
+                    start: at(20),
+                    ..action("root//:a", "cxx_link", &[(Phase::Execution, 10)])
+                },
+                ActionProfile {
+                    start: at(5),
+                    ..action("root//:b", "cxx_compile", &[(Phase::Execution, 10)])
+                },
+            ],
+            ..Default::default()
+        };
+        let trace: serde_json::Value =
+            serde_json::from_str(&profile.chrome_trace().unwrap()).unwrap();
+        let threads: Vec<_> = trace["traceEvents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|e| e["ph"] == "X" && e["cat"] == "action")
+            .map(|e| (e["pid"].as_u64().unwrap(), e["tid"].as_u64().unwrap()))
+            .collect();
+        assert_eq!(vec![(1, 1), (1, 2), (1, 1), (2, 1)], threads);
+        let thread_names = trace["traceEvents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|e| e["name"] == "thread_name")
+            .count();
+        assert_eq!(3, thread_names);
+    }
+}
@@ -26,6 +26,9 @@ enum QueryOutputFormatArg {
     Json,
     DotCompact,
     Starlark,
+    Graphml,
+    Mermaid,
+    JsonGraph,
 }
 
 /// Args common to all the query commands
@@ -55,9 +58,12 @@ pub(crate) struct CommonQueryOptions {
            dot -  dot graph format. \n
            dot_compact - compact alternative to dot format. \n
            json - JSON format. \n
-           starlark - targets are printed like starlark code that would produce them.
+           starlark - targets are printed like starlark code that would produce them. \n
+           graphml - GraphML graph format, e.g. for yEd or Gephi. \n
+           mermaid - Mermaid flowchart, e.g. for markdown docs. \n
+           json_graph - JSON object with lists of nodes, with their attributes, and edges.
          ",
-        value_name = "dot|dot_compact|json|starlark",
+        value_name = "dot|dot_compact|json|starlark|graphml|mermaid|json_graph",
         value_enum
     )]
     output_format: Option<QueryOutputFormatArg>,
@@ -90,6 +96,9 @@ impl CommonQueryOptions {
             Some(QueryOutputFormatArg::Dot) => QueryOutputFormat::Dot,
             Some(QueryOutputFormatArg::DotCompact) => QueryOutputFormat::DotCompact,
             Some(QueryOutputFormatArg::Starlark) => QueryOutputFormat::Starlark,
+            Some(QueryOutputFormatArg::Graphml) => QueryOutputFormat::Graphml,
+            Some(QueryOutputFormatArg::Mermaid) => QueryOutputFormat::Mermaid,
+            Some(QueryOutputFormatArg::JsonGraph) => QueryOutputFormat::JsonGraph,
             None => {
                 if self.json {
                     QueryOutputFormat::Json
@@ -64,6 +64,11 @@ List the deps of a target (special characters in a target will
 require quotes):
 
 `buck2 cquery 'deps("//java/com/example/app:amazing+more")'`
+
+Explain why a binary depends on a library, with the attribute
+that declared each dependency on the way:
+
+`buck2 cquery 'somepath(//app:bin, //lib:util)' --explain-paths`
 "#
     )
 }
@@ -85,6 +90,16 @@ pub struct CqueryCommand {
     )]
     show_providers: bool,
 
+    #[clap(
+        long,
+        help = "Print the dependencies between the targets of the query result instead of the targets, \
+            with the attribute and the kind (target, exec, toolchain or configuration) of each \
+            dependency. Use with `somepath` or `allpaths` to explain why a target depends on another. \
+            Prints JSON with `--json`; other output formats are not supported",
+        conflicts_with = "show_providers"
+    )]
+    explain_paths: bool,
+
     #[clap(flatten)]
     target_cfg: TargetCfgWithUniverseOptions,
 
@@ -121,6 +136,7 @@ impl StreamingCommand for CqueryCommand {
                     target_universe: self.target_cfg.target_universe,
                     target_cfg: Some(self.target_cfg.target_cfg.target_cfg()),
                     show_providers: self.show_providers,
+                    explain_paths: self.explain_paths,
                     unstable_output_format,
                     profile_mode: self.profile_options.profile_mode_proto().map(|m| m as i32),
                     profile_output: self
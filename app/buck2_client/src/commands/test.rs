@@ -11,6 +11,7 @@ use async_trait::async_trait;
 use buck2_cli_proto::CounterWithExamples;
 use buck2_cli_proto::TestRequest;
 use buck2_cli_proto::TestSessionOptions;
+use buck2_cli_proto::TestSharding;
 use buck2_client_ctx::client_ctx::ClientCommandContext;
 use buck2_client_ctx::common::build::CommonBuildOptions;
 use buck2_client_ctx::common::target_cfg::TargetCfgOptions;
@@ -143,6 +144,28 @@ If include patterns are present, regardless of whether exclude patterns are pres
     #[clap(long)]
     test_executor_stdout: Option<OutputDestinationArg>,
 
+    /// Only run the tests in this shard, numbered from 0. Requires `--shard-count`.
+    ///
+    /// Tests are assigned to shards by a stable hash of their name, so that several machines
+    /// each running one shard together run every test exactly once.
+    #[clap(long, requires = "shard_count")]
+    shard_index: Option<u32>,
+
+    /// The number of shards to split the tests into. Requires `--shard-index`.
+    #[clap(long, requires = "shard_index")]
+    shard_count: Option<u32>,
+
+    /// A JSON file mapping test names to their duration in seconds, used to give every shard
+    /// about the same amount of work. Tests that are not in the file are assigned by hash.
+    #[clap(long, requires = "shard_index", value_name = "PATH")]
+    shard_timings: Option<PathArg>,
+
+    /// Shard the test cases of each target rather than the targets. This is done by the test
+    /// executor, which must be able to list test cases and accept the `--shard-index`,
+    /// `--shard-count` and `--shard-timings` arguments.
+    #[clap(long, requires = "shard_index")]
+    shard_test_cases: bool,
+
     /// Normally testing will follow the `tests` attribute of all targets, to find their associated tests.
     /// When passed, this flag will disable that, and only run the directly supplied targets.
     #[clap(long)]
@@ -227,6 +250,18 @@ impl StreamingCommand for TestCommand {
                         .transpose()
                         .buck_error_context("Invalid `timeout`")?,
                     ignore_tests_attribute: self.ignore_tests_attribute,
+                    sharding: self
+                        .shard_index
+                        .zip(self.shard_count)
+                        .map(|(index, count)| TestSharding {
+                            index,
+                            count,
+                            timings_path: self
+                                .shard_timings
+                                .as_ref()
+                                .map(|p| p.resolve(&ctx.working_dir).to_string()),
+                            by_test_case: self.shard_test_cases,
+                        }),
                 },
                 ctx.console_interaction_stream(&self.common_opts.console_opts),
                 &mut NoPartialResultHandler,
@@ -258,6 +293,10 @@ impl StreamingCommand for TestCommand {
             .skipped
             .as_ref()
             .buck_error_context("Missing `skipped`")?;
+        let flaky = statuses
+            .flaky
+            .as_ref()
+            .buck_error_context("Missing `flaky`")?;
 
         let console = self.common_opts.console_opts.final_console();
         print_build_result(&console, &response.errors)?;
@@ -292,13 +331,18 @@ impl StreamingCommand for TestCommand {
             line.push(column.to_span_from_test_statuses(statuses)?);
             line.push(Span::new_unstyled_lossy(". "));
         }
+        if flaky.count > 0 {
+            line.push(TestCounterColumn::FLAKY.to_span_from_test_statuses(statuses)?);
+            line.push(Span::new_unstyled_lossy(". "));
+        }
         line.push(span_from_build_failure_count(build_errors.len())?);
         eprint_line(&line)?;
 
         print_error_counter(&console, listing_failed, "LISTINGS FAILED", "⚠")?;
         print_error_counter(&console, failed, "TESTS FAILED", "✗")?;
         print_error_counter(&console, fatals, "TESTS FATALS", "⚠")?;
-        if passed.count + failed.count + fatals.count + skipped.count == 0 {
+        print_error_counter(&console, flaky, "TESTS FLAKY", "⚠")?;
+        if passed.count + failed.count + fatals.count + skipped.count + flaky.count == 0 {
             console.print_warning("NO TESTS RAN")?;
         }
 
@@ -138,6 +138,10 @@ pub enum SuperConsoleToggle {
     Commands,
     IncrLines,
     DecrLines,
+    ActionInspector,
+    NextAction,
+    PreviousAction,
+    ActionDetails,
     Help,
 }
 
@@ -154,6 +158,10 @@ impl SuperConsoleToggle {
             SuperConsoleToggle::Commands => "commands",
             SuperConsoleToggle::IncrLines => "more lines",
             SuperConsoleToggle::DecrLines => "less lines",
+            SuperConsoleToggle::ActionInspector => "action inspector",
+            SuperConsoleToggle::NextAction => "next action (in the action inspector)",
+            SuperConsoleToggle::PreviousAction => "previous action (in the action inspector)",
+            SuperConsoleToggle::ActionDetails => "action details (in the action inspector)",
             SuperConsoleToggle::Help => "help",
         }
     }
@@ -170,6 +178,10 @@ impl SuperConsoleToggle {
             SuperConsoleToggle::Commands => 'c',
             SuperConsoleToggle::IncrLines => '+',
             SuperConsoleToggle::DecrLines => '-',
+            SuperConsoleToggle::ActionInspector => 'a',
+            SuperConsoleToggle::NextAction => 'j',
+            SuperConsoleToggle::PreviousAction => 'k',
+            SuperConsoleToggle::ActionDetails => 'o',
             SuperConsoleToggle::Help => '?',
         }
     }
@@ -197,6 +209,10 @@ impl<'a> SuperConsoleInteraction for ConsoleInteractionStream<'a> {
                     'c' => Some(SuperConsoleToggle::Commands),
                     '+' => Some(SuperConsoleToggle::IncrLines),
                     '-' => Some(SuperConsoleToggle::DecrLines),
+                    'a' => Some(SuperConsoleToggle::ActionInspector),
+                    'j' => Some(SuperConsoleToggle::NextAction),
+                    'k' => Some(SuperConsoleToggle::PreviousAction),
+                    'o' => Some(SuperConsoleToggle::ActionDetails),
                     '?' | 'h' => Some(SuperConsoleToggle::Help),
                     _ => None,
                 };
@@ -12,6 +12,7 @@ use std::fmt::Debug;
 use std::io::Write;
 use std::sync::Arc;
 use std::time::Duration;
+use std::time::Instant;
 
 use async_trait::async_trait;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
@@ -60,6 +61,8 @@ use crate::subscribers::superconsole::debug_events::DebugEventsComponent;
 use crate::subscribers::superconsole::debugger::StarlarkDebuggerComponent;
 use crate::subscribers::superconsole::dice::DiceComponent;
 use crate::subscribers::superconsole::header::TasksHeader;
+use crate::subscribers::superconsole::inspector::ActionInspector;
+use crate::subscribers::superconsole::inspector::ActionInspectorComponent;
 use crate::subscribers::superconsole::io::IoHeader;
 use crate::subscribers::superconsole::re::ReHeader;
 use crate::subscribers::superconsole::session_info::SessionInfoComponent;
@@ -74,6 +77,7 @@ pub(crate) mod debug_events;
 mod debugger;
 pub(crate) mod dice;
 mod header;
+mod inspector;
 pub(crate) mod io;
 mod re;
 pub mod session_info;
@@ -132,6 +136,8 @@ pub struct SuperConsoleState {
     /// This contains the SpanTracker, which is why it's part of the SuperConsoleState.
     simple_console: SimpleConsole<DebugEventObserverExtra>,
     config: SuperConsoleConfig,
+    /// Replaces the list of running spans while it is open.
+    inspector: Option<ActionInspector>,
 }
 
 impl SuperConsoleState {
@@ -280,7 +286,12 @@ impl<'s> Component for BuckRootComponent<'s> {
             mode,
         )?;
         draw.draw(&TasksHeader::new(&self.header, self.state), mode)?;
-        draw.draw(&TimedList::new(&CUTOFFS, self.state), mode)?;
+        match &self.state.inspector {
+            Some(inspector) => {
+                draw.draw(&ActionInspectorComponent::new(inspector, self.state), mode)?
+            }
+            None => draw.draw(&TimedList::new(&CUTOFFS, self.state), mode)?,
+        }
 
         Ok(draw.finish())
     }
@@ -445,6 +456,7 @@ impl SuperConsoleState {
                 build_count_dir,
             ),
             config,
+            inspector: None,
         })
     }
 
@@ -452,6 +464,9 @@ impl SuperConsoleState {
         &mut self,
         event: &Arc<BuckEvent>,
     ) -> buck2_error::Result<()> {
+        if let Some(inspector) = &mut self.inspector {
+            inspector.handle_event(Instant::now(), event);
+        }
         self.simple_console.update_event_observer(event).await
     }
 
@@ -479,6 +494,28 @@ impl StatefulSuperConsoleImpl {
             .await
     }
 
+    /// Navigation keys of the action inspector do nothing while it is closed.
+    async fn with_inspector(
+        &mut self,
+        toggle: &SuperConsoleToggle,
+        f: impl FnOnce(&mut ActionInspector),
+    ) -> buck2_error::Result<()> {
+        match &mut self.state.inspector {
+            Some(inspector) => {
+                f(inspector);
+                Ok(())
+            }
+            None => {
+                self.handle_stderr(&format!(
+                    "`{}` only works in the action inspector, press `{}` to open it",
+                    toggle.key(),
+                    SuperConsoleToggle::ActionInspector.key()
+                ))
+                .await
+            }
+        }
+    }
+
     async fn handle_event(&mut self, event: &Arc<BuckEvent>) -> buck2_error::Result<()> {
         self.state.update_event_observer(event).await?;
 
@@ -761,6 +798,35 @@ impl StatefulSuperConsoleImpl {
                 SuperConsoleToggle::DecrLines { .. } => {
                     self.state.config.max_lines = self.state.config.max_lines.saturating_sub(1)
                 }
+                SuperConsoleToggle::ActionInspector => {
+                    self.state.inspector = match self.state.inspector {
+                        Some(_) => None,
+                        None => Some(ActionInspector::new(
+                            self.state.simple_console.observer().spans(),
+                        )),
+                    };
+                    let on_off = match self.state.inspector {
+                        Some(_) => "on",
+                        None => "off",
+                    };
+                    self.handle_stderr(&format!(
+                        "{}: {on_off}, press `{}` to revert",
+                        c.description(),
+                        c.key()
+                    ))
+                    .await?
+                }
+                SuperConsoleToggle::NextAction => {
+                    self.with_inspector(c, ActionInspector::select_next).await?
+                }
+                SuperConsoleToggle::PreviousAction => {
+                    self.with_inspector(c, ActionInspector::select_previous)
+                        .await?
+                }
+                SuperConsoleToggle::ActionDetails => {
+                    self.with_inspector(c, ActionInspector::toggle_details)
+                        .await?
+                }
                 SuperConsoleToggle::Help { .. } => {
                     let help_message = SuperConsoleToggle::iter()
                         .map(|t| format!("`{}` = toggle {}", t.key(), t.description()))
@@ -910,7 +976,7 @@ fn lines_for_command_details(
                     help_message.with(Color::DarkRed),
                 )]));
             }
-            Some(Command::OmittedLocalCommand(..)) | None => {
+            Some(Command::OmittedLocalCommand(..)) | Some(Command::DiskCacheCommand(..)) | None => {
                 // Nothing to show in this case.
             }
             Some(Command::WorkerInitCommand(worker_init_command)) => {
@@ -0,0 +1,692 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! The action inspector pauses the list of running actions, so that the user can move through
+//! them and open one to see its command line, executor, phases and stderr.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use buck2_event_observer::display;
+use buck2_event_observer::display::TargetDisplayOptions;
+use buck2_event_observer::fmt_duration;
+use buck2_event_observer::span_tracker::is_span_shown;
+use buck2_event_observer::span_tracker::BuckEventSpanHandle;
+use buck2_event_observer::span_tracker::BuckEventSpanTracker;
+use buck2_event_observer::what_ran::CommandReproducer;
+use buck2_event_observer::what_ran::WhatRanOptions;
+use buck2_events::span::SpanId;
+use buck2_events::BuckEvent;
+use dupe::Dupe;
+use superconsole::components::DetailPane;
+use superconsole::components::ScrollableList;
+use superconsole::style::Color;
+use superconsole::style::Stylize;
+use superconsole::Component;
+use superconsole::Dimensions;
+use superconsole::DrawMode;
+use superconsole::Line;
+use superconsole::Lines;
+use superconsole::Span;
+
+use crate::subscribers::superconsole::SuperConsoleState;
+
+/// How many of the last lines of stderr are shown in the details of an action.
+const STDERR_TAIL_LINES: usize = 10;
+
+/// How much of the stderr streamed by a running action is kept, enough for its tail.
+const MAX_LIVE_STDERR_BYTES: usize = 16 * 1024;
+
+/// The details of an action don't fit in the default number of lines of the list.
+const MIN_DETAILS_LINES: usize = 25;
+
+/// A span started by an action while it runs, e.g. an executor stage.
+struct Phase {
+    span_id: SpanId,
+    name: String,
+    start: Instant,
+    end: Option<Instant>,
+}
+
+struct InspectedAction {
+    event: Arc<BuckEvent>,
+    start: Instant,
+    phases: Vec<Phase>,
+    /// The last phase that ran a command, which tells the executor and the command line.
+    command: Option<Arc<BuckEvent>>,
+    /// The spans below the action, which the stderr of its commands is reported in.
+    descendants: Vec<SpanId>,
+    /// The tail of the stderr streamed by the commands of the action since the inspector was
+    /// opened.
+    live_stderr: String,
+    end: Option<(Instant, Arc<BuckEvent>)>,
+}
+
+impl InspectedAction {
+    fn new(span: &BuckEventSpanHandle) -> Self {
+        let info = span.info();
+        let mut action = Self {
+            event: info.event.dupe(),
+            start: info.start,
+            phases: Vec::new(),
+            command: None,
+            descendants: Vec::new(),
+            live_stderr: String::new(),
+            end: None,
+        };
+        for child in span.children() {
+            action.start_phase(&child.info().event, child.info().start);
+            action.add_descendants(&child);
+        }
+        action
+    }
+
+    fn add_descendants(&mut self, span: &BuckEventSpanHandle) {
+        if let Some(span_id) = span.info().event.span_id() {
+            self.descendants.push(span_id);
+        }
+        for child in span.children() {
+            self.add_descendants(&child);
+        }
+    }
+
+    fn push_stderr(&mut self, stderr: &str) {
+        self.live_stderr.push_str(stderr);
+        if self.live_stderr.len() > MAX_LIVE_STDERR_BYTES {
+            let mut cut = self.live_stderr.len() - MAX_LIVE_STDERR_BYTES;
+            while !self.live_stderr.is_char_boundary(cut) {
+                cut += 1;
+            }
+            self.live_stderr.drain(..cut);
+        }
+    }
+
+    fn start_phase(&mut self, event: &Arc<BuckEvent>, start: Instant) {
+        let Some(span_id) = event.span_id() else {
+            return;
+        };
+        if CommandReproducer::from_buck_data(event.data(), &WhatRanOptions::default()).is_some() {
+            self.command = Some(event.dupe());
+        }
+        self.phases.push(Phase {
+            span_id,
+            name: display::display_event(event, TargetDisplayOptions::for_console(false))
+                .unwrap_or_else(|_| "unknown".to_owned()),
+            start,
+            end: None,
+        });
+    }
+
+    fn action_end(&self) -> Option<&buck2_data::ActionExecutionEnd> {
+        let (_, event) = self.end.as_ref()?;
+        match event.span_end_event()?.data.as_ref()? {
+            buck2_data::span_end_event::Data::ActionExecution(end) => Some(end),
+            _ => None,
+        }
+    }
+
+    fn failed(&self) -> bool {
+        self.action_end()
+            .is_some_and(|end| end.failed || end.error.is_some())
+    }
+
+    fn status(&self, now: Instant) -> (&'static str, Color, std::time::Duration) {
+        match &self.end {
+            None => ("running", Color::Reset, now - self.start),
+            Some((end, _)) if self.failed() => ("failed", Color::DarkRed, *end - self.start),
+            Some((end, _)) => ("done", Color::DarkGreen, *end - self.start),
+        }
+    }
+
+    fn list_item(&self, now: Instant, speed: f64, display_platform: bool) -> anyhow::Result<Line> {
+        let (status, color, elapsed) = self.status(now);
+        let identity = display::display_event(
+            &self.event,
+            TargetDisplayOptions::for_console(display_platform),
+        )?;
+        Ok(Line::from_iter([
+            Span::new_styled_lossy(format!("{:<8}", status).with(color)),
+            Span::new_unstyled_lossy(format!(
+                "{:>7} ",
+                fmt_duration::fmt_duration(elapsed, speed)
+            )),
+            Span::sanitized(identity),
+        ]))
+    }
+
+    fn details(
+        &self,
+        now: Instant,
+        speed: f64,
+        display_platform: bool,
+        width: usize,
+    ) -> anyhow::Result<DetailPane> {
+        let title = Line::sanitized(&display::display_event(
+            &self.event,
+            TargetDisplayOptions::for_console(display_platform),
+        )?);
+        let mut body = Lines::new();
+
+        let command = self.command.as_ref().and_then(|event| {
+            CommandReproducer::from_buck_data(event.data(), &WhatRanOptions::default())
+        });
+        body.push(Line::sanitized(&format!(
+            "Executor: {}",
+            command.map_or_else(|| "not known yet".to_owned(), |c| c.executor())
+        )));
+
+        let (status, color, elapsed) = self.status(now);
+        let elapsed = fmt_duration::fmt_duration(elapsed, speed);
+        let status = match self.end {
+            None => format!("{} for {}", status, elapsed),
+            Some(_) => format!("{} after {}", status, elapsed),
+        };
+        body.push(Line::from_iter([
+            Span::new_unstyled_lossy("Status: "),
+            Span::new_styled_lossy(status.with(color)),
+        ]));
+
+        body.push(Line::sanitized("Phases:"));
+        if self.phases.is_empty() {
+            body.push(Line::sanitized("  none yet"));
+        }
+        for phase in &self.phases {
+            let (elapsed, running) = match phase.end {
+                Some(end) => (end - phase.start, ""),
+                None => (now - phase.start, " (running)"),
+            };
+            body.push(Line::sanitized(&format!(
+                "  {:<24} {:>7}{}",
+                phase.name,
+                fmt_duration::fmt_duration(elapsed, speed),
+                running
+            )));
+        }
+
+        body.push(Line::sanitized("Command:"));
+        match command {
+            Some(command) => body.extend(wrap(&command.as_human_readable().to_string(), width)),
+            None => body.push(Line::sanitized("  not known yet")),
+        }
+
+        match self.action_end() {
+            // The stderr of a running action is only known from what its commands streamed.
+            None => push_stderr_tail(
+                &mut body,
+                "Stderr (live)",
+                &display::sanitize_output_colors(self.live_stderr.as_bytes()),
+            ),
+            Some(end) => {
+                let stderr = end
+                    .commands
+                    .last()
+                    .and_then(|command| command.details.as_ref())
+                    .map(|details| display::sanitize_output_colors(details.stderr.as_bytes()))
+                    .unwrap_or_default();
+                push_stderr_tail(&mut body, "Stderr", &stderr);
+            }
+        }
+
+        Ok(DetailPane::new(title, body))
+    }
+}
+
+/// Add a header and the last `STDERR_TAIL_LINES` lines of `stderr` to `body`.
+fn push_stderr_tail(body: &mut Lines, header: &str, stderr: &str) {
+    let lines: Vec<&str> = stderr.lines().collect();
+    if lines.is_empty() {
+        body.push(Line::sanitized(&format!("{}: empty", header)));
+    } else if lines.len() <= STDERR_TAIL_LINES {
+        body.push(Line::sanitized(&format!("{}:", header)));
+    } else {
+        body.push(Line::sanitized(&format!(
+            "{} (last {} of {} lines):",
+            header,
+            STDERR_TAIL_LINES,
+            lines.len()
+        )));
+    }
+    for line in &lines[lines.len().saturating_sub(STDERR_TAIL_LINES)..] {
+        body.push(Line::sanitized(&format!(
+            "  {}",
+            line.replace('\t', "    ")
+        )));
+    }
+}
+
+/// Split `text` into indented lines of at most `width` characters, so that long command lines can
+/// be read in full.
+fn wrap(text: &str, width: usize) -> Vec<Line> {
+    let chars: Vec<char> = text.chars().collect();
+    chars
+        .chunks(width.saturating_sub(2).max(1))
+        .map(|chunk| Line::sanitized(&format!("  {}", chunk.iter().collect::<String>())))
+        .collect()
+}
+
+/// The actions that were running when the inspector was opened, and what happened to them since.
+pub(crate) struct ActionInspector {
+    /// Longest running first.
+    actions: Vec<InspectedAction>,
+    actions_by_span: HashMap<SpanId, usize>,
+    /// The action and the index of the phase of phase spans.
+    phases_by_span: HashMap<SpanId, (usize, usize)>,
+    /// The action of every span below an action.
+    descendants_by_span: HashMap<SpanId, usize>,
+    selected: usize,
+    details: bool,
+}
+
+impl ActionInspector {
+    pub(crate) fn new(spans: &BuckEventSpanTracker) -> Self {
+        fn collect_actions(span: &BuckEventSpanHandle, actions: &mut Vec<InspectedAction>) {
+            if let Some(buck2_data::span_start_event::Data::ActionExecution(_)) = span
+                .info()
+                .event
+                .span_start_event()
+                .and_then(|start| start.data.as_ref())
+            {
+                actions.push(InspectedAction::new(span));
+                return;
+            }
+            for child in span.children() {
+                collect_actions(&child, actions);
+            }
+        }
+
+        let mut actions = Vec::new();
+        for root in spans.iter_roots() {
+            collect_actions(&root, &mut actions);
+        }
+        actions.sort_by_key(|action| action.start);
+
+        let mut actions_by_span = HashMap::new();
+        let mut phases_by_span = HashMap::new();
+        let mut descendants_by_span = HashMap::new();
+        for (i, action) in actions.iter().enumerate() {
+            if let Some(span_id) = action.event.span_id() {
+                actions_by_span.insert(span_id, i);
+            }
+            for (j, phase) in action.phases.iter().enumerate() {
+                phases_by_span.insert(phase.span_id, (i, j));
+            }
+            for span_id in &action.descendants {
+                descendants_by_span.insert(*span_id, i);
+            }
+        }
+
+        Self {
+            actions,
+            actions_by_span,
+            phases_by_span,
+            descendants_by_span,
+            selected: 0,
+            details: false,
+        }
+    }
+
+    /// The inspected action that `span_id` is, or is below.
+    fn action_of_span(&self, span_id: SpanId) -> Option<usize> {
+        self.actions_by_span
+            .get(&span_id)
+            .or_else(|| self.descendants_by_span.get(&span_id))
+            .copied()
+    }
+
+    /// Records the phases, the stderr and the end of the inspected actions. New actions are not
+    /// added: the list stays paused until the inspector is reopened.
+    pub(crate) fn handle_event(&mut self, receive_time: Instant, event: &Arc<BuckEvent>) {
+        if let buck2_data::buck_event::Data::Instant(buck2_data::InstantEvent {
+            data: Some(buck2_data::instant_event::Data::CommandStderr(stderr)),
+        }) = event.data()
+        {
+            if let Some(action) = event
+                .parent_id()
+                .and_then(|parent_id| self.action_of_span(parent_id))
+            {
+                self.actions[action].push_stderr(&stderr.stderr);
+            }
+        } else if event.span_start_event().is_some() {
+            let Some(span_id) = event.span_id() else {
+                return;
+            };
+            let Some(parent_id) = event.parent_id() else {
+                return;
+            };
+            let Some(action) = self.action_of_span(parent_id) else {
+                return;
+            };
+            self.descendants_by_span.insert(span_id, action);
+            // Only the spans directly below the action are its phases.
+            if !self.actions_by_span.contains_key(&parent_id) || !is_span_shown(event) {
+                return;
+            }
+            self.phases_by_span
+                .insert(span_id, (action, self.actions[action].phases.len()));
+            self.actions[action].start_phase(event, receive_time);
+        } else if event.span_end_event().is_some() {
+            let Some(span_id) = event.span_id() else {
+                return;
+            };
+            if let Some((action, phase)) = self.phases_by_span.get(&span_id) {
+                self.actions[*action].phases[*phase].end = Some(receive_time);
+            } else if let Some(action) = self.actions_by_span.get(&span_id) {
+                self.actions[*action].end = Some((receive_time, event.dupe()));
+            }
+        }
+    }
+
+    pub(crate) fn select_next(&mut self) {
+        if self.selected + 1 < self.actions.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub(crate) fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub(crate) fn toggle_details(&mut self) {
+        self.details = !self.details;
+    }
+}
+
+/// Draws the inspector in place of the list of running spans.
+pub(crate) struct ActionInspectorComponent<'a> {
+    inspector: &'a ActionInspector,
+    state: &'a SuperConsoleState,
+}
+
+impl<'a> ActionInspectorComponent<'a> {
+    pub(crate) fn new(inspector: &'a ActionInspector, state: &'a SuperConsoleState) -> Self {
+        Self { inspector, state }
+    }
+}
+
+impl<'a> Component for ActionInspectorComponent<'a> {
+    fn draw_unchecked(&self, dimensions: Dimensions, mode: DrawMode) -> anyhow::Result<Lines> {
+        if mode == DrawMode::Final {
+            return Ok(Lines::new());
+        }
+
+        let now = Instant::now();
+        let speed = self.state.time_speed.speed();
+        let config = &self.state.config;
+        let selected = self.inspector.actions.get(self.inspector.selected);
+
+        let (header, body) = match selected {
+            Some(action) if self.inspector.details => {
+                let header = format!(
+                    "Action {} of {}: press `o` to go back to the list, `j`/`k` to move, `a` to close",
+                    self.inspector.selected + 1,
+                    self.inspector.actions.len()
+                );
+                let height = config.max_lines.max(MIN_DETAILS_LINES);
+                let body = action
+                    .details(now, speed, config.display_platform, dimensions.width)?
+                    .draw(
+                        Dimensions {
+                            width: dimensions.width,
+                            height,
+                        },
+                        mode,
+                    )?;
+                (header, body)
+            }
+            _ => {
+                let header = format!(
+                    "Action inspector, paused with {} actions: press `j`/`k` to move, `o` for details, `a` to close",
+                    self.inspector.actions.len()
+                );
+                let items = self
+                    .inspector
+                    .actions
+                    .iter()
+                    .map(|action| action.list_item(now, speed, config.display_platform))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                let body = ScrollableList::new(items, Some(self.inspector.selected)).draw(
+                    Dimensions {
+                        width: dimensions.width,
+                        height: config.max_lines,
+                    },
+                    mode,
+                )?;
+                (header, body)
+            }
+        };
+
+        let mut lines = Lines(vec![
+            Line::unstyled(&"-".repeat(dimensions.width))?,
+            Line::from_iter([Span::new_styled_lossy(header.italic())]),
+        ]);
+        lines.extend(body);
+        Ok(lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use std::time::UNIX_EPOCH;
+
+    use buck2_data::SpanEndEvent;
+    use buck2_data::SpanStartEvent;
+    use buck2_event_observer::verbosity::Verbosity;
+    use buck2_wrapper_common::invocation_id::TraceId;
+
+    use super::*;
+    use crate::subscribers::superconsole::SuperConsoleConfig;
+
+    fn event(
+        span_id: SpanId,
+        parent_id: Option<SpanId>,
+        data: buck2_data::buck_event::Data,
+    ) -> Arc<BuckEvent> {
+        Arc::new(BuckEvent::new(
+            UNIX_EPOCH,
+            TraceId::new(),
+            Some(span_id),
+            parent_id,
+            data,
+        ))
+    }
+
+    fn action_start(span_id: SpanId, name: &str) -> Arc<BuckEvent> {
+        event(
+            span_id,
+            None,
+            SpanStartEvent {
+                data: Some(
+                    buck2_data::ActionExecutionStart {
+                        key: Some(buck2_data::ActionKey {
+                            id: Default::default(),
+                            owner: Some(buck2_data::action_key::Owner::TargetLabel(
+                                buck2_data::ConfiguredTargetLabel {
+                                    label: Some(buck2_data::TargetLabel {
+                                        package: "pkg".into(),
+                                        name: name.into(),
+                                    }),
+                                    configuration: Some(buck2_data::Configuration {
+                                        full_name: "conf".into(),
+                                    }),
+                                    execution_configuration: None,
+                                },
+                            )),
+                            key: "".to_owned(),
+                        }),
+                        name: Some(buck2_data::ActionName {
+                            category: "cxx_compile".into(),
+                            identifier: "foo.c".into(),
+                        }),
+                        kind: buck2_data::ActionKind::NotSet as i32,
+                    }
+                    .into(),
+                ),
+            }
+            .into(),
+        )
+    }
+
+    fn local_execute_start(span_id: SpanId, parent_id: SpanId) -> Arc<BuckEvent> {
+        event(
+            span_id,
+            Some(parent_id),
+            SpanStartEvent {
+                data: Some(
+                    buck2_data::ExecutorStageStart {
+                        stage: Some(buck2_data::executor_stage_start::Stage::Local(
+                            buck2_data::LocalStage {
+                                stage: Some(buck2_data::local_stage::Stage::Execute(
+                                    buck2_data::LocalExecute {
+                                        command: Some(buck2_data::LocalCommand {
+                                            argv: vec![
+                                                "clang".to_owned(),
+                                                "-c".to_owned(),
+                                                "foo.c".to_owned(),
+                                            ],
+                                            env: Vec::new(),
+                                            action_digest: "".to_owned(),
+                                        }),
+                                    },
+                                )),
+                            },
+                        )),
+                    }
+                    .into(),
+                ),
+            }
+            .into(),
+        )
+    }
+
+    fn command_stderr(parent_id: SpanId, stderr: &str) -> Arc<BuckEvent> {
+        Arc::new(BuckEvent::new(
+            UNIX_EPOCH,
+            TraceId::new(),
+            None,
+            Some(parent_id),
+            buck2_data::InstantEvent {
+                data: Some(
+                    buck2_data::CommandStderr {
+                        stderr: stderr.to_owned(),
+                    }
+                    .into(),
+                ),
+            }
+            .into(),
+        ))
+    }
+
+    fn span_end(
+        span_id: SpanId,
+        parent_id: Option<SpanId>,
+        data: buck2_data::span_end_event::Data,
+    ) -> Arc<BuckEvent> {
+        event(
+            span_id,
+            parent_id,
+            SpanEndEvent {
+                data: Some(data),
+                ..Default::default()
+            }
+            .into(),
+        )
+    }
+
+    fn draw(inspector: &ActionInspector) -> anyhow::Result<String> {
+        let state = SuperConsoleState::new(
+            None,
+            TraceId::null(),
+            Verbosity::default(),
+            false,
+            SuperConsoleConfig::default(),
+            None,
+        )?;
+        let lines = ActionInspectorComponent::new(inspector, &state)
+            .draw(Dimensions::new(100, 40), DrawMode::Normal)?;
+        Ok(lines
+            .iter()
+            .map(|line| format!("{}\n", line.to_unstyled()))
+            .collect())
+    }
+
+    #[test]
+    fn test_inspect_action() -> anyhow::Result<()> {
+        let now = Instant::now();
+        let earlier = now - Duration::from_secs(2);
+
+        let slow = SpanId::next();
+        let fast = SpanId::next();
+        let stage = SpanId::next();
+        let mut spans = BuckEventSpanTracker::new();
+        spans.start_at(&action_start(slow, "slow"), earlier)?;
+        spans.start_at(&action_start(fast, "fast"), now)?;
+        spans.start_at(&local_execute_start(stage, slow), now)?;
+
+        let mut inspector = ActionInspector::new(&spans);
+        let list = draw(&inspector)?;
+        let slow_line = list.lines().position(|l| l.contains("pkg:slow")).unwrap();
+        let fast_line = list.lines().position(|l| l.contains("pkg:fast")).unwrap();
+        assert!(slow_line < fast_line, "{}", list);
+
+        // Actions started after the inspector was opened are not listed.
+        let started_later = SpanId::next();
+        inspector.handle_event(now, &action_start(started_later, "later"));
+        inspector.handle_event(
+            now,
+            &span_end(stage, Some(slow), buck2_data::ExecutorStageEnd {}.into()),
+        );
+        inspector.handle_event(
+            now,
+            &span_end(
+                slow,
+                None,
+                buck2_data::ActionExecutionEnd {
+                    commands: vec![buck2_data::CommandExecution {
+                        details: Some(buck2_data::CommandExecutionDetails {
+                            stderr: "foo.c:1: warning: unused variable\n".to_owned(),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }
+                .into(),
+            ),
+        );
+        assert!(!draw(&inspector)?.contains("pkg:later"));
+
+        inspector.toggle_details();
+        let details = draw(&inspector)?;
+        assert!(details.contains("Executor: local"), "{}", details);
+        assert!(details.contains("Status: done after"), "{}", details);
+        assert!(details.contains("local_execute"), "{}", details);
+        assert!(details.contains("clang -c foo.c"), "{}", details);
+        assert!(
+            details.contains("foo.c:1: warning: unused variable"),
+            "{}",
+            details
+        );
+
+        inspector.select_next();
+        let details = draw(&inspector)?;
+        assert!(details.contains("pkg:fast"), "{}", details);
+        assert!(details.contains("Stderr (live): empty"), "{}", details);
+
+        // The stderr of a running action is shown as its commands write it.
+        let fast_stage = SpanId::next();
+        inspector.handle_event(now, &local_execute_start(fast_stage, fast));
+        inspector.handle_event(now, &command_stderr(fast_stage, "compiling foo.c\n"));
+        let details = draw(&inspector)?;
+        assert!(details.contains("Stderr (live):"), "{}", details);
+        assert!(details.contains("compiling foo.c"), "{}", details);
+        Ok(())
+    }
+}
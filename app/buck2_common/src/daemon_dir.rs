@@ -37,4 +37,9 @@ impl DaemonDir {
     pub fn buckd_pid(&self) -> AbsNormPathBuf {
         self.path.join(FileName::new("buckd.pid").unwrap())
     }
+
+    /// Path to the state of the files persisted by the file watcher.
+    pub fn file_watcher_state(&self) -> AbsNormPathBuf {
+        self.path.join(FileName::new("file_watcher_state").unwrap())
+    }
 }
@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::time::Duration;
 
@@ -57,6 +58,14 @@ pub struct HttpConfig {
     write_timeout_ms: Option<u64>,
     pub http2: bool,
     pub max_redirects: Option<usize>,
+    /// The directory of the user-level download cache. A leading `~/` is expanded to the home
+    /// directory. The corresponding buckconfig is `http.download_cache_dir`.
+    download_cache_dir: Option<String>,
+    /// The corresponding buckconfig is `http.download_cache_max_size_bytes`.
+    pub download_cache_max_size_bytes: Option<u64>,
+    /// Only use the download cache, never the network.
+    /// The corresponding buckconfig is `http.download_cache_offline`.
+    pub download_cache_offline: bool,
 }
 
 impl HttpConfig {
@@ -83,6 +92,22 @@ impl HttpConfig {
                 property: "http2",
             })?
             .unwrap_or(true);
+        let download_cache_dir = config
+            .get(BuckconfigKeyRef {
+                section: "http",
+                property: "download_cache_dir",
+            })
+            .map(ToOwned::to_owned);
+        let download_cache_max_size_bytes = config.parse(BuckconfigKeyRef {
+            section: "http",
+            property: "download_cache_max_size_bytes",
+        })?;
+        let download_cache_offline = config
+            .parse(BuckconfigKeyRef {
+                section: "http",
+                property: "download_cache_offline",
+            })?
+            .unwrap_or(false);
 
         Ok(Self {
             connect_timeout_ms,
@@ -90,9 +115,25 @@ impl HttpConfig {
             write_timeout_ms,
             max_redirects,
             http2,
+            download_cache_dir,
+            download_cache_max_size_bytes,
+            download_cache_offline,
         })
     }
 
+    pub fn download_cache_dir(&self) -> buck2_error::Result<Option<PathBuf>> {
+        let Some(dir) = &self.download_cache_dir else {
+            return Ok(None);
+        };
+        let dir = match dir.strip_prefix("~/") {
+            Some(rest) => dirs::home_dir()
+                .buck_error_context("Expected a HOME directory to be available")?
+                .join(rest),
+            None => PathBuf::from(dir),
+        };
+        Ok(Some(dir))
+    }
+
     pub fn connect_timeout(&self) -> Timeout {
         match self.connect_timeout_ms.map(Duration::from_millis) {
             Some(Duration::ZERO) => Timeout::NoTimeout,
@@ -237,6 +278,16 @@ pub struct ResourceControlConfig {
     /// If provided and above the threshold, hybrid executor will stop scheduling local actions.
     /// The corresponding buckconfig is `buck2_resource_control.hybrid_execution_memory_limit_gibibytes`.
     pub hybrid_execution_memory_limit_gibibytes: Option<u64>,
+    /// Whether the forkserver runs each local action in its own cgroup, which applies the
+    /// memory and CPU limits from the action's executor config and reports its resource usage.
+    /// This is independent of `status` and requires cgroup v2. Linux only.
+    /// The corresponding buckconfig is `buck2_resource_control.per_action_cgroups`.
+    pub per_action_cgroups: bool,
+    /// Whether the forkserver may move the processes in its cgroup (itself, and normally the
+    /// daemon) into a `leaf` child cgroup, which it has to do to enable the memory and CPU
+    /// controllers for action cgroups if they aren't enabled already.
+    /// The corresponding buckconfig is `buck2_resource_control.per_action_cgroups_move_to_leaf`.
+    pub per_action_cgroups_move_to_leaf: bool,
 }
 
 #[derive(
@@ -302,11 +353,25 @@ impl ResourceControlConfig {
                 section: "buck2_resource_control",
                 property: "hybrid_execution_memory_limit_gibibytes",
             })?;
+            let per_action_cgroups = config
+                .parse(BuckconfigKeyRef {
+                    section: "buck2_resource_control",
+                    property: "per_action_cgroups",
+                })?
+                .unwrap_or(false);
+            let per_action_cgroups_move_to_leaf = config
+                .parse(BuckconfigKeyRef {
+                    section: "buck2_resource_control",
+                    property: "per_action_cgroups_move_to_leaf",
+                })?
+                .unwrap_or(false);
             Ok(Self {
                 status,
                 memory_max,
                 memory_max_per_action,
                 hybrid_execution_memory_limit_gibibytes,
+                per_action_cgroups,
+                per_action_cgroups_move_to_leaf,
             })
         }
     }
@@ -320,6 +385,68 @@ impl ResourceControlConfig {
     }
 }
 
+#[derive(
+    Allocative,
+    Clone,
+    Debug,
+    Default,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq
+)]
+pub struct SandboxConfig {
+    /// Whether local actions run by the forkserver are sandboxed so that they only see their
+    /// declared inputs within the project. Linux only.
+    /// The corresponding buckconfig is `buck2_sandbox.enabled`.
+    pub enabled: bool,
+    /// Project-relative directories that are exposed read-only to every sandboxed action, in
+    /// addition to its declared inputs. This is intended for checked-in toolchains.
+    /// The corresponding buckconfig is `buck2_sandbox.toolchain_dirs`.
+    pub toolchain_dirs: Vec<String>,
+    /// Whether the paths that sandboxed actions access are recorded, to report the hidden ones as
+    /// sandbox violations. This reports every path lookup of the action to the forkserver, which
+    /// slows down actions that do many of them. Linux 5.5 or later only.
+    /// The corresponding buckconfig is `buck2_sandbox.record_accesses`.
+    pub record_accesses: bool,
+}
+
+impl SandboxConfig {
+    pub fn from_config(config: &LegacyBuckConfig) -> buck2_error::Result<Self> {
+        let enabled = config
+            .parse(BuckconfigKeyRef {
+                section: "buck2_sandbox",
+                property: "enabled",
+            })?
+            .unwrap_or(false);
+        let toolchain_dirs = config
+            .parse_list(BuckconfigKeyRef {
+                section: "buck2_sandbox",
+                property: "toolchain_dirs",
+            })?
+            .unwrap_or_default();
+        let record_accesses = config
+            .parse(BuckconfigKeyRef {
+                section: "buck2_sandbox",
+                property: "record_accesses",
+            })?
+            .unwrap_or(false);
+        Ok(Self {
+            enabled,
+            toolchain_dirs,
+            record_accesses,
+        })
+    }
+
+    pub fn serialize(&self) -> buck2_error::Result<String> {
+        serde_json::to_string(&self).buck_error_context("Error serializing SandboxConfig")
+    }
+
+    pub fn deserialize(s: &str) -> anyhow::Result<Self> {
+        serde_json::from_str::<Self>(s).context("Error deserializing SandboxConfig")
+    }
+}
+
 /// Configurations that are used at startup by the daemon. Those are actually read by the client,
 /// and passed on to the daemon.
 ///
@@ -339,6 +466,7 @@ pub struct DaemonStartupConfig {
     pub materializations: Option<String>,
     pub http: HttpConfig,
     pub resource_control: ResourceControlConfig,
+    pub sandbox: SandboxConfig,
 }
 
 impl DaemonStartupConfig {
@@ -372,6 +500,7 @@ impl DaemonStartupConfig {
                 .map(ToOwned::to_owned),
             http: HttpConfig::from_config(config)?,
             resource_control: ResourceControlConfig::from_config(config)?,
+            sandbox: SandboxConfig::from_config(config)?,
         })
     }
 
@@ -392,6 +521,7 @@ impl DaemonStartupConfig {
             materializations: None,
             http: HttpConfig::default(),
             resource_control: ResourceControlConfig::default(),
+            sandbox: SandboxConfig::default(),
         }
     }
 }
@@ -157,8 +157,27 @@ impl InvocationPaths {
         FileName::unchecked_new("materializer_state")
     }
 
+    /// Subdirectory of `cache_dir` storing the results of locally executed actions
+    pub fn disk_action_cache_dir(&self) -> ProjectRelativePathBuf {
+        self.cache_dir().join(self.disk_action_cache_dir_name())
+    }
+
+    pub fn disk_action_cache_path(&self) -> AbsNormPathBuf {
+        self.roots
+            .project_root
+            .root()
+            .join(self.disk_action_cache_dir())
+    }
+
+    pub fn disk_action_cache_dir_name(&self) -> &FileName {
+        FileName::unchecked_new("disk_action_cache")
+    }
+
     pub fn valid_cache_dirs(&self) -> Vec<&FileName> {
-        vec![self.materializer_state_dir_name()]
+        vec![
+            self.materializer_state_dir_name(),
+            self.disk_action_cache_dir_name(),
+        ]
     }
 }
 
@@ -258,5 +277,15 @@ mod tests {
             paths.materializer_state_path().as_os_str(),
             OsStr::new(expected_path),
         );
+
+        let expected_path = if cfg!(windows) {
+            "C:\\my\\project\\buck-out\\isolation\\cache\\disk_action_cache"
+        } else {
+            "/my/project/buck-out/isolation/cache/disk_action_cache"
+        };
+        assert_eq!(
+            paths.disk_action_cache_path().as_os_str(),
+            OsStr::new(expected_path),
+        );
     }
 }
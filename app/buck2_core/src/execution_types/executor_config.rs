@@ -28,16 +28,27 @@ use static_interner::Interner;
 #[derive(Debug, Eq, Hash, PartialEq, Clone, Dupe, Allocative)]
 pub struct LocalExecutorOptions {
     pub use_persistent_workers: bool,
+    pub resource_limits: LocalResourceLimits,
 }
 
 impl Default for LocalExecutorOptions {
     fn default() -> Self {
         Self {
             use_persistent_workers: true,
+            resource_limits: LocalResourceLimits::default(),
         }
     }
 }
 
+/// Limits applied to each local action. These are only enforced when the forkserver runs actions
+/// in their own cgroup (`buck2_resource_control.per_action_cgroups`).
+#[derive(Debug, Default, Eq, Hash, PartialEq, Clone, Copy, Dupe, Allocative)]
+pub struct LocalResourceLimits {
+    pub memory_max_bytes: Option<u64>,
+    /// As a percentage of one CPU, so 200 allows the action to use two CPUs.
+    pub cpu_max_percent: Option<u64>,
+}
+
 #[derive(Debug, Eq, Hash, PartialEq, Clone, Allocative)]
 pub struct RemoteEnabledExecutorOptions {
     pub executor: RemoteEnabledExecutor,
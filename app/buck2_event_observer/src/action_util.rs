@@ -25,6 +25,9 @@ pub fn get_action_digest(commands: &[buck2_data::CommandExecution]) -> Option<St
                         buck2_data::command_execution_kind::Command::OmittedLocalCommand(
                             omitted_local_command,
                         ) => Some(omitted_local_command.action_digest.to_owned()),
+                        buck2_data::command_execution_kind::Command::DiskCacheCommand(
+                            disk_cache_command,
+                        ) => Some(disk_cache_command.action_digest.to_owned()),
                         _ => None,
                     };
                 }
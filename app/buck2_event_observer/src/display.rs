@@ -464,6 +464,10 @@ pub fn display_file_watcher_end(file_watcher_end: &buck2_data::FileWatcherEnd) -
                 comma(&mut msg).unwrap();
                 write!(&mut msg, "cleared dep files").unwrap();
             }
+            if fresh_instance.rescanned_files {
+                comma(&mut msg).unwrap();
+                write!(&mut msg, "rescanned files").unwrap();
+            }
             res.push(msg);
         }
     }
@@ -578,6 +582,7 @@ pub fn format_test_result(
         TestStatus::UNKNOWN => Span::new_styled("? Unknown".to_owned().cyan()),
         TestStatus::RERUN => Span::new_styled("↻ Rerun".to_owned().cyan()),
         TestStatus::LISTING_FAILED => Span::new_styled("⚠ Listing failed".to_owned().red()),
+        TestStatus::FLAKY => Span::new_styled("⚠ Flaky".to_owned().yellow()),
     }?;
     let mut base = Line::from_iter([prefix, Span::new_unstyled(format!(": {}", name,))?]);
     if let Some(duration) = duration {
@@ -686,7 +691,9 @@ impl<'a> ActionErrorDisplay<'a> {
                         );
                     }
                 }
-                Some(Command::OmittedLocalCommand(..)) | None => {
+                Some(Command::OmittedLocalCommand(..))
+                | Some(Command::DiskCacheCommand(..))
+                | None => {
                     // Nothing to show in this case.
                 }
             };
@@ -811,6 +818,7 @@ fn failure_reason_for_command_execution(
             Some(Command::LocalCommand(..)) | Some(Command::OmittedLocalCommand(..)) => "Local ",
             Some(Command::WorkerInitCommand(..)) => "Local Worker Initialization ",
             Some(Command::WorkerCommand(..)) => "Local Worker ",
+            Some(Command::DiskCacheCommand(..)) => "Disk Cache ",
             None => "",
         }
     } else {
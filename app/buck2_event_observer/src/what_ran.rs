@@ -127,6 +127,8 @@ impl Display for WhatRanOutputCommandHeader<'_, '_> {
 #[derive(Clone, Copy, Dupe)]
 pub enum WhatRanOutputCommandExtra<'a> {
     TestCases(&'a [String]),
+    /// The command failed in the sandbox after referencing undeclared inputs.
+    SandboxViolation(&'a str),
 }
 
 /// Output to log commands that ran. The expectation is that we can use this to print out events.
@@ -197,7 +199,7 @@ pub fn emit_what_ran_entry(
                 action.name.as_ref(),
                 TargetDisplayOptions::for_log(),
             )?),
-            None,
+            sandbox_violation(data).map(WhatRanOutputCommandExtra::SandboxViolation),
         ),
         Some(WhatRanRelevantAction::TestDiscovery(test)) => (
             "test.discovery",
@@ -257,6 +259,24 @@ pub fn emit_what_ran_entry(
     Ok(())
 }
 
+/// The error of an action whose last command failed after referencing paths hidden by the
+/// sandbox, if any.
+fn sandbox_violation(data: &Option<buck2_data::span_end_event::Data>) -> Option<&str> {
+    match data {
+        Some(buck2_data::span_end_event::Data::ActionExecution(action_exec)) => {
+            match action_exec.commands.last()?.status.as_ref()? {
+                buck2_data::command_execution::Status::Error(error)
+                    if error.stage == "sandbox_violation" =>
+                {
+                    Some(&error.error)
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
 /// The reproduction details for this command.
 #[derive(Clone, Copy, Dupe)]
 pub enum CommandReproducer<'a> {
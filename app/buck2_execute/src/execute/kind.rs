@@ -55,6 +55,9 @@ pub enum CommandExecutionKind {
     RemoteDepFileCache {
         details: RemoteCommandExecutionDetails,
     },
+    /// This action was served by the disk action cache and not executed.
+    #[display("disk_action_cache")]
+    DiskActionCache { digest: ActionDigest },
     /// This action would have executed via a local worker but failed during worker initialization.
     #[display("worker_init")]
     LocalWorkerInit {
@@ -81,6 +84,7 @@ impl CommandExecutionKind {
             Self::Remote { .. } => buck2_data::ActionExecutionKind::Remote,
             Self::ActionCache { .. } => buck2_data::ActionExecutionKind::ActionCache,
             Self::RemoteDepFileCache { .. } => buck2_data::ActionExecutionKind::RemoteDepFileCache,
+            Self::DiskActionCache { .. } => buck2_data::ActionExecutionKind::DiskActionCache,
         }
     }
 
@@ -153,6 +157,12 @@ impl CommandExecutionKind {
                 })
             }
 
+            Self::DiskActionCache { digest } => {
+                Command::DiskCacheCommand(buck2_data::DiskCacheCommand {
+                    action_digest: digest.to_string(),
+                })
+            }
+
             Self::LocalWorkerInit { command, env } => {
                 Command::WorkerInitCommand(buck2_data::WorkerInitCommand {
                     argv: command.to_owned(),
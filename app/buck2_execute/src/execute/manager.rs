@@ -259,6 +259,18 @@ pub trait CommandExecutionManagerExt: Sized {
         additional_message: Option<String>,
     ) -> CommandExecutionResult;
 
+    /// The command ran and failed, and we know more about why than its exit code tells. Unlike
+    /// `error`, this keeps the command's output.
+    fn failure_with_error(
+        self,
+        stage: &'static str,
+        error: impl Into<buck2_error::Error>,
+        execution_kind: CommandExecutionKind,
+        std_streams: CommandStdStreams,
+        exit_code: Option<i32>,
+        timing: CommandExecutionMetadata,
+    ) -> CommandExecutionResult;
+
     fn error(
         self,
         stage: &'static str,
@@ -319,6 +331,30 @@ where
         )
     }
 
+    fn failure_with_error(
+        self,
+        stage: &'static str,
+        error: impl Into<buck2_error::Error>,
+        execution_kind: CommandExecutionKind,
+        std_streams: CommandStdStreams,
+        exit_code: Option<i32>,
+        timing: CommandExecutionMetadata,
+    ) -> CommandExecutionResult {
+        self.result(
+            CommandExecutionStatus::Error {
+                stage,
+                error: error.into(),
+                execution_kind: Some(execution_kind),
+                typ: CommandExecutionErrorType::Other,
+            },
+            IndexMap::new(),
+            std_streams,
+            exit_code,
+            timing,
+            None,
+        )
+    }
+
     fn error_classified(
         self,
         stage: &'static str,
@@ -283,6 +283,31 @@ pub struct WorkerSpec {
     pub id: WorkerId,
     pub exe: Vec<String>,
     pub concurrency: Option<usize>,
+    pub protocol: WorkerProtocol,
+}
+
+/// How the daemon talks to a worker.
+#[derive(Copy, Clone, Dupe, Debug, PartialEq, Eq)]
+pub enum WorkerProtocol {
+    /// The gRPC service in `worker.proto`, over a Unix socket.
+    Buck2,
+    /// Bazel's persistent worker protocol: `WorkRequest`s on the worker's stdin, and
+    /// `WorkResponse`s on its stdout.
+    Bazel {
+        encoding: BazelWorkerEncoding,
+        /// Whether the worker accepts concurrent requests, told apart by their request ID.
+        multiplex: bool,
+        /// Whether a multiplex worker should be sent a cancel request for requests whose result
+        /// isn't needed anymore. Workers that don't declare support may treat one as a new
+        /// request.
+        supports_cancellation: bool,
+    },
+}
+
+#[derive(Copy, Clone, Dupe, Debug, PartialEq, Eq)]
+pub enum BazelWorkerEncoding {
+    Json,
+    Proto,
 }
 
 /// The data contains the information about the command to be executed.
@@ -406,6 +406,8 @@ mod tests {
                     time_running: 100,
                 }),
                 memory_peak: None,
+                cpu_time_us: None,
+                oom_killed: None,
             }),
             input_materialization_duration: Duration::from_secs(6),
             hashing_duration: Duration::from_secs(7),
@@ -458,6 +460,8 @@ mod tests {
                 time_running: 100,
             }),
             memory_peak: None,
+            cpu_time_us: None,
+            oom_killed: None,
         };
         let command_execution_metadata = buck2_data::CommandExecutionMetadata {
             wall_time: Some(Duration {
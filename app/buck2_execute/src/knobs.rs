@@ -17,4 +17,8 @@ pub struct ExecutorGlobalKnobs {
     /// Whether to emit action keys to execution logs (thos are pretty verbose and omitted by
     /// default).
     pub log_action_keys: bool,
+
+    /// Whether to stream the stderr of local commands while they run, for the action inspector
+    /// of the console.
+    pub stream_stderr: bool,
 }
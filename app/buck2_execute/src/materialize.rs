@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+pub(crate) mod download_cache;
 pub mod http;
 
 pub mod materializer;
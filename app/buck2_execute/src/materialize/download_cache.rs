@@ -0,0 +1,367 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A user-level, content-addressed cache of downloaded files.
+//!
+//! Entries are stored as `<dir>/<sha256 or sha1>/<checksum>`. They are only ever written after
+//! their checksum was verified, and are moved into place atomically, so every daemon of the user
+//! can share the cache. Entries are still verified when read back, and corrupted entries are
+//! dropped.
+//!
+//! Eviction walks the whole cache, so it runs in the background, shortly after entries are added.
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use buck2_common::cas_digest::CasDigestConfig;
+use buck2_common::file_ops::FileDigest;
+use buck2_error::BuckErrorContext;
+use buck2_http::DownloadCacheConfig;
+
+use crate::materialize::http::Checksum;
+use crate::materialize::http::ContentHasher;
+
+/// How long to wait after adding an entry before evicting, so that a build downloading many files
+/// evicts once rather than after every download.
+const EVICTION_DELAY: Duration = Duration::from_secs(10);
+
+/// Cache directories with an eviction scheduled by this process.
+static SCHEDULED_EVICTIONS: Mutex<Option<HashSet<PathBuf>>> = Mutex::new(None);
+
+#[derive(Debug, buck2_error::Error)]
+pub(crate) enum DownloadCacheError {
+    #[error(
+        "`{url}` is not in the download cache at `{dir}`, and downloads are disabled by `http.download_cache_offline`"
+    )]
+    #[buck2(input)]
+    OfflineMiss { url: String, dir: String },
+}
+
+pub(crate) struct DownloadCache<'a> {
+    config: &'a DownloadCacheConfig,
+}
+
+impl<'a> DownloadCache<'a> {
+    pub(crate) fn new(config: &'a DownloadCacheConfig) -> Self {
+        Self { config }
+    }
+
+    pub(crate) fn is_offline(&self) -> bool {
+        self.config.offline
+    }
+
+    pub(crate) fn offline_miss(&self, url: &str) -> buck2_error::Error {
+        DownloadCacheError::OfflineMiss {
+            url: url.to_owned(),
+            dir: self.config.dir.display().to_string(),
+        }
+        .into()
+    }
+
+    /// The paths an entry for `checksum` may be stored at, preferred first.
+    fn entry_paths(&self, checksum: &Checksum) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        if let Some(sha256) = checksum.sha256() {
+            paths.push(self.config.dir.join("sha256").join(sha256));
+        }
+        if let Some(sha1) = checksum.sha1() {
+            paths.push(self.config.dir.join("sha1").join(sha1));
+        }
+        paths
+    }
+
+    /// The size of the cached content for `checksum`, if it is in the cache.
+    pub(crate) fn size(&self, checksum: &Checksum) -> Option<u64> {
+        self.entry_paths(checksum)
+            .iter()
+            .find_map(|path| std::fs::metadata(path).ok())
+            .map(|metadata| metadata.len())
+    }
+
+    /// Copy the cached content for `checksum` to `dest`, and return its digest. Returns `None` if
+    /// the content is not in the cache.
+    pub(crate) fn fetch(
+        &self,
+        checksum: &Checksum,
+        dest: &Path,
+        digest_config: CasDigestConfig,
+    ) -> buck2_error::Result<Option<FileDigest>> {
+        for path in self.entry_paths(checksum) {
+            let mut src = match File::open(&path) {
+                Ok(src) => src,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => {
+                    return Err(buck2_error::Error::from(e)).with_buck_error_context(|| {
+                        format!("Error opening download cache entry `{}`", path.display())
+                    });
+                }
+            };
+
+            let mut hasher = ContentHasher::new(digest_config, checksum);
+            copy_hashing(&mut src, dest, &mut hasher).with_buck_error_context(|| {
+                format!(
+                    "Error copying download cache entry `{}` to `{}`",
+                    path.display(),
+                    dest.display()
+                )
+            })?;
+
+            match hasher.finalize() {
+                Ok(digest) => {
+                    // Used for eviction: entries that are used are kept.
+                    let _ignored = src.set_modified(SystemTime::now());
+                    return Ok(Some(digest));
+                }
+                Err(mismatch) => {
+                    tracing::warn!(
+                        "Dropping corrupted download cache entry `{}`: expected {} {}, got {}",
+                        path.display(),
+                        mismatch.kind,
+                        mismatch.expected,
+                        mismatch.obtained
+                    );
+                    let _ignored = std::fs::remove_file(&path);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Add the downloaded file at `src`, whose content matches `checksum`, to the cache. Must be
+    /// called from within a Tokio runtime if the cache has a maximum size, to schedule eviction.
+    pub(crate) fn store(&self, checksum: &Checksum, src: &Path) -> buck2_error::Result<()> {
+        if self.config.offline {
+            return Ok(());
+        }
+        let Some(entry) = self.entry_paths(checksum).into_iter().next() else {
+            return Ok(());
+        };
+        let dir = entry.parent().internal_error("Cache entry has no parent")?;
+        std::fs::create_dir_all(dir)?;
+
+        // Copy to a temporary file first, so that other daemons never see a partial entry. Its
+        // name starts with a dot, so eviction skips it.
+        let mut tmp = tempfile::NamedTempFile::new_in(dir)
+            .with_buck_error_context(|| format!("Error creating a file in `{}`", dir.display()))?;
+        std::io::copy(&mut File::open(src)?, tmp.as_file_mut())
+            .with_buck_error_context(|| format!("Error copying `{}`", src.display()))?;
+        tmp.persist(&entry)
+            .map_err(|e| buck2_error::Error::from(e.error))
+            .with_buck_error_context(|| format!("Error adding `{}`", entry.display()))?;
+
+        if let Some(max_size_bytes) = self.config.max_size_bytes {
+            schedule_eviction(self.config.dir.clone(), max_size_bytes, EVICTION_DELAY);
+        }
+        Ok(())
+    }
+}
+
+/// Evict entries from the cache at `dir` after `delay`, unless an eviction is already scheduled.
+/// Returns the task doing the eviction, if one was scheduled.
+fn schedule_eviction(
+    dir: PathBuf,
+    max_size_bytes: u64,
+    delay: Duration,
+) -> Option<tokio::task::JoinHandle<()>> {
+    if !SCHEDULED_EVICTIONS
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashSet::new)
+        .insert(dir.clone())
+    {
+        return None;
+    }
+    Some(tokio::spawn(async move {
+        tokio::time::sleep(delay).await;
+        // Entries added from now on need another eviction.
+        if let Some(scheduled) = SCHEDULED_EVICTIONS.lock().unwrap().as_mut() {
+            scheduled.remove(&dir);
+        }
+        let res = tokio::task::spawn_blocking(move || evict(&dir, max_size_bytes)).await;
+        match res {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => tracing::warn!("Error evicting from the download cache: {:#}", e),
+            Err(e) => tracing::warn!("Error evicting from the download cache: {:#}", e),
+        }
+    }))
+}
+
+/// Delete the least recently used entries until the cache at `dir` is no larger than
+/// `max_size_bytes`.
+fn evict(cache_dir: &Path, max_size_bytes: u64) -> buck2_error::Result<()> {
+    let mut entries = Vec::new();
+    for kind in ["sha256", "sha1"] {
+        let dir = match std::fs::read_dir(cache_dir.join(kind)) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        for entry in dir {
+            let entry = entry?;
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                // In-progress copy of another daemon.
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            entries.push((metadata.modified()?, metadata.len(), entry.path()));
+        }
+    }
+
+    let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+    entries.sort();
+    for (_, size, path) in entries {
+        if total <= max_size_bytes {
+            break;
+        }
+        match std::fs::remove_file(&path) {
+            Ok(()) => total -= size,
+            // Another daemon evicted it already.
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => total -= size,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+fn copy_hashing(
+    src: &mut File,
+    dest: &Path,
+    hasher: &mut ContentHasher<'_>,
+) -> buck2_error::Result<()> {
+    let mut dest = std::io::BufWriter::new(File::create(dest)?);
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = src.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        dest.write_all(&buffer[..read])?;
+        hasher.update(&buffer[..read]);
+    }
+    dest.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use buck2_common::cas_digest::testing;
+
+    use super::*;
+
+    const FOOBAR_SHA1: &str = "8843d7f92416211de9ebb963ff4ce28125932878";
+
+    fn cache_config(dir: &Path, max_size_bytes: Option<u64>) -> DownloadCacheConfig {
+        DownloadCacheConfig {
+            dir: dir.join("cache"),
+            max_size_bytes,
+            offline: false,
+        }
+    }
+
+    #[test]
+    fn test_store_and_fetch() -> buck2_error::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let config = cache_config(tempdir.path(), None);
+        let cache = DownloadCache::new(&config);
+        let checksum = Checksum::Sha1(Arc::from(FOOBAR_SHA1));
+
+        let downloaded = tempdir.path().join("downloaded");
+        std::fs::write(&downloaded, "foobar")?;
+        assert_eq!(None, cache.size(&checksum));
+        cache.store(&checksum, &downloaded)?;
+        assert_eq!(Some(6), cache.size(&checksum));
+
+        let dest = tempdir.path().join("dest");
+        let digest = cache.fetch(&checksum, &dest, testing::sha1())?;
+        assert_eq!(
+            Some(format!("{}:6", FOOBAR_SHA1)),
+            digest.map(|d| d.to_string())
+        );
+        assert_eq!("foobar", std::fs::read_to_string(&dest)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrupted_entry_is_dropped() -> buck2_error::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let config = cache_config(tempdir.path(), None);
+        let cache = DownloadCache::new(&config);
+        let checksum = Checksum::Sha1(Arc::from(FOOBAR_SHA1));
+
+        std::fs::create_dir_all(config.dir.join("sha1"))?;
+        std::fs::write(config.dir.join("sha1").join(FOOBAR_SHA1), "oops")?;
+
+        let dest = tempdir.path().join("dest");
+        assert!(cache.fetch(&checksum, &dest, testing::sha1())?.is_none());
+        assert_eq!(None, cache.size(&checksum));
+        Ok(())
+    }
+
+    #[test]
+    fn test_evict() -> buck2_error::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        // Without a maximum size, storing doesn't schedule an eviction.
+        let config = cache_config(tempdir.path(), None);
+        let cache = DownloadCache::new(&config);
+
+        let downloaded = tempdir.path().join("downloaded");
+        std::fs::write(&downloaded, "foobar")?;
+        let first = Checksum::Sha1(Arc::from("1".repeat(40)));
+        let second = Checksum::Sha1(Arc::from("2".repeat(40)));
+        cache.store(&first, &downloaded)?;
+        // Make sure the first entry is the least recently used one.
+        File::options()
+            .write(true)
+            .open(config.dir.join("sha1").join("1".repeat(40)))?
+            .set_modified(SystemTime::UNIX_EPOCH)?;
+        cache.store(&second, &downloaded)?;
+
+        evict(&config.dir, 10)?;
+        assert_eq!(None, cache.size(&first));
+        assert_eq!(Some(6), cache.size(&second));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_schedule_eviction() -> buck2_error::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let config = cache_config(tempdir.path(), None);
+        let cache = DownloadCache::new(&config);
+
+        let downloaded = tempdir.path().join("downloaded");
+        std::fs::write(&downloaded, "foobar")?;
+        let first = Checksum::Sha1(Arc::from("1".repeat(40)));
+        let second = Checksum::Sha1(Arc::from("2".repeat(40)));
+        cache.store(&first, &downloaded)?;
+        cache.store(&second, &downloaded)?;
+
+        let eviction = schedule_eviction(config.dir.clone(), 10, Duration::ZERO).unwrap();
+        // Only one eviction is scheduled at a time.
+        assert!(schedule_eviction(config.dir.clone(), 10, Duration::ZERO).is_none());
+        eviction.await.unwrap();
+
+        let remaining = [&first, &second]
+            .iter()
+            .filter_map(|checksum| cache.size(checksum))
+            .count();
+        assert_eq!(1, remaining);
+        // Once it ran, entries added later get their own eviction.
+        assert!(schedule_eviction(config.dir.clone(), 10, Duration::ZERO).is_some());
+        Ok(())
+    }
+}
@@ -15,9 +15,11 @@ use std::time::Duration;
 use allocative::Allocative;
 use buck2_common::cas_digest::CasDigestConfig;
 use buck2_common::cas_digest::DigestAlgorithmFamily;
+use buck2_common::cas_digest::Digester;
 use buck2_common::cas_digest::SHA1_SIZE;
 use buck2_common::cas_digest::SHA256_SIZE;
 use buck2_common::file_ops::FileDigest;
+use buck2_common::file_ops::FileDigestKind;
 use buck2_common::file_ops::TrackedFileDigest;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::project::ProjectRoot;
@@ -39,6 +41,7 @@ use sha2::Sha256;
 use smallvec::SmallVec;
 
 use crate::digest_config::DigestConfig;
+use crate::materialize::download_cache::DownloadCache;
 
 #[derive(Debug, Clone, Dupe, Allocative)]
 pub enum Checksum {
@@ -233,6 +236,13 @@ pub async fn http_head(client: &HttpClient, url: &str) -> buck2_error::Result<Re
     Ok(response)
 }
 
+/// The size of the download with `checksum`, if it is in the download cache of `client`.
+pub fn cached_download_size(client: &HttpClient, checksum: &Checksum) -> Option<u64> {
+    client
+        .download_cache()
+        .and_then(|config| DownloadCache::new(config).size(checksum))
+}
+
 pub async fn http_download(
     client: &HttpClient,
     fs: &ProjectRoot,
@@ -247,7 +257,27 @@ pub async fn http_download(
         fs_util::create_dir_all(dir)?;
     }
 
-    Ok(http_retry(
+    let download_cache = client.download_cache().map(DownloadCache::new);
+    if let Some(download_cache) = &download_cache {
+        if let Some(digest) = download_cache.fetch(
+            checksum,
+            abs_path.as_ref(),
+            digest_config.cas_digest_config(),
+        )? {
+            if executable {
+                fs.set_executable(path)?;
+            }
+            return Ok(TrackedFileDigest::new(
+                digest,
+                digest_config.cas_digest_config(),
+            ));
+        }
+        if download_cache.is_offline() {
+            return Err(download_cache.offline_miss(url));
+        }
+    }
+
+    let digest = http_retry(
         || async {
             let file = fs_util::create_file(&abs_path)
                 .map_err(|e| HttpDownloadError::IoError(buck2_error::Error::from(e)))?;
@@ -283,56 +313,122 @@ pub async fn http_download(
         vec![2, 4, 8].into_iter().map(Duration::from_secs).collect(),
     )
     .await
-    .map_err(|e| e.into_final())?)
-}
+    .map_err(|e| e.into_final())?;
 
-/// Copy a stream into a writer while producing its digest and checksumming it.
-async fn copy_and_hash(
-    url: &str,
-    abs_path: &(impl std::fmt::Display + ?Sized),
-    mut stream: impl Stream<Item = Result<Bytes, hyper::Error>> + Unpin,
-    mut writer: impl Write,
-    digest_config: CasDigestConfig,
-    checksum: &Checksum,
-    is_vpnless: bool,
-) -> Result<FileDigest, HttpDownloadError> {
-    let mut digester = FileDigest::digester(digest_config);
+    if let Some(download_cache) = &download_cache {
+        // The file was downloaded fine, so failing to cache it is not an error.
+        if let Err(e) = download_cache.store(checksum, abs_path.as_ref()) {
+            tracing::warn!("Error adding `{}` to the download cache: {:#}", url, e);
+        }
+    }
+
+    Ok(digest)
+}
 
+/// Produces the digest of some content while checking it against a `Checksum`.
+pub(crate) struct ContentHasher<'a> {
+    digester: Digester<FileDigestKind>,
     // For each checksum entry we have, we're going to add a validator. We might have to create
     // a new hasher, or reuse the `FileDigest::digester` if it matches.
+    validators: SmallVec<[(Validator, &'a str, &'static str); 2]>,
+}
 
-    enum Validator {
-        PrimaryDigest,
-        ExtraDigest(Box<dyn DynDigest + Send>),
-    }
+enum Validator {
+    PrimaryDigest,
+    ExtraDigest(Box<dyn DynDigest + Send>),
+}
 
-    let mut validators = SmallVec::<[_; 2]>::new();
+/// A checksum that did not match the content.
+pub(crate) struct ChecksumMismatch {
+    pub(crate) kind: &'static str,
+    pub(crate) expected: String,
+    pub(crate) obtained: String,
+}
 
-    if let Some(sha1) = checksum.sha1() {
-        let validator = if digester.algorithm() == DigestAlgorithmFamily::Sha1 {
-            Validator::PrimaryDigest
-        } else {
-            Validator::ExtraDigest(Box::new(Sha1::new()) as _)
-        };
+impl<'a> ContentHasher<'a> {
+    pub(crate) fn new(digest_config: CasDigestConfig, checksum: &'a Checksum) -> Self {
+        let digester = FileDigest::digester(digest_config);
+        let mut validators = SmallVec::new();
 
-        validators.push((validator, sha1, "sha1"));
+        if let Some(sha1) = checksum.sha1() {
+            let validator = if digester.algorithm() == DigestAlgorithmFamily::Sha1 {
+                Validator::PrimaryDigest
+            } else {
+                Validator::ExtraDigest(Box::new(Sha1::new()) as _)
+            };
+
+            validators.push((validator, sha1, "sha1"));
+        }
+
+        if let Some(sha256) = checksum.sha256() {
+            let validator = if digester.algorithm() == DigestAlgorithmFamily::Sha256 {
+                Validator::PrimaryDigest
+            } else {
+                Validator::ExtraDigest(Box::new(Sha256::new()) as _)
+            };
+
+            validators.push((validator, sha256, "sha256"));
+        }
+
+        Self {
+            digester,
+            validators,
+        }
     }
 
-    if let Some(sha256) = checksum.sha256() {
-        let validator = if digester.algorithm() == DigestAlgorithmFamily::Sha256 {
-            Validator::PrimaryDigest
-        } else {
-            Validator::ExtraDigest(Box::new(Sha256::new()) as _)
-        };
+    pub(crate) fn update(&mut self, chunk: &[u8]) {
+        self.digester.update(chunk);
+        for (validator, _expected, _kind) in self.validators.iter_mut() {
+            if let Validator::ExtraDigest(hasher) = validator {
+                hasher.update(chunk);
+            }
+        }
+    }
 
-        validators.push((validator, sha256, "sha256"));
+    pub(crate) fn bytes_read(&self) -> u64 {
+        self.digester.bytes_read()
     }
 
+    /// The digest of the content, or the first checksum that does not match it.
+    pub(crate) fn finalize(self) -> Result<FileDigest, ChecksumMismatch> {
+        let digest = self.digester.finalize();
+
+        for (validator, expected, kind) in self.validators {
+            let obtained = match validator {
+                Validator::PrimaryDigest => digest.raw_digest().to_string(),
+                Validator::ExtraDigest(hasher) => hex::encode(hasher.finalize()),
+            };
+
+            if expected != obtained {
+                return Err(ChecksumMismatch {
+                    kind,
+                    expected: expected.to_owned(),
+                    obtained,
+                });
+            }
+        }
+
+        Ok(digest)
+    }
+}
+
+/// Copy a stream into a writer while producing its digest and checksumming it.
+async fn copy_and_hash(
+    url: &str,
+    abs_path: &(impl std::fmt::Display + ?Sized),
+    mut stream: impl Stream<Item = Result<Bytes, hyper::Error>> + Unpin,
+    mut writer: impl Write,
+    digest_config: CasDigestConfig,
+    checksum: &Checksum,
+    is_vpnless: bool,
+) -> Result<FileDigest, HttpDownloadError> {
+    let mut hasher = ContentHasher::new(digest_config, checksum);
+
     let mut buff = DebugBuffer::new(512);
 
     while let Some(chunk) = stream.next().await {
         let chunk = chunk.map_err(|source| HttpError::Transfer {
-            received: digester.bytes_read(),
+            received: hasher.bytes_read(),
             url: url.to_owned(),
             source,
         })?;
@@ -344,28 +440,20 @@ async fn copy_and_hash(
             .with_buck_error_context(|| format!("write({})", abs_path))
             .map_err(HttpDownloadError::IoError)?;
 
-        digester.update(&chunk);
-        for (validator, _expected, _kind) in validators.iter_mut() {
-            if let Validator::ExtraDigest(hasher) = validator {
-                hasher.update(&chunk);
-            }
-        }
+        hasher.update(&chunk);
     }
     writer
         .flush()
         .with_buck_error_context(|| format!("flush({})", abs_path))
         .map_err(HttpDownloadError::IoError)?;
 
-    let digest = digester.finalize();
-
     // Validate
-    for (validator, expected, kind) in validators {
-        let obtained = match validator {
-            Validator::PrimaryDigest => digest.raw_digest().to_string(),
-            Validator::ExtraDigest(hasher) => hex::encode(hasher.finalize()),
-        };
-
-        if expected != obtained {
+    hasher.finalize().map_err(
+        |ChecksumMismatch {
+             kind,
+             expected,
+             obtained,
+         }| {
             let debug = MaybeDebugBufferAsUtf8 {
                 bytes_seen: buff.bytes_seen,
                 buff: buff.to_utf8().map(ToOwned::to_owned),
@@ -373,25 +461,24 @@ async fn copy_and_hash(
             };
 
             if is_vpnless {
-                return Err(HttpDownloadError::MaybeNotAllowedOnVpnless {
+                HttpDownloadError::MaybeNotAllowedOnVpnless {
                     kind,
-                    want: expected.to_owned(),
+                    want: expected,
                     got: obtained,
                     url: url.to_owned(),
                     debug,
-                });
+                }
+            } else {
+                HttpDownloadError::InvalidChecksum {
+                    digest_kind: kind,
+                    expected,
+                    obtained,
+                    url: url.to_owned(),
+                    debug,
+                }
             }
-            return Err(HttpDownloadError::InvalidChecksum {
-                digest_kind: kind,
-                expected: expected.to_owned(),
-                obtained,
-                url: url.to_owned(),
-                debug,
-            });
-        }
-    }
-
-    Ok(digest)
+        },
+    )
 }
 
 struct DebugBuffer {
@@ -223,6 +223,8 @@ fn convert_perf_counts(
             userspace_events: userspace_counter.map(|p| p.to_proto()),
             kernel_events: kernel_counter.map(|p| p.to_proto()),
             memory_peak: memory_stat.map(|m| m.max_used_mem as u64),
+            cpu_time_us: None,
+            oom_killed: None,
         }
     })
 }
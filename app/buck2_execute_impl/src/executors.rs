@@ -10,11 +10,14 @@
 pub mod action_cache;
 pub mod action_cache_upload_permission_checker;
 pub mod caching;
+pub mod disk_action_cache;
 pub(crate) mod empty_action_result;
 pub mod hybrid;
 pub mod local;
 pub mod local_actions_throttle;
+pub(crate) mod local_sandbox;
 pub mod re;
 pub mod stacked;
+pub(crate) mod stderr_events;
 pub mod to_re_platform;
 pub mod worker;
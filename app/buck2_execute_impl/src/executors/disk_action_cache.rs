@@ -0,0 +1,977 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! An action cache and blob store on local disk, used to avoid re-running locally executed
+//! actions when no remote cache is available.
+//!
+//! Entries follow the REv2 `ActionResult` model. The action cache (`ac/`) maps action digests to
+//! `DiskActionResult`s, which reference output file contents and directory trees by digest. Those
+//! are stored in the blob store (`cas/`). When the cache grows past its size limit, the least
+//! recently used entries and blobs are deleted. The blobs of an entry that is being restored are
+//! pinned with hard links, so that evicting them concurrently does not fail the restore.
+
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use allocative::Allocative;
+use async_trait::async_trait;
+use buck2_action_metadata_proto::DiskActionResult;
+use buck2_action_metadata_proto::DiskOutputDirectory;
+use buck2_action_metadata_proto::DiskOutputFile;
+use buck2_action_metadata_proto::DiskOutputSymlink;
+use buck2_common::file_ops::FileDigest;
+use buck2_common::file_ops::FileMetadata;
+use buck2_common::file_ops::TrackedFileDigest;
+use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::paths::RelativePathBuf;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_directory::directory::directory::Directory;
+use buck2_directory::directory::directory_iterator::DirectoryIterator;
+use buck2_directory::directory::entry::DirectoryEntry;
+use buck2_directory::directory::walk::unordered_entry_walk;
+use buck2_error::BuckErrorContext;
+use buck2_execute::artifact_value::ArtifactValue;
+use buck2_execute::digest_config::DigestConfig;
+use buck2_execute::directory::directory_to_re_tree;
+use buck2_execute::directory::extract_artifact_value;
+use buck2_execute::directory::re_tree_to_directory;
+use buck2_execute::directory::ActionDirectoryMember;
+use buck2_execute::directory::Symlink;
+use buck2_execute::execute::action_digest::ActionDigest;
+use buck2_execute::execute::action_digest_and_blobs::ActionDigestAndBlobs;
+use buck2_execute::execute::blocking::BlockingExecutor;
+use buck2_execute::execute::cache_uploader::CacheUploadInfo;
+use buck2_execute::execute::cache_uploader::CacheUploadResult;
+use buck2_execute::execute::cache_uploader::IntoRemoteDepFile;
+use buck2_execute::execute::cache_uploader::UploadCache;
+use buck2_execute::execute::kind::CommandExecutionKind;
+use buck2_execute::execute::manager::CommandExecutionManager;
+use buck2_execute::execute::manager::CommandExecutionManagerExt;
+use buck2_execute::execute::output::CommandStdStreams;
+use buck2_execute::execute::prepared::PreparedCommand;
+use buck2_execute::execute::prepared::PreparedCommandOptionalExecutor;
+use buck2_execute::execute::request::CommandExecutionOutput;
+use buck2_execute::execute::request::CommandExecutionRequest;
+use buck2_execute::execute::result::CommandExecutionMetadata;
+use buck2_execute::execute::result::CommandExecutionResult;
+use buck2_execute::materialize::materializer::Materializer;
+use buck2_futures::cancellation::CancellationContext;
+use chrono::DateTime;
+use chrono::Utc;
+use dupe::Dupe;
+use filetime::FileTime;
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+use prost::Message;
+use remote_execution as RE;
+
+use crate::executors::local::create_output_dirs;
+use crate::materializers::io::materialize_dirs_and_syms;
+
+/// When the cache exceeds its size limit, evict entries until it is below this fraction of it,
+/// so that we don't evict on every write.
+const EVICTION_TARGET_RATIO: f64 = 0.9;
+
+#[derive(buck2_error::Error, Debug)]
+enum DiskActionCacheError {
+    #[error("Invalid entry in disk action cache for action `{0}`")]
+    InvalidEntry(String),
+}
+
+/// The on-disk action cache and blob store. Cheap to clone.
+#[derive(Allocative, Dupe, Clone)]
+pub struct DiskActionCache {
+    inner: Arc<DiskActionCacheInner>,
+}
+
+#[derive(Allocative)]
+struct DiskActionCacheInner {
+    fs: ProjectRoot,
+    io: Arc<dyn BlockingExecutor>,
+    cache_path: ProjectRelativePathBuf,
+    max_bytes: u64,
+    /// Bytes currently used by the cache. Computed by scanning the cache on first write.
+    #[allocative(skip)]
+    used_bytes: Mutex<Option<u64>>,
+    #[allocative(skip)]
+    next_temp_file: AtomicU64,
+}
+
+/// A file output of an action, or a file in a directory output.
+struct CachedFile {
+    path: ProjectRelativePathBuf,
+    digest: TrackedFileDigest,
+    is_executable: bool,
+}
+
+/// Outputs of an action found in the cache, ready to be restored.
+struct DiskCacheHit {
+    result: DiskActionResult,
+    outputs: IndexMap<CommandExecutionOutput, ArtifactValue>,
+    pinned: PinnedBlobs,
+}
+
+/// Hard links to the blobs of a cache hit, which keep their contents around while the outputs
+/// are restored, even if the blobs are evicted meanwhile. Removed on drop.
+struct PinnedBlobs {
+    dir: AbsNormPathBuf,
+}
+
+impl PinnedBlobs {
+    fn path(&self, digest: &TrackedFileDigest) -> AbsNormPathBuf {
+        self.dir
+            .join(ForwardRelativePath::unchecked_new(&entry_name(
+                &digest.raw_digest().to_string(),
+                digest.size(),
+            )))
+    }
+
+    /// Link the blob at `blob` for `digest`. Returns `false` if the blob was evicted.
+    fn pin(&self, digest: &TrackedFileDigest, blob: &AbsNormPath) -> buck2_error::Result<bool> {
+        let path = self.path(digest);
+        if fs_util::symlink_metadata_if_exists(&path)?.is_some() {
+            return Ok(true);
+        }
+        match std::fs::hard_link(blob.as_path(), path.as_path()) {
+            Ok(()) => {
+                // Links share their modification time, so this marks the blob as used.
+                touch(&path)?;
+                Ok(true)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e).with_buck_error_context(|| format!("Error pinning `{}`", blob)),
+        }
+    }
+}
+
+impl Drop for PinnedBlobs {
+    fn drop(&mut self) {
+        if let Err(e) = fs_util::remove_all(&self.dir) {
+            tracing::warn!("Error removing pinned disk action cache blobs: {:#}", e);
+        }
+    }
+}
+
+impl DiskActionCache {
+    pub fn new(
+        fs: ProjectRoot,
+        io: Arc<dyn BlockingExecutor>,
+        cache_path: ProjectRelativePathBuf,
+        max_bytes: u64,
+    ) -> Self {
+        Self {
+            inner: Arc::new(DiskActionCacheInner {
+                fs,
+                io,
+                cache_path,
+                max_bytes,
+                used_bytes: Mutex::new(None),
+                next_temp_file: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Look up the outputs of an action. Entries whose blobs were evicted are treated as misses
+    /// and removed.
+    async fn lookup(
+        &self,
+        action_digest: &ActionDigest,
+        request: &CommandExecutionRequest,
+        digest_config: DigestConfig,
+    ) -> buck2_error::Result<Option<DiskCacheHit>> {
+        self.inner
+            .io
+            .execute_io_inline(|| self.inner.lookup(action_digest, request, digest_config))
+            .await
+    }
+
+    /// Store the outputs of a successful action, then evict old entries if the cache is over its
+    /// size limit.
+    async fn store(
+        &self,
+        action_digest: &ActionDigest,
+        result: DiskActionResult,
+        files: Vec<CachedFile>,
+        trees: Vec<(TrackedFileDigest, Vec<u8>)>,
+    ) -> buck2_error::Result<()> {
+        self.inner
+            .io
+            .execute_io_inline(|| {
+                let mut added = 0;
+                for file in &files {
+                    added += self
+                        .inner
+                        .put_blob_from_file(&file.digest, &self.inner.fs.resolve(&file.path))?;
+                }
+                for (digest, tree) in &trees {
+                    added += self.inner.put_blob(digest, tree)?;
+                }
+                added += self
+                    .inner
+                    .put_action_result(action_digest, &result.encode_to_vec())?;
+                self.inner.record_added_bytes(added)
+            })
+            .await
+    }
+}
+
+impl DiskActionCacheInner {
+    fn root(&self) -> AbsNormPathBuf {
+        self.fs.resolve(&self.cache_path)
+    }
+
+    fn action_result_dir(&self) -> AbsNormPathBuf {
+        self.root().join(ForwardRelativePath::unchecked_new("ac"))
+    }
+
+    fn blob_dir(&self) -> AbsNormPathBuf {
+        self.root().join(ForwardRelativePath::unchecked_new("cas"))
+    }
+
+    fn temp_dir(&self) -> AbsNormPathBuf {
+        self.root().join(ForwardRelativePath::unchecked_new("tmp"))
+    }
+
+    fn action_result_path(&self, digest: &ActionDigest) -> AbsNormPathBuf {
+        self.action_result_dir()
+            .join(ForwardRelativePath::unchecked_new(&entry_name(
+                &digest.raw_digest().to_string(),
+                digest.size(),
+            )))
+    }
+
+    fn blob_path(&self, digest: &TrackedFileDigest) -> AbsNormPathBuf {
+        let hash = digest.raw_digest().to_string();
+        self.blob_dir()
+            .join(ForwardRelativePath::unchecked_new(&hash[..2]))
+            .join(ForwardRelativePath::unchecked_new(&entry_name(
+                &hash,
+                digest.size(),
+            )))
+    }
+
+    fn lookup(
+        &self,
+        action_digest: &ActionDigest,
+        request: &CommandExecutionRequest,
+        digest_config: DigestConfig,
+    ) -> buck2_error::Result<Option<DiskCacheHit>> {
+        let entry_path = self.action_result_path(action_digest);
+        let result = match fs_util::read_if_exists(&entry_path)? {
+            Some(bytes) => DiskActionResult::decode(bytes.as_slice()).buck_error_context(
+                DiskActionCacheError::InvalidEntry(action_digest.to_string()),
+            )?,
+            None => return Ok(None),
+        };
+
+        let pinned = PinnedBlobs {
+            dir: self.temp_path("pinned"),
+        };
+        fs_util::create_dir_all(&pinned.dir)?;
+        match self.extract_outputs(&result, request, digest_config, &pinned)? {
+            Some(outputs) => {
+                touch(&entry_path)?;
+                Ok(Some(DiskCacheHit {
+                    result,
+                    outputs,
+                    pinned,
+                }))
+            }
+            None => {
+                // Some blobs were evicted, so this entry can't be used anymore.
+                fs_util::remove_file(&entry_path)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Compute the values of the outputs of an action from its cached result, the same way as
+    /// for results of the remote action cache, and pin the blobs of their files. Returns `None`
+    /// if some blobs are missing.
+    fn extract_outputs(
+        &self,
+        result: &DiskActionResult,
+        request: &CommandExecutionRequest,
+        digest_config: DigestConfig,
+        pinned: &PinnedBlobs,
+    ) -> buck2_error::Result<Option<IndexMap<CommandExecutionOutput, ArtifactValue>>> {
+        let cas_digest_config = digest_config.cas_digest_config();
+        let mut input_dir = request.paths().input_directory().clone().into_builder();
+
+        for file in &result.output_files {
+            let (digest, _) = FileDigest::parse_digest(&file.digest, cas_digest_config)?;
+            let entry = DirectoryEntry::Leaf(ActionDirectoryMember::File(FileMetadata {
+                digest: TrackedFileDigest::new(digest, cas_digest_config),
+                is_executable: file.is_executable,
+            }));
+            input_dir.insert(ForwardRelativePath::new(&file.path)?, entry)?;
+        }
+
+        for symlink in &result.output_symlinks {
+            let entry = DirectoryEntry::Leaf(ActionDirectoryMember::Symlink(Arc::new(
+                Symlink::new(RelativePathBuf::from_path(Path::new(&symlink.target))?),
+            )));
+            input_dir.insert(ForwardRelativePath::new(&symlink.path)?, entry)?;
+        }
+
+        for dir in &result.output_directories {
+            let (digest, _) = FileDigest::parse_digest(&dir.tree_digest, cas_digest_config)?;
+            let digest = TrackedFileDigest::new(digest, cas_digest_config);
+            let tree = match fs_util::read_if_exists(self.blob_path(&digest))? {
+                Some(bytes) => RE::Tree::decode(bytes.as_slice())
+                    .buck_error_context(DiskActionCacheError::InvalidEntry(dir.path.clone()))?,
+                None => return Ok(None),
+            };
+            let entry = re_tree_to_directory(&tree, &DateTime::<Utc>::default(), digest_config)?;
+            input_dir.insert(
+                ForwardRelativePath::new(&dir.path)?,
+                DirectoryEntry::Dir(entry),
+            )?;
+        }
+
+        let mut outputs = IndexMap::new();
+        for (requested, (path, _)) in request.outputs().zip(request.paths().output_paths()) {
+            if let Some(value) = extract_artifact_value(&input_dir, path, digest_config)? {
+                outputs.insert(requested.cloned(), value);
+            }
+        }
+
+        for value in outputs.values() {
+            for file in files_in_value(ProjectRelativePath::empty(), value) {
+                if !pinned.pin(&file.digest, &self.blob_path(&file.digest))? {
+                    return Ok(None);
+                }
+            }
+        }
+
+        Ok(Some(outputs))
+    }
+
+    fn put_action_result(&self, digest: &ActionDigest, bytes: &[u8]) -> buck2_error::Result<u64> {
+        self.write_atomically(&self.action_result_path(digest), |temp| {
+            fs_util::write(temp, bytes)?;
+            Ok(())
+        })?;
+        Ok(bytes.len() as u64)
+    }
+
+    /// Store a blob, returning how many bytes were added to the cache.
+    fn put_blob(&self, digest: &TrackedFileDigest, bytes: &[u8]) -> buck2_error::Result<u64> {
+        let path = self.blob_path(digest);
+        if fs_util::symlink_metadata_if_exists(&path)?.is_some() {
+            touch(&path)?;
+            return Ok(0);
+        }
+        self.write_atomically(&path, |temp| {
+            fs_util::write(temp, bytes)?;
+            Ok(())
+        })?;
+        Ok(digest.size())
+    }
+
+    fn put_blob_from_file(
+        &self,
+        digest: &TrackedFileDigest,
+        source: &AbsNormPath,
+    ) -> buck2_error::Result<u64> {
+        let path = self.blob_path(digest);
+        if fs_util::symlink_metadata_if_exists(&path)?.is_some() {
+            touch(&path)?;
+            return Ok(0);
+        }
+        self.write_atomically(&path, |temp| {
+            fs_util::copy(source, temp)?;
+            set_executable_bit(temp, false)
+        })?;
+        Ok(digest.size())
+    }
+
+    /// Write a file in the cache by writing it to a temporary path first, so that concurrent
+    /// readers never observe partially written files.
+    fn write_atomically(
+        &self,
+        path: &AbsNormPath,
+        write: impl FnOnce(&AbsNormPath) -> buck2_error::Result<()>,
+    ) -> buck2_error::Result<()> {
+        fs_util::create_dir_all(self.temp_dir())?;
+        let temp = self.temp_path("write");
+        write(&temp)?;
+        if let Some(parent) = path.parent() {
+            fs_util::create_dir_all(parent)?;
+        }
+        fs_util::rename(&temp, path)?;
+        Ok(())
+    }
+
+    /// A unique path in the temporary directory.
+    fn temp_path(&self, prefix: &str) -> AbsNormPathBuf {
+        self.temp_dir()
+            .join(ForwardRelativePath::unchecked_new(&format!(
+                "{}.{}.{}",
+                prefix,
+                std::process::id(),
+                self.next_temp_file.fetch_add(1, Ordering::Relaxed)
+            )))
+    }
+
+    fn record_added_bytes(&self, added: u64) -> buck2_error::Result<()> {
+        let mut used_bytes = self.used_bytes.lock();
+        let used = match *used_bytes {
+            Some(used) => used + added,
+            None => self.cache_files()?.iter().map(|f| f.size).sum(),
+        };
+        *used_bytes = Some(if used > self.max_bytes {
+            self.evict(used)?
+        } else {
+            used
+        });
+        Ok(())
+    }
+
+    /// Delete the least recently used files in the cache until it is below the eviction target.
+    /// Returns the number of bytes used afterwards.
+    fn evict(&self, mut used: u64) -> buck2_error::Result<u64> {
+        let target = (self.max_bytes as f64 * EVICTION_TARGET_RATIO) as u64;
+        let mut files = self.cache_files()?;
+        files.sort_by_key(|f| f.last_used);
+        let mut evicted = 0;
+        for file in files {
+            if used <= target {
+                break;
+            }
+            fs_util::remove_file(&file.path)?;
+            used = used.saturating_sub(file.size);
+            evicted += 1;
+        }
+        tracing::debug!(
+            "Evicted {} files from the disk action cache, {} bytes remain",
+            evicted,
+            used
+        );
+        Ok(used)
+    }
+
+    fn cache_files(&self) -> buck2_error::Result<Vec<CacheFile>> {
+        let mut files = Vec::new();
+        collect_cache_files(&self.action_result_dir(), &mut files)?;
+        if let Some(shards) = fs_util::read_dir_if_exists(self.blob_dir())? {
+            for shard in shards {
+                collect_cache_files(&shard?.path(), &mut files)?;
+            }
+        }
+        Ok(files)
+    }
+}
+
+struct CacheFile {
+    path: AbsNormPathBuf,
+    size: u64,
+    last_used: SystemTime,
+}
+
+fn collect_cache_files(dir: &AbsNormPath, out: &mut Vec<CacheFile>) -> buck2_error::Result<()> {
+    if let Some(entries) = fs_util::read_dir_if_exists(dir)? {
+        for entry in entries {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_file() {
+                out.push(CacheFile {
+                    path: entry.path(),
+                    size: metadata.len(),
+                    last_used: metadata.modified()?,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Name of the file storing the entry or blob with the given digest. This avoids `:` so that
+/// names are valid on all platforms.
+fn entry_name(hash: &str, size: u64) -> String {
+    format!("{}_{}", hash, size)
+}
+
+/// Mark a cache file as recently used, for eviction.
+fn touch(path: &AbsNormPath) -> buck2_error::Result<()> {
+    filetime::set_file_mtime(path.as_path(), FileTime::now())
+        .with_buck_error_context(|| format!("Error touching `{}`", path))?;
+    Ok(())
+}
+
+fn set_executable_bit(path: &AbsNormPath, is_executable: bool) -> buck2_error::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut perms = fs_util::metadata(path)?.permissions();
+        let mode = if is_executable {
+            perms.mode() | 0o111
+        } else {
+            perms.mode() & !0o111
+        };
+        perms.set_mode(mode);
+        fs_util::set_permissions(path, perms)?;
+    }
+    #[cfg(not(unix))]
+    {
+        // Nothing to do
+        let _ignore = (path, is_executable);
+    }
+
+    Ok(())
+}
+
+/// All the files in an output, with their paths relative to `base`.
+fn files_in_value(base: &ProjectRelativePath, value: &ArtifactValue) -> Vec<CachedFile> {
+    let mut files = Vec::new();
+    let mut walk = unordered_entry_walk(value.entry().as_ref().map_dir(Directory::as_ref));
+    while let Some((entry_path, entry)) = walk.next() {
+        if let DirectoryEntry::Leaf(ActionDirectoryMember::File(f)) = entry {
+            files.push(CachedFile {
+                path: base.join(entry_path.get()),
+                digest: f.digest.dupe(),
+                is_executable: f.is_executable,
+            });
+        }
+    }
+    files
+}
+
+/// Serves actions from the disk action cache, restoring their outputs from the blob store.
+pub struct DiskActionCacheChecker {
+    pub artifact_fs: ArtifactFs,
+    pub materializer: Arc<dyn Materializer>,
+    pub blocking_executor: Arc<dyn BlockingExecutor>,
+    pub cache: DiskActionCache,
+}
+
+impl DiskActionCacheChecker {
+    async fn restore_outputs(
+        &self,
+        request: &CommandExecutionRequest,
+        hit: &DiskCacheHit,
+        cancellations: &CancellationContext<'_>,
+    ) -> buck2_error::Result<()> {
+        create_output_dirs(
+            &self.artifact_fs,
+            request,
+            self.materializer.dupe(),
+            self.blocking_executor.dupe(),
+            cancellations,
+        )
+        .await?;
+
+        let fs = self.artifact_fs.fs();
+        let mut to_declare = Vec::new();
+        self.blocking_executor
+            .execute_io_inline(|| {
+                for (output, value) in &hit.outputs {
+                    let path = output.as_ref().resolve(&self.artifact_fs).into_path();
+                    materialize_dirs_and_syms(value.entry().as_ref(), fs.resolve(&path))?;
+                    for file in files_in_value(&path, value) {
+                        let dest = fs.resolve(&file.path);
+                        fs_util::copy(hit.pinned.path(&file.digest), &dest)?;
+                        set_executable_bit(&dest, file.is_executable)?;
+                    }
+                }
+                Ok(())
+            })
+            .await?;
+
+        for (output, value) in &hit.outputs {
+            match output {
+                CommandExecutionOutput::BuildArtifact { .. } => {
+                    let path = output.as_ref().resolve(&self.artifact_fs).into_path();
+                    to_declare.push((path, value.dupe()));
+                }
+                CommandExecutionOutput::TestPath { .. } => {
+                    // Not declared, like outputs of local execution.
+                }
+            }
+        }
+        self.materializer.declare_existing(to_declare).await
+    }
+}
+
+#[async_trait]
+impl PreparedCommandOptionalExecutor for DiskActionCacheChecker {
+    async fn maybe_execute(
+        &self,
+        command: &PreparedCommand<'_, '_>,
+        manager: CommandExecutionManager,
+        cancellations: &CancellationContext,
+    ) -> ControlFlow<CommandExecutionResult, CommandExecutionManager> {
+        let request = command.request;
+        // Actions that don't clean up their outputs may depend on previous outputs, which the
+        // action digest doesn't capture.
+        if !request.outputs_cleanup() || request.worker().is_some() {
+            return ControlFlow::Continue(manager);
+        }
+
+        let start_time = SystemTime::now();
+        let now = Instant::now();
+        let action_digest = &command.prepared_action.action_and_blobs.action;
+        let hit = match self
+            .cache
+            .lookup(action_digest, request, command.digest_config)
+            .await
+        {
+            Ok(Some(hit)) => hit,
+            Ok(None) => return ControlFlow::Continue(manager),
+            Err(e) => {
+                tracing::warn!("Error reading from the disk action cache: {:#}", e);
+                return ControlFlow::Continue(manager);
+            }
+        };
+
+        let execution_kind = CommandExecutionKind::DiskActionCache {
+            digest: action_digest.dupe(),
+        };
+        let manager = manager
+            .with_execution_kind(execution_kind.clone())
+            .claim()
+            .await;
+
+        // The blobs are pinned, so restoring can only fail for reasons that would fail running the
+        // action too.
+        let restored = cancellations
+            .critical_section(|| self.restore_outputs(request, &hit, cancellations))
+            .await;
+        if let Err(e) = restored {
+            return ControlFlow::Break(manager.error("disk_action_cache", e));
+        }
+
+        tracing::info!(
+            "Action result is cached on disk, skipping execution of:\n```\n$ {}\n```\n for action `{}`",
+            request.all_args_str(),
+            action_digest,
+        );
+
+        ControlFlow::Break(manager.success(
+            execution_kind,
+            hit.outputs,
+            CommandStdStreams::Local {
+                stdout: hit.result.stdout_raw,
+                stderr: hit.result.stderr_raw,
+            },
+            CommandExecutionMetadata {
+                wall_time: now.elapsed(),
+                execution_time: Duration::from_micros(hit.result.execution_time_us),
+                start_time,
+                ..Default::default()
+            },
+        ))
+    }
+}
+
+/// Stores the outputs of locally executed actions in the disk action cache.
+pub struct DiskCacheUploader {
+    pub artifact_fs: ArtifactFs,
+    pub cache: DiskActionCache,
+}
+
+impl DiskCacheUploader {
+    async fn store(
+        &self,
+        action_digest: &ActionDigest,
+        res: &CommandExecutionResult,
+        digest_config: DigestConfig,
+    ) -> buck2_error::Result<bool> {
+        let mut result = DiskActionResult::default();
+        let mut files = Vec::new();
+        let mut trees = Vec::new();
+
+        for (output, value) in res.resolve_outputs(&self.artifact_fs) {
+            let path = output.path();
+            match value.entry().as_ref() {
+                DirectoryEntry::Leaf(ActionDirectoryMember::File(f)) => {
+                    result.output_files.push(DiskOutputFile {
+                        path: path.to_string(),
+                        digest: f.digest.to_string(),
+                        is_executable: f.is_executable,
+                    });
+                }
+                DirectoryEntry::Leaf(ActionDirectoryMember::Symlink(s)) => {
+                    result.output_symlinks.push(DiskOutputSymlink {
+                        path: path.to_string(),
+                        target: s.target().to_string(),
+                    });
+                }
+                DirectoryEntry::Dir(d) => {
+                    let tree = directory_to_re_tree(d);
+                    let tree = tree.encode_to_vec();
+                    let tree_digest =
+                        TrackedFileDigest::from_content(&tree, digest_config.cas_digest_config());
+                    result.output_directories.push(DiskOutputDirectory {
+                        path: path.to_string(),
+                        tree_digest: tree_digest.to_string(),
+                    });
+                    trees.push((tree_digest, tree));
+                }
+                DirectoryEntry::Leaf(ActionDirectoryMember::ExternalSymlink(..)) => {
+                    // Can't be restored from the blob store.
+                    return Ok(false);
+                }
+            }
+
+            let mut walk = unordered_entry_walk(value.entry().as_ref().map_dir(Directory::as_ref));
+            while let Some((_, entry)) = walk.next() {
+                if let DirectoryEntry::Leaf(ActionDirectoryMember::ExternalSymlink(..)) = entry {
+                    return Ok(false);
+                }
+            }
+            files.extend(files_in_value(path, value));
+        }
+
+        let std_streams = res.report.std_streams.clone().into_bytes().await?;
+        result.stdout_raw = std_streams.stdout;
+        result.stderr_raw = std_streams.stderr;
+        result.execution_time_us = res.report.timing.execution_time.as_micros() as u64;
+
+        self.cache
+            .store(action_digest, result, files, trees)
+            .await?;
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl UploadCache for DiskCacheUploader {
+    async fn upload(
+        &self,
+        info: &CacheUploadInfo<'_>,
+        res: &CommandExecutionResult,
+        _re_result: Option<RE::TActionResult2>,
+        _dep_file_bundle: Option<&mut dyn IntoRemoteDepFile>,
+        action_digest_and_blobs: &ActionDigestAndBlobs,
+    ) -> buck2_error::Result<CacheUploadResult> {
+        let did_cache_upload = if res.was_locally_executed() {
+            match self
+                .store(&action_digest_and_blobs.action, res, info.digest_config)
+                .await
+            {
+                Ok(stored) => stored,
+                Err(e) => {
+                    // Failing to cache an action should not fail the build.
+                    tracing::warn!("Error writing to the disk action cache: {:#}", e);
+                    false
+                }
+            }
+        } else {
+            false
+        };
+        Ok(CacheUploadResult {
+            did_cache_upload,
+            did_dep_file_cache_upload: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::cells::cell_root_path::CellRootPathBuf;
+    use buck2_core::cells::name::CellName;
+    use buck2_core::cells::CellResolver;
+    use buck2_core::fs::buck_out_path::BuckOutPathResolver;
+    use buck2_core::fs::buck_out_path::BuckOutTestPath;
+    use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+    use buck2_core::fs::project::ProjectRootTemp;
+    use buck2_execute::execute::blocking::testing::DummyBlockingExecutor;
+    use buck2_execute::execute::request::CommandExecutionPaths;
+    use buck2_execute::execute::request::OutputCreationBehavior;
+    use indexmap::indexset;
+
+    use super::*;
+
+    fn artifact_fs(project_fs: ProjectRoot) -> ArtifactFs {
+        ArtifactFs::new(
+            CellResolver::testing_with_name_and_path(
+                CellName::testing_new("cell"),
+                CellRootPathBuf::new(ProjectRelativePathBuf::unchecked_new("cell_path".into())),
+            ),
+            BuckOutPathResolver::new(ProjectRelativePathBuf::unchecked_new("buck_out/v2".into())),
+            project_fs,
+        )
+    }
+
+    fn cache(fs: &ProjectRoot, max_bytes: u64) -> DiskActionCache {
+        DiskActionCache::new(
+            fs.dupe(),
+            Arc::new(DummyBlockingExecutor { fs: fs.dupe() }),
+            ProjectRelativePathBuf::unchecked_new("cache".into()),
+            max_bytes,
+        )
+    }
+
+    fn request(
+        artifact_fs: &ArtifactFs,
+        name: &str,
+    ) -> buck2_error::Result<CommandExecutionRequest> {
+        let output = CommandExecutionOutput::TestPath {
+            path: BuckOutTestPath::new(
+                ForwardRelativePathBuf::unchecked_new("test".to_owned()),
+                ForwardRelativePathBuf::unchecked_new(name.to_owned()),
+            ),
+            create: OutputCreationBehavior::Parent,
+        };
+        let paths = CommandExecutionPaths::new(
+            Vec::new(),
+            indexset![output],
+            artifact_fs,
+            DigestConfig::testing_default(),
+        )?;
+        Ok(CommandExecutionRequest::new(
+            Vec::new(),
+            Vec::new(),
+            paths,
+            Default::default(),
+        ))
+    }
+
+    fn digest(contents: &str) -> TrackedFileDigest {
+        TrackedFileDigest::from_content(
+            contents.as_bytes(),
+            DigestConfig::testing_default().cas_digest_config(),
+        )
+    }
+
+    /// Write the output of `request` and store it as the result of `action`.
+    async fn store(
+        cache: &DiskActionCache,
+        action: &ActionDigest,
+        request: &CommandExecutionRequest,
+        contents: &str,
+    ) -> buck2_error::Result<()> {
+        let path = request.paths().output_paths()[0].0.clone();
+        cache.inner.fs.write_file(&path, contents, false)?;
+        let result = DiskActionResult {
+            output_files: vec![DiskOutputFile {
+                path: path.to_string(),
+                digest: digest(contents).to_string(),
+                is_executable: false,
+            }],
+            ..Default::default()
+        };
+        let files = vec![CachedFile {
+            path,
+            digest: digest(contents),
+            is_executable: false,
+        }];
+        cache.store(action, result, files, Vec::new()).await
+    }
+
+    async fn lookup(
+        cache: &DiskActionCache,
+        action: &ActionDigest,
+        request: &CommandExecutionRequest,
+    ) -> buck2_error::Result<Option<DiskCacheHit>> {
+        cache
+            .lookup(action, request, DigestConfig::testing_default())
+            .await
+    }
+
+    #[test]
+    fn test_entry_name() {
+        assert_eq!(
+            "0000000000000000000000000000000000000000_17",
+            entry_name("0000000000000000000000000000000000000000", 17)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_store_and_lookup() -> buck2_error::Result<()> {
+        let temp = ProjectRootTemp::new()?;
+        let artifact_fs = artifact_fs(temp.path().dupe());
+        let cache = cache(temp.path(), u64::MAX);
+        let action = ActionDigest::new_sha1([1; 20], 10);
+        let other_action = ActionDigest::new_sha1([2; 20], 10);
+        let request = request(&artifact_fs, "out")?;
+
+        assert!(lookup(&cache, &action, &request).await?.is_none());
+        store(&cache, &action, &request, "hello").await?;
+
+        let hit = lookup(&cache, &action, &request).await?.unwrap();
+        assert_eq!(1, hit.outputs.len());
+        assert_eq!(
+            "hello",
+            fs_util::read_to_string(hit.pinned.path(&digest("hello")))?
+        );
+        assert!(lookup(&cache, &other_action, &request).await?.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pinned_blobs_survive_eviction() -> buck2_error::Result<()> {
+        let temp = ProjectRootTemp::new()?;
+        let artifact_fs = artifact_fs(temp.path().dupe());
+        let cache = cache(temp.path(), u64::MAX);
+        let action = ActionDigest::new_sha1([1; 20], 10);
+        let request = request(&artifact_fs, "out")?;
+        store(&cache, &action, &request, "hello").await?;
+
+        let hit = lookup(&cache, &action, &request).await?.unwrap();
+        fs_util::remove_file(cache.inner.blob_path(&digest("hello")))?;
+        let pinned = hit.pinned.path(&digest("hello"));
+        assert_eq!("hello", fs_util::read_to_string(&pinned)?);
+
+        drop(hit);
+        assert!(fs_util::symlink_metadata_if_exists(&pinned)?.is_none());
+        // The entry is now a miss, and is removed.
+        assert!(lookup(&cache, &action, &request).await?.is_none());
+        assert!(
+            fs_util::symlink_metadata_if_exists(cache.inner.action_result_path(&action))?.is_none()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_evicts_least_recently_used() -> buck2_error::Result<()> {
+        let temp = ProjectRootTemp::new()?;
+        let artifact_fs = artifact_fs(temp.path().dupe());
+        let old_action = ActionDigest::new_sha1([1; 20], 10);
+        let new_action = ActionDigest::new_sha1([2; 20], 10);
+        let old_request = request(&artifact_fs, "old")?;
+        let new_request = request(&artifact_fs, "new")?;
+
+        store(
+            &cache(temp.path(), u64::MAX),
+            &old_action,
+            &old_request,
+            "aaaa",
+        )
+        .await?;
+        let cache = cache(temp.path(), u64::MAX);
+        let files = cache.inner.cache_files()?;
+        let entry_bytes: u64 = files.iter().map(|f| f.size).sum();
+        for file in &files {
+            filetime::set_file_mtime(file.path.as_path(), FileTime::from_unix_time(0, 0))?;
+        }
+
+        // Both entries don't fit, but one does.
+        let cache = self::cache(temp.path(), entry_bytes * 3 / 2);
+        store(&cache, &new_action, &new_request, "bbbb").await?;
+
+        assert!(lookup(&cache, &old_action, &old_request).await?.is_none());
+        assert!(lookup(&cache, &new_action, &new_request).await?.is_some());
+        Ok(())
+    }
+}
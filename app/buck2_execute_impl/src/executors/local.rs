@@ -12,6 +12,7 @@ use std::ffi::OsString;
 use std::ops::ControlFlow;
 use std::process::Command;
 use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Duration;
 use std::time::Instant;
 use std::time::SystemTime;
@@ -21,6 +22,7 @@ use buck2_common::file_ops::FileDigestConfig;
 use buck2_common::liveliness_observer::LivelinessObserver;
 use buck2_common::liveliness_observer::LivelinessObserverExt;
 use buck2_common::local_resource_state::LocalResourceHolder;
+use buck2_core::execution_types::executor_config::LocalResourceLimits;
 use buck2_core::fs::artifact_path_resolver::ArtifactFs;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
@@ -63,7 +65,7 @@ use buck2_execute::knobs::ExecutorGlobalKnobs;
 use buck2_execute::materialize::materializer::MaterializationError;
 use buck2_execute::materialize::materializer::Materializer;
 use buck2_forkserver::client::ForkserverClient;
-use buck2_forkserver::run::gather_output;
+use buck2_forkserver::run::gather_output_streaming_stderr;
 use buck2_forkserver::run::maybe_absolutize_exe;
 use buck2_forkserver::run::timeout_into_cancellation;
 use buck2_forkserver::run::GatherOutputStatus;
@@ -83,6 +85,10 @@ use host_sharing::HostSharingRequirements;
 use indexmap::IndexMap;
 use tracing::info;
 
+use crate::executors::local_sandbox::SandboxPaths;
+use crate::executors::local_sandbox::SandboxViolation;
+use crate::executors::stderr_events::send_stderr_events;
+use crate::executors::stderr_events::StderrTail;
 use crate::executors::worker::WorkerHandle;
 use crate::executors::worker::WorkerPool;
 
@@ -96,6 +102,20 @@ enum LocalExecutionError {
     RemoteOnlyAction,
 }
 
+#[derive(Debug, buck2_error::Error)]
+#[buck2(input, tag = ActionOomKilled)]
+#[error("Command was killed by the OOM killer{0}")]
+struct CommandOomKilled(String);
+
+impl CommandOomKilled {
+    fn new(memory_max_bytes: Option<u64>) -> Self {
+        Self(match memory_max_bytes {
+            Some(bytes) => format!(" after reaching its memory limit of {} bytes", bytes),
+            None => String::new(),
+        })
+    }
+}
+
 #[derive(Clone)]
 pub struct LocalExecutor {
     artifact_fs: ArtifactFs,
@@ -109,6 +129,8 @@ pub struct LocalExecutor {
     knobs: ExecutorGlobalKnobs,
     #[allow(unused)]
     worker_pool: Option<Arc<WorkerPool>>,
+    #[cfg_attr(not(unix), allow(unused))]
+    resource_limits: LocalResourceLimits,
 }
 
 impl LocalExecutor {
@@ -121,6 +143,7 @@ impl LocalExecutor {
         forkserver: Option<ForkserverClient>,
         knobs: ExecutorGlobalKnobs,
         worker_pool: Option<Arc<WorkerPool>>,
+        resource_limits: LocalResourceLimits,
     ) -> Self {
         Self {
             artifact_fs,
@@ -131,6 +154,7 @@ impl LocalExecutor {
             forkserver,
             knobs,
             worker_pool,
+            resource_limits,
         }
     }
 
@@ -147,6 +171,7 @@ impl LocalExecutor {
         liveliness_observer: impl LivelinessObserver + 'static,
         disable_miniperf: bool,
         action_digest: &'a str,
+        sandbox: Option<buck2_forkserver_proto::SandboxSpec>,
     ) -> impl futures::future::Future<
         Output = buck2_error::Result<(GatherOutputStatus, Vec<u8>, Vec<u8>)>,
     > + Send
@@ -154,57 +179,90 @@ impl LocalExecutor {
         async move {
             let working_directory = self.root.join_cow(working_directory);
 
-            match &self.forkserver {
-                Some(forkserver) => {
-                    #[cfg(unix)]
-                    {
-                        unix::exec_via_forkserver(
-                            forkserver,
-                            exe,
-                            args,
-                            env,
-                            &working_directory,
-                            timeout,
-                            env_inheritance,
-                            liveliness_observer,
-                            self.knobs.enable_miniperf && !disable_miniperf,
-                            action_digest,
-                        )
-                        .await
-                    }
+            // Stream the stderr while the command runs, for the action inspector of the console.
+            let stderr_tail = match get_dispatcher_opt() {
+                Some(dispatcher) if self.knobs.stream_stderr => {
+                    Some((dispatcher, Arc::new(Mutex::new(StderrTail::default()))))
+                }
+                _ => None,
+            };
+            let tail = stderr_tail.as_ref().map(|(_, tail)| tail.dupe());
+            let on_stderr = move |stderr: &[u8]| {
+                if let Some(tail) = &tail {
+                    tail.lock().unwrap().push(stderr);
+                }
+            };
+
+            let run = async {
+                match &self.forkserver {
+                    Some(forkserver) => {
+                        #[cfg(unix)]
+                        {
+                            unix::exec_via_forkserver(
+                                forkserver,
+                                exe,
+                                args,
+                                env,
+                                &working_directory,
+                                timeout,
+                                env_inheritance,
+                                liveliness_observer,
+                                self.knobs.enable_miniperf && !disable_miniperf,
+                                action_digest,
+                                sandbox,
+                                self.resource_limits,
+                                on_stderr,
+                            )
+                            .await
+                        }
 
-                    #[cfg(not(unix))]
-                    {
-                        let _unused = (forkserver, disable_miniperf, action_digest);
-                        Err(buck2_error!([], "Forkserver is not supported off-UNIX"))
+                        #[cfg(not(unix))]
+                        {
+                            let _unused = (
+                                forkserver,
+                                disable_miniperf,
+                                action_digest,
+                                sandbox,
+                                on_stderr,
+                            );
+                            Err(buck2_error!([], "Forkserver is not supported off-UNIX"))
+                        }
                     }
-                }
 
-                None => {
-                    let exe = maybe_absolutize_exe(exe, &working_directory)?;
-                    let mut cmd = background_command(exe.as_ref());
-                    cmd.current_dir(working_directory.as_path());
-                    cmd.args(args);
-                    apply_local_execution_environment(
-                        &mut cmd,
-                        &working_directory,
-                        env,
-                        env_inheritance,
-                    );
-                    let timeout = timeout_into_cancellation(timeout);
+                    None => {
+                        let exe = maybe_absolutize_exe(exe, &working_directory)?;
+                        let mut cmd = background_command(exe.as_ref());
+                        cmd.current_dir(working_directory.as_path());
+                        cmd.args(args);
+                        apply_local_execution_environment(
+                            &mut cmd,
+                            &working_directory,
+                            env,
+                            env_inheritance,
+                        );
+                        let timeout = timeout_into_cancellation(timeout);
 
-                    let alive = liveliness_observer
-                        .while_alive()
-                        .map(|()| Ok(GatherOutputStatus::Cancelled));
+                        let alive = liveliness_observer
+                            .while_alive()
+                            .map(|()| Ok(GatherOutputStatus::Cancelled));
 
-                    let cancellation =
-                        select(timeout.boxed(), alive.boxed()).map(|r| r.factor_first().0);
+                        let cancellation =
+                            select(timeout.boxed(), alive.boxed()).map(|r| r.factor_first().0);
 
-                    gather_output(cmd, cancellation).await
+                        gather_output_streaming_stderr(cmd, cancellation, on_stderr).await
+                    }
+                    .with_buck_error_context(|| {
+                        format!("Failed to gather output from command: {}", exe)
+                    }),
                 }
-                .with_buck_error_context(|| {
-                    format!("Failed to gather output from command: {}", exe)
-                }),
+            };
+
+            match stderr_tail {
+                Some((dispatcher, tail)) => tokio::select! {
+                    res = run => res,
+                    never = send_stderr_events(dispatcher, tail) => match never {},
+                },
+                None => run.await,
             }
         }
     }
@@ -381,6 +439,15 @@ impl LocalExecutor {
             .boxed()
             .await?;
 
+        // Workers are long-lived and shared between actions, so they can't be sandboxed.
+        let sandbox_spec = match (&worker, self.forkserver.as_ref().and_then(|f| f.sandbox())) {
+            (None, Some(config)) => match SandboxPaths::new(config, &self.artifact_fs, request) {
+                Ok(sandbox) => Some(sandbox.to_proto(&self.artifact_fs)),
+                Err(e) => return manager.error("sandbox_paths", e),
+            },
+            _ => None,
+        };
+
         let execution_kind = match worker {
             None => CommandExecutionKind::Local {
                 digest: action_digest.dupe(),
@@ -448,6 +515,7 @@ impl LocalExecutor {
                         liveliness_observer,
                         request.disable_miniperf(),
                         &action_digest.to_string(),
+                        sandbox_spec,
                     )
                     .await
                 };
@@ -484,6 +552,7 @@ impl LocalExecutor {
             GatherOutputStatus::Finished {
                 exit_code,
                 execution_stats,
+                sandbox_violations,
             } => {
                 let (outputs, hashing_time) = match self
                     .calculate_and_declare_output_values(request, digest_config)
@@ -496,12 +565,34 @@ impl LocalExecutor {
                     }
                 };
 
+                let oom_killed = execution_stats
+                    .as_ref()
+                    .is_some_and(|stats| stats.oom_killed == Some(true));
+
                 timing.execution_stats = execution_stats;
                 timing.hashing_duration = hashing_time.hashing_duration;
                 timing.hashed_artifacts_count = hashing_time.hashed_artifacts_count;
 
                 if exit_code == 0 {
                     manager.success(execution_kind, outputs, std_streams, *timing)
+                } else if oom_killed {
+                    manager.failure_with_error(
+                        "oom_killed",
+                        CommandOomKilled::new(self.resource_limits.memory_max_bytes),
+                        execution_kind,
+                        std_streams,
+                        Some(exit_code),
+                        *timing,
+                    )
+                } else if !sandbox_violations.is_empty() {
+                    manager.failure_with_error(
+                        "sandbox_violation",
+                        SandboxViolation::new(&sandbox_violations),
+                        execution_kind,
+                        std_streams,
+                        Some(exit_code),
+                        *timing,
+                    )
                 } else {
                     let manager = check_inputs(
                         manager,
@@ -1096,6 +1187,9 @@ mod unix {
         liveliness_observer: impl LivelinessObserver + 'static,
         enable_miniperf: bool,
         action_digest: &str,
+        sandbox: Option<buck2_forkserver_proto::SandboxSpec>,
+        resource_limits: LocalResourceLimits,
+        on_stderr: impl FnMut(&[u8]) + Send,
     ) -> buck2_error::Result<(GatherOutputStatus, Vec<u8>, Vec<u8>)> {
         let exe = exe.as_ref();
 
@@ -1114,10 +1208,19 @@ mod unix {
             std_redirects: None,
             graceful_shutdown_timeout_s: None,
             action_digest: Some(action_digest.to_owned()),
+            sandbox,
+            resource_limits: Some(buck2_forkserver_proto::ResourceLimits {
+                memory_max_bytes: resource_limits.memory_max_bytes,
+                cpu_max_percent: resource_limits.cpu_max_percent,
+            }),
         };
         apply_local_execution_environment(&mut req, working_directory, env, env_inheritance);
         forkserver
-            .execute(req, async move { liveliness_observer.while_alive().await })
+            .execute(
+                req,
+                async move { liveliness_observer.while_alive().await },
+                on_stderr,
+            )
             .await
     }
 
@@ -1213,6 +1316,7 @@ mod tests {
             None,
             ExecutorGlobalKnobs::default(),
             None,
+            LocalResourceLimits::default(),
         );
 
         Ok((executor, temp.path().root().to_buf(), temp))
@@ -1234,6 +1338,7 @@ mod tests {
                 NoopLivelinessObserver::create(),
                 false,
                 "",
+                None,
             )
             .await?;
         assert!(matches!(status, GatherOutputStatus::Finished { exit_code, .. } if exit_code == 0));
@@ -1271,6 +1376,7 @@ mod tests {
                 NoopLivelinessObserver::create(),
                 false,
                 "",
+                None,
             )
             .await?;
         assert!(matches!(status, GatherOutputStatus::Finished { exit_code, .. } if exit_code == 0));
@@ -0,0 +1,99 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! The daemon side of sandboxed local execution: which paths a command gets to see. If
+//! `buck2_sandbox.record_accesses` is set, the forkserver records which hidden paths the command
+//! tried to access, and reports them as violations when the command exits.
+
+use buck2_common::init::SandboxConfig;
+use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_execute::execute::request::CommandExecutionInput;
+use buck2_execute::execute::request::CommandExecutionRequest;
+
+#[derive(buck2_error::Error, Debug)]
+#[buck2(input, tag = ActionSandboxViolation)]
+#[error("Command failed in the sandbox after referencing paths that are not declared inputs: {0}")]
+pub(crate) struct SandboxViolation(String);
+
+impl SandboxViolation {
+    pub(crate) fn new(paths: &[String]) -> Self {
+        Self(
+            paths
+                .iter()
+                .map(|p| format!("`{}`", p))
+                .collect::<Vec<_>>()
+                .join(", "),
+        )
+    }
+}
+
+/// The parts of the project a sandboxed command can access.
+pub(crate) struct SandboxPaths {
+    readonly: Vec<ProjectRelativePathBuf>,
+    writable: Vec<ProjectRelativePathBuf>,
+}
+
+impl SandboxPaths {
+    pub(crate) fn new(
+        config: &SandboxConfig,
+        artifact_fs: &ArtifactFs,
+        request: &CommandExecutionRequest,
+    ) -> buck2_error::Result<Self> {
+        let mut readonly = Vec::new();
+        let mut writable = Vec::new();
+
+        for dir in &config.toolchain_dirs {
+            readonly.push(ProjectRelativePath::new(dir)?.to_buf());
+        }
+
+        for input in request.inputs() {
+            match input {
+                CommandExecutionInput::Artifact(group) => {
+                    for (artifact, _) in group.iter() {
+                        readonly.push(artifact.resolve_path(artifact_fs)?);
+                    }
+                }
+                CommandExecutionInput::ActionMetadata(metadata) => {
+                    readonly.push(
+                        artifact_fs
+                            .buck_out_path_resolver()
+                            .resolve_gen(&metadata.path),
+                    );
+                }
+                CommandExecutionInput::ScratchPath(path) => {
+                    writable.push(artifact_fs.buck_out_path_resolver().resolve_scratch(path));
+                }
+            }
+        }
+
+        for output in request.outputs() {
+            if let Some(path) = output.resolve(artifact_fs).path_to_create() {
+                writable.push(path.to_buf());
+            }
+        }
+
+        Ok(Self { readonly, writable })
+    }
+
+    pub(crate) fn to_proto(&self, artifact_fs: &ArtifactFs) -> buck2_forkserver_proto::SandboxSpec {
+        let to_bytes = |paths: &[ProjectRelativePathBuf]| {
+            paths
+                .iter()
+                .map(|p| p.as_str().as_bytes().to_vec())
+                .collect()
+        };
+        buck2_forkserver_proto::SandboxSpec {
+            project_root: artifact_fs.fs().root().to_string().into_bytes(),
+            readonly_paths: to_bytes(&self.readonly),
+            writable_paths: to_bytes(&self.writable),
+        }
+    }
+}
@@ -0,0 +1,152 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Stream the stderr of local commands while they run, for the action inspector of the console.
+//! The whole stderr is reported when the command finishes, and the inspector only shows the tail
+//! of it, so what is streamed is limited: the stderr is sent at most every
+//! `STDERR_EVENT_INTERVAL`, and only the last `MAX_STDERR_EVENT_BYTES` of what the command wrote
+//! in between.
+
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use buck2_events::dispatch::EventDispatcher;
+
+/// How often to send the stderr of a command.
+const STDERR_EVENT_INTERVAL: Duration = Duration::from_millis(200);
+
+/// The most stderr sent at once.
+const MAX_STDERR_EVENT_BYTES: usize = 16 * 1024;
+
+/// The stderr written by a command since it was last sent.
+#[derive(Default)]
+pub(crate) struct StderrTail {
+    pending: Vec<u8>,
+    /// Whether the start of `pending` was dropped to keep it bounded.
+    truncated: bool,
+}
+
+impl StderrTail {
+    pub(crate) fn push(&mut self, stderr: &[u8]) {
+        self.pending.extend_from_slice(stderr);
+        if self.pending.len() > MAX_STDERR_EVENT_BYTES {
+            let mut start = self.pending.len() - MAX_STDERR_EVENT_BYTES;
+            // Don't start in the middle of a character.
+            while start < self.pending.len() && is_utf8_continuation(self.pending[start]) {
+                start += 1;
+            }
+            self.pending.drain(..start);
+            self.truncated = true;
+        }
+    }
+
+    /// Take the complete lines, so that characters are not split between events. If a line
+    /// overflowed the buffer, take as much of it as there are complete characters.
+    pub(crate) fn take(&mut self) -> Option<String> {
+        let end = match self.pending.iter().rposition(|b| *b == b'\n') {
+            Some(i) => i + 1,
+            None if self.truncated => complete_utf8_len(&self.pending),
+            None => 0,
+        };
+        if end == 0 {
+            return None;
+        }
+        let stderr = String::from_utf8_lossy(&self.pending[..end]).into_owned();
+        self.pending.drain(..end);
+        self.truncated = false;
+        Some(stderr)
+    }
+}
+
+fn is_utf8_continuation(b: u8) -> bool {
+    b & 0xC0 == 0x80
+}
+
+/// The length of `bytes` without the character cut at its end, if any.
+fn complete_utf8_len(bytes: &[u8]) -> usize {
+    // A character is at most 4 bytes long, so only the start of the last one matters.
+    for start in (bytes.len().saturating_sub(4)..bytes.len()).rev() {
+        let char_len = match bytes[start] {
+            b if is_utf8_continuation(b) => continue,
+            b if b >= 0xF0 => 4,
+            b if b >= 0xE0 => 3,
+            b if b >= 0xC0 => 2,
+            _ => 1,
+        };
+        return if start + char_len > bytes.len() {
+            start
+        } else {
+            bytes.len()
+        };
+    }
+    bytes.len()
+}
+
+/// Send what is pushed to `tail` as `CommandStderr` events, until dropped.
+pub(crate) async fn send_stderr_events(
+    dispatcher: EventDispatcher,
+    tail: Arc<Mutex<StderrTail>>,
+) -> Infallible {
+    let mut interval = tokio::time::interval(STDERR_EVENT_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let stderr = tail.lock().unwrap().take();
+        if let Some(stderr) = stderr {
+            dispatcher.instant_event(buck2_data::CommandStderr { stderr });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::executors::stderr_events::StderrTail;
+    use crate::executors::stderr_events::MAX_STDERR_EVENT_BYTES;
+
+    #[test]
+    fn test_take_complete_lines() {
+        let mut tail = StderrTail::default();
+        tail.push(b"first\nsec");
+        assert_eq!(Some("first\n".to_owned()), tail.take());
+        assert_eq!(None, tail.take());
+        tail.push(b"ond\n");
+        assert_eq!(Some("second\n".to_owned()), tail.take());
+    }
+
+    #[test]
+    fn test_characters_split_between_chunks() {
+        let mut tail = StderrTail::default();
+        let line = "héllo ✓\n".as_bytes();
+        for chunk in line.chunks(1) {
+            tail.push(chunk);
+        }
+        assert_eq!(Some("héllo ✓\n".to_owned()), tail.take());
+    }
+
+    #[test]
+    fn test_keeps_the_tail() {
+        let mut tail = StderrTail::default();
+        tail.push(b"dropped\n");
+        tail.push("é".repeat(MAX_STDERR_EVENT_BYTES).as_bytes());
+        tail.push(b"\n");
+        let stderr = tail.take().unwrap();
+        assert!(stderr.len() <= MAX_STDERR_EVENT_BYTES);
+        assert!(stderr.starts_with('é'));
+        assert!(stderr.ends_with("é\n"));
+
+        // A line overflowing the buffer is sent without the character cut at its end.
+        tail.push("é".repeat(MAX_STDERR_EVENT_BYTES).as_bytes());
+        tail.push(&"✓".as_bytes()[..1]);
+        let stderr = tail.take().unwrap();
+        assert!(stderr.chars().all(|c| c == 'é'));
+        assert_eq!(None, tail.take());
+    }
+}
@@ -7,6 +7,8 @@
  * of this source tree.
  */
 
+mod bazel;
+
 use std::collections::HashMap;
 use std::ffi::OsString;
 use std::sync::Arc;
@@ -16,6 +18,7 @@ use buck2_common::client_utils::get_channel_uds;
 use buck2_common::client_utils::retrying;
 use buck2_common::liveliness_observer::LivelinessGuard;
 use buck2_common::liveliness_observer::LivelinessObserver;
+use buck2_common::liveliness_observer::LivelinessObserverSync;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::paths::file_name::FileName;
@@ -27,6 +30,7 @@ use buck2_execute::execute::manager::CommandExecutionManagerWithClaim;
 use buck2_execute::execute::output::CommandStdStreams;
 use buck2_execute::execute::request::CommandExecutionRequest;
 use buck2_execute::execute::request::WorkerId;
+use buck2_execute::execute::request::WorkerProtocol;
 use buck2_execute::execute::request::WorkerSpec;
 use buck2_execute::execute::result::CommandExecutionMetadata;
 use buck2_execute::execute::result::CommandExecutionResult;
@@ -45,6 +49,9 @@ use indexmap::IndexMap;
 use tokio::task::JoinHandle;
 use tonic::transport::Channel;
 
+use crate::executors::worker::bazel::spawn_bazel_worker;
+use crate::executors::worker::bazel::BazelWorkerClient;
+
 const MAX_MESSAGE_SIZE_BYTES: usize = 8 * 1024 * 1024; // 8MB
 
 #[derive(buck2_error::Error, Debug)]
@@ -155,10 +162,16 @@ fn spawn_via_forkserver(
             }),
             graceful_shutdown_timeout_s,
             action_digest: None,
+            sandbox: None,
+            resource_limits: None,
         };
         apply_local_execution_environment(&mut req, &working_directory, env, None);
         let res = forkserver
-            .execute(req, async move { liveliness_observer.while_alive().await })
+            .execute(
+                req,
+                async move { liveliness_observer.while_alive().await },
+                |_| {},
+            )
             .await
             .map(|(status, _, _)| status);
 
@@ -218,6 +231,31 @@ async fn spawn_worker(
         args.join(" ")
     );
 
+    if let WorkerProtocol::Bazel {
+        encoding,
+        multiplex,
+        supports_cancellation,
+    } = worker_spec.protocol
+    {
+        // Bazel workers talk to us over stdin and stdout, so they are spawned directly rather
+        // than through the forkserver.
+        let (client, child_exited_observer, liveliness_guard) = spawn_bazel_worker(
+            &args,
+            env.into_iter().collect(),
+            root,
+            &stderr_path,
+            encoding,
+            multiplex,
+            supports_cancellation,
+        )?;
+        return Ok(WorkerHandle::new(
+            WorkerTransport::Bazel(client),
+            child_exited_observer,
+            vec![stderr_path],
+            liveliness_guard,
+        ));
+    }
+
     let worker_env = vec![("WORKER_SOCKET", socket_path.as_os_str())]
         .into_iter()
         .map(|(k, v)| (OsString::from(k), OsString::from(v)));
@@ -294,7 +332,7 @@ async fn spawn_worker(
         }?
     };
 
-    let (child_exited_observer, child_exited_guard) = LivelinessGuard::create();
+    let (child_exited_observer, child_exited_guard) = LivelinessGuard::create_sync();
     tokio::spawn(async move {
         drop(check_exit.await);
         drop(child_exited_guard);
@@ -305,10 +343,9 @@ async fn spawn_worker(
         .max_encoding_message_size(MAX_MESSAGE_SIZE_BYTES)
         .max_decoding_message_size(MAX_MESSAGE_SIZE_BYTES);
     Ok(WorkerHandle::new(
-        client,
+        WorkerTransport::Buck2(client),
         child_exited_observer,
-        stdout_path,
-        stderr_path,
+        vec![stdout_path, stderr_path],
         liveliness_guard,
     ))
 }
@@ -355,7 +392,15 @@ impl WorkerPool {
         dispatcher: EventDispatcher,
     ) -> (bool, WorkerFuture) {
         let mut workers = self.workers.lock();
-        if let Some(worker_fut) = workers.get(&worker_spec.id) {
+        // Workers that exited, e.g. because they were killed when a request timed out, are
+        // started again.
+        let exited = |worker_fut: &WorkerFuture| {
+            matches!(
+                worker_fut.peek(),
+                Some(Ok(worker)) if !worker.child_exited_observer.is_alive_sync()
+            )
+        };
+        if let Some(worker_fut) = workers.get(&worker_spec.id).filter(|fut| !exited(fut)) {
             (false, worker_fut.clone())
         } else {
             let worker_id = worker_spec.id;
@@ -387,30 +432,43 @@ impl WorkerPool {
     }
 }
 
+/// How we send commands to a worker.
+enum WorkerTransport {
+    /// The gRPC protocol from `buck2_worker_proto`, over a socket.
+    Buck2(WorkerClient<Channel>),
+    /// Bazel's persistent worker protocol, over the worker's stdin and stdout.
+    Bazel(BazelWorkerClient),
+}
+
 pub struct WorkerHandle {
-    client: WorkerClient<Channel>,
-    child_exited_observer: Arc<dyn LivelinessObserver>,
-    stdout_path: AbsNormPathBuf,
-    stderr_path: AbsNormPathBuf,
+    transport: WorkerTransport,
+    child_exited_observer: Arc<dyn LivelinessObserverSync>,
+    log_paths: Vec<AbsNormPathBuf>,
     _liveliness_guard: LivelinessGuard,
 }
 
 impl WorkerHandle {
     fn new(
-        client: WorkerClient<Channel>,
-        child_exited_observer: Arc<dyn LivelinessObserver>,
-        stdout_path: AbsNormPathBuf,
-        stderr_path: AbsNormPathBuf,
+        transport: WorkerTransport,
+        child_exited_observer: Arc<dyn LivelinessObserverSync>,
+        log_paths: Vec<AbsNormPathBuf>,
         liveliness_guard: LivelinessGuard,
     ) -> Self {
         Self {
-            client,
+            transport,
             child_exited_observer,
-            stdout_path,
-            stderr_path,
+            log_paths,
             _liveliness_guard: liveliness_guard,
         }
     }
+
+    fn logs(&self) -> String {
+        self.log_paths
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 }
 
 #[cfg(unix)]
@@ -441,6 +499,10 @@ impl WorkerHandle {
             args,
             env,
         );
+        let client = match &self.transport {
+            WorkerTransport::Buck2(client) => client,
+            WorkerTransport::Bazel(client) => return self.exec_bazel(client, args, timeout).await,
+        };
         let argv: Vec<Vec<u8>> = args.iter().map(|s| s.as_str().into()).collect();
         let env: Vec<EnvironmentEntry> = env_entries(&env);
 
@@ -450,7 +512,7 @@ impl WorkerHandle {
             timeout_s: timeout.map(|v| v.as_secs()),
         };
 
-        let mut client = self.client.clone();
+        let mut client = client.clone();
         tokio::select! {
             response = client.execute(request) => {
                 match response {
@@ -468,6 +530,7 @@ impl WorkerHandle {
                                 GatherOutputStatus::Finished {
                                     exit_code: exec_response.exit_code,
                                     execution_stats: None,
+                                    sandbox_violations: Vec::new(),
                                 },
                                 vec![],
                                 exec_response.stderr.into(),
@@ -477,8 +540,8 @@ impl WorkerHandle {
                     Err(err) => {
                         (
                             GatherOutputStatus::SpawnFailed(format!(
-                                "Error sending ExecuteCommand to worker: {:?}, see worker logs:\n{}\n{}",
-                                err, self.stdout_path, self.stderr_path,
+                                "Error sending ExecuteCommand to worker: {:?}, see worker logs:\n{}",
+                                err, self.logs(),
                             )),
                             // stdout/stderr logs for worker are for multiple commands, probably do not want to dump contents here
                             vec![],
@@ -490,8 +553,67 @@ impl WorkerHandle {
             _ = self.child_exited_observer.while_alive() => {
                 (
                     GatherOutputStatus::SpawnFailed(format!(
-                        "Worker exited while running command, see worker logs:\n{}\n{}",
-                        self.stdout_path, self.stderr_path,
+                        "Worker exited while running command, see worker logs:\n{}",
+                        self.logs(),
+                    )),
+                    vec![],
+                    vec![],
+                )
+            }
+        }
+    }
+
+    /// Bazel workers get the command's arguments but not its environment: the worker's
+    /// environment is fixed when it starts.
+    async fn exec_bazel(
+        &self,
+        client: &BazelWorkerClient,
+        args: &[String],
+        timeout: Option<Duration>,
+    ) -> (GatherOutputStatus, Vec<u8>, Vec<u8>) {
+        let response = async {
+            match timeout {
+                // Dropping the request on timeout cancels it if the worker supports that, and
+                // kills singleplex workers, which are started again for the next request.
+                Some(timeout) => tokio::time::timeout(timeout, client.execute(args.to_vec()))
+                    .await
+                    .map_err(|_| timeout),
+                None => Ok(client.execute(args.to_vec()).await),
+            }
+        };
+
+        tokio::select! {
+            response = response => {
+                match response {
+                    Ok(Ok(response)) => {
+                        tracing::info!("Worker response:\n{:?}\n", response);
+                        (
+                            GatherOutputStatus::Finished {
+                                exit_code: response.exit_code,
+                                execution_stats: None,
+                                sandbox_violations: Vec::new(),
+                            },
+                            vec![],
+                            response.output.into_bytes(),
+                        )
+                    }
+                    Ok(Err(err)) => (
+                        GatherOutputStatus::SpawnFailed(format!(
+                            "Error sending WorkRequest to worker: {:?}, see worker logs:\n{}",
+                            err,
+                            self.logs(),
+                        )),
+                        vec![],
+                        vec![],
+                    ),
+                    Err(timeout) => (GatherOutputStatus::TimedOut(timeout), vec![], vec![]),
+                }
+            }
+            _ = self.child_exited_observer.while_alive() => {
+                (
+                    GatherOutputStatus::SpawnFailed(format!(
+                        "Worker exited while running command, see worker logs:\n{}",
+                        self.logs(),
                     )),
                     vec![],
                     vec![],
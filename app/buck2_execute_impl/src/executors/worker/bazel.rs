@@ -0,0 +1,603 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Workers that speak Bazel's persistent worker protocol: they read `WorkRequest`s from stdin
+//! and write `WorkResponse`s to stdout, either as length-delimited protobuf or as JSON.
+//!
+//! Singleplex workers handle one request at a time. Multiplex workers handle several concurrent
+//! requests, which are told apart by their request ID, and can be asked to cancel a request if
+//! they declare support for cancellation.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::process::Stdio;
+use std::sync::atomic::AtomicI32;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use buck2_common::liveliness_observer::LivelinessGuard;
+use buck2_common::liveliness_observer::LivelinessObserverSync;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_error::BuckErrorContext;
+use buck2_execute::execute::request::BazelWorkerEncoding;
+use buck2_util::process::background_command;
+use buck2_worker_proto::bazel::WorkRequest;
+use buck2_worker_proto::bazel::WorkResponse;
+use prost::Message as _;
+use tokio::io::AsyncBufRead;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+use tokio::io::BufReader;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+
+use crate::executors::local::apply_local_execution_environment;
+use crate::executors::worker::WorkerInitError;
+
+/// Bazel passes this to every worker it starts, so that tools know to run as a worker.
+const PERSISTENT_WORKER_FLAG: &str = "--persistent_worker";
+
+#[derive(buck2_error::Error, Debug)]
+#[buck2(tier0)]
+enum BazelWorkerError {
+    #[error("Worker stopped handling requests")]
+    Stopped,
+    #[error("Invalid length prefix in worker response")]
+    InvalidLength,
+    #[error("Request was abandoned while the worker was running it")]
+    Abandoned,
+}
+
+enum Message {
+    Request {
+        request: WorkRequest,
+        response: oneshot::Sender<WorkResponse>,
+    },
+    Cancel {
+        request_id: i32,
+    },
+}
+
+pub(crate) struct BazelWorkerClient {
+    messages: mpsc::UnboundedSender<Message>,
+    multiplex: bool,
+    supports_cancellation: bool,
+    next_request_id: AtomicI32,
+}
+
+/// Start a worker, returning a client for it, an observer that is notified when the worker
+/// exits, and a guard that kills the worker when dropped.
+pub(crate) fn spawn_bazel_worker(
+    args: &[String],
+    env: Vec<(OsString, OsString)>,
+    root: &AbsNormPathBuf,
+    stderr_path: &AbsNormPathBuf,
+    encoding: BazelWorkerEncoding,
+    multiplex: bool,
+    supports_cancellation: bool,
+) -> Result<
+    (
+        BazelWorkerClient,
+        Arc<dyn LivelinessObserverSync>,
+        LivelinessGuard,
+    ),
+    WorkerInitError,
+> {
+    let stderr =
+        std::fs::File::create(stderr_path).map_err(|e| WorkerInitError::InternalError(e.into()))?;
+
+    let mut cmd = background_command(&args[0]);
+    cmd.args(&args[1..]);
+    cmd.arg(PERSISTENT_WORKER_FLAG);
+    cmd.current_dir(root.as_path());
+    apply_local_execution_environment(&mut cmd, root, env, None);
+    cmd.stdin(Stdio::piped());
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(stderr);
+
+    let mut child = tokio::process::Command::from(cmd)
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| WorkerInitError::SpawnFailed(e.to_string()))?;
+    let stdin = child.stdin.take().expect("stdin is piped");
+    let stdout = child.stdout.take().expect("stdout is piped");
+
+    let (liveliness_observer, liveliness_guard) = LivelinessGuard::create();
+    let (child_exited_observer, child_exited_guard) = LivelinessGuard::create_sync();
+    let (serving_observer, serving_guard) = LivelinessGuard::create();
+    tokio::spawn(async move {
+        let exited = tokio::select! {
+            status = child.wait() => {
+                tracing::info!("Worker exited: {:?}", status);
+                true
+            }
+            _ = liveliness_observer.while_alive() => false,
+            // Once we stopped talking to the worker, e.g. because a singleplex worker was still
+            // running a request that timed out, it is of no use anymore.
+            _ = serving_observer.while_alive() => false,
+        };
+        if !exited {
+            drop(child.kill().await);
+        }
+        drop(child_exited_guard);
+    });
+
+    let (client, receiver) = BazelWorkerClient::new(multiplex, supports_cancellation);
+    tokio::spawn(async move {
+        let _serving_guard = serving_guard;
+        let res = if multiplex {
+            serve_multiplex(stdin, BufReader::new(stdout), encoding, receiver).await
+        } else {
+            serve_singleplex(stdin, BufReader::new(stdout), encoding, receiver).await
+        };
+        if let Err(e) = res {
+            tracing::warn!("Error communicating with worker: {:#}", e);
+        }
+    });
+
+    Ok((client, child_exited_observer, liveliness_guard))
+}
+
+impl BazelWorkerClient {
+    fn new(
+        multiplex: bool,
+        supports_cancellation: bool,
+    ) -> (Self, mpsc::UnboundedReceiver<Message>) {
+        let (messages, receiver) = mpsc::unbounded_channel();
+        (
+            Self {
+                messages,
+                multiplex,
+                supports_cancellation,
+                // Request ID 0 is reserved for singleplex workers.
+                next_request_id: AtomicI32::new(1),
+            },
+            receiver,
+        )
+    }
+
+    pub(crate) async fn execute(
+        &self,
+        arguments: Vec<String>,
+    ) -> buck2_error::Result<WorkResponse> {
+        let request_id = if self.multiplex {
+            self.next_request_id.fetch_add(1, Ordering::Relaxed)
+        } else {
+            0
+        };
+
+        let (sender, receiver) = oneshot::channel();
+        self.messages
+            .send(Message::Request {
+                request: WorkRequest {
+                    arguments,
+                    request_id,
+                    ..Default::default()
+                },
+                response: sender,
+            })
+            .map_err(|_| BazelWorkerError::Stopped)?;
+
+        // If we stop waiting, e.g. because the command timed out or the build was cancelled, let
+        // a worker that supports cancellation know that it can stop working on the request.
+        let mut cancel_on_drop = CancelOnDrop {
+            messages: self.supports_cancellation.then(|| self.messages.clone()),
+            request_id,
+        };
+        let response = receiver.await.map_err(|_| BazelWorkerError::Stopped)?;
+        cancel_on_drop.messages = None;
+
+        Ok(response)
+    }
+}
+
+struct CancelOnDrop {
+    messages: Option<mpsc::UnboundedSender<Message>>,
+    request_id: i32,
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(messages) = self.messages.take() {
+            drop(messages.send(Message::Cancel {
+                request_id: self.request_id,
+            }));
+        }
+    }
+}
+
+/// Send requests one at a time, and wait for each response before sending the next request.
+/// Singleplex workers can't be told to stop working on a request, so if nobody waits for the
+/// response anymore, we stop serving the worker and it gets killed, rather than have the next
+/// request wait for it.
+async fn serve_singleplex(
+    mut stdin: impl AsyncWrite + Unpin,
+    mut stdout: impl AsyncBufRead + Unpin,
+    encoding: BazelWorkerEncoding,
+    mut messages: mpsc::UnboundedReceiver<Message>,
+) -> buck2_error::Result<()> {
+    while let Some(message) = messages.recv().await {
+        match message {
+            Message::Request {
+                request,
+                mut response,
+            } => {
+                write_request(&mut stdin, encoding, &request).await?;
+                let res = tokio::select! {
+                    res = read_response(&mut stdout, encoding) => res?,
+                    _ = response.closed() => return Err(BazelWorkerError::Abandoned.into()),
+                };
+                drop(response.send(res));
+            }
+            // Singleplex workers can't be cancelled.
+            Message::Cancel { .. } => {}
+        }
+    }
+    Ok(())
+}
+
+/// Send requests as they come in, and route responses to the request with the same ID.
+async fn serve_multiplex(
+    mut stdin: impl AsyncWrite + Unpin,
+    mut stdout: impl AsyncBufRead + Unpin,
+    encoding: BazelWorkerEncoding,
+    mut messages: mpsc::UnboundedReceiver<Message>,
+) -> buck2_error::Result<()> {
+    let pending = parking_lot::Mutex::new(HashMap::<i32, oneshot::Sender<WorkResponse>>::new());
+
+    let write = async {
+        while let Some(message) = messages.recv().await {
+            match message {
+                Message::Request { request, response } => {
+                    pending.lock().insert(request.request_id, response);
+                    write_request(&mut stdin, encoding, &request).await?;
+                }
+                Message::Cancel { request_id } => {
+                    // The worker still responds to the request, and that response is ignored.
+                    if pending.lock().remove(&request_id).is_some() {
+                        let request = WorkRequest {
+                            request_id,
+                            cancel: true,
+                            ..Default::default()
+                        };
+                        write_request(&mut stdin, encoding, &request).await?;
+                    }
+                }
+            }
+        }
+        buck2_error::Ok(())
+    };
+
+    // Once there are no more requests the worker is no longer needed, so don't wait for reads.
+    tokio::select! {
+        res = write => res,
+        res = route_responses(&mut stdout, encoding, &pending) => res,
+    }
+}
+
+async fn route_responses(
+    stdout: &mut (impl AsyncBufRead + Unpin),
+    encoding: BazelWorkerEncoding,
+    pending: &parking_lot::Mutex<HashMap<i32, oneshot::Sender<WorkResponse>>>,
+) -> buck2_error::Result<()> {
+    loop {
+        let response = read_response(stdout, encoding).await?;
+        if let Some(sender) = pending.lock().remove(&response.request_id) {
+            drop(sender.send(response));
+        }
+    }
+}
+
+async fn write_request(
+    stdin: &mut (impl AsyncWrite + Unpin),
+    encoding: BazelWorkerEncoding,
+    request: &WorkRequest,
+) -> buck2_error::Result<()> {
+    let bytes = match encoding {
+        BazelWorkerEncoding::Proto => request.encode_length_delimited_to_vec(),
+        BazelWorkerEncoding::Json => {
+            let mut bytes = serde_json::to_vec(request)?;
+            bytes.push(b'\n');
+            bytes
+        }
+    };
+    stdin
+        .write_all(&bytes)
+        .await
+        .buck_error_context("Error writing WorkRequest")?;
+    stdin
+        .flush()
+        .await
+        .buck_error_context("Error writing WorkRequest")?;
+    Ok(())
+}
+
+async fn read_response(
+    stdout: &mut (impl AsyncBufRead + Unpin),
+    encoding: BazelWorkerEncoding,
+) -> buck2_error::Result<WorkResponse> {
+    match encoding {
+        BazelWorkerEncoding::Proto => {
+            let len = read_varint(stdout).await?;
+            let mut bytes = vec![0; len];
+            stdout
+                .read_exact(&mut bytes)
+                .await
+                .buck_error_context("Error reading WorkResponse")?;
+            Ok(
+                WorkResponse::decode(bytes.as_slice())
+                    .buck_error_context("Invalid WorkResponse")?,
+            )
+        }
+        BazelWorkerEncoding::Json => {
+            // Responses are usually written on a single line, but a JSON value may span several.
+            let mut json = String::new();
+            loop {
+                if stdout
+                    .read_line(&mut json)
+                    .await
+                    .buck_error_context("Error reading WorkResponse")?
+                    == 0
+                {
+                    return Err(BazelWorkerError::Stopped.into());
+                }
+                if json.trim().is_empty() {
+                    json.clear();
+                    continue;
+                }
+                match serde_json::from_str(&json) {
+                    Ok(response) => return Ok(response),
+                    Err(e) if e.is_eof() => continue,
+                    Err(e) => {
+                        return Err(buck2_error::Error::from(e).context("Invalid WorkResponse"));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Read the base 128 varint that precedes a length-delimited protobuf message.
+async fn read_varint(stdout: &mut (impl AsyncBufRead + Unpin)) -> buck2_error::Result<usize> {
+    let mut value: u64 = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = match stdout.read_u8().await {
+            Ok(byte) => byte,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                return Err(BazelWorkerError::Stopped.into());
+            }
+            Err(e) => return Err(buck2_error::Error::from(e).context("Error reading WorkResponse")),
+        };
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return usize::try_from(value).map_err(|_| BazelWorkerError::InvalidLength.into());
+        }
+    }
+    Err(BazelWorkerError::InvalidLength.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::io::DuplexStream;
+
+    use super::*;
+
+    fn response(request_id: i32, output: &str) -> WorkResponse {
+        WorkResponse {
+            exit_code: 1,
+            output: output.to_owned(),
+            request_id,
+            was_cancelled: false,
+        }
+    }
+
+    /// The worker's ends of its stdin and stdout, for tests to play the worker.
+    struct InMemoryWorker {
+        requests: BufReader<DuplexStream>,
+        responses: DuplexStream,
+    }
+
+    impl InMemoryWorker {
+        /// Serve an in-memory worker that uses the proto encoding.
+        fn start(
+            multiplex: bool,
+            supports_cancellation: bool,
+        ) -> (
+            BazelWorkerClient,
+            Self,
+            tokio::task::JoinHandle<buck2_error::Result<()>>,
+        ) {
+            let (client, receiver) = BazelWorkerClient::new(multiplex, supports_cancellation);
+            let (stdin, requests) = tokio::io::duplex(4096);
+            let (responses, stdout) = tokio::io::duplex(4096);
+            let stdout = BufReader::new(stdout);
+            let server = if multiplex {
+                tokio::spawn(serve_multiplex(
+                    stdin,
+                    stdout,
+                    BazelWorkerEncoding::Proto,
+                    receiver,
+                ))
+            } else {
+                tokio::spawn(serve_singleplex(
+                    stdin,
+                    stdout,
+                    BazelWorkerEncoding::Proto,
+                    receiver,
+                ))
+            };
+            let worker = Self {
+                requests: BufReader::new(requests),
+                responses,
+            };
+            (client, worker, server)
+        }
+
+        async fn read_request(&mut self) -> buck2_error::Result<WorkRequest> {
+            let len = read_varint(&mut self.requests).await?;
+            let mut bytes = vec![0; len];
+            self.requests.read_exact(&mut bytes).await?;
+            Ok(WorkRequest::decode(bytes.as_slice())?)
+        }
+
+        /// Respond to `request` with its first argument as output.
+        async fn respond(&mut self, request: &WorkRequest) -> buck2_error::Result<()> {
+            let response = response(request.request_id, &request.arguments[0]);
+            self.responses
+                .write_all(&response.encode_length_delimited_to_vec())
+                .await?;
+            Ok(())
+        }
+    }
+
+    fn args(arg: &str) -> Vec<String> {
+        vec![arg.to_owned()]
+    }
+
+    #[tokio::test]
+    async fn test_multiplex_routes_responses_by_request_id() -> buck2_error::Result<()> {
+        let (client, mut worker, _server) = InMemoryWorker::start(true, false);
+
+        let respond_in_reverse = async {
+            let first = worker.read_request().await?;
+            let second = worker.read_request().await?;
+            assert_ne!(first.request_id, second.request_id);
+            worker.respond(&second).await?;
+            worker.respond(&first).await?;
+            buck2_error::Ok(())
+        };
+        let (first, second, worker) = tokio::join!(
+            client.execute(args("first")),
+            client.execute(args("second")),
+            respond_in_reverse,
+        );
+        worker?;
+        assert_eq!("first", first?.output);
+        assert_eq!("second", second?.output);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_multiplex_cancels_dropped_requests() -> buck2_error::Result<()> {
+        let (client, mut worker, _server) = InMemoryWorker::start(true, true);
+
+        assert!(
+            tokio::time::timeout(Duration::from_millis(10), client.execute(args("slow")))
+                .await
+                .is_err()
+        );
+
+        let request = worker.read_request().await?;
+        assert!(!request.cancel);
+        let cancel = worker.read_request().await?;
+        assert!(cancel.cancel);
+        assert_eq!(request.request_id, cancel.request_id);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_multiplex_no_cancel_without_support() -> buck2_error::Result<()> {
+        let (client, mut worker, _server) = InMemoryWorker::start(true, false);
+
+        assert!(
+            tokio::time::timeout(Duration::from_millis(10), client.execute(args("slow")))
+                .await
+                .is_err()
+        );
+
+        let respond_to_next = async {
+            let slow = worker.read_request().await?;
+            assert_eq!(args("slow"), slow.arguments);
+            // The next request follows, rather than a cancel request.
+            let next = worker.read_request().await?;
+            assert!(!next.cancel);
+            worker.respond(&next).await?;
+            buck2_error::Ok(())
+        };
+        let (next, worker) = tokio::join!(client.execute(args("next")), respond_to_next);
+        worker?;
+        assert_eq!("next", next?.output);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_singleplex_stops_serving_abandoned_requests() -> buck2_error::Result<()> {
+        let (client, mut worker, server) = InMemoryWorker::start(false, false);
+
+        assert!(
+            tokio::time::timeout(Duration::from_millis(10), client.execute(args("slow")))
+                .await
+                .is_err()
+        );
+        assert_eq!(args("slow"), worker.read_request().await?.arguments);
+
+        // The worker would still be running the request, so it can't be given another one.
+        assert!(server.await.unwrap().is_err());
+        assert!(client.execute(args("next")).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_read_proto_responses() -> buck2_error::Result<()> {
+        let mut bytes = Vec::new();
+        response(1, "first").encode_length_delimited(&mut bytes)?;
+        response(2, &"x".repeat(300)).encode_length_delimited(&mut bytes)?;
+
+        let mut stdout = BufReader::new(bytes.as_slice());
+        assert_eq!(
+            response(1, "first"),
+            read_response(&mut stdout, BazelWorkerEncoding::Proto).await?
+        );
+        assert_eq!(
+            response(2, &"x".repeat(300)),
+            read_response(&mut stdout, BazelWorkerEncoding::Proto).await?
+        );
+        assert!(read_response(&mut stdout, BazelWorkerEncoding::Proto)
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_read_json_responses() -> buck2_error::Result<()> {
+        let json = concat!(
+            r#"{"exitCode": 1, "output": "first", "requestId": 1}"#,
+            "\n\n",
+            "{\n  \"requestId\": 2,\n  \"exitCode\": 1,\n  \"output\": \"second\"\n}\n",
+        );
+
+        let mut stdout = BufReader::new(json.as_bytes());
+        assert_eq!(
+            response(1, "first"),
+            read_response(&mut stdout, BazelWorkerEncoding::Json).await?
+        );
+        assert_eq!(
+            response(2, "second"),
+            read_response(&mut stdout, BazelWorkerEncoding::Json).await?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_request() -> buck2_error::Result<()> {
+        let request = WorkRequest {
+            arguments: vec!["--flag".to_owned()],
+            request_id: 3,
+            ..Default::default()
+        };
+        let json: serde_json::Value = serde_json::to_value(&request)?;
+        assert_eq!(json["arguments"], serde_json::json!(["--flag"]));
+        assert_eq!(json["requestId"], 3);
+        Ok(())
+    }
+}
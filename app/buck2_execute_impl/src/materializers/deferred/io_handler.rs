@@ -15,7 +15,9 @@ use std::sync::Arc;
 use allocative::Allocative;
 use async_trait::async_trait;
 use buck2_common::file_ops::FileDigest;
+use buck2_common::file_ops::TrackedFileDigest;
 use buck2_core::buck2_env;
+use buck2_core::execution_types::executor_config::RemoteExecutorUseCase;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::fs_util::IoError;
 use buck2_core::fs::fs_util::ReadDir;
@@ -44,6 +46,7 @@ use buck2_execute::materialize::materializer::CasNotFoundError;
 use buck2_execute::materialize::materializer::WriteRequest;
 use buck2_execute::output_size::OutputSize;
 use buck2_execute::re::error::RemoteExecutionError;
+use buck2_execute::re::manager::ManagedRemoteExecutionClient;
 use buck2_execute::re::manager::ReConnectionManager;
 use buck2_futures::cancellation::CancellationContext;
 use buck2_http::HttpClient;
@@ -563,34 +566,37 @@ pub(super) fn create_ttl_refresh(
             for chunk in digests_to_refresh.as_slice().chunks(REFRESH_CHUNK_SIZE) {
                 tracing::debug!("Update {} TTLs", chunk.len());
 
-                let digests_expires = re_client
-                    .get_digest_expirations(chunk.iter().map(|d| d.to_re()).collect(), use_case)
-                    .await?;
+                update_digest_expirations(&re_client, chunk, use_case, digest_config).await?;
 
-                let mut digests_expires = digests_expires.into_try_map(|(digest, expires)| {
-                    buck2_error::Ok((FileDigest::from_re(&digest, digest_config)?, expires))
-                })?;
-                digests_expires.sort();
-
-                if chunk.len() != digests_expires.len() {
-                    return Err(buck2_error::buck2_error!(
-                        [],
-                        "Invalid response from get_digests_ttl: expected {}, got {} digests",
-                        chunk.len(),
-                        digests_expires.len()
-                    ));
+                // Some RE backends (notably REv2 ones) only report how long digests will live, and
+                // do not extend that when asked. Explicitly extend the ones that are still about
+                // to expire. Those that already expired are gone and cannot be extended.
+                let now = Utc::now();
+                let expiring = chunk
+                    .iter()
+                    .filter(|digest| digest.expires() > now && digest.expires() < ttl_deadline)
+                    .map(|digest| digest.dupe())
+                    .collect::<Vec<_>>();
+                if expiring.is_empty() {
+                    continue;
                 }
 
-                for (digest, (matching_digest, expires)) in chunk.iter().zip(&digests_expires) {
-                    if digest.data() != matching_digest {
-                        return Err(buck2_error::buck2_error!(
-                            [],
-                            "Invalid response from get_digests_ttl"
-                        ));
-                    }
+                tracing::debug!("Extend {} TTLs", expiring.len());
 
-                    digest.update_expires(*expires);
+                // A digest that is gone by now fails the whole request on some backends. Whether
+                // or not it did, reading the expirations back marks the digests that are gone as
+                // expired and keeps the ones that were extended, so carry on with the rest.
+                if let Err(e) = re_client
+                    .extend_digest_ttl(
+                        expiring.iter().map(|d| d.to_re()).collect(),
+                        std::time::Duration::from_secs(min_ttl.num_seconds().max(0) as u64),
+                        use_case,
+                    )
+                    .await
+                {
+                    tracing::info!("Extending {} TTLs failed: {:#}", expiring.len(), e);
                 }
+                update_digest_expirations(&re_client, &expiring, use_case, digest_config).await?;
             }
         }
 
@@ -607,6 +613,45 @@ pub(super) fn create_ttl_refresh(
     Some(fut)
 }
 
+/// Update the expiration of each of the sorted `digests` with what RE reports.
+async fn update_digest_expirations(
+    re_client: &ManagedRemoteExecutionClient,
+    digests: &[TrackedFileDigest],
+    use_case: RemoteExecutorUseCase,
+    digest_config: DigestConfig,
+) -> buck2_error::Result<()> {
+    let digests_expires = re_client
+        .get_digest_expirations(digests.iter().map(|d| d.to_re()).collect(), use_case)
+        .await?;
+
+    let mut digests_expires = digests_expires.into_try_map(|(digest, expires)| {
+        buck2_error::Ok((FileDigest::from_re(&digest, digest_config)?, expires))
+    })?;
+    digests_expires.sort();
+
+    if digests.len() != digests_expires.len() {
+        return Err(buck2_error::buck2_error!(
+            [],
+            "Invalid response from get_digests_ttl: expected {}, got {} digests",
+            digests.len(),
+            digests_expires.len()
+        ));
+    }
+
+    for (digest, (matching_digest, expires)) in digests.iter().zip(&digests_expires) {
+        if digest.data() != matching_digest {
+            return Err(buck2_error::buck2_error!(
+                [],
+                "Invalid response from get_digests_ttl"
+            ));
+        }
+
+        digest.update_expires(*expires);
+    }
+
+    Ok(())
+}
+
 struct WriteIoRequest {
     path: ProjectRelativePathBuf,
     write: Arc<WriteFile>,
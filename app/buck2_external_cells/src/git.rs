@@ -17,6 +17,7 @@ use std::sync::Mutex;
 use std::sync::OnceLock;
 
 use buck2_build_api::actions::artifact::get_artifact_fs::GetArtifactFs;
+use buck2_build_api::actions::impls::run_action_knobs::HasRunActionKnobs;
 use buck2_common::dice::data::HasIoProvider;
 use buck2_common::dice::file_ops::delegate::FileOpsDelegate;
 use buck2_common::file_ops::FileDigestConfig;
@@ -47,6 +48,7 @@ use buck2_execute::execute::blocking::IoRequest;
 use buck2_execute::execute::clean_output_paths::CleanOutputPaths;
 use buck2_execute::materialize::materializer::HasMaterializer;
 use buck2_execute::materialize::materializer::Materializer;
+use buck2_offline_archive::bundle::OfflineBundle;
 use buck2_util::process::background_command;
 use cmp_any::PartialEqAny;
 use dice::CancellationContext;
@@ -69,6 +71,7 @@ enum GitError {
 struct GitFetchIoRequest {
     setup: GitCellSetup,
     path: ProjectRelativePathBuf,
+    offline_bundle: Option<Arc<OfflineBundle>>,
 }
 
 impl IoRequest for GitFetchIoRequest {
@@ -107,11 +110,15 @@ impl IoRequest for GitFetchIoRequest {
             c.arg("init");
         })?;
 
+        let origin = match &self.offline_bundle {
+            Some(bundle) => bundle
+                .existing_git_repo_path(&self.setup.commit)?
+                .as_os_str()
+                .to_owned(),
+            None => self.setup.git_origin.as_ref().into(),
+        };
         run_git(&path, |c| {
-            c.arg("remote")
-                .arg("add")
-                .arg("origin")
-                .arg(self.setup.git_origin.as_ref());
+            c.arg("remote").arg("add").arg("origin").arg(origin);
         })?;
 
         run_git(&path, |c| {
@@ -146,6 +153,10 @@ async fn download_impl(
         Box::new(GitFetchIoRequest {
             setup: setup.dupe(),
             path: path.to_owned(),
+            offline_bundle: ctx
+                .per_transaction_data()
+                .get_run_action_knobs()
+                .offline_bundle,
         }),
         cancellations,
     )
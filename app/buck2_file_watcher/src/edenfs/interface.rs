@@ -418,6 +418,7 @@ impl EdenFsFileWatcher {
                 new_mergebase: false,
                 cleared_dice: true,
                 cleared_dep_files: true,
+                rescanned_files: false,
             }),
             ..Default::default()
         };
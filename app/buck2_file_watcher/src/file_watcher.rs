@@ -12,6 +12,7 @@ use std::sync::Arc;
 
 use allocative::Allocative;
 use async_trait::async_trait;
+use buck2_common::daemon_dir::DaemonDir;
 use buck2_common::ignores::ignore_set::IgnoreSet;
 use buck2_common::legacy_configs::configs::LegacyBuckConfig;
 use buck2_common::legacy_configs::key::BuckconfigKeyRef;
@@ -47,6 +48,7 @@ impl dyn FileWatcher {
         root_config: &LegacyBuckConfig,
         cells: CellResolver,
         ignore_specs: HashMap<CellName, IgnoreSet>,
+        daemon_dir: &DaemonDir,
     ) -> buck2_error::Result<Arc<dyn FileWatcher>> {
         let default = if is_open_source() {
             "notify"
@@ -57,6 +59,16 @@ impl dyn FileWatcher {
 
         let _allow_unused = fb;
 
+        // Persist the state of the files, so that the next daemon can find the changes made while
+        // no daemon was running. Only supported by `notify` and `fs_hash_crawler`.
+        let state_path = root_config
+            .parse::<bool>(BuckconfigKeyRef {
+                section: "buck2",
+                property: "persist_file_watcher_state",
+            })?
+            .unwrap_or(false)
+            .then(|| daemon_dir.file_watcher_state());
+
         match root_config
             .get(BuckconfigKeyRef {
                 section: "buck2",
@@ -69,11 +81,11 @@ impl dyn FileWatcher {
                     .buck_error_context("Creating watchman file watcher")?,
             )),
             "notify" => Ok(Arc::new(
-                NotifyFileWatcher::new(project_root, cells, ignore_specs)
+                NotifyFileWatcher::new(project_root, cells, ignore_specs, state_path)
                     .buck_error_context("Creating notify file watcher")?,
             )),
             "fs_hash_crawler" => Ok(Arc::new(
-                FsHashCrawler::new(project_root, cells, ignore_specs)
+                FsHashCrawler::new(project_root, cells, ignore_specs, state_path)
                     .buck_error_context("Creating fs_crawler file watcher")?,
             )),
             #[cfg(fbcode_build)]
@@ -12,8 +12,12 @@ use std::fs::File;
 use std::io::Read;
 use std::mem;
 use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Duration;
+use std::time::SystemTime;
 
 use allocative::Allocative;
 use async_trait::async_trait;
@@ -27,6 +31,7 @@ use buck2_core::cells::name::CellName;
 use buck2_core::cells::CellResolver;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::paths::file_name::FileNameBuf;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
@@ -37,6 +42,9 @@ use buck2_events::dispatch::span_async;
 use compact_str::CompactString;
 use dice::DiceTransactionUpdater;
 use dupe::Dupe;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::warn;
 
 use crate::file_watcher::FileWatcher;
 use crate::mergebase::Mergebase;
@@ -49,8 +57,12 @@ use crate::stats::FileWatcherStats;
 pub struct FsHashCrawler {
     root: ProjectRoot,
     cells: CellResolver,
-    ignore_specs: HashMap<CellName, IgnoreSet>,
-    snapshot: Arc<Mutex<FsSnapshot>>,
+    ignore_specs: Arc<HashMap<CellName, IgnoreSet>>,
+    snapshot: Arc<Mutex<Arc<FsSnapshot>>>,
+    /// Where the snapshot is persisted, if enabled.
+    state_path: Option<AbsNormPathBuf>,
+    /// Whether a save of the snapshot is pending.
+    save_scheduled: Arc<AtomicBool>,
 }
 
 impl FsHashCrawler {
@@ -58,13 +70,30 @@ impl FsHashCrawler {
         root: &ProjectRoot,
         cells: CellResolver,
         ignore_specs: HashMap<CellName, IgnoreSet>,
+        state_path: Option<AbsNormPathBuf>,
     ) -> buck2_error::Result<Self> {
-        let snapshot = Arc::new(Mutex::new(FsSnapshot::build(root, &cells)?));
+        // Starting from the snapshot of the previous daemon makes the first sync report the
+        // changes made while no daemon was running.
+        let snapshot = match state_path
+            .as_ref()
+            .and_then(|path| load_state(&cells, path))
+        {
+            Some(snapshot) => snapshot,
+            None => {
+                let snapshot = FsSnapshot::build(root, &cells, &ignore_specs, None)?;
+                if let Some(path) = &state_path {
+                    save_state(&snapshot, &cells, path);
+                }
+                snapshot
+            }
+        };
         Ok(Self {
             root: root.dupe(),
             cells,
-            ignore_specs,
-            snapshot,
+            ignore_specs: Arc::new(ignore_specs),
+            snapshot: Arc::new(Mutex::new(Arc::new(snapshot))),
+            state_path,
+            save_scheduled: Arc::new(AtomicBool::new(false)),
         })
     }
 
@@ -74,14 +103,46 @@ impl FsHashCrawler {
     ) -> buck2_error::Result<(buck2_data::FileWatcherStats, DiceTransactionUpdater)> {
         let root = self.root.dupe();
         let cells = self.cells.dupe();
-        let new_snapshot =
-            tokio::task::spawn_blocking(move || FsSnapshot::build(&root, &cells)).await??;
-        let mut guard = self.snapshot.lock().unwrap();
-        let old_snapshot = mem::replace(&mut *guard, new_snapshot);
-        let (stats, changes) = old_snapshot.get_updates_for_dice(&guard, &self.ignore_specs)?;
+        let ignore_specs = self.ignore_specs.dupe();
+        let new_snapshot = tokio::task::spawn_blocking(move || {
+            // Don't reuse the hashes of the previous snapshot: this watcher is meant for file
+            // systems whose metadata can't be trusted.
+            FsSnapshot::build(&root, &cells, &ignore_specs, None)
+        })
+        .await??;
+        let (stats, changes) = {
+            let mut guard = self.snapshot.lock().unwrap();
+            let old_snapshot = mem::replace(&mut *guard, Arc::new(new_snapshot));
+            old_snapshot.get_updates_for_dice(&guard, &self.ignore_specs)?
+        };
         changes.write_to_dice(&mut dice)?;
+        self.schedule_save();
         Ok((stats, dice))
     }
+
+    /// Persist the snapshot after `STATE_SAVE_DELAY`, so that a burst of syncs only writes it once.
+    fn schedule_save(&self) {
+        let Some(state_path) = self.state_path.clone() else {
+            return;
+        };
+        if self.save_scheduled.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        let snapshot = self.snapshot.dupe();
+        let cells = self.cells.dupe();
+        let save_scheduled = self.save_scheduled.dupe();
+        tokio::spawn(async move {
+            tokio::time::sleep(STATE_SAVE_DELAY).await;
+            // Syncs from now on schedule another save.
+            save_scheduled.store(false, Ordering::Relaxed);
+            // Syncs replace the snapshot rather than modify it, so it is saved without the lock.
+            let snapshot = snapshot.lock().unwrap().dupe();
+            let _ignored = tokio::task::spawn_blocking(move || {
+                save_state(&snapshot, &cells, &state_path);
+            })
+            .await;
+        });
+    }
 }
 
 #[async_trait]
@@ -119,11 +180,54 @@ struct FsEvent {
 #[derive(Allocative)]
 enum EntryInfo {
     #[allocative(skip)]
-    File(Hash),
+    File(FileState),
     Directory,
     Symlink,
 }
 
+/// Files modified this recently may be modified again without their mtime changing, since mtimes
+/// are only as precise as the kernel clock tick.
+const RACY_MTIME: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct FileState {
+    hash: Hash,
+    size: u64,
+    /// Since the Unix epoch. `None` if the file system doesn't support it, or if it can't be
+    /// trusted to change with the file.
+    mtime: Option<Duration>,
+}
+
+impl FileState {
+    /// Hash the file at `path`, unless its size and mtime match `previous`.
+    fn read(
+        path: &AbsNormPath,
+        metadata: &std::fs::Metadata,
+        previous: Option<&FileState>,
+    ) -> buck2_error::Result<Self> {
+        let size = metadata.len();
+        let now = SystemTime::now();
+        let mtime = metadata
+            .modified()
+            .ok()
+            .filter(|mtime| {
+                now.duration_since(*mtime)
+                    .map_or(false, |age| age > RACY_MTIME)
+            })
+            .and_then(|mtime| mtime.duration_since(SystemTime::UNIX_EPOCH).ok());
+        if let Some(previous) = previous {
+            if mtime.is_some() && previous.mtime == mtime && previous.size == size {
+                return Ok(*previous);
+            }
+        }
+        Ok(Self {
+            hash: file_hash(path.as_maybe_relativized())?,
+            size,
+            mtime,
+        })
+    }
+}
+
 impl EntryInfo {
     fn to_file_watcher_kind(&self) -> FileWatcherKind {
         match self {
@@ -134,26 +238,131 @@ impl EntryInfo {
     }
 }
 
-#[derive(Allocative)]
-struct FsSnapshot(HashMap<CellPath, EntryInfo>);
+#[derive(Allocative, Default)]
+pub(crate) struct FsSnapshot {
+    entries: HashMap<CellPath, EntryInfo>,
+    /// The entries of every directory, so that `update_paths` can drop everything below a
+    /// directory without going through all the entries.
+    children: HashMap<CellPath, Vec<CellPath>>,
+}
 
 impl FsSnapshot {
-    fn build(root: &ProjectRoot, cells: &CellResolver) -> buck2_error::Result<Self> {
-        let mut snapshot = FsSnapshot(HashMap::new());
-        snapshot.build_fs_snapshot(root, cells, root.root())?;
+    /// Take a snapshot of all the files that are not ignored. The hashes of `previous` are reused
+    /// for the files whose size and mtime did not change.
+    pub(crate) fn build(
+        root: &ProjectRoot,
+        cells: &CellResolver,
+        ignore_specs: &HashMap<CellName, IgnoreSet>,
+        previous: Option<&FsSnapshot>,
+    ) -> buck2_error::Result<Self> {
+        let mut snapshot = FsSnapshot::default();
+        snapshot.build_fs_snapshot(root, cells, ignore_specs, root.root(), previous)?;
         Ok(snapshot)
     }
 
-    fn add_entry(&mut self, cell: CellPath, info: EntryInfo) {
-        self.0.insert(cell, info);
+    /// Update the entries for `paths`, and for everything below them, e.g. after a file watcher
+    /// reported them as changed.
+    pub(crate) fn update_paths<'a>(
+        &mut self,
+        root: &ProjectRoot,
+        cells: &CellResolver,
+        ignore_specs: &HashMap<CellName, IgnoreSet>,
+        paths: impl IntoIterator<Item = &'a CellPath>,
+    ) -> buck2_error::Result<()> {
+        for cell_path in paths {
+            let rel_path = cells.resolve_path(cell_path.as_ref())?;
+            if is_excluded(&rel_path, cell_path, ignore_specs) {
+                continue;
+            }
+            // The project root is not an entry, its own changes are reported for its children.
+            let Some(dir) = rel_path.parent() else {
+                continue;
+            };
+            let dir = cells.get_cell_path(dir)?;
+
+            let previous = self.entries.remove(cell_path);
+            let previous_children = match previous {
+                Some(EntryInfo::Directory) => self.take_children(cell_path),
+                _ => FsSnapshot::default(),
+            };
+
+            let disk_path = root.resolve(&rel_path);
+            let info = match fs_util::symlink_metadata_if_exists(&disk_path)? {
+                None => None,
+                Some(metadata) => match FileType::from(metadata.file_type()) {
+                    FileType::File => {
+                        let previous = match &previous {
+                            Some(EntryInfo::File(state)) => Some(state),
+                            _ => None,
+                        };
+                        let state = FileState::read(&disk_path, &metadata, previous)?;
+                        Some(EntryInfo::File(state))
+                    }
+                    FileType::Directory => {
+                        self.build_fs_snapshot(
+                            root,
+                            cells,
+                            ignore_specs,
+                            &disk_path,
+                            Some(&previous_children),
+                        )?;
+                        Some(EntryInfo::Directory)
+                    }
+                    FileType::Symlink => Some(EntryInfo::Symlink),
+                    FileType::Unknown => None,
+                },
+            };
+            match (previous, info) {
+                (Some(_), Some(info)) => {
+                    self.entries.insert(cell_path.clone(), info);
+                }
+                (None, Some(info)) => self.add_entry(&dir, cell_path.clone(), info),
+                (Some(_), None) => {
+                    if let Some(children) = self.children.get_mut(&dir) {
+                        children.retain(|child| child != cell_path);
+                    }
+                }
+                (None, None) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn add_entry(&mut self, dir: &CellPath, cell: CellPath, info: EntryInfo) {
+        if self.entries.insert(cell.clone(), info).is_some() {
+            return;
+        }
+        match self.children.get_mut(dir) {
+            Some(children) => children.push(cell),
+            None => {
+                self.children.insert(dir.clone(), vec![cell]);
+            }
+        }
+    }
+
+    /// Remove everything below `dir`. The entries removed are returned, to reuse their hashes.
+    fn take_children(&mut self, dir: &CellPath) -> FsSnapshot {
+        let mut taken = FsSnapshot::default();
+        let mut dirs = vec![dir.clone()];
+        while let Some(dir) = dirs.pop() {
+            for child in self.children.remove(&dir).unwrap_or_default() {
+                if let Some(info) = self.entries.remove(&child) {
+                    if matches!(info, EntryInfo::Directory) {
+                        dirs.push(child.clone());
+                    }
+                    taken.entries.insert(child, info);
+                }
+            }
+        }
+        taken
     }
 
     fn get_updates(&self, new_snapshot: &FsSnapshot) -> buck2_error::Result<Vec<FsEvent>> {
         let mut events = Vec::new();
-        for (cell_path, prev_info) in self.0.iter() {
-            if let Some(current_info) = new_snapshot.0.get(cell_path) {
+        for (cell_path, prev_info) in self.entries.iter() {
+            if let Some(current_info) = new_snapshot.entries.get(cell_path) {
                 match (current_info, prev_info) {
-                    (EntryInfo::File(cur), EntryInfo::File(prev)) if cur != prev => {
+                    (EntryInfo::File(cur), EntryInfo::File(prev)) if cur.hash != prev.hash => {
                         events.push(FsEvent {
                             cell_path: cell_path.to_owned(),
                             event: FileWatcherEventType::Modify,
@@ -171,9 +380,9 @@ impl FsSnapshot {
             }
         }
         let new_entries = new_snapshot
-            .0
+            .entries
             .iter()
-            .filter(|(path, _)| !self.0.contains_key(*path));
+            .filter(|(path, _)| !self.entries.contains_key(*path));
         for (cell_path, info) in new_entries {
             events.push(FsEvent {
                 cell_path: cell_path.to_owned(),
@@ -184,7 +393,7 @@ impl FsSnapshot {
         Ok(events)
     }
 
-    fn get_updates_for_dice(
+    pub(crate) fn get_updates_for_dice(
         &self,
         new_snapshot: &FsSnapshot,
         ignore_specs: &HashMap<CellName, IgnoreSet>,
@@ -242,8 +451,11 @@ impl FsSnapshot {
         &mut self,
         root: &ProjectRoot,
         cells: &CellResolver,
+        ignore_specs: &HashMap<CellName, IgnoreSet>,
         disk_path: &AbsNormPath,
+        previous: Option<&FsSnapshot>,
     ) -> buck2_error::Result<()> {
+        let dir = cells.get_cell_path(&root.relativize(disk_path)?)?;
         for file in fs_util::read_dir(disk_path)? {
             let file = file?;
             let filetype = file.file_type()?;
@@ -260,25 +472,27 @@ impl FsSnapshot {
             let rel_path = root.relativize(&disk_path)?;
             let cell_path = cells.get_cell_path(&rel_path)?;
 
-            // We ignore buck-out and .hg dirs, as those are uninteresting events caused by us.
-            if rel_path.starts_with(InvocationPaths::buck_out_dir_prefix())
-                || rel_path.starts_with(ProjectRelativePath::unchecked_new(".hg"))
-            {
+            if is_excluded(&rel_path, &cell_path, ignore_specs) {
                 continue;
             }
 
             let filetype = FileType::from(filetype);
             match filetype {
                 FileType::File => {
-                    let hash = file_hash(disk_path.as_maybe_relativized())?;
-                    self.add_entry(cell_path, EntryInfo::File(hash));
+                    let previous =
+                        match previous.and_then(|previous| previous.entries.get(&cell_path)) {
+                            Some(EntryInfo::File(state)) => Some(state),
+                            _ => None,
+                        };
+                    let state = FileState::read(&disk_path, &file.metadata()?, previous)?;
+                    self.add_entry(&dir, cell_path, EntryInfo::File(state));
                 }
                 FileType::Directory => {
-                    self.build_fs_snapshot(root, cells, &disk_path)?;
-                    self.add_entry(cell_path, EntryInfo::Directory);
+                    self.build_fs_snapshot(root, cells, ignore_specs, &disk_path, previous)?;
+                    self.add_entry(&dir, cell_path, EntryInfo::Directory);
                 }
                 FileType::Symlink => {
-                    self.add_entry(cell_path, EntryInfo::Symlink);
+                    self.add_entry(&dir, cell_path, EntryInfo::Symlink);
                 }
                 FileType::Unknown => (),
             }
@@ -287,6 +501,136 @@ impl FsSnapshot {
     }
 }
 
+/// We ignore buck-out, as those are uninteresting events caused by us, and the .hg and .git dirs.
+/// Ignored paths are left out with everything below them, so that ignored directories, which
+/// tend to be large, are not hashed at all.
+fn is_excluded(
+    rel_path: &ProjectRelativePath,
+    cell_path: &CellPath,
+    ignore_specs: &HashMap<CellName, IgnoreSet>,
+) -> bool {
+    rel_path.starts_with(InvocationPaths::buck_out_dir_prefix())
+        || rel_path.starts_with(ProjectRelativePath::unchecked_new(".hg"))
+        || rel_path.starts_with(ProjectRelativePath::unchecked_new(".git"))
+        || ignore_specs
+            .get(&cell_path.cell())
+            .map_or(false, |ignore| ignore.is_match(cell_path.path()))
+}
+
+/// Bumped whenever the layout of persisted snapshots changes.
+const STATE_FORMAT_VERSION: u32 = 1;
+
+/// An `FsSnapshot` persisted across daemons. Paths are relative to the project root, since the
+/// cells may change between daemons.
+#[derive(Serialize, Deserialize)]
+struct PersistedState {
+    format_version: u32,
+    entries: Vec<(String, PersistedEntryInfo)>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum PersistedEntryInfo {
+    File {
+        hash: [u8; 32],
+        size: u64,
+        mtime: Option<Duration>,
+    },
+    Directory,
+    Symlink,
+}
+
+impl FsSnapshot {
+    fn save(&self, cells: &CellResolver, path: &AbsNormPath) -> buck2_error::Result<()> {
+        let mut entries = Vec::with_capacity(self.entries.len());
+        for (cell_path, info) in &self.entries {
+            let info = match info {
+                EntryInfo::File(state) => PersistedEntryInfo::File {
+                    hash: *state.hash.as_bytes(),
+                    size: state.size,
+                    mtime: state.mtime,
+                },
+                EntryInfo::Directory => PersistedEntryInfo::Directory,
+                EntryInfo::Symlink => PersistedEntryInfo::Symlink,
+            };
+            entries.push((cells.resolve_path(cell_path.as_ref())?.to_string(), info));
+        }
+        let state = bincode::serialize(&PersistedState {
+            format_version: STATE_FORMAT_VERSION,
+            entries,
+        })
+        .buck_error_context("Error serializing file state")?;
+
+        if let Some(dir) = path.parent() {
+            fs_util::create_dir_all(dir)?;
+        }
+        // Write to a temporary file first, so that a crash never leaves a partial state behind.
+        let tmp = path.as_path().with_extension("tmp");
+        std::fs::write(&tmp, state)?;
+        std::fs::rename(&tmp, path.as_path())?;
+        Ok(())
+    }
+
+    fn load(cells: &CellResolver, path: &AbsNormPath) -> buck2_error::Result<Option<Self>> {
+        let Some(state) = fs_util::read_if_exists(path)? else {
+            return Ok(None);
+        };
+        let state: PersistedState =
+            bincode::deserialize(&state).buck_error_context("Error deserializing file state")?;
+        if state.format_version != STATE_FORMAT_VERSION {
+            return Ok(None);
+        }
+
+        let mut snapshot = FsSnapshot::default();
+        for (path, info) in state.entries {
+            let path = ProjectRelativePath::new(&path)?;
+            // Only the project root has no parent, and it is not an entry.
+            let Some(dir) = path.parent() else {
+                continue;
+            };
+            let dir = cells.get_cell_path(dir)?;
+            let cell_path = cells.get_cell_path(path)?;
+            let info = match info {
+                PersistedEntryInfo::File { hash, size, mtime } => EntryInfo::File(FileState {
+                    hash: Hash::from(hash),
+                    size,
+                    mtime,
+                }),
+                PersistedEntryInfo::Directory => EntryInfo::Directory,
+                PersistedEntryInfo::Symlink => EntryInfo::Symlink,
+            };
+            snapshot.add_entry(&dir, cell_path, info);
+        }
+        Ok(Some(snapshot))
+    }
+}
+
+/// Load the snapshot persisted by a previous daemon. Failing to do so is not fatal: it only means
+/// the changes made since then can't be computed.
+pub(crate) fn load_state(cells: &CellResolver, path: &AbsNormPath) -> Option<FsSnapshot> {
+    match FsSnapshot::load(cells, path) {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            warn!(
+                "Error loading file state from `{}`: {:#}",
+                path.display(),
+                e
+            );
+            None
+        }
+    }
+}
+
+/// How long to wait after the snapshot changes before persisting it.
+pub(crate) const STATE_SAVE_DELAY: Duration = Duration::from_secs(10);
+
+/// Persist `snapshot` for the next daemon. Failing to do so is not fatal: the next daemon will
+/// start without a baseline.
+pub(crate) fn save_state(snapshot: &FsSnapshot, cells: &CellResolver, path: &AbsNormPath) {
+    if let Err(e) = snapshot.save(cells, path) {
+        warn!("Error saving file state to `{}`: {:#}", path.display(), e);
+    }
+}
+
 fn file_hash(path: &Path) -> buck2_error::Result<Hash> {
     let mut reader = File::open(path)?;
     let mut hasher = blake3::Hasher::new();
@@ -306,7 +650,9 @@ fn file_hash(path: &Path) -> buck2_error::Result<Hash> {
 #[cfg(test)]
 mod tests {
     use std::collections::BTreeSet;
+    use std::collections::HashMap;
 
+    use buck2_common::ignores::ignore_set::IgnoreSet;
     use buck2_core::cells::cell_path::CellPath;
     use buck2_core::cells::cell_root_path::CellRootPathBuf;
     use buck2_core::cells::name::CellName;
@@ -347,11 +693,11 @@ mod tests {
         fs_util::create_dir_all(&dir2)?;
         fs_util::write(file2, "old content")?;
 
-        let old_snapshot = FsSnapshot::build(&proj_root, &cell_resolver)?;
+        let old_snapshot = FsSnapshot::build(&proj_root, &cell_resolver, &HashMap::new(), None)?;
         fs_util::write(file1, "new content")?;
         fs_util::remove_all(dir2)?;
         fs_util::write(file3, "new content")?;
-        let new_snapshot = FsSnapshot::build(&proj_root, &cell_resolver)?;
+        let new_snapshot = FsSnapshot::build(&proj_root, &cell_resolver, &HashMap::new(), None)?;
         let events = old_snapshot.get_updates(&new_snapshot)?;
 
         let expected = [
@@ -382,4 +728,117 @@ mod tests {
         assert_eq!(events, expected);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_persisted_state() -> buck2_error::Result<()> {
+        let cell_resolver = CellResolver::testing_with_name_and_path(
+            CellName::testing_new("root"),
+            CellRootPathBuf::testing_new(""),
+        );
+        let tempdir = tempfile::tempdir()?;
+        let root_path = fs_util::canonicalize(AbsNormPathBuf::new(tempdir.path().to_owned())?)?;
+        let proj_root = ProjectRoot::new(root_path)?;
+        let state_dir = tempfile::tempdir()?;
+        let state_path = AbsNormPathBuf::new(state_dir.path().join("state"))?;
+
+        let file1 = ProjectRelativePath::new("dir1/file1")?;
+        let file2 = ProjectRelativePath::new("dir1/file2")?;
+        fs_util::create_dir_all(proj_root.resolve(ProjectRelativePath::new("dir1")?))?;
+        fs_util::write(proj_root.resolve(file1), "old content")?;
+        fs_util::write(proj_root.resolve(file2), "old content")?;
+        FsSnapshot::build(&proj_root, &cell_resolver, &HashMap::new(), None)?
+            .save(&cell_resolver, &state_path)?;
+
+        // Changed while no daemon was running.
+        fs_util::write(proj_root.resolve(file1), "new content")?;
+
+        let old_snapshot = FsSnapshot::load(&cell_resolver, &state_path)?.unwrap();
+        let new_snapshot = FsSnapshot::build(
+            &proj_root,
+            &cell_resolver,
+            &HashMap::new(),
+            Some(&old_snapshot),
+        )?;
+        let events = old_snapshot.get_updates(&new_snapshot)?;
+        assert_eq!(
+            vec![FsEvent {
+                cell_path: cell_resolver.get_cell_path(file1)?,
+                event: FileWatcherEventType::Modify,
+                kind: FileWatcherKind::File,
+            }],
+            events
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_update_paths() -> buck2_error::Result<()> {
+        let cell_resolver = CellResolver::testing_with_name_and_path(
+            CellName::testing_new("root"),
+            CellRootPathBuf::testing_new(""),
+        );
+        let tempdir = tempfile::tempdir()?;
+        let root_path = fs_util::canonicalize(AbsNormPathBuf::new(tempdir.path().to_owned())?)?;
+        let proj_root = ProjectRoot::new(root_path)?;
+
+        let dir1 = ProjectRelativePath::new("dir1")?;
+        let file1 = ProjectRelativePath::new("dir1/file1")?;
+        let file2 = ProjectRelativePath::new("dir1/file2")?;
+        let dir2 = ProjectRelativePath::new("dir2")?;
+        let file3 = ProjectRelativePath::new("dir2/file3")?;
+        fs_util::create_dir_all(proj_root.resolve(dir1))?;
+        fs_util::write(proj_root.resolve(file1), "old content")?;
+        fs_util::write(proj_root.resolve(file2), "old content")?;
+        let mut snapshot = FsSnapshot::build(&proj_root, &cell_resolver, &HashMap::new(), None)?;
+
+        fs_util::write(proj_root.resolve(file1), "new content")?;
+        fs_util::remove_all(proj_root.resolve(file2))?;
+        fs_util::create_dir_all(proj_root.resolve(dir2))?;
+        fs_util::write(proj_root.resolve(file3), "new content")?;
+        let changed = [
+            cell_resolver.get_cell_path(file1)?,
+            cell_resolver.get_cell_path(file2)?,
+            cell_resolver.get_cell_path(dir2)?,
+        ];
+        snapshot.update_paths(&proj_root, &cell_resolver, &HashMap::new(), &changed)?;
+
+        let rebuilt = FsSnapshot::build(&proj_root, &cell_resolver, &HashMap::new(), None)?;
+        assert_eq!(Vec::<FsEvent>::new(), snapshot.get_updates(&rebuilt)?);
+        assert_eq!(Vec::<FsEvent>::new(), rebuilt.get_updates(&snapshot)?);
+
+        // Removing a directory removes everything below it, including what was added since.
+        fs_util::remove_all(proj_root.resolve(dir2))?;
+        snapshot.update_paths(&proj_root, &cell_resolver, &HashMap::new(), [&changed[2]])?;
+
+        let rebuilt = FsSnapshot::build(&proj_root, &cell_resolver, &HashMap::new(), None)?;
+        assert_eq!(Vec::<FsEvent>::new(), snapshot.get_updates(&rebuilt)?);
+        assert_eq!(Vec::<FsEvent>::new(), rebuilt.get_updates(&snapshot)?);
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_excluded_paths() -> buck2_error::Result<()> {
+        let cell_resolver = CellResolver::testing_with_name_and_path(
+            CellName::testing_new("root"),
+            CellRootPathBuf::testing_new(""),
+        );
+        let tempdir = tempfile::tempdir()?;
+        let root_path = fs_util::canonicalize(AbsNormPathBuf::new(tempdir.path().to_owned())?)?;
+        let proj_root = ProjectRoot::new(root_path)?;
+
+        for file in ["src/file", "ignored/file", ".git/HEAD", "buck-out/v2/file"] {
+            let path = ProjectRelativePath::new(file)?;
+            fs_util::create_dir_all(proj_root.resolve(path.parent().unwrap()))?;
+            fs_util::write(proj_root.resolve(path), "content")?;
+        }
+        let ignore_specs = HashMap::from([(
+            CellName::testing_new("root"),
+            IgnoreSet::from_ignore_spec("ignored", true)?,
+        )]);
+
+        let snapshot = FsSnapshot::build(&proj_root, &cell_resolver, &ignore_specs, None)?;
+        let mut paths: Vec<String> = snapshot.entries.keys().map(|p| p.to_string()).collect();
+        paths.sort();
+        assert_eq!(vec!["root//src", "root//src/file"], paths);
+        Ok(())
+    }
 }
@@ -9,6 +9,8 @@
 
 use std::collections::HashMap;
 use std::mem;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::Mutex;
 
@@ -21,8 +23,10 @@ use buck2_core::cells::cell_path::CellPath;
 use buck2_core::cells::name::CellName;
 use buck2_core::cells::CellResolver;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_events::dispatch::span_async;
+use buck2_util::threads::thread_spawn;
 use dice::DiceTransactionUpdater;
 use dupe::Dupe;
 use notify::event::CreateKind;
@@ -34,8 +38,13 @@ use notify::RecommendedWatcher;
 use notify::Watcher;
 use starlark_map::ordered_set::OrderedSet;
 use tracing::info;
+use tracing::warn;
 
 use crate::file_watcher::FileWatcher;
+use crate::fs_hash_crawler::load_state;
+use crate::fs_hash_crawler::save_state;
+use crate::fs_hash_crawler::FsSnapshot;
+use crate::fs_hash_crawler::STATE_SAVE_DELAY;
 use crate::mergebase::Mergebase;
 use crate::stats::FileWatcherStats;
 
@@ -77,12 +86,25 @@ impl ChangeType {
     }
 }
 
+/// Why the events reported by notify can't be trusted, so the files have to be rescanned.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq, Allocative)]
+enum RescanReason {
+    /// The kernel queue overflowed (`IN_Q_OVERFLOW` for inotify), so events were dropped.
+    Overflow,
+    /// We ran out of watches (`ENOSPC` for inotify), so some directories are not watched at all.
+    WatchLimit,
+    /// The daemon started, and the files may have changed since the previous daemon persisted
+    /// their state.
+    Startup,
+}
+
 /// Buffer containing the events that have happened since we last got a message.
 /// Used to dedupe events, since notify sends a notification on every change.
 #[derive(Allocative)]
 struct NotifyFileData {
     ignored: u64,
     events: OrderedSet<(CellPath, ChangeType)>,
+    rescan: Option<RescanReason>,
 }
 
 impl NotifyFileData {
@@ -90,6 +112,14 @@ impl NotifyFileData {
         Self {
             ignored: 0,
             events: OrderedSet::new(),
+            rescan: None,
+        }
+    }
+
+    fn needs_rescan(&mut self, reason: RescanReason) {
+        // Running out of watches is permanent, so it takes precedence.
+        if self.rescan != Some(RescanReason::WatchLimit) {
+            self.rescan = Some(reason);
         }
     }
 
@@ -100,7 +130,25 @@ impl NotifyFileData {
         cells: &CellResolver,
         ignore_specs: &HashMap<CellName, IgnoreSet>,
     ) -> buck2_error::Result<()> {
-        let event = event?;
+        let event = match event {
+            Ok(event) => event,
+            // notify reports `ENOSPC` from `inotify_add_watch` as `MaxFilesWatch`. This happens
+            // for directories created after startup too, which are watched as they appear.
+            Err(e) if is_watch_limit(&e) => {
+                warn!(
+                    "FileWatcher: out of watches, some files are not watched: {}",
+                    e
+                );
+                self.needs_rescan(RescanReason::WatchLimit);
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if event.need_rescan() {
+            warn!("FileWatcher: events were dropped, the files will be rescanned");
+            self.needs_rescan(RescanReason::Overflow);
+            return Ok(());
+        }
         let change_type = ChangeType::new(event.kind);
         for path in event.paths {
             // Testing shows that we get absolute paths back from the `notify` library.
@@ -176,11 +224,61 @@ impl NotifyFileData {
     }
 }
 
+fn is_watch_limit(e: &notify::Error) -> bool {
+    matches!(e.kind, notify::ErrorKind::MaxFilesWatch)
+}
+
+/// Take the snapshot to persist when there is none yet, on another thread since it requires
+/// hashing every file of the repository. The snapshot is locked until it is taken, so a rescan
+/// waits for it. The files are already watched, so changes made while it is taken are reported as
+/// events.
+fn take_baseline(
+    root: &ProjectRoot,
+    cells: &CellResolver,
+    ignore_specs: &Arc<HashMap<CellName, IgnoreSet>>,
+    state_path: &AbsNormPathBuf,
+    snapshot: &Arc<tokio::sync::Mutex<Option<FsSnapshot>>>,
+) -> buck2_error::Result<()> {
+    // Nothing else has the snapshot yet.
+    let mut guard = snapshot.dupe().try_lock_owned().unwrap();
+    let root = root.dupe();
+    let cells = cells.dupe();
+    let ignore_specs = ignore_specs.dupe();
+    let state_path = state_path.clone();
+    thread_spawn("buck2-fs-baseline", move || {
+        match FsSnapshot::build(&root, &cells, &ignore_specs, None) {
+            Ok(snapshot) => {
+                save_state(&snapshot, &cells, &state_path);
+                *guard = Some(snapshot);
+            }
+            // The first rescan then drops all the state computed from files.
+            Err(e) => warn!("FileWatcher: error taking the baseline snapshot: {:#}", e),
+        }
+    })?;
+    Ok(())
+}
+
 #[derive(Allocative)]
 pub struct NotifyFileWatcher {
     #[allocative(skip)]
     watcher: RecommendedWatcher,
     data: Arc<Mutex<buck2_error::Result<NotifyFileData>>>,
+    root: ProjectRoot,
+    cells: CellResolver,
+    ignore_specs: Arc<HashMap<CellName, IgnoreSet>>,
+    /// The files as of the last rescan, which the next rescan diffs against, kept up to date with
+    /// the events. When the state is persisted, it starts from the snapshot of the previous
+    /// daemon, or else from a baseline taken in the background. Otherwise it is only taken by the
+    /// first rescan, which has nothing to diff against and drops all the state computed from
+    /// files, as a fresh start does.
+    snapshot: Arc<tokio::sync::Mutex<Option<FsSnapshot>>>,
+    /// Where the snapshot is persisted, if enabled.
+    state_path: Option<AbsNormPathBuf>,
+    /// Whether a save of the snapshot is pending.
+    save_scheduled: Arc<AtomicBool>,
+    /// Once we run out of watches, events are missing for some directories, so every sync has to
+    /// rescan the files.
+    watch_limit_reached: AtomicBool,
 }
 
 impl NotifyFileWatcher {
@@ -188,30 +286,213 @@ impl NotifyFileWatcher {
         root: &ProjectRoot,
         cells: CellResolver,
         ignore_specs: HashMap<CellName, IgnoreSet>,
+        state_path: Option<AbsNormPathBuf>,
     ) -> buck2_error::Result<Self> {
-        let data = Arc::new(Mutex::new(Ok(NotifyFileData::new())));
+        let ignore_specs = Arc::new(ignore_specs);
+        let snapshot = state_path
+            .as_ref()
+            .and_then(|path| load_state(&cells, path));
+        let mut initial = NotifyFileData::new();
+        if snapshot.is_some() {
+            // Diff against the previous daemon's snapshot.
+            initial.needs_rescan(RescanReason::Startup);
+        }
+        let data = Arc::new(Mutex::new(Ok(initial)));
         let data2 = data.dupe();
         let root2 = root.dupe();
+        let cells2 = cells.dupe();
+        let ignore_specs2 = ignore_specs.dupe();
         let mut watcher = notify::recommended_watcher(move |event| {
             let mut guard = data2.lock().unwrap();
             if let Ok(state) = &mut *guard {
-                if let Err(e) = state.process(event, &root2, &cells, &ignore_specs) {
+                if let Err(e) = state.process(event, &root2, &cells2, &ignore_specs2) {
                     *guard = Err(e);
                 }
             }
         })?;
-        watcher.watch(root.root().as_path(), notify::RecursiveMode::Recursive)?;
-        Ok(Self { watcher, data })
+        let watch_limit_reached =
+            match watcher.watch(root.root().as_path(), notify::RecursiveMode::Recursive) {
+                Ok(()) => false,
+                Err(e) if is_watch_limit(&e) => {
+                    // On Linux, this is fixed by raising `fs.inotify.max_user_watches`.
+                    warn!(
+                        "FileWatcher: out of watches, rescanning files on every sync: {}",
+                        e
+                    );
+                    true
+                }
+                Err(e) => return Err(e.into()),
+            };
+        let no_snapshot = snapshot.is_none();
+        let snapshot = Arc::new(tokio::sync::Mutex::new(snapshot));
+        if let Some(state_path) = &state_path {
+            if no_snapshot {
+                take_baseline(root, &cells, &ignore_specs, state_path, &snapshot)?;
+            }
+        }
+        Ok(Self {
+            watcher,
+            data,
+            root: root.dupe(),
+            cells,
+            ignore_specs,
+            snapshot,
+            state_path,
+            save_scheduled: Arc::new(AtomicBool::new(false)),
+            watch_limit_reached: AtomicBool::new(watch_limit_reached),
+        })
     }
 
-    fn sync2(
+    async fn sync2(
         &self,
         mut dice: DiceTransactionUpdater,
     ) -> buck2_error::Result<(buck2_data::FileWatcherStats, DiceTransactionUpdater)> {
-        let mut guard = self.data.lock().unwrap();
-        let old = mem::replace(&mut *guard, Ok(NotifyFileData::new()));
-        let (stats, changes) = old?.sync();
-        changes.write_to_dice(&mut dice)?;
+        let data = {
+            let mut guard = self.data.lock().unwrap();
+            mem::replace(&mut *guard, Ok(NotifyFileData::new()))?
+        };
+        if data.rescan == Some(RescanReason::WatchLimit) {
+            self.watch_limit_reached.store(true, Ordering::Relaxed);
+        }
+        let rescan = if self.watch_limit_reached.load(Ordering::Relaxed) {
+            Some(RescanReason::WatchLimit)
+        } else {
+            data.rescan
+        };
+
+        match rescan {
+            // The rescan finds every change since the previous one, so it subsumes the events.
+            Some(reason) => {
+                let res = self.rescan(dice, reason).await;
+                if res.is_err() {
+                    // Make sure the next sync rescans again.
+                    if let Ok(data) = &mut *self.data.lock().unwrap() {
+                        data.needs_rescan(reason);
+                    }
+                }
+                res
+            }
+            None => {
+                let changed: Vec<CellPath> =
+                    data.events.iter().map(|(path, _)| path.clone()).collect();
+                let (stats, changes) = data.sync();
+                changes.write_to_dice(&mut dice)?;
+                if !changed.is_empty() {
+                    if let Err(e) = self.update_state(changed).await {
+                        // The persisted state is then older than the files, which only means that
+                        // the next daemon sees more changes than there are.
+                        warn!("FileWatcher: error updating the file state: {:#}", e);
+                    }
+                }
+                Ok((stats, dice))
+            }
+        }
+    }
+
+    /// Apply the changes to the snapshot, so that the next rescan, or the next daemon, can diff
+    /// against the files as of now.
+    async fn update_state(&self, changed: Vec<CellPath>) -> buck2_error::Result<()> {
+        let mut guard = self.snapshot.dupe().lock_owned().await;
+        if guard.is_none() {
+            return Ok(());
+        }
+        let root = self.root.dupe();
+        let cells = self.cells.dupe();
+        let ignore_specs = self.ignore_specs.dupe();
+        // The snapshot stays in place even if the task fails. If some paths could not be updated,
+        // their entries are outdated or missing, so the next rescan reports them as changed
+        // again. That is still better than no snapshot at all.
+        let res = tokio::task::spawn_blocking(move || match &mut *guard {
+            Some(snapshot) => snapshot.update_paths(&root, &cells, &ignore_specs, &changed),
+            None => Ok(()),
+        })
+        .await;
+        self.schedule_save();
+        res?
+    }
+
+    /// Persist the snapshot after `STATE_SAVE_DELAY`, so that a burst of syncs only writes it once.
+    fn schedule_save(&self) {
+        let Some(state_path) = self.state_path.clone() else {
+            return;
+        };
+        if self.save_scheduled.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        let snapshot = self.snapshot.dupe();
+        let cells = self.cells.dupe();
+        let save_scheduled = self.save_scheduled.dupe();
+        tokio::spawn(async move {
+            tokio::time::sleep(STATE_SAVE_DELAY).await;
+            // Changes applied from now on schedule another save.
+            save_scheduled.store(false, Ordering::Relaxed);
+            let guard = snapshot.lock_owned().await;
+            let _ignored = tokio::task::spawn_blocking(move || {
+                if let Some(snapshot) = &*guard {
+                    save_state(snapshot, &cells, &state_path);
+                }
+            })
+            .await;
+        });
+    }
+
+    /// Find the changes by diffing the files against the previous rescan, like `FsHashCrawler`
+    /// does. Without a previous snapshot, all the state computed from files is dropped.
+    async fn rescan(
+        &self,
+        dice: DiceTransactionUpdater,
+        reason: RescanReason,
+    ) -> buck2_error::Result<(buck2_data::FileWatcherStats, DiceTransactionUpdater)> {
+        info!("FileWatcher: rescanning files ({:?})", reason);
+        let root = self.root.dupe();
+        let cells = self.cells.dupe();
+        let ignore_specs = self.ignore_specs.dupe();
+        let guard = self.snapshot.dupe().lock_owned().await;
+        // The guard is moved to the task so that the old snapshot stays in place if it fails.
+        let (mut guard, new_snapshot) = tokio::task::spawn_blocking(move || {
+            let new_snapshot = FsSnapshot::build(&root, &cells, &ignore_specs, (*guard).as_ref());
+            (guard, new_snapshot)
+        })
+        .await?;
+        // Keep the old snapshot unless the changes made it to DICE, so that the next rescan
+        // finds them again.
+        let new_snapshot = new_snapshot?;
+        let res = self.write_rescan_changes((*guard).as_ref(), &new_snapshot, dice)?;
+        *guard = Some(new_snapshot);
+        self.schedule_save();
+        Ok(res)
+    }
+
+    fn write_rescan_changes(
+        &self,
+        old_snapshot: Option<&FsSnapshot>,
+        new_snapshot: &FsSnapshot,
+        mut dice: DiceTransactionUpdater,
+    ) -> buck2_error::Result<(buck2_data::FileWatcherStats, DiceTransactionUpdater)> {
+        let (mut stats, cleared_dice) = match old_snapshot {
+            Some(old_snapshot) => {
+                let (stats, changes) =
+                    old_snapshot.get_updates_for_dice(new_snapshot, &self.ignore_specs)?;
+                changes.write_to_dice(&mut dice)?;
+                (stats, false)
+            }
+            None => {
+                // See the comment on the analogous code in `watchman/interface.rs`.
+                dice = dice.unstable_take();
+                let stats = buck2_data::FileWatcherStats {
+                    incomplete_events_reason: Some("Fresh instance".to_owned()),
+                    ..Default::default()
+                };
+                (stats, true)
+            }
+        };
+        stats.fresh_instance = true;
+        stats.fresh_instance_data = Some(buck2_data::FreshInstance {
+            new_mergebase: false,
+            cleared_dice,
+            cleared_dep_files: false,
+            rescanned_files: true,
+        });
         Ok((stats, dice))
     }
 }
@@ -227,7 +508,7 @@ impl FileWatcher for NotifyFileWatcher {
                 provider: buck2_data::FileWatcherProvider::RustNotify as i32,
             },
             async {
-                let (stats, res) = match self.sync2(dice) {
+                let (stats, res) = match self.sync2(dice).await {
                     Ok((stats, dice)) => {
                         let mergebase = Mergebase(Arc::new(stats.branched_from_revision.clone()));
                         ((Some(stats)), Ok((dice, mergebase)))
@@ -240,3 +521,102 @@ impl FileWatcher for NotifyFileWatcher {
         .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use buck2_core::cells::cell_root_path::CellRootPathBuf;
+    use buck2_core::cells::name::CellName;
+    use buck2_core::cells::CellResolver;
+    use buck2_core::fs::fs_util;
+    use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+    use buck2_core::fs::project::ProjectRoot;
+    use buck2_core::fs::project_rel_path::ProjectRelativePath;
+    use dice::DetectCycles;
+    use dice::Dice;
+    use dupe::Dupe;
+    use notify::event::Flag;
+    use notify::EventKind;
+
+    use crate::notify::NotifyFileData;
+    use crate::notify::NotifyFileWatcher;
+    use crate::notify::RescanReason;
+
+    #[test]
+    fn test_rescan_on_overflow_and_watch_limit() -> buck2_error::Result<()> {
+        let cells = CellResolver::testing_with_name_and_path(
+            CellName::testing_new("root"),
+            CellRootPathBuf::testing_new(""),
+        );
+        let tempdir = tempfile::tempdir()?;
+        let root_path = fs_util::canonicalize(AbsNormPathBuf::new(tempdir.path().to_owned())?)?;
+        let root = ProjectRoot::new(root_path)?;
+        let ignore_specs = HashMap::new();
+
+        let mut data = NotifyFileData::new();
+        let overflow = notify::Event::new(EventKind::Other).set_flag(Flag::Rescan);
+        data.process(Ok(overflow), &root, &cells, &ignore_specs)?;
+        assert_eq!(Some(RescanReason::Overflow), data.rescan);
+
+        let watch_limit = notify::Error::new(notify::ErrorKind::MaxFilesWatch);
+        data.process(Err(watch_limit), &root, &cells, &ignore_specs)?;
+        assert_eq!(Some(RescanReason::WatchLimit), data.rescan);
+
+        // Running out of watches is not forgotten by later overflows.
+        let overflow = notify::Event::new(EventKind::Other).set_flag(Flag::Rescan);
+        data.process(Ok(overflow), &root, &cells, &ignore_specs)?;
+        assert_eq!(Some(RescanReason::WatchLimit), data.rescan);
+
+        let error = notify::Error::generic("oops");
+        assert!(data
+            .process(Err(error), &root, &cells, &ignore_specs)
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rescan_after_overflow() -> buck2_error::Result<()> {
+        let cells = CellResolver::testing_with_name_and_path(
+            CellName::testing_new("root"),
+            CellRootPathBuf::testing_new(""),
+        );
+        let tempdir = tempfile::tempdir()?;
+        let root_path = fs_util::canonicalize(AbsNormPathBuf::new(tempdir.path().to_owned())?)?;
+        let root = ProjectRoot::new(root_path)?;
+        let file = ProjectRelativePath::new("file")?;
+        fs_util::write(root.resolve(file), "old content")?;
+
+        let watcher = NotifyFileWatcher::new(&root, cells.dupe(), HashMap::new(), None)?;
+        // Without persisted state, nothing is hashed until a rescan needs it.
+        assert!(watcher.snapshot.lock().await.is_none());
+        let dice = Dice::builder().build(DetectCycles::Enabled);
+
+        // The first rescan has nothing to diff against.
+        if let Ok(data) = &mut *watcher.data.lock().unwrap() {
+            data.needs_rescan(RescanReason::Overflow);
+        }
+        let (stats, _dice) = watcher.sync2(dice.updater()).await?;
+        assert!(stats.fresh_instance_data.unwrap().cleared_dice);
+        assert!(watcher.snapshot.lock().await.is_some());
+
+        fs_util::write(root.resolve(file), "new content")?;
+        if let Ok(data) = &mut *watcher.data.lock().unwrap() {
+            data.needs_rescan(RescanReason::Overflow);
+        }
+        let (stats, _dice) = watcher.sync2(dice.updater()).await?;
+        let fresh_instance_data = stats.fresh_instance_data.unwrap();
+        assert!(fresh_instance_data.rescanned_files);
+        // The snapshot of the first rescan was diffed against, rather than dropping everything.
+        assert!(!fresh_instance_data.cleared_dice);
+        assert_eq!(
+            vec!["root//file"],
+            stats
+                .events
+                .iter()
+                .map(|event| event.path.as_str())
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+}
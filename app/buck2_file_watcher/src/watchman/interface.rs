@@ -317,6 +317,7 @@ impl SyncableQueryProcessor for WatchmanQueryProcessor {
                 new_mergebase: has_new_mergebase,
                 cleared_dice: true,
                 cleared_dep_files: clear_dep_files,
+                rescanned_files: false,
             }),
             ..Default::default()
         };
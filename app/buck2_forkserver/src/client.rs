@@ -13,6 +13,7 @@ use std::sync::Arc;
 
 use allocative::Allocative;
 use arc_swap::ArcSwapOption;
+use buck2_common::init::SandboxConfig;
 use buck2_core::tag_error;
 use buck2_error::BuckErrorContext;
 use dupe::Dupe;
@@ -48,13 +49,15 @@ struct ForkserverClientInner {
     #[allocative(skip)]
     error: Arc<ArcSwapOption<buck2_error::Error>>,
     pid: u32,
+    /// Set if the forkserver runs commands that carry a sandbox spec in a sandbox.
+    sandbox: Option<SandboxConfig>,
     #[allocative(skip)]
     rpc: buck2_forkserver_proto::forkserver_client::ForkserverClient<Channel>,
 }
 
 impl ForkserverClient {
     #[allow(unused)] // Unused on Windows
-    pub(crate) fn new(mut child: Child, channel: Channel) -> Self {
+    pub(crate) fn new(mut child: Child, channel: Channel, sandbox: Option<SandboxConfig>) -> Self {
         let rpc = buck2_forkserver_proto::forkserver_client::ForkserverClient::new(channel)
             .max_encoding_message_size(usize::MAX)
             .max_decoding_message_size(usize::MAX);
@@ -78,7 +81,12 @@ impl ForkserverClient {
         });
 
         Self {
-            inner: Arc::new(ForkserverClientInner { error, pid, rpc }),
+            inner: Arc::new(ForkserverClientInner {
+                error,
+                pid,
+                sandbox,
+                rpc,
+            }),
         }
     }
 
@@ -86,10 +94,16 @@ impl ForkserverClient {
         self.inner.pid
     }
 
+    pub fn sandbox(&self) -> Option<&SandboxConfig> {
+        self.inner.sandbox.as_ref()
+    }
+
+    /// Run a command. `on_stderr` is called with the stderr as the command writes it.
     pub async fn execute<C>(
         &self,
         req: buck2_forkserver_proto::CommandRequest,
         cancel: C,
+        on_stderr: impl FnMut(&[u8]) + Send,
     ) -> buck2_error::Result<(GatherOutputStatus, Vec<u8>, Vec<u8>)>
     where
         C: Future<Output = ()> + Send + 'static,
@@ -123,7 +137,7 @@ impl ForkserverClient {
             .buck_error_context("Error dispatching command to Forkserver")?
             .into_inner();
         let stream = decode_event_stream(stream);
-        decode_command_event_stream(stream).await
+        decode_command_event_stream(stream, on_stderr).await
     }
 
     pub async fn set_log_filter(&self, log_filter: String) -> buck2_error::Result<()> {
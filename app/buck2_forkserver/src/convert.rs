@@ -35,9 +35,11 @@ where
             CommandEvent::Exit(GatherOutputStatus::Finished {
                 exit_code,
                 execution_stats,
+                sandbox_violations,
             }) => Data::Exit(buck2_forkserver_proto::ExitEvent {
                 exit_code,
                 execution_stats,
+                sandbox_violations,
             }),
             CommandEvent::Exit(GatherOutputStatus::TimedOut(duration)) => {
                 Data::Timeout(buck2_forkserver_proto::TimeoutEvent {
@@ -79,9 +81,11 @@ where
             Data::Exit(buck2_forkserver_proto::ExitEvent {
                 exit_code,
                 execution_stats,
+                sandbox_violations,
             }) => CommandEvent::Exit(GatherOutputStatus::Finished {
                 exit_code,
                 execution_stats,
+                sandbox_violations,
             }),
             Data::Timeout(buck2_forkserver_proto::TimeoutEvent { duration }) => {
                 CommandEvent::Exit(GatherOutputStatus::TimedOut(
@@ -49,6 +49,8 @@ pub enum GatherOutputStatus {
     Finished {
         exit_code: i32,
         execution_stats: Option<buck2_data::CommandExecutionStats>,
+        /// Project-relative paths hidden by the sandbox that the command tried to access.
+        sandbox_violations: Vec<String>,
     },
     TimedOut(Duration),
     Cancelled,
@@ -61,9 +63,11 @@ impl From<DecodedStatus> for GatherOutputStatus {
             DecodedStatus::Status {
                 exit_code,
                 execution_stats,
+                sandbox_violations,
             } => Self::Finished {
                 exit_code,
                 execution_stats,
+                sandbox_violations,
             },
             DecodedStatus::SpawnFailed(v) => Self::SpawnFailed(v),
         }
@@ -267,8 +271,11 @@ where
     Ok(CommandEventStream::new(status, stdio).right_stream())
 }
 
+/// Collect the output of a command. `on_stderr` is called with the stderr as the command writes
+/// it.
 pub(crate) async fn decode_command_event_stream<S>(
     stream: S,
+    mut on_stderr: impl FnMut(&[u8]),
 ) -> buck2_error::Result<(GatherOutputStatus, Vec<u8>, Vec<u8>)>
 where
     S: Stream<Item = buck2_error::Result<CommandEvent>>,
@@ -281,7 +288,10 @@ where
     while let Some(event) = stream.try_next().await? {
         match event {
             CommandEvent::Stdout(bytes) => stdout.extend(&bytes),
-            CommandEvent::Stderr(bytes) => stderr.extend(&bytes),
+            CommandEvent::Stderr(bytes) => {
+                on_stderr(&bytes);
+                stderr.extend(&bytes);
+            }
             CommandEvent::Exit(exit) => return Ok((exit, stdout, stderr)),
         }
     }
@@ -296,6 +306,18 @@ pub async fn gather_output<T>(
     cmd: Command,
     cancellation: T,
 ) -> buck2_error::Result<(GatherOutputStatus, Vec<u8>, Vec<u8>)>
+where
+    T: Future<Output = buck2_error::Result<GatherOutputStatus>> + Send,
+{
+    gather_output_streaming_stderr(cmd, cancellation, |_| {}).await
+}
+
+/// Like `gather_output`, but `on_stderr` is called with the stderr as the command writes it.
+pub async fn gather_output_streaming_stderr<T>(
+    cmd: Command,
+    cancellation: T,
+    on_stderr: impl FnMut(&[u8]) + Send,
+) -> buck2_error::Result<(GatherOutputStatus, Vec<u8>, Vec<u8>)>
 where
     T: Future<Output = buck2_error::Result<GatherOutputStatus>> + Send,
 {
@@ -311,7 +333,7 @@ where
         DefaultKillProcess::default(),
         true,
     )?;
-    decode_command_event_stream(stream).await
+    decode_command_event_stream(stream, on_stderr).await
 }
 
 /// Dependency injection for kill. We use this in testing.
@@ -439,6 +461,28 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_gather_output_streaming_stderr() -> buck2_error::Result<()> {
+        let mut cmd = if cfg!(windows) {
+            background_command("powershell")
+        } else {
+            background_command("sh")
+        };
+        cmd.args(["-c", "echo hello 1>&2"]);
+
+        let mut streamed = Vec::new();
+        let (status, _stdout, stderr) =
+            gather_output_streaming_stderr(cmd, futures::future::pending(), |bytes| {
+                streamed.extend_from_slice(bytes)
+            })
+            .await?;
+        assert!(matches!(status, GatherOutputStatus::Finished { exit_code, .. } if exit_code == 0));
+        assert_eq!(str::from_utf8(&stderr)?.trim(), "hello");
+        assert_eq!(streamed, stderr);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_gather_does_not_wait_for_children() -> buck2_error::Result<()> {
         // If we wait for sleep, this will time out.
@@ -710,7 +754,7 @@ mod tests {
             true,
         )?;
 
-        let (status, _stdout, _stderr) = decode_command_event_stream(stream).await?;
+        let (status, _stdout, _stderr) = decode_command_event_stream(stream, |_| {}).await?;
         assert!(matches!(status, GatherOutputStatus::TimedOut(..)));
 
         assert!(*killed.lock().unwrap());
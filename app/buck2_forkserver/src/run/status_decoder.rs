@@ -19,6 +19,8 @@ pub(crate) enum DecodedStatus {
     Status {
         exit_code: i32,
         execution_stats: Option<buck2_data::CommandExecutionStats>,
+        /// Project-relative paths hidden by the sandbox that the command tried to access.
+        sandbox_violations: Vec<String>,
     },
 
     /// Spawn failed, provide the error.
@@ -43,6 +45,7 @@ impl StatusDecoder for DefaultStatusDecoder {
         Ok(DecodedStatus::Status {
             exit_code: default_decode_exit_code(status),
             execution_stats: None,
+            sandbox_violations: Vec::new(),
         })
     }
 
@@ -88,6 +91,7 @@ impl StatusDecoder for MiniperfStatusDecoder {
             return Ok(DecodedStatus::Status {
                 exit_code: default_decode_exit_code(status),
                 execution_stats: None,
+                sandbox_violations: Vec::new(),
             });
         }
 
@@ -131,6 +135,8 @@ impl StatusDecoder for MiniperfStatusDecoder {
                                 userspace_events: Some(counters.user_instructions.to_proto()),
                                 kernel_events: Some(counters.kernel_instructions.to_proto()),
                                 memory_peak: counters.memory_peak,
+                                cpu_time_us: None,
+                                oom_killed: None,
                             });
 
                     if let Err(e) = execution_stats.as_ref() {
@@ -142,6 +148,7 @@ impl StatusDecoder for MiniperfStatusDecoder {
                     Ok(DecodedStatus::Status {
                         exit_code,
                         execution_stats: execution_stats.ok(),
+                        sandbox_violations: Vec::new(),
                     })
                 }
 
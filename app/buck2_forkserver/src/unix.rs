@@ -7,9 +7,11 @@
  * of this source tree.
  */
 
+mod cgroup;
 mod command;
 mod launch;
 pub(crate) mod process_group;
+mod sandbox;
 mod service;
 
 pub use command::run_forkserver;
@@ -0,0 +1,321 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Per-action cgroups.
+//!
+//! When enabled, every local action runs in its own cgroup v2, created as a child of the
+//! forkserver's cgroup. This is how the memory and CPU limits from the action's executor config
+//! are applied, and how we find out the action's peak memory, its CPU time, and whether it was
+//! OOM killed.
+
+use std::ffi::CStr;
+use std::ffi::CString;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::ExitStatus;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use async_trait::async_trait;
+use buck2_common::init::ResourceControlConfig;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_error::BuckErrorContext;
+use buck2_forkserver_proto::ResourceLimits;
+use buck2_util::cgroup_info::CGroupInfo;
+
+use crate::run::status_decoder::DecodedStatus;
+use crate::run::status_decoder::StatusDecoder;
+
+/// The period we express `cpu.max` quotas over.
+const CPU_MAX_PERIOD_US: u64 = 100_000;
+
+const ACTION_CGROUP_PREFIX: &str = "action-";
+
+#[derive(buck2_error::Error, Debug)]
+#[buck2(input)]
+enum CgroupError {
+    #[error("Per-action cgroups are only supported on Linux")]
+    Unsupported,
+    #[error("Per-action cgroups require cgroup v2, but `{0}` is not in a cgroup v2 hierarchy")]
+    NotCgroupV2(String),
+    #[error(
+        "Per-action cgroups require the `memory` and `cpu` controllers to be enabled for the children of `{0}`. Enable them (for example with systemd's `Delegate=`), or set `buck2_resource_control.per_action_cgroups_move_to_leaf = true` to let Buck2 move the processes in that cgroup to a `leaf` child cgroup and enable them itself"
+    )]
+    ControllersNotEnabled(String),
+}
+
+pub(crate) struct ActionCgroups {
+    /// The forkserver's own cgroup. Action cgroups are created directly under it.
+    root: AbsNormPathBuf,
+    next_id: AtomicU64,
+}
+
+impl ActionCgroups {
+    pub(crate) fn new(config: &ResourceControlConfig) -> buck2_error::Result<Option<Self>> {
+        if !config.per_action_cgroups {
+            return Ok(None);
+        }
+
+        if !cfg!(target_os = "linux") {
+            return Err(CgroupError::Unsupported.into());
+        }
+
+        let root = AbsNormPathBuf::new(PathBuf::from(CGroupInfo::read()?.path))?;
+        if !fs_util::try_exists(join(&root, "cgroup.controllers"))? {
+            return Err(CgroupError::NotCgroupV2(root.to_string()).into());
+        }
+
+        // A previous forkserver in this cgroup may have left some behind.
+        for entry in fs_util::read_dir(&root)? {
+            let entry = entry?;
+            if entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.starts_with(ACTION_CGROUP_PREFIX))
+            {
+                // This fails if the cgroup still has processes, in which case it's not stale.
+                let _ignored = std::fs::remove_dir(entry.path());
+            }
+        }
+
+        let subtree_control = join(&root, "cgroup.subtree_control");
+        let enabled = fs_util::read_to_string(&subtree_control)?;
+        if !["memory", "cpu"]
+            .iter()
+            .all(|c| enabled.split_whitespace().any(|e| e == *c))
+        {
+            // A cgroup can only enable controllers for its children if it has no processes of its
+            // own, so everything in our cgroup (us, and normally the daemon) has to move to a
+            // leaf. This affects processes we don't own, so only do it if asked to.
+            if !config.per_action_cgroups_move_to_leaf {
+                return Err(CgroupError::ControllersNotEnabled(root.to_string()).into());
+            }
+            let leaf = join(&root, "leaf");
+            fs_util::create_dir_all(&leaf)?;
+            let leaf_procs = join(&leaf, "cgroup.procs");
+            let procs = fs_util::read_to_string(join(&root, "cgroup.procs"))?;
+            tracing::info!(
+                "Moving processes {} from `{}` to `{}` to enable per-action cgroups",
+                procs.lines().collect::<Vec<_>>().join(", "),
+                root,
+                leaf
+            );
+            for pid in procs.lines() {
+                fs_util::write(&leaf_procs, pid).with_buck_error_context(|| {
+                    format!("Error moving process {} to a leaf", pid)
+                })?;
+            }
+            fs_util::write(&subtree_control, "+memory +cpu").with_buck_error_context(|| {
+                format!("Error enabling controllers in `{}`", root.display())
+            })?;
+        }
+
+        Ok(Some(Self {
+            root,
+            next_id: AtomicU64::new(0),
+        }))
+    }
+
+    /// Create the cgroup for a single action.
+    pub(crate) fn create(&self, limits: &ResourceLimits) -> buck2_error::Result<ActionCgroup> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let path = self.root.join(ForwardRelativePath::new(&format!(
+            "{}{}",
+            ACTION_CGROUP_PREFIX, id
+        ))?);
+        fs_util::create_dir(&path)?;
+
+        // From here on, dropping the cgroup removes it.
+        let cgroup = ActionCgroup {
+            procs: CString::new(join(&path, "cgroup.procs").as_os_str().as_bytes())?,
+            path,
+        };
+
+        // If the OOM killer picks any process of the action, kill all of them rather than leave
+        // the action running in a broken state.
+        cgroup.write("memory.oom.group", "1")?;
+
+        if let Some(memory_max) = limits.memory_max_bytes {
+            cgroup.write("memory.max", &memory_max.to_string())?;
+            // Otherwise the action starts swapping when it reaches the limit, instead of being
+            // killed. This file only exists if swap accounting is enabled.
+            if fs_util::try_exists(join(&cgroup.path, "memory.swap.max"))? {
+                cgroup.write("memory.swap.max", "0")?;
+            }
+        }
+
+        if let Some(cpu_max_percent) = limits.cpu_max_percent {
+            let quota = cpu_max_percent * CPU_MAX_PERIOD_US / 100;
+            cgroup.write("cpu.max", &format!("{} {}", quota, CPU_MAX_PERIOD_US))?;
+        }
+
+        Ok(cgroup)
+    }
+}
+
+/// Move the current process into the cgroup whose `cgroup.procs` is `procs`. This is called in
+/// the child between `fork` and `exec`, so it only makes syscalls.
+fn enter(procs: &CStr) -> io::Result<()> {
+    // Writing 0 to `cgroup.procs` moves the writing process.
+    let data = b"0";
+    unsafe {
+        let fd = libc::open(procs.as_ptr(), libc::O_WRONLY);
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let written = libc::write(fd, data.as_ptr() as *const libc::c_void, data.len());
+        libc::close(fd);
+        if written < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+fn join(path: &AbsNormPath, file: &str) -> AbsNormPathBuf {
+    path.join(ForwardRelativePath::unchecked_new(file))
+}
+
+pub(crate) struct ActionCgroup {
+    path: AbsNormPathBuf,
+    /// `cgroup.procs` of this cgroup, precomputed so that the child doesn't allocate.
+    procs: CString,
+}
+
+impl ActionCgroup {
+    fn write(&self, file: &str, value: &str) -> buck2_error::Result<()> {
+        let path = join(&self.path, file);
+        fs_util::write(&path, value)
+            .with_buck_error_context(|| format!("Error writing `{}` to `{}`", value, path))
+    }
+
+    /// Have `cmd` run in this cgroup.
+    pub(crate) fn add_to_command(&self, cmd: &mut Command) {
+        let procs = self.procs.clone();
+        // SAFETY: `enter` only makes syscalls.
+        unsafe {
+            cmd.pre_exec(move || enter(&procs));
+        }
+    }
+
+    fn stats(&self) -> buck2_error::Result<CgroupStats> {
+        let read = |file: &str| fs_util::read_to_string_if_exists(join(&self.path, file));
+
+        // `memory.peak` is only available on Linux 5.19 or later.
+        let memory_peak = read("memory.peak")?.and_then(|peak| peak.trim().parse().ok());
+        let cpu_time_us = read("cpu.stat")?.and_then(|stat| flat_keyed_value(&stat, "usage_usec"));
+        let oom_killed = read("memory.events")?
+            .and_then(|events| flat_keyed_value(&events, "oom_kill"))
+            .map(|kills| kills > 0);
+
+        Ok(CgroupStats {
+            memory_peak,
+            cpu_time_us,
+            oom_killed,
+        })
+    }
+}
+
+impl Drop for ActionCgroup {
+    fn drop(&mut self) {
+        // This fails if the action left processes behind. The cgroup gets cleaned up by the
+        // next forkserver instead.
+        if let Err(e) = fs_util::remove_dir(&self.path) {
+            tracing::debug!("Error removing action cgroup: {:#}", e);
+        }
+    }
+}
+
+struct CgroupStats {
+    memory_peak: Option<u64>,
+    cpu_time_us: Option<u64>,
+    oom_killed: Option<bool>,
+}
+
+/// Read a value out of a cgroup file that contains `key value` lines.
+fn flat_keyed_value(contents: &str, key: &str) -> Option<u64> {
+    contents.lines().find_map(|line| {
+        let (k, v) = line.split_once(' ')?;
+        if k == key {
+            v.trim().parse().ok()
+        } else {
+            None
+        }
+    })
+}
+
+/// Adds the resource usage of the action's cgroup to the stats reported by another decoder.
+pub(crate) struct CgroupStatusDecoder<D> {
+    inner: D,
+    cgroup: Option<ActionCgroup>,
+}
+
+impl<D> CgroupStatusDecoder<D> {
+    pub(crate) fn new(inner: D, cgroup: Option<ActionCgroup>) -> Self {
+        Self { inner, cgroup }
+    }
+}
+
+#[async_trait]
+impl<D: StatusDecoder + Send> StatusDecoder for CgroupStatusDecoder<D> {
+    async fn decode_status(self, status: ExitStatus) -> buck2_error::Result<DecodedStatus> {
+        let decoded = self.inner.decode_status(status).await?;
+        let cgroup = match self.cgroup {
+            Some(cgroup) => cgroup,
+            None => return Ok(decoded),
+        };
+
+        match decoded {
+            DecodedStatus::Status {
+                exit_code,
+                execution_stats,
+                sandbox_violations,
+            } => {
+                let stats = cgroup.stats()?;
+                let mut execution_stats = execution_stats.unwrap_or_default();
+                // The cgroup covers the whole action, so prefer its peak if we have one.
+                execution_stats.memory_peak = stats.memory_peak.or(execution_stats.memory_peak);
+                execution_stats.cpu_time_us = stats.cpu_time_us;
+                execution_stats.oom_killed = stats.oom_killed;
+                Ok(DecodedStatus::Status {
+                    exit_code,
+                    execution_stats: Some(execution_stats),
+                    sandbox_violations,
+                })
+            }
+            DecodedStatus::SpawnFailed(reason) => Ok(DecodedStatus::SpawnFailed(reason)),
+        }
+    }
+
+    async fn cancel(self) -> buck2_error::Result<()> {
+        self.inner.cancel().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flat_keyed_value() {
+        let memory_events = "low 0\nhigh 0\nmax 12\noom 1\noom_kill 1\noom_group_kill 1\n";
+        assert_eq!(Some(1), flat_keyed_value(memory_events, "oom_kill"));
+        assert_eq!(Some(12), flat_keyed_value(memory_events, "max"));
+        assert_eq!(None, flat_keyed_value(memory_events, "usage_usec"));
+
+        let cpu_stat = "usage_usec 1534\nuser_usec 1000\nsystem_usec 534\n";
+        assert_eq!(Some(1534), flat_keyed_value(cpu_stat, "usage_usec"));
+    }
+}
@@ -13,6 +13,7 @@ use std::os::unix::net::UnixStream as StdUnixStream;
 use std::sync::Arc;
 
 use buck2_common::init::ResourceControlConfig;
+use buck2_common::init::SandboxConfig;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::logging::LogConfigurationReloadHandle;
 use buck2_error::BuckErrorContext;
@@ -27,6 +28,7 @@ pub async fn run_forkserver(
     log_reload_handle: Arc<dyn LogConfigurationReloadHandle>,
     state_dir: AbsNormPathBuf,
     resource_control: ResourceControlConfig,
+    sandbox: SandboxConfig,
 ) -> buck2_error::Result<()> {
     // SAFETY: At worst, we just read (or close) the wrong FD.
     let io = UnixStream::from_std(unsafe { StdUnixStream::from_raw_fd(fd) })
@@ -37,8 +39,9 @@ pub async fn run_forkserver(
         DuplexChannel::new(read, write)
     };
 
-    let service = UnixForkserverService::new(log_reload_handle, &state_dir, resource_control)
-        .buck_error_context("Failed to create UnixForkserverService")?;
+    let service =
+        UnixForkserverService::new(log_reload_handle, &state_dir, resource_control, sandbox)
+            .buck_error_context("Failed to create UnixForkserverService")?;
 
     let router = tonic::transport::Server::builder().add_service(
         forkserver_server::ForkserverServer::new(service)
@@ -13,6 +13,7 @@ use std::os::unix::io::AsRawFd;
 use std::os::unix::process::CommandExt;
 use std::process::Stdio;
 
+use buck2_common::init::SandboxConfig;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
 use buck2_error::BuckErrorContext;
 use buck2_util::process::background_command;
@@ -26,6 +27,7 @@ pub async fn launch_forkserver(
     args: impl IntoIterator<Item = impl AsRef<OsStr>>,
     state_dir: &AbsNormPath,
     resource_control_arg: String,
+    sandbox: &SandboxConfig,
 ) -> buck2_error::Result<ForkserverClient> {
     let (client_io, server_io) =
         UnixStream::pair().buck_error_context("Failed to create fork server channel")?;
@@ -48,7 +50,9 @@ pub async fn launch_forkserver(
         .arg("--state-dir")
         .arg(state_dir.as_path())
         .arg("--resource-control")
-        .arg(resource_control_arg);
+        .arg(resource_control_arg)
+        .arg("--sandbox")
+        .arg(sandbox.serialize()?);
 
     let fds = [server_io.as_raw_fd()];
 
@@ -81,5 +85,9 @@ pub async fn launch_forkserver(
         .await
         .buck_error_context("Error connecting to Forkserver")?;
 
-    Ok(ForkserverClient::new(child, channel))
+    Ok(ForkserverClient::new(
+        child,
+        channel,
+        sandbox.enabled.then(|| sandbox.clone()),
+    ))
 }
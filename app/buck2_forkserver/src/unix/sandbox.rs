@@ -0,0 +1,371 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Hermetic execution of local commands.
+//!
+//! A sandboxed command runs in its own user and mount namespaces. Inside, the project root is
+//! replaced with a tmpfs that only contains the paths the command was given: its declared inputs
+//! and toolchain directories (read-only), and its output and scratch directories (read-write).
+//! Everything outside the project root is left untouched.
+//!
+//! All the paths and mount points are computed before forking. The child only performs
+//! syscalls, since it must not allocate between `fork` and `exec`.
+//!
+//! If enabled, the paths a sandboxed command accesses are recorded (see [`access_log`]), to report
+//! the hidden ones when the command exits.
+
+pub(crate) mod access_log;
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::ffi::CString;
+use std::ffi::OsStr;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::path::PathBuf;
+
+use buck2_common::init::SandboxConfig;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::abs_path::AbsPath;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+use buck2_error::BuckErrorContext;
+use buck2_forkserver_proto::SandboxSpec;
+
+use crate::unix::sandbox::access_log::AccessLog;
+use crate::unix::sandbox::access_log::AccessLogFilter;
+
+#[derive(buck2_error::Error, Debug)]
+#[buck2(input)]
+enum SandboxError {
+    #[error("Sandboxing local actions is only supported on Linux")]
+    Unsupported,
+    #[error("Working directory `{0}` is not in the project root `{1}`")]
+    CwdOutsideProjectRoot(String, String),
+}
+
+pub(crate) struct Sandbox {
+    /// The mount point of the tmpfs that becomes the sandboxed project root. This is only ever
+    /// mounted in the namespace of a sandboxed command.
+    staging_dir: AbsNormPathBuf,
+    /// Whether the paths that commands access can be recorded on this system.
+    record_accesses: bool,
+}
+
+impl Sandbox {
+    pub(crate) fn new(
+        forkserver_state_dir: &AbsNormPath,
+        config: &SandboxConfig,
+    ) -> buck2_error::Result<Option<Self>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+
+        if !cfg!(target_os = "linux") {
+            return Err(SandboxError::Unsupported.into());
+        }
+
+        let staging_dir = forkserver_state_dir.join(ForwardRelativePath::unchecked_new("sandbox"));
+        fs_util::remove_all(&staging_dir)?;
+        fs_util::create_dir_all(&staging_dir)?;
+
+        let record_accesses = config.record_accesses && access_log::is_supported();
+        if config.record_accesses && !record_accesses {
+            tracing::warn!(
+                "Sandbox violations are not reported: recording accesses needs Linux 5.5 on x86_64 or aarch64"
+            );
+        }
+
+        Ok(Some(Self {
+            staging_dir,
+            record_accesses,
+        }))
+    }
+
+    /// Compute the mounts that build the sandbox described by `spec` for a command running in
+    /// `cwd`, and the access log that records which hidden paths the command tries to access.
+    pub(crate) fn prepare(
+        &self,
+        spec: &SandboxSpec,
+        cwd: &AbsPath,
+    ) -> buck2_error::Result<(PreparedSandbox, Option<AccessLog>)> {
+        let project_root = AbsPath::new(Path::new(OsStr::from_bytes(&spec.project_root)))?;
+
+        let cwd = cwd
+            .strip_prefix(project_root)
+            .map_err(|_| {
+                SandboxError::CwdOutsideProjectRoot(
+                    cwd.as_path().display().to_string(),
+                    project_root.as_path().display().to_string(),
+                )
+            })?
+            .to_owned();
+
+        // Writable paths win if a path is listed as both.
+        let mut exposed = BTreeMap::new();
+        for path in &spec.readonly_paths {
+            exposed.insert(parse_path(path)?, true);
+        }
+        for path in &spec.writable_paths {
+            exposed.insert(parse_path(path)?, false);
+        }
+
+        let (access_log, access_log_filter) = if self.record_accesses {
+            access_log::access_log(
+                project_root.as_path(),
+                &cwd,
+                exposed.keys().cloned().collect(),
+            )?
+            .unzip()
+        } else {
+            (None, None)
+        };
+
+        let mut dirs = BTreeSet::new();
+        let mut files = BTreeSet::new();
+        let mut mounts = Vec::new();
+
+        dirs.insert(PathBuf::new());
+        add_ancestors(&mut dirs, &cwd);
+        dirs.insert(cwd.clone());
+
+        for (path, readonly) in exposed {
+            let source = project_root.join(path.as_str());
+            // Mounts follow symlinks, so the mount point has to match what the path resolves to.
+            // Paths that don't exist (or dangle) are not exposed.
+            let is_dir = match fs_util::metadata(&source) {
+                Ok(meta) => meta.is_dir(),
+                Err(_) => continue,
+            };
+            let target = PathBuf::from(path.as_str());
+            add_ancestors(&mut dirs, &target);
+            if is_dir {
+                dirs.insert(target.clone());
+            } else {
+                files.insert(target.clone());
+            }
+            mounts.push((path, source, readonly));
+        }
+
+        // Parents must be mounted before their children, otherwise the children are hidden.
+        mounts.sort_by_key(|(path, _, _)| path.iter().count());
+
+        let staging = |path: &Path| cstring(&self.staging_dir.as_path().join(path));
+
+        let prepared = PreparedSandbox {
+            staging_dir: cstring(self.staging_dir.as_path())?,
+            project_root: cstring(project_root.as_path())?,
+            cwd: cstring(project_root.join(&cwd).as_path())?,
+            dirs: dirs.iter().map(|d| staging(d)).collect::<Result<_, _>>()?,
+            files: files.iter().map(|f| staging(f)).collect::<Result<_, _>>()?,
+            mounts: mounts
+                .iter()
+                .map(|(path, source, readonly)| {
+                    buck2_error::Ok(BindMount {
+                        source: cstring(source.as_path())?,
+                        target: staging(Path::new(path.as_str()))?,
+                        readonly: *readonly,
+                    })
+                })
+                .collect::<Result<_, _>>()?,
+            uid_map: id_map(unsafe { libc::getuid() })?,
+            gid_map: id_map(unsafe { libc::getgid() })?,
+            access_log: access_log_filter,
+        };
+        Ok((prepared, access_log))
+    }
+}
+
+fn parse_path(path: &[u8]) -> buck2_error::Result<ForwardRelativePathBuf> {
+    let path = std::str::from_utf8(path).buck_error_context("Sandbox path is not UTF-8")?;
+    Ok(ForwardRelativePath::new(path)?.to_buf())
+}
+
+fn add_ancestors(dirs: &mut BTreeSet<PathBuf>, path: &Path) {
+    let mut parent = path.parent();
+    while let Some(p) = parent {
+        if !dirs.insert(p.to_owned()) {
+            break;
+        }
+        parent = p.parent();
+    }
+}
+
+fn cstring(path: &Path) -> buck2_error::Result<CString> {
+    CString::new(path.as_os_str().as_bytes())
+        .with_buck_error_context(|| format!("Invalid path: `{}`", path.display()))
+}
+
+/// Map our own id into the namespace, so that files keep their owners.
+fn id_map(id: libc::uid_t) -> buck2_error::Result<CString> {
+    Ok(CString::new(format!("{} {} 1\n", id, id))?)
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+struct BindMount {
+    source: CString,
+    target: CString,
+    readonly: bool,
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) struct PreparedSandbox {
+    staging_dir: CString,
+    project_root: CString,
+    cwd: CString,
+    /// Directories to create in the staging tmpfs, parents first.
+    dirs: Vec<CString>,
+    /// Empty files to create in the staging tmpfs, as mount points for files.
+    files: Vec<CString>,
+    mounts: Vec<BindMount>,
+    uid_map: CString,
+    gid_map: CString,
+    access_log: Option<AccessLogFilter>,
+}
+
+impl PreparedSandbox {
+    /// Move the current process into the sandbox. This is called in the child between `fork`
+    /// and `exec`, after the working directory has been set.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn enter(&self) -> io::Result<()> {
+        use std::ffi::CStr;
+        use std::ptr;
+
+        fn check(ret: libc::c_int) -> io::Result<()> {
+            if ret < 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        }
+
+        fn write_file(path: &CStr, data: &[u8]) -> io::Result<()> {
+            unsafe {
+                let fd = libc::open(path.as_ptr(), libc::O_WRONLY);
+                check(fd)?;
+                let written = libc::write(fd, data.as_ptr() as *const libc::c_void, data.len());
+                libc::close(fd);
+                if written < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        }
+
+        unsafe {
+            check(libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNS))?;
+            write_file(c"/proc/self/setgroups", b"deny")?;
+            write_file(c"/proc/self/uid_map", self.uid_map.as_bytes())?;
+            write_file(c"/proc/self/gid_map", self.gid_map.as_bytes())?;
+
+            // Don't let any of our mounts propagate back to the parent namespace.
+            check(libc::mount(
+                ptr::null(),
+                c"/".as_ptr(),
+                ptr::null(),
+                libc::MS_REC | libc::MS_PRIVATE,
+                ptr::null(),
+            ))?;
+
+            check(libc::mount(
+                c"tmpfs".as_ptr(),
+                self.staging_dir.as_ptr(),
+                c"tmpfs".as_ptr(),
+                libc::MS_NOSUID | libc::MS_NODEV,
+                ptr::null(),
+            ))?;
+
+            for dir in &self.dirs {
+                if libc::mkdir(dir.as_ptr(), 0o755) < 0 {
+                    let err = io::Error::last_os_error();
+                    if err.kind() != io::ErrorKind::AlreadyExists {
+                        return Err(err);
+                    }
+                }
+            }
+
+            for file in &self.files {
+                let fd = libc::open(file.as_ptr(), libc::O_WRONLY | libc::O_CREAT, 0o644);
+                check(fd)?;
+                libc::close(fd);
+            }
+
+            for mount in &self.mounts {
+                check(libc::mount(
+                    mount.source.as_ptr(),
+                    mount.target.as_ptr(),
+                    ptr::null(),
+                    libc::MS_BIND | libc::MS_REC,
+                    ptr::null(),
+                ))?;
+
+                if mount.readonly {
+                    // A bind mount can only be made read-only by remounting it, and the remount
+                    // has to preserve the flags that are locked by the user namespace.
+                    let mut stat: libc::statvfs = std::mem::zeroed();
+                    check(libc::statvfs(mount.target.as_ptr(), &mut stat))?;
+                    check(libc::mount(
+                        ptr::null(),
+                        mount.target.as_ptr(),
+                        ptr::null(),
+                        libc::MS_BIND
+                            | libc::MS_REMOUNT
+                            | libc::MS_RDONLY
+                            | locked_mount_flags(stat.f_flag),
+                        ptr::null(),
+                    ))?;
+                }
+            }
+
+            check(libc::mount(
+                self.staging_dir.as_ptr(),
+                self.project_root.as_ptr(),
+                ptr::null(),
+                libc::MS_BIND | libc::MS_REC,
+                ptr::null(),
+            ))?;
+
+            // Our working directory still points at the directory the sandbox just covered.
+            check(libc::chdir(self.cwd.as_ptr()))?;
+        }
+
+        // Last, so that setting up the sandbox isn't recorded.
+        if let Some(access_log) = &self.access_log {
+            access_log.install()?;
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub(crate) fn enter(&self) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Sandboxing is only supported on Linux",
+        ))
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn locked_mount_flags(statvfs_flags: libc::c_ulong) -> libc::c_ulong {
+    [
+        (libc::ST_NOSUID, libc::MS_NOSUID),
+        (libc::ST_NODEV, libc::MS_NODEV),
+        (libc::ST_NOEXEC, libc::MS_NOEXEC),
+        (libc::ST_NOATIME, libc::MS_NOATIME),
+        (libc::ST_NODIRATIME, libc::MS_NODIRATIME),
+        (libc::ST_RELATIME, libc::MS_RELATIME),
+    ]
+    .into_iter()
+    .filter(|(st, _)| statvfs_flags & st != 0)
+    .fold(0, |flags, (_, ms)| flags | ms)
+}
@@ -0,0 +1,833 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Recording which hidden paths a sandboxed command tries to access.
+//!
+//! Before `exec`, the sandboxed command installs a seccomp filter that reports the syscalls that
+//! look up a path (`SECCOMP_RET_USER_NOTIF`), and sends the filter's listener to the forkserver
+//! over a socket. The forkserver reads the path of every reported syscall, records it, and lets
+//! the syscall continue unchanged. When the command exits, the recorded paths that exist in the
+//! project but were hidden by the sandbox are its violations.
+//!
+//! `execve` is not reported: the first `exec` happens before the forkserver starts listening.
+
+use std::collections::BTreeSet;
+use std::os::fd::OwnedFd;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+use dupe::Dupe;
+use tokio::task::JoinHandle;
+
+use crate::run::status_decoder::DecodedStatus;
+use crate::run::status_decoder::StatusDecoder;
+
+/// Whether accesses can be recorded on this system. Listeners need Linux 5.0 and letting the
+/// reported syscalls continue needs Linux 5.5.
+pub(crate) fn is_supported() -> bool {
+    #[cfg(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    {
+        linux::kernel_version().is_some_and(|version| version >= (5, 5))
+    }
+    #[cfg(not(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    )))]
+    {
+        false
+    }
+}
+
+/// What the sandbox exposes, to tell which recorded accesses are violations.
+#[derive(Clone)]
+struct Exposed {
+    project_root: PathBuf,
+    /// The working directory of the command, relative to the project root.
+    cwd: PathBuf,
+    paths: Vec<ForwardRelativePathBuf>,
+}
+
+impl Exposed {
+    /// A path is visible in the sandbox if it is exposed, is within an exposed path, or is one of
+    /// the directories leading to an exposed path or to the working directory.
+    fn is_visible(&self, path: &ForwardRelativePath) -> bool {
+        self.cwd.starts_with(path.as_str())
+            || self
+                .paths
+                .iter()
+                .any(|exposed| path.starts_with(exposed) || exposed.starts_with(path))
+    }
+
+    /// The accessed paths that exist in the project but were hidden by the sandbox, relative to
+    /// the project root.
+    fn violations(&self, accesses: &BTreeSet<PathBuf>) -> Vec<String> {
+        let mut violations = BTreeSet::new();
+        for access in accesses {
+            let access = normalize(access);
+            let Ok(path) = access.strip_prefix(&self.project_root) else {
+                continue;
+            };
+            let Ok(path) = ForwardRelativePath::new(path) else {
+                continue;
+            };
+            if path.is_empty() || self.is_visible(path) {
+                continue;
+            }
+            if self
+                .project_root
+                .join(path.as_str())
+                .symlink_metadata()
+                .is_ok()
+            {
+                violations.insert(path.to_string());
+            }
+        }
+        violations.into_iter().collect()
+    }
+}
+
+/// Resolve `.` and `..` in an absolute path without looking at the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+/// The forkserver's end of the access log of a command that hasn't been spawned yet.
+pub(crate) struct AccessLog {
+    socket: OwnedFd,
+    exposed: Exposed,
+}
+
+/// The command's end of the access log, which installs the filter in the child.
+pub(crate) struct AccessLogFilter {
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    socket: OwnedFd,
+    #[cfg(target_os = "linux")]
+    filter: Vec<libc::sock_filter>,
+}
+
+/// Create both ends of the access log of a command. Returns `None` if accesses can't be recorded
+/// on this system, which callers should check with `is_supported` first.
+pub(crate) fn access_log(
+    project_root: &Path,
+    cwd: &Path,
+    exposed: Vec<ForwardRelativePathBuf>,
+) -> buck2_error::Result<Option<(AccessLog, AccessLogFilter)>> {
+    #[cfg(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    {
+        let (socket, child_socket) = linux::socket_pair()?;
+        Ok(Some((
+            AccessLog {
+                socket,
+                exposed: Exposed {
+                    project_root: project_root.to_owned(),
+                    cwd: cwd.to_owned(),
+                    paths: exposed,
+                },
+            },
+            AccessLogFilter {
+                socket: child_socket,
+                filter: linux::filter(),
+            },
+        )))
+    }
+    #[cfg(not(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    )))]
+    {
+        let _unused = (project_root, cwd, exposed);
+        Ok(None)
+    }
+}
+
+impl AccessLogFilter {
+    /// Install the filter and send its listener to the forkserver. This is called in the child
+    /// between `fork` and `exec`, once the sandbox is set up, so it must not allocate.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn install(&self) -> std::io::Result<()> {
+        #[cfg(all(
+            target_os = "linux",
+            any(target_arch = "x86_64", target_arch = "aarch64")
+        ))]
+        {
+            linux::install(&self.filter, &self.socket)
+        }
+        // Never created on other architectures.
+        #[cfg(not(all(
+            target_os = "linux",
+            any(target_arch = "x86_64", target_arch = "aarch64")
+        )))]
+        {
+            let _unused = (&self.socket, &self.filter);
+            Ok(())
+        }
+    }
+}
+
+impl AccessLog {
+    /// Start recording, once the command was spawned. If the command failed to spawn, nothing is
+    /// recorded.
+    pub(crate) fn start(self) -> RunningAccessLog {
+        let done = Arc::new(AtomicBool::new(false));
+        RunningAccessLog {
+            task: self.supervise(done.dupe()),
+            done,
+            exposed: self.exposed,
+        }
+    }
+
+    fn supervise(&self, done: Arc<AtomicBool>) -> Option<JoinHandle<BTreeSet<PathBuf>>> {
+        #[cfg(all(
+            target_os = "linux",
+            any(target_arch = "x86_64", target_arch = "aarch64")
+        ))]
+        {
+            match linux::recv_listener(&self.socket) {
+                Ok(Some(listener)) => Some(tokio::task::spawn_blocking(move || {
+                    linux::supervise(listener, &done)
+                })),
+                Ok(None) => None,
+                Err(e) => {
+                    tracing::warn!("Error receiving the sandbox access log listener: {}", e);
+                    None
+                }
+            }
+        }
+        // Never created on other systems.
+        #[cfg(not(all(
+            target_os = "linux",
+            any(target_arch = "x86_64", target_arch = "aarch64")
+        )))]
+        {
+            let _unused = (&self.socket, done);
+            None
+        }
+    }
+}
+
+/// Records the accesses of a running command.
+pub(crate) struct RunningAccessLog {
+    task: Option<JoinHandle<BTreeSet<PathBuf>>>,
+    /// Set once the command exited, so that recording stops even if some of its descendants are
+    /// still around. Their syscalls still continue normally.
+    done: Arc<AtomicBool>,
+    exposed: Exposed,
+}
+
+impl RunningAccessLog {
+    /// Stop recording and return the violations.
+    async fn finish(self) -> Vec<String> {
+        self.done.store(true, Ordering::Relaxed);
+        let accesses = match self.task {
+            Some(task) => match task.await {
+                Ok(accesses) => accesses,
+                Err(e) => {
+                    tracing::warn!("Error recording sandbox accesses: {}", e);
+                    return Vec::new();
+                }
+            },
+            None => return Vec::new(),
+        };
+        let exposed = self.exposed;
+        tokio::task::spawn_blocking(move || exposed.violations(&accesses))
+            .await
+            .unwrap_or_default()
+    }
+}
+
+/// Adds the sandbox violations of the command to the status reported by another decoder.
+pub(crate) struct SandboxStatusDecoder<D> {
+    inner: D,
+    access_log: Option<RunningAccessLog>,
+}
+
+impl<D> SandboxStatusDecoder<D> {
+    pub(crate) fn new(inner: D, access_log: Option<RunningAccessLog>) -> Self {
+        Self { inner, access_log }
+    }
+}
+
+#[async_trait]
+impl<D: StatusDecoder + Send> StatusDecoder for SandboxStatusDecoder<D> {
+    async fn decode_status(self, status: ExitStatus) -> buck2_error::Result<DecodedStatus> {
+        let decoded = self.inner.decode_status(status).await?;
+        let access_log = match self.access_log {
+            Some(access_log) => access_log,
+            None => return Ok(decoded),
+        };
+
+        match decoded {
+            DecodedStatus::Status {
+                exit_code,
+                execution_stats,
+                sandbox_violations: _,
+            } => Ok(DecodedStatus::Status {
+                exit_code,
+                execution_stats,
+                sandbox_violations: access_log.finish().await,
+            }),
+            DecodedStatus::SpawnFailed(reason) => Ok(DecodedStatus::SpawnFailed(reason)),
+        }
+    }
+
+    async fn cancel(self) -> buck2_error::Result<()> {
+        if let Some(access_log) = self.access_log {
+            access_log.finish().await;
+        }
+        self.inner.cancel().await
+    }
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod linux {
+    use std::collections::BTreeSet;
+    use std::ffi::OsStr;
+    use std::io;
+    use std::mem;
+    use std::os::fd::AsRawFd;
+    use std::os::fd::FromRawFd;
+    use std::os::fd::OwnedFd;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::PathBuf;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::atomic::Ordering;
+
+    /// `AUDIT_ARCH_*` of the syscalls we filter, from `linux/audit.h`.
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xc000_003e;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xc000_00b7;
+
+    /// Syscalls whose first argument is a directory fd and second argument is a path.
+    const AT_SYSCALLS: &[libc::c_long] = &[
+        libc::SYS_openat,
+        libc::SYS_openat2,
+        libc::SYS_newfstatat,
+        libc::SYS_statx,
+        libc::SYS_faccessat,
+        libc::SYS_faccessat2,
+        libc::SYS_readlinkat,
+    ];
+
+    /// Syscalls whose first argument is a path, which aarch64 doesn't have.
+    #[cfg(target_arch = "x86_64")]
+    const PATH_SYSCALLS: &[libc::c_long] = &[
+        libc::SYS_open,
+        libc::SYS_stat,
+        libc::SYS_lstat,
+        libc::SYS_access,
+        libc::SYS_readlink,
+    ];
+    #[cfg(target_arch = "aarch64")]
+    const PATH_SYSCALLS: &[libc::c_long] = &[];
+
+    /// How much of a path we read, like the kernel.
+    const PATH_MAX: usize = libc::PATH_MAX as usize;
+
+    pub(super) fn kernel_version() -> Option<(u32, u32)> {
+        let mut uts: libc::utsname = unsafe { mem::zeroed() };
+        if unsafe { libc::uname(&mut uts) } < 0 {
+            return None;
+        }
+        let release = unsafe { std::ffi::CStr::from_ptr(uts.release.as_ptr()) };
+        parse_kernel_version(release.to_str().ok()?)
+    }
+
+    pub(super) fn parse_kernel_version(release: &str) -> Option<(u32, u32)> {
+        let mut parts = release.split(|c: char| !c.is_ascii_digit());
+        Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+    }
+
+    pub(super) fn socket_pair() -> io::Result<(OwnedFd, OwnedFd)> {
+        let mut fds = [0; 2];
+        // Both ends are closed on `exec`: the command only needs its end until then.
+        if unsafe {
+            libc::socketpair(
+                libc::AF_UNIX,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+                0,
+                fds.as_mut_ptr(),
+            )
+        } < 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) })
+    }
+
+    fn stmt(code: u32, k: u32) -> libc::sock_filter {
+        libc::sock_filter {
+            code: code as u16,
+            jt: 0,
+            jf: 0,
+            k,
+        }
+    }
+
+    fn jump(k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+        libc::sock_filter {
+            code: (libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K) as u16,
+            jt,
+            jf,
+            k,
+        }
+    }
+
+    /// A BPF program that reports the syscalls that take a path and allows everything else.
+    pub(super) fn filter() -> Vec<libc::sock_filter> {
+        let syscalls: Vec<_> = AT_SYSCALLS.iter().chain(PATH_SYSCALLS).collect();
+        let count = syscalls.len() as u8;
+        // Offsets in `struct seccomp_data`.
+        let nr_offset = 0;
+        let arch_offset = 4;
+
+        let mut filter = vec![
+            stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, arch_offset),
+            // Syscalls of another architecture go straight to the final `ALLOW`.
+            jump(AUDIT_ARCH, 0, count + 1),
+            stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, nr_offset),
+        ];
+        for (i, nr) in syscalls.into_iter().enumerate() {
+            // Jump over the remaining comparisons and the `ALLOW`.
+            filter.push(jump(*nr as u32, count - i as u8, 0));
+        }
+        filter.push(stmt(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ALLOW));
+        filter.push(stmt(
+            libc::BPF_RET | libc::BPF_K,
+            libc::SECCOMP_RET_USER_NOTIF,
+        ));
+        filter
+    }
+
+    fn check(ret: libc::c_long) -> io::Result<libc::c_long> {
+        if ret < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(ret)
+        }
+    }
+
+    /// Space for a control message carrying one fd, aligned like a `cmsghdr`.
+    #[repr(C)]
+    union FdControlMessage {
+        _header: libc::cmsghdr,
+        buf: [u8; 32],
+    }
+
+    pub(super) fn install(filter: &[libc::sock_filter], socket: &OwnedFd) -> io::Result<()> {
+        unsafe {
+            check(libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0).into())?;
+            let prog = libc::sock_fprog {
+                len: filter.len() as u16,
+                filter: filter.as_ptr() as *mut libc::sock_filter,
+            };
+            let listener = check(libc::syscall(
+                libc::SYS_seccomp,
+                libc::SECCOMP_SET_MODE_FILTER,
+                libc::SECCOMP_FILTER_FLAG_NEW_LISTENER,
+                &prog as *const libc::sock_fprog,
+            ))? as libc::c_int;
+
+            let mut data = [0u8; 1];
+            let mut iov = libc::iovec {
+                iov_base: data.as_mut_ptr() as *mut libc::c_void,
+                iov_len: data.len(),
+            };
+            let mut control: FdControlMessage = mem::zeroed();
+            let mut msg: libc::msghdr = mem::zeroed();
+            msg.msg_iov = &mut iov;
+            msg.msg_iovlen = 1;
+            msg.msg_control = control.buf.as_mut_ptr() as *mut libc::c_void;
+            msg.msg_controllen = libc::CMSG_SPACE(mem::size_of::<libc::c_int>() as u32) as _;
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<libc::c_int>() as u32) as _;
+            std::ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut libc::c_int, listener);
+
+            let sent = check(libc::sendmsg(socket.as_raw_fd(), &msg, 0) as libc::c_long);
+            libc::close(listener);
+            sent?;
+        }
+        Ok(())
+    }
+
+    /// Receive the listener sent by `install`. The command was already spawned, so the listener
+    /// is there unless the command failed before installing the filter.
+    pub(super) fn recv_listener(socket: &OwnedFd) -> io::Result<Option<OwnedFd>> {
+        unsafe {
+            let mut data = [0u8; 1];
+            let mut iov = libc::iovec {
+                iov_base: data.as_mut_ptr() as *mut libc::c_void,
+                iov_len: data.len(),
+            };
+            let mut control: FdControlMessage = mem::zeroed();
+            let mut msg: libc::msghdr = mem::zeroed();
+            msg.msg_iov = &mut iov;
+            msg.msg_iovlen = 1;
+            msg.msg_control = control.buf.as_mut_ptr() as *mut libc::c_void;
+            msg.msg_controllen = mem::size_of::<FdControlMessage>() as _;
+
+            let received = libc::recvmsg(
+                socket.as_raw_fd(),
+                &mut msg,
+                libc::MSG_DONTWAIT | libc::MSG_CMSG_CLOEXEC,
+            );
+            if received < 0 {
+                let err = io::Error::last_os_error();
+                return if err.kind() == io::ErrorKind::WouldBlock {
+                    Ok(None)
+                } else {
+                    Err(err)
+                };
+            }
+
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            if cmsg.is_null()
+                || (*cmsg).cmsg_level != libc::SOL_SOCKET
+                || (*cmsg).cmsg_type != libc::SCM_RIGHTS
+            {
+                return Ok(None);
+            }
+            let fd = std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::c_int);
+            Ok(Some(OwnedFd::from_raw_fd(fd)))
+        }
+    }
+
+    enum Event {
+        Notification,
+        Timeout,
+        /// All the processes using the filter exited, or the listener broke.
+        Closed,
+    }
+
+    fn wait(listener: &OwnedFd, timeout_ms: libc::c_int) -> Event {
+        loop {
+            let mut poll = libc::pollfd {
+                fd: listener.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let ret = unsafe { libc::poll(&mut poll, 1, timeout_ms) };
+            if ret < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                tracing::warn!("Error polling the sandbox access log: {}", err);
+                return Event::Closed;
+            }
+            return if poll.revents & libc::POLLIN != 0 {
+                Event::Notification
+            } else if poll.revents & (libc::POLLHUP | libc::POLLERR | libc::POLLNVAL) != 0 {
+                Event::Closed
+            } else {
+                Event::Timeout
+            };
+        }
+    }
+
+    /// Record the paths of the reported syscalls until all the processes using the filter exited,
+    /// or until `done` is set.
+    ///
+    /// Closing the listener would make the reported syscalls of the processes still using the
+    /// filter fail with `ENOSYS`, so if some descendants of the command outlive it, their
+    /// syscalls keep being let through, without being recorded, on another thread until they
+    /// exit.
+    pub(super) fn supervise(listener: OwnedFd, done: &AtomicBool) -> BTreeSet<PathBuf> {
+        let mut accesses = BTreeSet::new();
+        while !done.load(Ordering::Relaxed) {
+            match wait(&listener, 100) {
+                Event::Notification => {
+                    if let Some(path) = handle_notification(&listener, true) {
+                        accesses.insert(path);
+                    }
+                }
+                Event::Timeout => {}
+                Event::Closed => return accesses,
+            }
+        }
+
+        if let Err(e) =
+            buck2_util::threads::thread_spawn("buck2-sandbox-access-log", move || loop {
+                match wait(&listener, -1) {
+                    Event::Notification => {
+                        handle_notification(&listener, false);
+                    }
+                    Event::Timeout => {}
+                    Event::Closed => break,
+                }
+            })
+        {
+            tracing::warn!(
+                "Error starting a thread for the processes left by a sandboxed command: {}",
+                e
+            );
+        }
+        accesses
+    }
+
+    /// Receive one reported syscall, read its path if `record` is set, and let it continue.
+    fn handle_notification(listener: &OwnedFd, record: bool) -> Option<PathBuf> {
+        let mut req: libc::seccomp_notif = unsafe { mem::zeroed() };
+        if unsafe {
+            libc::ioctl(
+                listener.as_raw_fd(),
+                libc::SECCOMP_IOCTL_NOTIF_RECV,
+                &mut req,
+            )
+        } < 0
+        {
+            // The process may have been killed since the syscall was reported.
+            return None;
+        }
+
+        let path = if record {
+            syscall_path(listener, &req)
+        } else {
+            None
+        };
+
+        let mut resp: libc::seccomp_notif_resp = unsafe { mem::zeroed() };
+        resp.id = req.id;
+        resp.flags = libc::SECCOMP_USER_NOTIF_FLAG_CONTINUE as u32;
+        // This only fails if the process is gone.
+        unsafe {
+            libc::ioctl(
+                listener.as_raw_fd(),
+                libc::SECCOMP_IOCTL_NOTIF_SEND,
+                &mut resp,
+            );
+        }
+
+        path
+    }
+
+    /// The absolute path a reported syscall looks up.
+    fn syscall_path(listener: &OwnedFd, req: &libc::seccomp_notif) -> Option<PathBuf> {
+        let nr = req.data.nr as libc::c_long;
+        let (dirfd, addr) = if AT_SYSCALLS.contains(&nr) {
+            (req.data.args[0] as libc::c_int, req.data.args[1])
+        } else {
+            (libc::AT_FDCWD, req.data.args[0])
+        };
+
+        let path = PathBuf::from(OsStr::from_bytes(&read_c_string(req.pid, addr)?));
+        let path = if path.is_absolute() {
+            path
+        } else {
+            let base = if dirfd == libc::AT_FDCWD {
+                format!("/proc/{}/cwd", req.pid)
+            } else {
+                format!("/proc/{}/fd/{}", req.pid, dirfd)
+            };
+            std::fs::read_link(base).ok()?.join(path)
+        };
+
+        // The pid may have been reused if the process was killed while we read from it.
+        let mut id = req.id;
+        if unsafe {
+            libc::ioctl(
+                listener.as_raw_fd(),
+                libc::SECCOMP_IOCTL_NOTIF_ID_VALID,
+                &mut id,
+            )
+        } < 0
+        {
+            return None;
+        }
+        Some(path)
+    }
+
+    /// Read a NUL-terminated string from the memory of another process, one page at a time so
+    /// that we never read past the mapping the string is in.
+    fn read_c_string(pid: u32, mut addr: u64) -> Option<Vec<u8>> {
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u64;
+        let mut string = Vec::new();
+        while string.len() < PATH_MAX {
+            let len = ((page_size - addr % page_size) as usize).min(PATH_MAX - string.len());
+            let mut buf = vec![0u8; len];
+            let local = libc::iovec {
+                iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+                iov_len: len,
+            };
+            let remote = libc::iovec {
+                iov_base: addr as *mut libc::c_void,
+                iov_len: len,
+            };
+            let read =
+                unsafe { libc::process_vm_readv(pid as libc::pid_t, &local, 1, &remote, 1, 0) };
+            if read <= 0 {
+                return None;
+            }
+            buf.truncate(read as usize);
+            if let Some(end) = buf.iter().position(|b| *b == 0) {
+                string.extend_from_slice(&buf[..end]);
+                return Some(string);
+            }
+            string.extend_from_slice(&buf);
+            addr += read as u64;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(paths: &[&str]) -> Vec<ForwardRelativePathBuf> {
+        paths
+            .iter()
+            .map(|p| ForwardRelativePath::new(p).unwrap().to_buf())
+            .collect()
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            Path::new("/repo/src/foo.h"),
+            normalize(Path::new("/repo/./out/../src//foo.h"))
+        );
+        assert_eq!(Path::new("/"), normalize(Path::new("/../..")));
+    }
+
+    #[test]
+    fn test_is_visible() {
+        let exposed = Exposed {
+            project_root: PathBuf::from("/repo"),
+            cwd: PathBuf::from("cell/pkg"),
+            paths: paths(&["src/lib/foo.h", "toolchains/cc", "buck-out/v2/gen/root/lib"]),
+        };
+
+        for visible in [
+            "src",
+            "src/lib/foo.h",
+            "toolchains/cc/bin/cc",
+            "buck-out/v2/gen",
+            "cell",
+        ] {
+            assert!(
+                exposed.is_visible(ForwardRelativePath::new(visible).unwrap()),
+                "{}",
+                visible
+            );
+        }
+        for hidden in [
+            "src/lib/bar.h",
+            "toolchains/rust",
+            "buck-out/v2/gen/root/other",
+            "cell/pkg/BUCK",
+        ] {
+            assert!(
+                !exposed.is_visible(ForwardRelativePath::new(hidden).unwrap()),
+                "{}",
+                hidden
+            );
+        }
+    }
+
+    #[test]
+    fn test_violations() -> buck2_error::Result<()> {
+        let root = tempfile::tempdir()?;
+        let project_root = root.path().canonicalize()?;
+        for file in ["src/foo.h", "src/bar.h", "secret/key"] {
+            let path = project_root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            std::fs::write(path, "")?;
+        }
+
+        let exposed = Exposed {
+            project_root: project_root.clone(),
+            cwd: PathBuf::new(),
+            paths: paths(&["src/foo.h"]),
+        };
+        let accesses = [
+            // Exposed.
+            "src/foo.h",
+            // Hidden, and reached through `..`.
+            "src/../src/bar.h",
+            "secret/key",
+            "secret/key",
+            // Hidden, but doesn't exist.
+            "src/baz.h",
+        ]
+        .iter()
+        .map(|p| project_root.join(p))
+        // Outside the project.
+        .chain([PathBuf::from("/usr/include/stdio.h")])
+        .collect();
+
+        assert_eq!(
+            vec!["secret/key".to_owned(), "src/bar.h".to_owned()],
+            exposed.violations(&accesses)
+        );
+        Ok(())
+    }
+
+    #[cfg(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    #[test]
+    fn test_parse_kernel_version() {
+        assert_eq!(
+            Some((6, 4)),
+            linux::parse_kernel_version("6.4.3-0_fbk1_zion")
+        );
+        assert_eq!(Some((5, 15)), linux::parse_kernel_version("5.15.0"));
+        assert_eq!(None, linux::parse_kernel_version("unknown"));
+    }
+
+    #[cfg(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    #[test]
+    fn test_filter_jumps_to_notify() {
+        let filter = linux::filter();
+        let notify = filter.len() - 1;
+        let allow = filter.len() - 2;
+        assert_eq!(libc::SECCOMP_RET_USER_NOTIF, filter[notify].k);
+        assert_eq!(libc::SECCOMP_RET_ALLOW, filter[allow].k);
+        // The architecture check skips to `ALLOW`.
+        assert_eq!(allow, 1 + 1 + filter[1].jf as usize);
+        // Every syscall comparison jumps to `NOTIFY` on a match.
+        for (i, insn) in filter.iter().enumerate().take(allow).skip(3) {
+            assert_eq!(notify, i + 1 + insn.jt as usize);
+        }
+    }
+}
@@ -12,12 +12,14 @@ use std::fs::File;
 use std::fs::OpenOptions;
 use std::io::Write;
 use std::os::unix::ffi::OsStrExt;
+use std::os::unix::process::CommandExt;
 use std::path::Path;
 use std::pin::Pin;
 use std::sync::Arc;
 
 use buck2_common::convert::ProstDurationExt;
 use buck2_common::init::ResourceControlConfig;
+use buck2_common::init::SandboxConfig;
 use buck2_common::systemd::ParentSlice;
 use buck2_common::systemd::SystemdRunner;
 use buck2_common::systemd::SystemdRunnerConfig;
@@ -55,6 +57,10 @@ use crate::run::stream_command_events;
 use crate::run::timeout_into_cancellation;
 use crate::run::DefaultKillProcess;
 use crate::run::GatherOutputStatus;
+use crate::unix::cgroup::ActionCgroups;
+use crate::unix::cgroup::CgroupStatusDecoder;
+use crate::unix::sandbox::access_log::SandboxStatusDecoder;
+use crate::unix::sandbox::Sandbox;
 
 // Not quite BoxStream: it has to be Sync (...)
 type RunStream =
@@ -68,6 +74,25 @@ pub struct UnixForkserverService {
 
     /// Systemd runner for resource control
     systemd_runner: Option<SystemdRunner>,
+
+    /// Set if commands that ask for it are run in a sandbox.
+    sandbox: Option<Sandbox>,
+
+    /// Set if commands that ask for it are run in their own cgroup.
+    cgroups: Option<ActionCgroups>,
+}
+
+#[derive(buck2_error::Error, Debug)]
+#[buck2(input)]
+enum ForkserverServiceError {
+    #[error(
+        "Sandboxing local actions (`buck2_sandbox.enabled`) is not compatible with resource control (`buck2_resource_control.status`)"
+    )]
+    SandboxWithResourceControl,
+    #[error(
+        "Per-action cgroups (`buck2_resource_control.per_action_cgroups`) are not compatible with running actions through systemd (`buck2_resource_control.status`)"
+    )]
+    CgroupsWithSystemd,
 }
 
 impl UnixForkserverService {
@@ -75,6 +100,7 @@ impl UnixForkserverService {
         log_reload_handle: Arc<dyn LogConfigurationReloadHandle>,
         state_dir: &AbsNormPath,
         resource_control: ResourceControlConfig,
+        sandbox: SandboxConfig,
     ) -> buck2_error::Result<Self> {
         let miniperf = MiniperfContainer::new(state_dir)?;
         let systemd_runner =
@@ -84,10 +110,22 @@ impl UnixForkserverService {
                 // for this we inherit slice
                 ParentSlice::Inherit("forkserver".to_owned()),
             ))?;
+        let sandbox = Sandbox::new(state_dir, &sandbox)?;
+        // Commands run through systemd are spawned outside of our namespaces.
+        if sandbox.is_some() && systemd_runner.is_some() {
+            return Err(ForkserverServiceError::SandboxWithResourceControl.into());
+        }
+        // Commands run through systemd get a scope of their own, outside of our cgroup.
+        if systemd_runner.is_some() && resource_control.per_action_cgroups {
+            return Err(ForkserverServiceError::CgroupsWithSystemd.into());
+        }
+        let cgroups = ActionCgroups::new(&resource_control)?;
         Ok(Self {
             log_reload_handle,
             miniperf,
             systemd_runner,
+            sandbox,
+            cgroups,
         })
     }
 }
@@ -131,6 +169,8 @@ impl Forkserver for UnixForkserverService {
                 std_redirects,
                 graceful_shutdown_timeout_s,
                 action_digest,
+                sandbox,
+                resource_limits,
             } = msg;
 
             let exe = OsStr::from_bytes(&exe);
@@ -144,6 +184,17 @@ impl Forkserver for UnixForkserverService {
                 .buck_error_context("Invalid timeout")?;
 
             let exe = maybe_absolutize_exe(exe, cwd)?;
+
+            let (sandbox, access_log) = match (&self.sandbox, sandbox) {
+                (Some(sandbox), Some(spec)) => {
+                    let (sandbox, access_log) = sandbox.prepare(&spec, cwd)?;
+                    (Some(sandbox), access_log)
+                }
+                _ => (None, None),
+            };
+            // Miniperf lives in buck-out, which the sandbox hides.
+            let enable_miniperf = enable_miniperf && sandbox.is_none();
+
             let systemd_context = self.systemd_runner.as_ref().zip(action_digest);
 
             let (mut cmd, miniperf_output) =
@@ -206,6 +257,23 @@ impl Forkserver for UnixForkserverService {
                 cmd.env("MINIPERF_READ_CGROUP", "1");
             }
 
+            let cgroup = match (&self.cgroups, resource_limits) {
+                (Some(cgroups), Some(limits)) => Some(cgroups.create(&limits)?),
+                _ => None,
+            };
+            // This has to happen before entering the sandbox: once in its own user namespace, the
+            // child is no longer allowed to move itself between cgroups.
+            if let Some(cgroup) = &cgroup {
+                cgroup.add_to_command(&mut cmd);
+            }
+
+            if let Some(sandbox) = sandbox {
+                // SAFETY: `enter` only makes syscalls.
+                unsafe {
+                    cmd.pre_exec(move || sandbox.enter());
+                }
+            }
+
             let stream_stdio = std_redirects.is_none();
             let mut cmd = ProcessCommand::new(cmd);
             if let Some(std_redirects) = std_redirects {
@@ -214,6 +282,7 @@ impl Forkserver for UnixForkserverService {
             }
 
             let process_group = cmd.spawn().map_err(buck2_error::Error::from);
+            let access_log = access_log.map(|access_log| access_log.start());
 
             let timeout = timeout_into_cancellation(timeout);
 
@@ -223,7 +292,10 @@ impl Forkserver for UnixForkserverService {
                 Some(out) => stream_command_events(
                     process_group,
                     cancellation,
-                    MiniperfStatusDecoder::new(out),
+                    SandboxStatusDecoder::new(
+                        CgroupStatusDecoder::new(MiniperfStatusDecoder::new(out), cgroup),
+                        access_log,
+                    ),
                     DefaultKillProcess {
                         graceful_shutdown_timeout_s,
                     },
@@ -233,7 +305,10 @@ impl Forkserver for UnixForkserverService {
                 None => stream_command_events(
                     process_group,
                     cancellation,
-                    DefaultStatusDecoder,
+                    SandboxStatusDecoder::new(
+                        CgroupStatusDecoder::new(DefaultStatusDecoder, cgroup),
+                        access_log,
+                    ),
                     DefaultKillProcess {
                         graceful_shutdown_timeout_s,
                     },
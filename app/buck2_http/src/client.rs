@@ -28,6 +28,7 @@ use hyper::Response;
 use tokio::io::AsyncReadExt;
 use tokio_util::io::StreamReader;
 
+use crate::download_cache::DownloadCacheConfig;
 use crate::redirect::PendingRequest;
 use crate::redirect::RedirectEngine;
 use crate::stats::CountingStream;
@@ -49,6 +50,8 @@ pub struct HttpClient {
     supports_vpnless: bool,
     http2: bool,
     stats: HttpNetworkStats,
+    #[allocative(skip)]
+    download_cache: Option<Arc<DownloadCacheConfig>>,
 }
 
 impl HttpClient {
@@ -197,6 +200,19 @@ impl HttpClient {
     pub fn http2(&self) -> bool {
         self.http2
     }
+
+    /// The download cache to consult before downloading files with a known checksum, if any.
+    pub fn download_cache(&self) -> Option<&DownloadCacheConfig> {
+        self.download_cache.as_deref()
+    }
+
+    /// A client sharing the connections of this one, but using a different download cache.
+    pub fn with_download_cache(&self, download_cache: DownloadCacheConfig) -> Self {
+        Self {
+            download_cache: Some(Arc::new(download_cache)),
+            ..self.dupe()
+        }
+    }
 }
 
 /// Trait wrapper around a hyper::Client because hyper::Client is parameterized by
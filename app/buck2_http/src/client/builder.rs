@@ -32,6 +32,7 @@ use tokio_rustls::TlsConnector;
 
 use super::HttpClient;
 use super::RequestClient;
+use crate::download_cache::DownloadCacheConfig;
 use crate::proxy;
 use crate::stats::HttpNetworkStats;
 use crate::x2p;
@@ -66,6 +67,7 @@ pub struct HttpClientBuilder {
     supports_vpnless: bool,
     http2: bool,
     timeout_config: Option<TimeoutConfig>,
+    download_cache: Option<DownloadCacheConfig>,
 }
 
 impl HttpClientBuilder {
@@ -104,6 +106,7 @@ impl HttpClientBuilder {
             supports_vpnless: false,
             http2: true,
             timeout_config: None,
+            download_cache: None,
         })
     }
 
@@ -214,6 +217,15 @@ impl HttpClientBuilder {
         self.supports_vpnless
     }
 
+    pub fn with_download_cache(&mut self, download_cache: DownloadCacheConfig) -> &mut Self {
+        self.download_cache = Some(download_cache);
+        self
+    }
+
+    pub fn download_cache(&self) -> Option<&DownloadCacheConfig> {
+        self.download_cache.as_ref()
+    }
+
     fn build_inner(&self) -> Arc<dyn RequestClient> {
         match (self.proxies.as_slice(), &self.timeout_config) {
             // Construct x2p unix socket client.
@@ -294,6 +306,7 @@ impl HttpClientBuilder {
             supports_vpnless: self.supports_vpnless,
             http2: self.http2,
             stats: HttpNetworkStats::new(),
+            download_cache: self.download_cache.clone().map(Arc::new),
         }
     }
 }
@@ -0,0 +1,23 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::path::PathBuf;
+
+/// A user-level cache of downloaded files, keyed by their checksum. It is shared by all the
+/// daemons of a user, and consulted before downloading anything with a checksum.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DownloadCacheConfig {
+    /// The directory where downloads are stored.
+    pub dir: PathBuf,
+    /// When the cache grows beyond this size, the least recently used downloads are evicted.
+    pub max_size_bytes: Option<u64>,
+    /// Serve downloads from the cache only, without ever using the network. Downloads missing
+    /// from the cache are errors, and the cache is never written to.
+    pub offline: bool,
+}
@@ -13,6 +13,7 @@
 use hyper::StatusCode;
 
 mod client;
+mod download_cache;
 mod proxy;
 mod redirect;
 pub mod retries;
@@ -22,6 +23,7 @@ mod x2p;
 pub use client::to_bytes;
 pub use client::HttpClient;
 pub use client::HttpClientBuilder;
+pub use download_cache::DownloadCacheConfig;
 
 fn http_error_label(status: StatusCode) -> &'static str {
     if status.is_server_error() {
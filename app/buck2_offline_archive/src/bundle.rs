@@ -0,0 +1,435 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! An "offline bundle" is a directory containing everything buck2 would otherwise fetch from the
+//! network to build a set of targets: `download_file` and `cas_artifact` outputs, and the commits
+//! of git external cells.
+//!
+//! A bundle is recorded with `buck2 debug offline-bundle <dir> <targets>`, and used by building
+//! with `buck2.offline_bundle_dir = <dir>`, in which case no fetch goes to the network. The layout
+//! of a bundle is:
+//!
+//! ```text
+//! <bundle>/manifest.json
+//! <bundle>/downloads/{sha1,sha256}/<checksum>   (same layout as the download cache)
+//! <bundle>/cas/<hash>-<size>
+//! <bundle>/git/<commit>                          (a bare repository containing the commit)
+//! ```
+
+use std::collections::BTreeSet;
+use std::process::ExitStatus;
+use std::process::Stdio;
+use std::sync::Mutex;
+
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_error::internal_error;
+use buck2_error::BuckErrorContext;
+use buck2_util::process::background_command;
+
+#[derive(Debug, buck2_error::Error)]
+#[buck2(input)]
+enum OfflineBundleError {
+    #[error("`{0}` is not an offline bundle: it has no `manifest.json`")]
+    NoManifest(AbsNormPathBuf),
+    #[error("The offline bundle at `{bundle}` does not contain {what}")]
+    Missing {
+        bundle: AbsNormPathBuf,
+        what: String,
+    },
+    #[error("Error fetching `{commit}` from `{origin}` with git, exit code: {exit_code:?}, stderr:\n{stderr}")]
+    Git {
+        origin: String,
+        commit: String,
+        exit_code: ExitStatus,
+        stderr: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OfflineBundleMode {
+    /// Add fetches to the bundle, creating it if needed.
+    Record,
+    /// Serve all fetches from the bundle, and fail the ones it can't serve.
+    Replay,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    serde::Serialize,
+    serde::Deserialize
+)]
+pub struct BundledDownload {
+    pub url: String,
+    pub sha1: Option<String>,
+    pub sha256: Option<String>,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    serde::Serialize,
+    serde::Deserialize
+)]
+pub struct BundledCasArtifact {
+    /// The digest of the artifact, as `<hash>:<size>`.
+    pub digest: String,
+    pub is_directory: bool,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    serde::Serialize,
+    serde::Deserialize
+)]
+pub struct BundledGitCell {
+    pub origin: String,
+    pub commit: String,
+}
+
+/// What a network action fetches.
+#[derive(Debug, Clone)]
+pub enum BundledFetch {
+    Download(BundledDownload),
+    CasArtifact(BundledCasArtifact),
+}
+
+/// Lists the contents of an offline bundle.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct OfflineBundleManifest {
+    pub downloads: BTreeSet<BundledDownload>,
+    pub cas_artifacts: BTreeSet<BundledCasArtifact>,
+    pub git_cells: BTreeSet<BundledGitCell>,
+}
+
+pub struct OfflineBundle {
+    root: AbsNormPathBuf,
+    manifest: Mutex<OfflineBundleManifest>,
+}
+
+impl OfflineBundle {
+    /// Open the bundle at `root`. In record mode, the bundle is created if it does not exist yet,
+    /// and recording adds to what the bundle already contains.
+    pub fn open(root: AbsNormPathBuf, mode: OfflineBundleMode) -> buck2_error::Result<Self> {
+        let manifest_path = Self::manifest_path(&root);
+        let manifest = match fs_util::read_to_string_if_exists(&manifest_path)? {
+            Some(manifest) => serde_json::from_str(&manifest).with_buck_error_context(|| {
+                format!("Error parsing offline bundle manifest `{}`", manifest_path)
+            })?,
+            None if mode == OfflineBundleMode::Record => {
+                fs_util::create_dir_all(&root)?;
+                OfflineBundleManifest::default()
+            }
+            None => return Err(OfflineBundleError::NoManifest(root).into()),
+        };
+        Ok(Self {
+            root,
+            manifest: Mutex::new(manifest),
+        })
+    }
+
+    pub fn root(&self) -> &AbsNormPath {
+        &self.root
+    }
+
+    pub fn manifest(&self) -> OfflineBundleManifest {
+        self.manifest.lock().unwrap().clone()
+    }
+
+    fn manifest_path(root: &AbsNormPath) -> AbsNormPathBuf {
+        root.join(ForwardRelativePath::unchecked_new("manifest.json"))
+    }
+
+    /// The directory containing the downloads, laid out like the download cache so that it can
+    /// be used as one.
+    pub fn downloads_dir(&self) -> AbsNormPathBuf {
+        self.root
+            .join(ForwardRelativePath::unchecked_new("downloads"))
+    }
+
+    fn download_path(&self, download: &BundledDownload) -> buck2_error::Result<AbsNormPathBuf> {
+        // The download cache looks for sha256 entries first.
+        let (kind, checksum) = match (&download.sha256, &download.sha1) {
+            (Some(sha256), _) => ("sha256", sha256),
+            (None, Some(sha1)) => ("sha1", sha1),
+            (None, None) => {
+                return Err(internal_error!(
+                    "Download of `{}` has no checksum",
+                    download.url
+                ));
+            }
+        };
+        Ok(self
+            .downloads_dir()
+            .join(ForwardRelativePath::unchecked_new(kind))
+            .join(ForwardRelativePath::new(checksum)?))
+    }
+
+    fn cas_path(&self, digest: &str) -> buck2_error::Result<AbsNormPathBuf> {
+        // `:` is not allowed in file names on Windows.
+        let name = digest.replace(':', "-");
+        Ok(self
+            .root
+            .join(ForwardRelativePath::unchecked_new("cas"))
+            .join(ForwardRelativePath::new(&name)?))
+    }
+
+    /// The bare repository containing `commit`.
+    fn git_repo_path(&self, commit: &str) -> buck2_error::Result<AbsNormPathBuf> {
+        Ok(self
+            .root
+            .join(ForwardRelativePath::unchecked_new("git"))
+            .join(ForwardRelativePath::new(commit)?))
+    }
+
+    /// The bare repository containing `commit`, failing if it's not in the bundle.
+    pub fn existing_git_repo_path(&self, commit: &str) -> buck2_error::Result<AbsNormPathBuf> {
+        let path = self.git_repo_path(commit)?;
+        if !fs_util::try_exists(&path)? {
+            return Err(self.missing(format!("git commit `{}`", commit)));
+        }
+        Ok(path)
+    }
+
+    /// Copy `output`, the output of the network action that does `fetch`, to the bundle and
+    /// record it.
+    pub fn store_fetch(
+        &self,
+        fetch: &BundledFetch,
+        output: &AbsNormPath,
+    ) -> buck2_error::Result<()> {
+        match fetch {
+            BundledFetch::Download(download) => {
+                self.store(output, &self.download_path(download)?)?;
+                self.record(|manifest| manifest.downloads.insert(download.clone()))
+            }
+            BundledFetch::CasArtifact(artifact) => {
+                self.store(output, &self.cas_path(&artifact.digest)?)?;
+                self.record(|manifest| manifest.cas_artifacts.insert(artifact.clone()))
+            }
+        }
+    }
+
+    fn store(&self, src: &AbsNormPath, dest: &AbsNormPath) -> buck2_error::Result<()> {
+        fs_util::remove_all(dest)?;
+        if let Some(parent) = dest.parent() {
+            fs_util::create_dir_all(parent)?;
+        }
+        copy_entry(src, dest)
+            .with_buck_error_context(|| format!("Error adding `{}` to the offline bundle", src))
+    }
+
+    /// Fetch the commit of `git_cell` from its origin to the bundle and record it. This runs git,
+    /// so it blocks.
+    pub fn store_git_cell(&self, git_cell: &BundledGitCell) -> buck2_error::Result<()> {
+        let repo = self.git_repo_path(&git_cell.commit)?;
+        fs_util::remove_all(&repo)?;
+        fs_util::create_dir_all(&repo)?;
+
+        let run_git = |args: &[&str]| -> buck2_error::Result<()> {
+            let output = background_command("git")
+                .args(args)
+                .current_dir(&repo)
+                .stderr(Stdio::piped())
+                .stdout(Stdio::null())
+                .output()
+                .buck_error_context("Could not run git to add a commit to the offline bundle")?;
+            if !output.status.success() {
+                return Err(OfflineBundleError::Git {
+                    origin: git_cell.origin.clone(),
+                    commit: git_cell.commit.clone(),
+                    exit_code: output.status,
+                    stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+                }
+                .into());
+            }
+            Ok(())
+        };
+        run_git(&["init", "--bare"])?;
+        // Git servers only serve commits by hash if a ref points to them, so give the commit one.
+        run_git(&[
+            "fetch",
+            &git_cell.origin,
+            &format!("{}:refs/heads/main", git_cell.commit),
+        ])?;
+
+        self.record(|manifest| manifest.git_cells.insert(git_cell.clone()))
+    }
+
+    /// Copy the artifact with `digest` from the bundle to `dest`.
+    pub fn restore_cas_artifact(
+        &self,
+        digest: &str,
+        dest: &AbsNormPath,
+    ) -> buck2_error::Result<()> {
+        let src = self.cas_path(digest)?;
+        if !fs_util::try_exists(&src)? {
+            return Err(self.missing(format!("CAS artifact `{}`", digest)));
+        }
+        if let Some(parent) = dest.parent() {
+            fs_util::create_dir_all(parent)?;
+        }
+        copy_entry(&src, dest)
+            .with_buck_error_context(|| format!("Error copying `{}` from the offline bundle", src))
+    }
+
+    /// Update the manifest, and write it if it changed.
+    fn record(
+        &self,
+        update: impl FnOnce(&mut OfflineBundleManifest) -> bool,
+    ) -> buck2_error::Result<()> {
+        let mut manifest = self.manifest.lock().unwrap();
+        if !update(&mut manifest) {
+            return Ok(());
+        }
+
+        // Write to a temporary file first so that the manifest is never partially written.
+        let manifest_path = Self::manifest_path(&self.root);
+        let tmp = self
+            .root
+            .join(ForwardRelativePath::unchecked_new(".manifest.json.tmp"));
+        fs_util::write(&tmp, serde_json::to_string_pretty(&*manifest)?)?;
+        fs_util::rename(&tmp, &manifest_path)?;
+        Ok(())
+    }
+
+    fn missing(&self, what: String) -> buck2_error::Error {
+        OfflineBundleError::Missing {
+            bundle: self.root.clone(),
+            what,
+        }
+        .into()
+    }
+}
+
+/// Copy a file, symlink or directory. Symlinks are copied as they are, which is correct for
+/// artifacts since these only contain relative symlinks pointing within the artifact.
+fn copy_entry(src: &AbsNormPath, dest: &AbsNormPath) -> buck2_error::Result<()> {
+    let file_type = fs_util::symlink_metadata(src)?.file_type();
+    if file_type.is_symlink() {
+        fs_util::symlink(fs_util::read_link(src)?, dest)?;
+    } else if file_type.is_dir() {
+        fs_util::create_dir_all(dest)?;
+        for entry in fs_util::read_dir(src)? {
+            let entry = entry?;
+            let dest = dest.join(ForwardRelativePath::new(&entry.file_name())?);
+            copy_entry(&entry.path(), &dest)?;
+        }
+    } else {
+        fs_util::copy(src, dest)?;
+    }
+    Ok(())
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn bundle_root(tempdir: &TempDir) -> buck2_error::Result<AbsNormPathBuf> {
+        AbsNormPathBuf::new(tempdir.path().canonicalize()?.join("bundle"))
+    }
+
+    #[test]
+    fn test_record_and_replay() -> buck2_error::Result<()> {
+        let tempdir = TempDir::new()?;
+        let root = bundle_root(&tempdir)?;
+
+        let downloaded = AbsNormPathBuf::new(tempdir.path().canonicalize()?.join("foo.tar.gz"))?;
+        fs_util::write(&downloaded, "foo")?;
+
+        let bundle = OfflineBundle::open(root.clone(), OfflineBundleMode::Record)?;
+        let download = BundledDownload {
+            url: "https://example.com/foo.tar.gz".to_owned(),
+            sha1: Some("0beec7b5ea3f0fdbc95d0dd47f3c5bc275da8a33".to_owned()),
+            sha256: None,
+        };
+        let fetch = BundledFetch::Download(download.clone());
+        bundle.store_fetch(&fetch, &downloaded)?;
+        bundle.store_fetch(&fetch, &downloaded)?;
+
+        let bundle = OfflineBundle::open(root, OfflineBundleMode::Replay)?;
+        let manifest = bundle.manifest();
+        assert_eq!(
+            vec![&download],
+            manifest.downloads.iter().collect::<Vec<_>>()
+        );
+        assert!(manifest.cas_artifacts.is_empty());
+        // Laid out like the download cache.
+        assert_eq!(
+            "foo",
+            fs_util::read_to_string(bundle.downloads_dir().join(
+                ForwardRelativePath::unchecked_new("sha1/0beec7b5ea3f0fdbc95d0dd47f3c5bc275da8a33")
+            ))?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_replay_requires_manifest() -> buck2_error::Result<()> {
+        let tempdir = TempDir::new()?;
+        let root = bundle_root(&tempdir)?;
+        assert!(OfflineBundle::open(root, OfflineBundleMode::Replay).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cas_artifact_roundtrip() -> buck2_error::Result<()> {
+        let tempdir = TempDir::new()?;
+        let root = bundle_root(&tempdir)?;
+        let work = AbsNormPathBuf::new(tempdir.path().canonicalize()?)?;
+
+        let src = work.join(ForwardRelativePath::unchecked_new("src"));
+        fs_util::create_dir_all(src.join(ForwardRelativePath::unchecked_new("dir")))?;
+        fs_util::write(
+            src.join(ForwardRelativePath::unchecked_new("dir/file")),
+            "contents",
+        )?;
+        fs_util::symlink(
+            "dir/file",
+            src.join(ForwardRelativePath::unchecked_new("link")),
+        )?;
+
+        let bundle = OfflineBundle::open(root, OfflineBundleMode::Record)?;
+        bundle.store_fetch(
+            &BundledFetch::CasArtifact(BundledCasArtifact {
+                digest: "abcd:8".to_owned(),
+                is_directory: true,
+            }),
+            &src,
+        )?;
+
+        let dest = work.join(ForwardRelativePath::unchecked_new("out/dest"));
+        bundle.restore_cas_artifact("abcd:8", &dest)?;
+        assert_eq!(
+            "contents",
+            fs_util::read_to_string(dest.join(ForwardRelativePath::unchecked_new("link")))?
+        );
+        assert!(bundle.restore_cas_artifact("efgh:8", &dest).is_err());
+        assert_eq!(1, bundle.manifest().cas_artifacts.len());
+        Ok(())
+    }
+}
@@ -9,6 +9,8 @@
 
 #![feature(error_generic_member_access)]
 
+pub mod bundle;
+
 use std::ffi::OsStr;
 use std::fmt;
 use std::path::Path;
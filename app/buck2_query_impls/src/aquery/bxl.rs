@@ -301,6 +301,102 @@ impl BxlAqueryFunctions for BxlAqueryFunctionsImpl {
         })
         .await
     }
+
+    async fn inputs_of(
+        &self,
+        dice: &mut DiceComputations<'_>,
+        targets: &TargetSet<ActionQueryNode>,
+    ) -> buck2_error::Result<TargetSet<ActionQueryNode>> {
+        dice.with_linear_recompute(|dice| async move {
+            let query_val = special_aquery_functions()
+                .inputs_of(
+                    &self.aquery_env(&self.aquery_delegate(&dice).await?).await?,
+                    targets.clone(),
+                )
+                .await?;
+
+            match &query_val {
+                QueryValue::TargetSet(s) => Ok(s.clone()),
+                _ => unreachable!("inputs_of should always return target set"),
+            }
+        })
+        .await
+    }
+
+    async fn consumers(
+        &self,
+        universe: &TargetSet<ActionQueryNode>,
+        targets: &TargetSet<ActionQueryNode>,
+    ) -> buck2_error::Result<TargetSet<ActionQueryNode>> {
+        let query_val = special_aquery_functions()
+            .consumers(universe.clone(), targets.clone())
+            .await?;
+
+        match query_val {
+            QueryValue::TargetSet(s) => Ok(s),
+            _ => unreachable!("consumers should always return target set"),
+        }
+    }
+
+    async fn producers(
+        &self,
+        regex: &str,
+        targets: &TargetSet<ActionQueryNode>,
+    ) -> buck2_error::Result<TargetSet<ActionQueryNode>> {
+        let query_val = special_aquery_functions()
+            .producers(regex.to_owned(), targets.clone())
+            .await?;
+
+        match query_val {
+            QueryValue::TargetSet(s) => Ok(s),
+            _ => unreachable!("producers should always return target set"),
+        }
+    }
+
+    async fn category(
+        &self,
+        regex: &str,
+        targets: &TargetSet<ActionQueryNode>,
+    ) -> buck2_error::Result<TargetSet<ActionQueryNode>> {
+        let query_val = special_aquery_functions()
+            .category(regex.to_owned(), targets.clone())
+            .await?;
+
+        match query_val {
+            QueryValue::TargetSet(s) => Ok(s),
+            _ => unreachable!("category should always return target set"),
+        }
+    }
+
+    async fn identifier(
+        &self,
+        regex: &str,
+        targets: &TargetSet<ActionQueryNode>,
+    ) -> buck2_error::Result<TargetSet<ActionQueryNode>> {
+        let query_val = special_aquery_functions()
+            .identifier(regex.to_owned(), targets.clone())
+            .await?;
+
+        match query_val {
+            QueryValue::TargetSet(s) => Ok(s),
+            _ => unreachable!("identifier should always return target set"),
+        }
+    }
+
+    async fn executor(
+        &self,
+        regex: &str,
+        targets: &TargetSet<ActionQueryNode>,
+    ) -> buck2_error::Result<TargetSet<ActionQueryNode>> {
+        let query_val = special_aquery_functions()
+            .executor(regex.to_owned(), targets.clone())
+            .await?;
+
+        match query_val {
+            QueryValue::TargetSet(s) => Ok(s),
+            _ => unreachable!("executor should always return target set"),
+        }
+    }
 }
 
 pub(crate) fn init_new_bxl_aquery_functions() {
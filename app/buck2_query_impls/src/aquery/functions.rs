@@ -7,12 +7,14 @@
  * of this source tree.
  */
 
+use std::collections::HashSet;
 use std::fmt;
 use std::fmt::Debug;
 use std::marker::PhantomData;
 
 use buck2_build_api::actions::query::ActionQueryNode;
 use buck2_build_api::actions::query::ActionQueryNodeData;
+use buck2_query::query::environment::QueryTarget;
 use buck2_query::query::syntax::simple::eval::error::QueryError;
 use buck2_query::query::syntax::simple::eval::set::TargetSet;
 use buck2_query::query::syntax::simple::eval::values::QueryValue;
@@ -23,6 +25,7 @@ use buck2_query::query::syntax::simple::functions::QueryFunctions;
 use buck2_query::query_module;
 use buck2_query_parser::BinaryOp;
 use dupe::Dupe;
+use fancy_regex::Regex;
 
 use crate::aquery::environment::AqueryEnvironment;
 
@@ -130,4 +133,128 @@ impl<'a> AqueryFunctions<'a> {
 
         Ok(res.into())
     }
+
+    /// Obtain the actions that produce the direct inputs of the given actions.
+    ///
+    /// This is the same as `deps(actions, 1)` without the actions themselves.
+    pub(crate) async fn inputs_of(
+        &self,
+        env: &AqueryEnvironment<'a>,
+        actions: TargetSet<ActionQueryNode>,
+    ) -> Result<QueryValue<ActionQueryNode>, QueryError> {
+        let mut input_keys = Vec::new();
+        let mut seen = HashSet::new();
+
+        for node in &actions {
+            for dep in node.deps() {
+                if seen.insert(dep) {
+                    input_keys.push(dep.dupe());
+                }
+            }
+        }
+
+        let nodes = buck2_util::future::try_join_all(
+            input_keys.iter().map(|key| env.delegate.get_node(key)),
+        )
+        .await?;
+        Ok(nodes.into_iter().collect::<TargetSet<_>>().into())
+    }
+
+    /// Obtain the actions in `universe` that directly consume an output of any of the given
+    /// actions.
+    ///
+    /// For example, `consumers(deps(//foo:bar), all_actions(//foo:baz))` returns the actions
+    /// building `//foo:bar` that take an output of `//foo:baz` as an input.
+    pub(crate) async fn consumers(
+        &self,
+        universe: TargetSet<ActionQueryNode>,
+        actions: TargetSet<ActionQueryNode>,
+    ) -> Result<QueryValue<ActionQueryNode>, QueryError> {
+        let mut res = TargetSet::new();
+        for node in universe.into_iter() {
+            if node.deps().any(|dep| actions.contains(dep)) {
+                res.insert(node);
+            }
+        }
+        Ok(res.into())
+    }
+
+    /// Filter actions by the paths of their outputs.
+    ///
+    /// Returns the actions in `actions` that produce an output whose path (relative to the
+    /// project root, e.g. `buck-out/v2/gen/...`) matches `regex`. This answers "which action
+    /// produces this artifact".
+    pub(crate) async fn producers(
+        &self,
+        regex: String,
+        actions: TargetSet<ActionQueryNode>,
+    ) -> Result<QueryValue<ActionQueryNode>, QueryError> {
+        let re = Regex::new(&regex).map_err(buck2_error::Error::from)?;
+        filter_actions(actions, |node| {
+            for path in node.output_paths() {
+                if re.is_match(path.as_str())? {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        })
+    }
+
+    /// Filter actions by category (e.g. `cxx_compile`), using a partial regex match.
+    pub(crate) async fn category(
+        &self,
+        regex: String,
+        actions: TargetSet<ActionQueryNode>,
+    ) -> Result<QueryValue<ActionQueryNode>, QueryError> {
+        let re = Regex::new(&regex).map_err(buck2_error::Error::from)?;
+        filter_actions(actions, |node| match node.action() {
+            Some(action) => Ok(re.is_match(action.category().as_str())?),
+            None => Ok(false),
+        })
+    }
+
+    /// Filter actions by identifier (e.g. the source file of a `cxx_compile` action), using a
+    /// partial regex match. Actions without an identifier never match.
+    pub(crate) async fn identifier(
+        &self,
+        regex: String,
+        actions: TargetSet<ActionQueryNode>,
+    ) -> Result<QueryValue<ActionQueryNode>, QueryError> {
+        let re = Regex::new(&regex).map_err(buck2_error::Error::from)?;
+        filter_actions(actions, |node| {
+            match node.action().and_then(|action| action.identifier()) {
+                Some(identifier) => Ok(re.is_match(identifier)?),
+                None => Ok(false),
+            }
+        })
+    }
+
+    /// Filter actions by where they are configured to run, using a partial regex match against
+    /// `local`, `remote` or `hybrid`.
+    ///
+    /// A local executor that only uses remote execution for caching counts as `local`.
+    pub(crate) async fn executor(
+        &self,
+        regex: String,
+        actions: TargetSet<ActionQueryNode>,
+    ) -> Result<QueryValue<ActionQueryNode>, QueryError> {
+        let re = Regex::new(&regex).map_err(buck2_error::Error::from)?;
+        filter_actions(actions, |node| match node.executor_kind() {
+            Some(kind) => Ok(re.is_match(kind)?),
+            None => Ok(false),
+        })
+    }
+}
+
+fn filter_actions(
+    actions: TargetSet<ActionQueryNode>,
+    filter: impl Fn(&ActionQueryNode) -> buck2_error::Result<bool>,
+) -> Result<QueryValue<ActionQueryNode>, QueryError> {
+    let mut res = TargetSet::new();
+    for node in actions.into_iter() {
+        if filter(&node)? {
+            res.insert(node);
+        }
+    }
+    Ok(res.into())
 }
@@ -11,6 +11,7 @@ use std::collections::HashMap;
 use std::collections::HashSet;
 use std::io::BufWriter;
 use std::marker::PhantomData;
+use std::path::Path;
 use std::sync::Arc;
 
 use allocative::Allocative;
@@ -93,6 +94,8 @@ use buck2_interpreter::starlark_profiler::config::StarlarkProfilerConfiguration;
 use buck2_interpreter_for_build::interpreter::configuror::BuildInterpreterConfiguror;
 use buck2_interpreter_for_build::interpreter::cycles::LoadCycleDescriptor;
 use buck2_interpreter_for_build::interpreter::interpreter_setup::setup_interpreter;
+use buck2_offline_archive::bundle::OfflineBundle;
+use buck2_offline_archive::bundle::OfflineBundleMode;
 use buck2_server_ctx::concurrency::DiceUpdater;
 use buck2_server_ctx::ctx::DiceAccessor;
 use buck2_server_ctx::ctx::PrivateStruct;
@@ -377,6 +380,7 @@ impl<'a> ServerCommandContext<'a> {
                 .daemon
                 .use_network_action_output_cache,
             eager_dep_files,
+            offline_bundle: None,
         };
 
         let concurrency = self
@@ -669,6 +673,13 @@ impl<'a, 's> DiceCommandUpdater<'a, 's> {
             .unwrap_or_else(RolloutPercentage::always)
             .roll();
 
+        let stream_stderr = root_config
+            .parse::<bool>(BuckconfigKeyRef {
+                section: "buck2",
+                property: "stream_action_stderr",
+            })?
+            .unwrap_or(false);
+
         let log_configured_graph_size = root_config
             .parse::<bool>(BuckconfigKeyRef {
                 section: "buck2",
@@ -686,6 +697,7 @@ impl<'a, 's> DiceCommandUpdater<'a, 's> {
         let executor_global_knobs = ExecutorGlobalKnobs {
             enable_miniperf,
             log_action_keys,
+            stream_stderr,
         };
 
         let host_sharing_broker =
@@ -721,6 +733,25 @@ impl<'a, 's> DiceCommandUpdater<'a, 's> {
                 property: "use_network_action_output_cache",
             })?
             .unwrap_or(false);
+        if let Some(dir) = root_config.get(BuckconfigKeyRef {
+            section: "buck2",
+            property: "offline_bundle_dir",
+        }) {
+            // Relative paths are relative to the project root.
+            let dir = if Path::new(dir).is_absolute() {
+                AbsNormPathBuf::new(dir.into())?
+            } else {
+                self.cmd_ctx
+                    .base_context
+                    .project_root
+                    .root()
+                    .join_normalized(dir)?
+            };
+            run_action_knobs.offline_bundle = Some(Arc::new(OfflineBundle::open(
+                dir,
+                OfflineBundleMode::Replay,
+            )?));
+        }
 
         let mut data = UserComputationData {
             data,
@@ -766,6 +797,7 @@ impl<'a, 's> DiceCommandUpdater<'a, 's> {
             self.cmd_ctx.base_context.daemon.io.project_root().dupe(),
             worker_pool,
             self.cmd_ctx.base_context.daemon.paranoid.dupe(),
+            self.cmd_ctx.base_context.daemon.disk_action_cache.dupe(),
             self.materialize_failed_inputs,
             override_use_case,
             self.cmd_ctx.base_context.daemon.memory_tracker.dupe(),
@@ -35,6 +35,7 @@ use buck2_error::BuckErrorContext;
 use buck2_execute::execute::blocking::BlockingExecutor;
 use buck2_execute::execute::cache_uploader::force_cache_upload;
 use buck2_execute::execute::cache_uploader::NoOpCacheUploader;
+use buck2_execute::execute::cache_uploader::UploadCache;
 use buck2_execute::execute::prepared::NoOpCommandOptionalExecutor;
 use buck2_execute::execute::prepared::PreparedCommandExecutor;
 use buck2_execute::execute::prepared::PreparedCommandOptionalExecutor;
@@ -47,6 +48,9 @@ use buck2_execute_impl::executors::action_cache::ActionCacheChecker;
 use buck2_execute_impl::executors::action_cache::RemoteDepFileCacheChecker;
 use buck2_execute_impl::executors::action_cache_upload_permission_checker::ActionCacheUploadPermissionChecker;
 use buck2_execute_impl::executors::caching::CacheUploader;
+use buck2_execute_impl::executors::disk_action_cache::DiskActionCache;
+use buck2_execute_impl::executors::disk_action_cache::DiskActionCacheChecker;
+use buck2_execute_impl::executors::disk_action_cache::DiskCacheUploader;
 use buck2_execute_impl::executors::hybrid::FallbackTracker;
 use buck2_execute_impl::executors::hybrid::HybridExecutor;
 use buck2_execute_impl::executors::local::LocalExecutor;
@@ -94,6 +98,8 @@ pub struct CommandExecutorFactory {
     project_root: ProjectRoot,
     worker_pool: Arc<WorkerPool>,
     paranoid: Option<ParanoidDownloader>,
+    /// Cache for actions executed locally, used when remote execution is not configured.
+    disk_action_cache: Option<DiskActionCache>,
     materialize_failed_inputs: bool,
     /// Cache permission checks per command.
     cache_upload_permission_checker: Arc<ActionCacheUploadPermissionChecker>,
@@ -118,6 +124,7 @@ impl CommandExecutorFactory {
         project_root: ProjectRoot,
         worker_pool: Arc<WorkerPool>,
         paranoid: Option<ParanoidDownloader>,
+        disk_action_cache: Option<DiskActionCache>,
         materialize_failed_inputs: bool,
         re_use_case_override: Option<RemoteExecutorUseCase>,
         memory_tracker: Option<Arc<MemoryTracker>>,
@@ -145,6 +152,7 @@ impl CommandExecutorFactory {
             project_root,
             worker_pool,
             paranoid,
+            disk_action_cache,
             materialize_failed_inputs,
             cache_upload_permission_checker,
             fallback_tracker: Arc::new(FallbackTracker::new()),
@@ -191,9 +199,32 @@ impl HasCommandExecutor for CommandExecutorFactory {
                 self.forkserver.dupe(),
                 self.executor_global_knobs.dupe(),
                 worker_pool,
+                options.resource_limits,
             )
         };
 
+        let local_cache_checker = || -> Arc<dyn PreparedCommandOptionalExecutor> {
+            match &self.disk_action_cache {
+                Some(cache) if !self.skip_cache_read => Arc::new(DiskActionCacheChecker {
+                    artifact_fs: artifact_fs.clone(),
+                    materializer: self.materializer.dupe(),
+                    blocking_executor: self.blocking_executor.dupe(),
+                    cache: cache.dupe(),
+                }),
+                _ => Arc::new(NoOpCommandOptionalExecutor {}),
+            }
+        };
+
+        let local_cache_uploader = || -> Arc<dyn UploadCache> {
+            match &self.disk_action_cache {
+                Some(cache) if !self.skip_cache_write => Arc::new(DiskCacheUploader {
+                    artifact_fs: artifact_fs.clone(),
+                    cache: cache.dupe(),
+                }),
+                _ => Arc::new(NoOpCacheUploader {}),
+            }
+        };
+
         if !buck2_core::is_open_source() && !cfg!(fbcode_build) {
             static WARN: OnceLock<()> = OnceLock::new();
             WARN.get_or_init(|| {
@@ -211,8 +242,8 @@ impl HasCommandExecutor for CommandExecutorFactory {
             return Ok(CommandExecutorResponse {
                 executor: Arc::new(local_executor_new(&LocalExecutorOptions::default())),
                 platform: Default::default(),
-                cache_checker: Arc::new(NoOpCommandOptionalExecutor {}),
-                cache_uploader: Arc::new(NoOpCacheUploader {}),
+                cache_checker: local_cache_checker(),
+                cache_uploader: local_cache_uploader(),
             });
         }
 
@@ -248,8 +279,8 @@ impl HasCommandExecutor for CommandExecutorFactory {
                     Some(CommandExecutorResponse {
                         executor: Arc::new(local_executor_new(local)),
                         platform: Default::default(),
-                        cache_checker: Arc::new(NoOpCommandOptionalExecutor {}),
-                        cache_uploader: Arc::new(NoOpCacheUploader {}),
+                        cache_checker: local_cache_checker(),
+                        cache_uploader: local_cache_uploader(),
                     })
                 }
             }
@@ -8,6 +8,7 @@
  */
 
 use buck2_common::init::ResourceControlConfig;
+use buck2_common::init::SandboxConfig;
 use buck2_common::legacy_configs::configs::LegacyBuckConfig;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
 use buck2_forkserver::client::ForkserverClient;
@@ -17,6 +18,7 @@ pub async fn maybe_launch_forkserver(
     root_config: &LegacyBuckConfig,
     forkserver_state_dir: &AbsNormPath,
     resource_control: &ResourceControlConfig,
+    sandbox: &SandboxConfig,
 ) -> buck2_error::Result<Option<ForkserverClient>> {
     use buck2_common::legacy_configs::key::BuckconfigKeyRef;
     use buck2_core::rollout_percentage::RolloutPercentage;
@@ -40,6 +42,7 @@ pub async fn maybe_launch_forkserver(
             &["forkserver"],
             forkserver_state_dir,
             resource_control.serialize()?,
+            sandbox,
         )
         .await,
     )
@@ -51,6 +54,7 @@ pub async fn maybe_launch_forkserver(
     _root_config: &LegacyBuckConfig,
     _forkserver_state_dir: &AbsNormPath,
     _resource_control: &ResourceControlConfig,
+    _sandbox: &SandboxConfig,
 ) -> buck2_error::Result<Option<ForkserverClient>> {
     Ok(None)
 }
@@ -53,6 +53,7 @@ use buck2_execute::execute::blocking::BuckBlockingExecutor;
 use buck2_execute::materialize::materializer::MaterializationMethod;
 use buck2_execute::materialize::materializer::Materializer;
 use buck2_execute::re::manager::ReConnectionManager;
+use buck2_execute_impl::executors::disk_action_cache::DiskActionCache;
 use buck2_execute_impl::materializers::deferred::clean_stale::CleanStaleConfig;
 use buck2_execute_impl::materializers::deferred::AccessTimesUpdates;
 use buck2_execute_impl::materializers::deferred::DeferredMaterializer;
@@ -65,6 +66,7 @@ use buck2_execute_impl::materializers::sqlite::MaterializerStateSqliteDb;
 use buck2_execute_impl::re::paranoid_download::ParanoidDownloader;
 use buck2_file_watcher::file_watcher::FileWatcher;
 use buck2_forkserver::client::ForkserverClient;
+use buck2_http::DownloadCacheConfig;
 use buck2_http::HttpClient;
 use buck2_http::HttpClientBuilder;
 use buck2_re_configuration::RemoteExecutionStaticMetadata;
@@ -89,6 +91,9 @@ use crate::daemon::io_provider::create_io_provider;
 use crate::daemon::panic::DaemonStatePanicDiceDump;
 use crate::daemon::server::BuckdServerInitPreferences;
 
+/// Default size limit of the disk action cache: 10 GiB.
+const DEFAULT_DISK_ACTION_CACHE_MAX_BYTES: u64 = 10 * 1024 * 1024 * 1024;
+
 /// For a buckd process there is a single DaemonState created at startup and never destroyed.
 #[derive(Allocative)]
 pub struct DaemonState {
@@ -177,6 +182,9 @@ pub struct DaemonStateData {
     /// If enabled, paranoid RE downloads.
     pub paranoid: Option<ParanoidDownloader>,
 
+    /// If enabled, cache for the results of locally executed actions.
+    pub disk_action_cache: Option<DiskActionCache>,
+
     /// Spawner
     pub spawner: Arc<BuckSpawner>,
 
@@ -397,12 +405,20 @@ impl DaemonState {
                     })?
                     .unwrap_or(3600);
 
+                // Without it, deferred outputs expire from OSS remote execution CASes when the
+                // daemon is idle for longer than their TTL.
                 let ttl_refresh_enabled = root_config
                     .parse::<RolloutPercentage>(BuckconfigKeyRef {
                         section: "buck2",
                         property: "ttl_refresh_enabled",
                     })?
-                    .unwrap_or_else(RolloutPercentage::never)
+                    .unwrap_or_else(|| {
+                        if is_open_source() {
+                            RolloutPercentage::always()
+                        } else {
+                            RolloutPercentage::never()
+                        }
+                    })
                     .roll();
 
                 let update_access_times = AccessTimesUpdates::try_new_from_config_value(
@@ -520,6 +536,7 @@ impl DaemonState {
                 root_config,
                 &paths.forkserver_state_dir(),
                 &init_ctx.daemon_startup_config.resource_control,
+                &init_ctx.daemon_startup_config.sandbox,
             )
             .await?;
 
@@ -537,6 +554,7 @@ impl DaemonState {
                 root_config,
                 cells.dupe(),
                 ignore_specs,
+                &paths.daemon_dir()?,
             )
             .with_buck_error_context(|| {
                 format!(
@@ -581,6 +599,29 @@ impl DaemonState {
                 None
             };
 
+            let disk_action_cache = if root_config
+                .parse(BuckconfigKeyRef {
+                    section: "buck2",
+                    property: "disk_action_cache",
+                })?
+                .unwrap_or(false)
+            {
+                let max_bytes = root_config
+                    .parse(BuckconfigKeyRef {
+                        section: "buck2",
+                        property: "disk_action_cache_max_bytes",
+                    })?
+                    .unwrap_or(DEFAULT_DISK_ACTION_CACHE_MAX_BYTES);
+                Some(DiskActionCache::new(
+                    fs.clone(),
+                    blocking_executor.dupe(),
+                    paths.disk_action_cache_dir(),
+                    max_bytes,
+                ))
+            } else {
+                None
+            };
+
             let remote_dep_files_enabled = root_config
                 .parse(BuckconfigKeyRef {
                     section: "build",
@@ -597,6 +638,7 @@ impl DaemonState {
                     disk_state_options.sqlite_materializer_state
                 ),
                 format!("paranoid:{}", paranoid.is_some()),
+                format!("disk-action-cache:{}", disk_action_cache.is_some()),
                 format!("remote-dep-files:{}", remote_dep_files_enabled),
                 #[cfg(fbcode_build)]
                 format!(
@@ -636,6 +678,7 @@ impl DaemonState {
                 enable_restarter,
                 http_client,
                 paranoid,
+                disk_action_cache,
                 spawner: Arc::new(BuckSpawner::new(daemon_state_data_rt)),
                 tags,
                 system_warning_config,
@@ -949,6 +992,13 @@ async fn http_client_from_startup_config(
         }
         _ => {}
     }
+    if let Some(dir) = config.http.download_cache_dir()? {
+        builder.with_download_cache(DownloadCacheConfig {
+            dir,
+            max_size_bytes: config.http.download_cache_max_size_bytes,
+            offline: config.http.download_cache_offline,
+        });
+    }
 
     Ok(builder)
 }
@@ -0,0 +1,97 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Explain why DICE keys were recomputed: walk their deps back to the keys changed by the latest
+//! committed transaction, e.g. the files or buckconfigs changed before the last build. Only the
+//! keys whose value actually changed are explained.
+
+use buck2_cli_proto::new_generic::ExplainRecomputeRequest;
+use buck2_cli_proto::new_generic::ExplainRecomputeResponse;
+use buck2_cli_proto::new_generic::RecomputeExplanation;
+use buck2_cli_proto::new_generic::RecomputeKey;
+use buck2_events::dispatch::span_async;
+use buck2_server_ctx::commands::command_end;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use dice::DynKey;
+
+use crate::ctx::BaseServerCommandContext;
+use crate::ctx::ServerCommandContext;
+
+/// Explaining a key walks its deps on the DICE state thread, which blocks all other DICE
+/// operations, so a broad filter only explains this many keys.
+const MAX_EXPLAINED_KEYS: usize = 100;
+
+pub(crate) async fn explain_recompute_command(
+    context: &ServerCommandContext<'_>,
+    req: ExplainRecomputeRequest,
+) -> buck2_error::Result<ExplainRecomputeResponse> {
+    let start_event = buck2_data::CommandStart {
+        metadata: context.request_metadata().await?,
+        data: Some(buck2_data::ExplainRecomputeCommandStart {}.into()),
+    };
+    span_async(start_event, async move {
+        let result = explain_recompute(&context.base_context, req).await;
+        let end_event = command_end(&result, buck2_data::ExplainRecomputeCommandEnd {});
+        (result, end_event)
+    })
+    .await
+}
+
+async fn explain_recompute(
+    server_ctx: &BaseServerCommandContext,
+    req: ExplainRecomputeRequest,
+) -> buck2_error::Result<ExplainRecomputeResponse> {
+    // Don't start a transaction: it would commit the changes made since the last command, which
+    // are not the ones the user wants explained.
+    let dice = server_ctx.daemon.dice_manager.unsafe_dice();
+    let result = dice
+        .explain_recompute(
+            |key| {
+                req.key_type
+                    .as_ref()
+                    .map_or(true, |key_type| key_type == key_type_name(key))
+                    && key.to_string().contains(&req.key)
+            },
+            MAX_EXPLAINED_KEYS,
+        )
+        .await;
+
+    let mut explanations: Vec<RecomputeExplanation> = result
+        .explanations
+        .iter()
+        .map(|explanation| RecomputeExplanation {
+            key: recompute_key(&explanation.key),
+            chains: explanation
+                .chains
+                .iter()
+                .map(|chain| chain.iter().map(recompute_key).collect())
+                .collect(),
+        })
+        .collect();
+    explanations.sort_by(|a, b| (&a.key.key_type, &a.key.key).cmp(&(&b.key.key_type, &b.key.key)));
+
+    Ok(ExplainRecomputeResponse {
+        version: result.version.to_introspectable().0,
+        explanations,
+        omitted: result.omitted,
+    })
+}
+
+/// The name of the type of `key`, without its module path.
+fn key_type_name(key: &DynKey) -> &'static str {
+    let name = key.key_type_name();
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+fn recompute_key(key: &DynKey) -> RecomputeKey {
+    RecomputeKey {
+        key_type: key_type_name(key).to_owned(),
+        key: key.to_string(),
+    }
+}
@@ -18,6 +18,7 @@ mod cpu_usage_collector;
 mod ctx;
 pub mod daemon;
 mod dice_tracker;
+mod explain_recompute;
 mod file_status;
 mod heartbeat_guard;
 mod host_info;
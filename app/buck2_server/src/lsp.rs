@@ -40,6 +40,7 @@ use buck2_interpreter::load_module::InterpreterCalculation;
 use buck2_interpreter::paths::module::OwnedStarlarkModulePath;
 use buck2_interpreter::prelude_path::prelude_path;
 use buck2_interpreter_for_build::interpreter::dice_calculation_delegate::HasCalculationDelegate;
+use buck2_interpreter_for_build::interpreter::global_interpreter_state::HasGlobalInterpreterState;
 use buck2_interpreter_for_build::interpreter::globals::base_globals;
 use buck2_interpreter_for_build::interpreter::interpreter_for_cell::ParseData;
 use buck2_server_ctx::commands::command_end;
@@ -58,9 +59,11 @@ use lsp_server::Connection;
 use lsp_server::Message;
 use lsp_types::Url;
 use starlark::analysis::find_call_name::AstModuleFindCallName;
+use starlark::analysis::AstModuleLint;
 use starlark::codemap::Span;
 use starlark::docs::markdown::render_doc_item_no_link;
 use starlark::docs::DocModule;
+use starlark::environment::Globals;
 use starlark::errors::EvalMessage;
 use starlark::syntax::AstModule;
 use starlark_lsp::error::eval_message_to_lsp_diagnostic;
@@ -137,7 +140,12 @@ impl DocsCacheManager {
         if let Some((import_path, docs)) = get_prelude_docs(dice_ctx, &builtin_names).await? {
             builtin_docs.push((Some(import_path), docs));
         }
-        DocsCache::new(&builtin_docs, fs, &cell_resolver).await
+        let typecheck_globals = dice_ctx
+            .get_global_interpreter_state()
+            .await?
+            .globals()
+            .dupe();
+        DocsCache::new(&builtin_docs, typecheck_globals, fs, &cell_resolver).await
     }
 }
 
@@ -180,6 +188,13 @@ struct DocsCache {
     global_urls: HashMap<String, LspUrl>,
     /// Mapping of starlark: urls to a synthesized starlark representation.
     native_starlark_files: HashMap<LspUrl, String>,
+    /// Documentation for all of the global symbols, e.g. for signature help.
+    global_docs: DocModule,
+    /// The names of all of the global symbols, which are all the names in scope for lints.
+    global_names: HashSet<String>,
+    /// The globals files are typechecked against. Like the docs, these are the same for
+    /// every cell, so one copy is kept and refreshed with the rest of the cache.
+    typecheck_globals: Option<Globals>,
 }
 
 #[derive(buck2_error::Error, Debug)]
@@ -205,13 +220,16 @@ impl DocsCache {
 
     async fn new(
         builtin_symbols: &[(Option<ImportPath>, DocModule)],
+        typecheck_globals: Globals,
         fs: &ProjectRoot,
         cell_resolver: &CellResolver,
     ) -> buck2_error::Result<Self> {
-        Self::new_with_lookup(builtin_symbols, |location| async {
+        let mut docs_cache = Self::new_with_lookup(builtin_symbols, |location| async {
             Self::get_prelude_uri(location, fs, cell_resolver).await
         })
-        .await
+        .await?;
+        docs_cache.typecheck_globals = Some(typecheck_globals);
+        Ok(docs_cache)
     }
 
     async fn new_with_lookup<
@@ -238,7 +256,11 @@ impl DocsCache {
         };
 
         let mut native_starlark_files = HashMap::new();
+        let mut global_docs = DocModule::default();
         for (import_path, docs) in builtin_symbols {
+            for (sym, mem) in &docs.members {
+                global_docs.members.insert(sym.clone(), mem.clone());
+            }
             match import_path {
                 Some(l) => {
                     let url = location_lookup(l).await?;
@@ -266,9 +288,13 @@ impl DocsCache {
                 }
             };
         }
+        let global_names = global_docs.members.keys().cloned().collect();
         Ok(Self {
             global_urls,
             native_starlark_files,
+            global_docs,
+            global_names,
+            typecheck_globals: None,
         })
     }
 
@@ -281,6 +307,18 @@ impl DocsCache {
     fn url_for_symbol(&self, symbol: &str) -> Option<&LspUrl> {
         self.global_urls.get(symbol)
     }
+
+    fn global_docs(&self) -> &DocModule {
+        &self.global_docs
+    }
+
+    fn global_names(&self) -> &HashSet<String> {
+        &self.global_names
+    }
+
+    fn typecheck_globals(&self) -> Option<&Globals> {
+        self.typecheck_globals.as_ref()
+    }
 }
 
 struct BuckLspContext<'a> {
@@ -390,7 +428,7 @@ impl<'a> BuckLspContext<'a> {
             .parse_file_from_contents_and_handle_diagnostic(uri, content)
             .await
         {
-            Ok(res) => res,
+            Ok(res) => self.lint(res).await,
             Err(e) => {
                 let message = EvalMessage::from_any_error(uri.path(), &e);
                 LspEvalResult {
@@ -401,6 +439,27 @@ impl<'a> BuckLspContext<'a> {
         }
     }
 
+    /// Add lint warnings for a successfully parsed file to its diagnostics.
+    async fn lint(&self, mut res: LspEvalResult) -> LspEvalResult {
+        let Some(ast) = &res.ast else {
+            return res;
+        };
+        // The docs cover both the builtins and the prelude, which are all the names in scope.
+        let docs_cache = self
+            .with_dice_ctx(|dice_ctx| async { self.docs_cache_manager.get_cache(dice_ctx).await })
+            .await;
+        // Without the names in scope every global would be reported as undefined,
+        // so skip linting rather than be wrong.
+        if let Ok(docs_cache) = docs_cache {
+            res.diagnostics.extend(
+                ast.lint(Some(docs_cache.global_names()))
+                    .into_iter()
+                    .map(|lint| eval_message_to_lsp_diagnostic(EvalMessage::from(lint))),
+            );
+        }
+        res
+    }
+
     async fn parse_file_from_contents_and_handle_diagnostic(
         &self,
         uri: &LspUrl,
@@ -693,7 +752,36 @@ impl<'a> LspContext for BuckLspContext<'a> {
     }
 
     fn get_environment(&self, _uri: &LspUrl) -> DocModule {
-        DocModule::default()
+        let dispatcher = self.server_ctx.events().dupe();
+        self.runtime
+            .block_on(with_dispatcher_async(dispatcher, async {
+                let docs_cache = self
+                    .with_dice_ctx(|dice_ctx| async {
+                        self.docs_cache_manager.get_cache(dice_ctx).await
+                    })
+                    .await?;
+                buck2_error::Ok(docs_cache.global_docs().clone())
+            }))
+            // The environment is only used to offer completions and signatures,
+            // so fall back to no globals rather than failing the request.
+            .unwrap_or_default()
+    }
+
+    fn get_typecheck_globals(&self, _uri: &LspUrl) -> Option<Globals> {
+        let dispatcher = self.server_ctx.events().dupe();
+        self.runtime
+            .block_on(with_dispatcher_async(dispatcher, async {
+                let docs_cache = self
+                    .with_dice_ctx(|dice_ctx| async {
+                        self.docs_cache_manager.get_cache(dice_ctx).await
+                    })
+                    .await?;
+                buck2_error::Ok(docs_cache.typecheck_globals().duped())
+            }))
+            // Type errors are extra information, so skip typechecking rather than
+            // failing to report the other diagnostics.
+            .ok()
+            .flatten()
     }
 }
 
@@ -930,6 +1018,15 @@ mod tests {
             &LspUrl::try_from(Url::parse("file:/c:/usr/local/dir/prelude.bzl")?)?,
             cache.url_for_symbol("prelude_function").unwrap()
         );
+        assert_eq!(
+            vec!["native_function1", "native_function2", "prelude_function"],
+            cache
+                .global_docs()
+                .members
+                .keys()
+                .map(|k| k.as_str())
+                .collect::<Vec<_>>()
+        );
 
         Ok(())
     }
@@ -16,6 +16,7 @@ use buck2_server_ctx::partial_result_dispatcher::NoPartialResult;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
 
 use crate::ctx::ServerCommandContext;
+use crate::explain_recompute::explain_recompute_command;
 use crate::materialize::materialize_command;
 
 pub(crate) async fn new_generic_command(
@@ -51,6 +52,15 @@ pub(crate) async fn new_generic_command(
                 .expand_external_cells(context, partial_result_dispatcher, e)
                 .await?,
         ),
+        NewGenericRequest::ExplainRecompute(e) => {
+            NewGenericResponse::ExplainRecompute(explain_recompute_command(context, e).await?)
+        }
+        NewGenericRequest::OfflineBundle(b) => NewGenericResponse::OfflineBundle(
+            OTHER_SERVER_COMMANDS
+                .get()?
+                .offline_bundle(context, partial_result_dispatcher, b)
+                .await?,
+        ),
         NewGenericRequest::Docs(d) => NewGenericResponse::Docs(
             DOCS_SERVER_COMMAND
                 .get()?
@@ -17,6 +17,7 @@ pub mod explain;
 pub(crate) mod explain_code;
 pub(crate) mod init_commands;
 pub mod install;
+pub mod offline_bundle;
 pub mod query;
 pub mod targets;
 pub mod targets_show_outputs;
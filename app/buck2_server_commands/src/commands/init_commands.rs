@@ -16,6 +16,8 @@ use buck2_cli_proto::new_generic::ExpandExternalCellsRequest;
 use buck2_cli_proto::new_generic::ExpandExternalCellsResponse;
 use buck2_cli_proto::new_generic::ExplainRequest;
 use buck2_cli_proto::new_generic::ExplainResponse;
+use buck2_cli_proto::new_generic::OfflineBundleRequest;
+use buck2_cli_proto::new_generic::OfflineBundleResponse;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::late_bindings::OtherServerCommands;
 use buck2_server_ctx::late_bindings::OTHER_SERVER_COMMANDS;
@@ -29,6 +31,7 @@ use crate::commands::debug_eval::debug_eval_command;
 use crate::commands::expand_external_cells::expand_external_cells_command;
 use crate::commands::explain::explain_command;
 use crate::commands::install::install_command;
+use crate::commands::offline_bundle::offline_bundle_command;
 use crate::commands::query::aquery::aquery_command;
 use crate::commands::query::cquery::cquery_command;
 use crate::commands::query::uquery::uquery_command;
@@ -138,6 +141,15 @@ impl OtherServerCommands for OtherServerCommandsInstance {
     ) -> buck2_error::Result<ExpandExternalCellsResponse> {
         expand_external_cells_command(ctx, partial_result_dispatcher, req).await
     }
+
+    async fn offline_bundle(
+        &self,
+        ctx: &dyn ServerCommandContextTrait,
+        partial_result_dispatcher: PartialResultDispatcher<NoPartialResult>,
+        req: OfflineBundleRequest,
+    ) -> buck2_error::Result<OfflineBundleResponse> {
+        offline_bundle_command(ctx, partial_result_dispatcher, req).await
+    }
 }
 
 pub(crate) fn init_other_server_commands() {
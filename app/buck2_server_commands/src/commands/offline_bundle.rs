@@ -0,0 +1,190 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use async_trait::async_trait;
+use buck2_artifact::artifact::build_artifact::BuildArtifact;
+use buck2_build_api::actions::artifact::get_artifact_fs::GetArtifactFs;
+use buck2_build_api::analysis::calculation::RuleAnalysisCalculation;
+use buck2_build_api::artifact_groups::ArtifactGroup;
+use buck2_build_api::configure_targets::load_compatible_patterns;
+use buck2_build_api::materialize::materialize_artifact_group;
+use buck2_build_api::materialize::MaterializationContext;
+use buck2_cli_proto::new_generic::OfflineBundleRequest;
+use buck2_cli_proto::new_generic::OfflineBundleResponse;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::pattern::parse_from_cli::parse_patterns_from_cli_args;
+use buck2_core::cells::external::ExternalCellOrigin;
+use buck2_core::configuration::compatibility::MaybeCompatible;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
+use buck2_execute::execute::blocking::HasBlockingExecutor;
+use buck2_node::load_patterns::MissingTargetBehavior;
+use buck2_node::nodes::configured_node_visit_all_deps::configured_node_visit_all_deps;
+use buck2_offline_archive::bundle::BundledFetch;
+use buck2_offline_archive::bundle::BundledGitCell;
+use buck2_offline_archive::bundle::OfflineBundle;
+use buck2_offline_archive::bundle::OfflineBundleMode;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::global_cfg_options::global_cfg_options_from_client_context;
+use buck2_server_ctx::partial_result_dispatcher::NoPartialResult;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::template::run_server_command;
+use buck2_server_ctx::template::ServerCommandTemplate;
+use dice::DiceComputations;
+use dice::DiceTransaction;
+use dupe::Dupe;
+use futures::FutureExt;
+
+pub(crate) async fn offline_bundle_command(
+    ctx: &dyn ServerCommandContextTrait,
+    partial_result_dispatcher: PartialResultDispatcher<NoPartialResult>,
+    req: OfflineBundleRequest,
+) -> buck2_error::Result<OfflineBundleResponse> {
+    run_server_command(
+        OfflineBundleServerCommand { req },
+        ctx,
+        partial_result_dispatcher,
+    )
+    .await
+}
+
+struct OfflineBundleServerCommand {
+    req: OfflineBundleRequest,
+}
+
+#[async_trait]
+impl ServerCommandTemplate for OfflineBundleServerCommand {
+    type StartEvent = buck2_data::OfflineBundleCommandStart;
+    type EndEvent = buck2_data::OfflineBundleCommandEnd;
+    type Response = OfflineBundleResponse;
+    type PartialResult = NoPartialResult;
+
+    async fn command(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        _partial_result_dispatcher: PartialResultDispatcher<Self::PartialResult>,
+        mut ctx: DiceTransaction,
+    ) -> buck2_error::Result<Self::Response> {
+        let bundle = OfflineBundle::open(
+            AbsNormPathBuf::new(self.req.bundle_dir.clone().into_path_buf())?,
+            OfflineBundleMode::Record,
+        )?;
+
+        let parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
+            &mut ctx,
+            &self.req.target_patterns,
+            server_ctx.working_dir(),
+        )
+        .await?;
+        let global_cfg_options =
+            global_cfg_options_from_client_context(&self.req.target_cfg, server_ctx, &mut ctx)
+                .await?;
+        let targets = load_compatible_patterns(
+            &mut ctx,
+            parsed_patterns,
+            &global_cfg_options,
+            MissingTargetBehavior::Fail,
+        )
+        .await?;
+
+        let mut labels = Vec::new();
+        configured_node_visit_all_deps(targets.iter().map(|node| node.as_ref()), |node| {
+            labels.push(node.label().dupe())
+        });
+
+        // Request the outputs of the network actions rather than recording the ones that run, so
+        // that the outputs DICE or the materializer already have are added too.
+        let outputs = ctx
+            .try_compute_join(labels, |ctx, label| {
+                async move { network_action_outputs(ctx, &label).await }.boxed()
+            })
+            .await?;
+        let artifact_fs = ctx.get_artifact_fs().await?;
+        let outputs: Vec<_> = outputs
+            .into_iter()
+            .flatten()
+            .map(|(fetch, output)| {
+                let path = artifact_fs.resolve_build(output.get_path());
+                (fetch, artifact_fs.fs().resolve(&path))
+            })
+            .collect();
+
+        // Which cells the targets load files from is not tracked, so add every git cell.
+        let git_cells: Vec<_> = ctx
+            .get_cell_resolver()
+            .await?
+            .cells()
+            .filter_map(|(_, instance)| match instance.external() {
+                Some(ExternalCellOrigin::Git(setup)) => Some(BundledGitCell {
+                    origin: setup.git_origin.to_string(),
+                    commit: setup.commit.to_string(),
+                }),
+                _ => None,
+            })
+            .collect();
+
+        ctx.get_blocking_executor()
+            .execute_io_inline(|| {
+                for (fetch, output) in &outputs {
+                    bundle.store_fetch(fetch, output)?;
+                }
+                for git_cell in &git_cells {
+                    bundle.store_git_cell(git_cell)?;
+                }
+                Ok(())
+            })
+            .await?;
+
+        let manifest = bundle.manifest();
+        Ok(OfflineBundleResponse {
+            downloads: manifest.downloads.len(),
+            cas_artifacts: manifest.cas_artifacts.len(),
+            git_cells: manifest.git_cells.len(),
+        })
+    }
+
+    fn is_success(&self, _response: &Self::Response) -> bool {
+        true
+    }
+}
+
+/// Build and materialize the outputs of the network actions registered by the analysis of
+/// `label`.
+async fn network_action_outputs(
+    ctx: &mut DiceComputations<'_>,
+    label: &ConfiguredTargetLabel,
+) -> buck2_error::Result<Vec<(BundledFetch, BuildArtifact)>> {
+    let analysis = match ctx.get_analysis_result(label).await? {
+        MaybeCompatible::Compatible(analysis) => analysis,
+        MaybeCompatible::Incompatible(_) => return Ok(Vec::new()),
+    };
+    let actions: Vec<_> = analysis
+        .analysis_values()
+        .iter_actions()
+        .filter_map(|action| {
+            let fetch = action.offline_bundle_fetch()?;
+            Some((fetch, action.first_output().dupe()))
+        })
+        .collect();
+
+    ctx.try_compute_join(actions, |ctx, (fetch, output)| {
+        async move {
+            materialize_artifact_group(
+                ctx,
+                &ArtifactGroup::Artifact(output.dupe().into()),
+                &MaterializationContext::Materialize { force: true },
+            )
+            .await?;
+            Ok((fetch, output))
+        }
+        .boxed()
+    })
+    .await
+}
@@ -9,6 +9,7 @@
 
 pub mod aquery;
 pub mod cquery;
+pub(crate) mod explain;
 pub mod printer;
 pub(crate) mod query_target_ext;
 pub(crate) mod starlark_profile;
@@ -20,4 +21,10 @@ enum QueryCommandError {
         "query result was a set of files and one or more --output-attribute was requested, but files have not attributes"
     )]
     FileSetHasNoAttributes,
+    #[error("query result was a set of files, which has no graph to print in {0} format")]
+    FileSetHasNoGraph(&'static str),
+    #[error("--explain-paths requires the query to evaluate to a single set of targets")]
+    ExplainPathsNeedsTargetSet,
+    #[error("--explain-paths only supports the default and json output formats, not {0}")]
+    ExplainPathsUnsupportedFormat(String),
 }
@@ -15,6 +15,7 @@ use buck2_build_api::interpreter::rule_defs::provider::collection::FrozenProvide
 use buck2_build_api::query::oneshot::QUERY_FRONTEND;
 use buck2_cli_proto::CqueryRequest;
 use buck2_cli_proto::CqueryResponse;
+use buck2_cli_proto::QueryOutputFormat;
 use buck2_common::dice::cells::HasCellResolver;
 use buck2_core::configuration::compatibility::MaybeCompatible;
 use buck2_core::provider::label::ConfiguredProvidersLabel;
@@ -28,6 +29,7 @@ use buck2_node::attrs::serialize::AttrSerializeWithContext;
 use buck2_node::nodes::configured::ConfiguredTargetNode;
 use buck2_query::query::environment::AttrFmtOptions;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
+use buck2_query::query::syntax::simple::eval::values::QueryEvaluationValue;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::global_cfg_options::global_cfg_options_from_client_context;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
@@ -38,11 +40,15 @@ use dice::DiceTransaction;
 use dice::LinearRecomputeDiceComputations;
 use dupe::Dupe;
 
+use crate::commands::query::explain::explain_edges;
+use crate::commands::query::explain::explained_edges_json;
+use crate::commands::query::explain::print_explained_edges;
 use crate::commands::query::printer::ProviderLookUp;
 use crate::commands::query::printer::QueryResultPrinter;
 use crate::commands::query::printer::ShouldPrintProviders;
 use crate::commands::query::query_target_ext::QueryCommandTarget;
 use crate::commands::query::starlark_profile::write_query_profile_for_targets;
+use crate::commands::query::QueryCommandError;
 
 impl QueryCommandTarget for ConfiguredTargetNode {
     fn call_stack(&self) -> Option<String> {
@@ -156,6 +162,7 @@ async fn cquery(
         target_universe,
         context,
         show_providers,
+        explain_paths,
         target_cfg,
         ..
     } = request;
@@ -167,6 +174,16 @@ async fn cquery(
     };
     let client_ctx = context.as_ref().internal_error("No client context")?;
 
+    // Check the output format before evaluating the query, which may take a while.
+    let explain_paths_json = if *explain_paths {
+        Some(explained_edges_json(
+            QueryOutputFormat::from_i32(request.unstable_output_format)
+                .expect("cli should send a valid output_format enum"),
+        )?)
+    } else {
+        None
+    };
+
     let target_call_stacks = client_ctx.target_call_stacks;
 
     let global_cfg_options = global_cfg_options_from_client_context(
@@ -221,6 +238,15 @@ async fn cquery(
         }
     }
 
+    if let Some(json) = explain_paths_json {
+        let targets = match query_result {
+            QueryEvaluationResult::Single(QueryEvaluationValue::TargetSet(targets)) => targets,
+            _ => return Err(QueryCommandError::ExplainPathsNeedsTargetSet.into()),
+        };
+        print_explained_edges(&mut stdout, &explain_edges(&targets)?, json)?;
+        return Ok(CqueryResponse {});
+    }
+
     ctx.with_linear_recompute(|ctx| async move {
         let should_print_providers = if *show_providers {
             ShouldPrintProviders::Yes(&ctx as &dyn ProviderLookUp<ConfiguredTargetNode>)
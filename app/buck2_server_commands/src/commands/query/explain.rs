@@ -0,0 +1,374 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Explain the edges of a cquery result, e.g. of `somepath(//:bin, //:lib)`: for every dependency
+//! between two targets of the result, print the attribute that declared it and the kind of the
+//! dependency.
+
+use std::io::Write;
+
+use buck2_cli_proto::QueryOutputFormat;
+use buck2_core::provider::label::ConfiguredProvidersLabel;
+use buck2_core::provider::label::ProvidersLabel;
+use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
+use buck2_node::attrs::configured_traversal::ConfiguredAttrTraversal;
+use buck2_node::attrs::inspect_options::AttrInspectOptions;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_node::nodes::unconfigured::RuleKind;
+use buck2_query::query::syntax::simple::eval::set::TargetSet;
+use dupe::Dupe;
+use serde::Serialize;
+
+use crate::commands::query::QueryCommandError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, derive_more::Display)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum DepKind {
+    #[display("target")]
+    Target,
+    #[display("exec")]
+    Exec,
+    #[display("toolchain")]
+    Toolchain,
+    #[display("configuration")]
+    Configuration,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct ExplainedEdge {
+    pub(crate) from: ConfiguredTargetLabel,
+    pub(crate) to: ConfiguredTargetLabel,
+    /// `None` for dependencies that don't come from an attribute, e.g. the exec deps of the
+    /// execution platform.
+    pub(crate) attribute: Option<String>,
+    pub(crate) kind: DepKind,
+}
+
+/// Collects the dependencies declared by a single attribute.
+#[derive(Default)]
+struct AttrDeps {
+    deps: Vec<(ConfiguredProvidersLabel, DepKind)>,
+    configuration_deps: Vec<ProvidersLabel>,
+}
+
+impl ConfiguredAttrTraversal for AttrDeps {
+    fn dep(&mut self, dep: &ConfiguredProvidersLabel) -> buck2_error::Result<()> {
+        self.deps.push((dep.clone(), DepKind::Target));
+        Ok(())
+    }
+
+    fn exec_dep(&mut self, dep: &ConfiguredProvidersLabel) -> buck2_error::Result<()> {
+        self.deps.push((dep.clone(), DepKind::Exec));
+        Ok(())
+    }
+
+    fn toolchain_dep(&mut self, dep: &ConfiguredProvidersLabel) -> buck2_error::Result<()> {
+        self.deps.push((dep.clone(), DepKind::Toolchain));
+        Ok(())
+    }
+
+    fn configuration_dep(&mut self, dep: &ProvidersLabel) -> buck2_error::Result<()> {
+        self.configuration_deps.push(dep.clone());
+        Ok(())
+    }
+}
+
+/// Find all the edges between the targets of `targets`, in the order of the targets.
+pub(crate) fn explain_edges(
+    targets: &TargetSet<ConfiguredTargetNode>,
+) -> buck2_error::Result<Vec<ExplainedEdge>> {
+    let mut edges = Vec::new();
+
+    for node in targets {
+        let first = edges.len();
+        let add = |edges: &mut Vec<ExplainedEdge>,
+                   to: &ConfiguredTargetLabel,
+                   attribute: Option<&str>,
+                   kind: DepKind| {
+            let edge = ExplainedEdge {
+                from: node.label().dupe(),
+                to: to.dupe(),
+                attribute: attribute.map(str::to_owned),
+                kind,
+            };
+            if !edges[first..].contains(&edge) {
+                edges.push(edge);
+            }
+        };
+
+        for attr in node.attrs(AttrInspectOptions::All) {
+            let mut attr_deps = AttrDeps::default();
+            attr.traverse(node.label().pkg(), &mut attr_deps)?;
+
+            for (dep, kind) in &attr_deps.deps {
+                if targets.contains(dep.target()) {
+                    add(&mut edges, dep.target(), Some(attr.name), *kind);
+                }
+            }
+            // Configuration deps are unconfigured, so they match the target in any configuration.
+            for dep in &attr_deps.configuration_deps {
+                for to in targets {
+                    if to.label().unconfigured() == dep.target() {
+                        add(
+                            &mut edges,
+                            to.label(),
+                            Some(attr.name),
+                            DepKind::Configuration,
+                        );
+                    }
+                }
+            }
+        }
+
+        // Dependencies of the configured graph which no attribute declares.
+        let exec_deps = node.exec_deps().map(|dep| (dep, DepKind::Exec));
+        let deps = node.deps().map(|dep| {
+            let kind = match dep.rule_kind() {
+                RuleKind::Toolchain => DepKind::Toolchain,
+                RuleKind::Configuration => DepKind::Configuration,
+                _ => DepKind::Target,
+            };
+            (dep, kind)
+        });
+        for (dep, kind) in deps.chain(exec_deps) {
+            let explained = edges[first..].iter().any(|edge| &edge.to == dep.label());
+            if !explained && targets.contains(dep.label()) {
+                add(&mut edges, dep.label(), None, kind);
+            }
+        }
+    }
+
+    Ok(edges)
+}
+
+/// Whether to print the edges as JSON rather than text. Graph formats are not supported, since
+/// the edges are the output.
+pub(crate) fn explained_edges_json(output_format: QueryOutputFormat) -> buck2_error::Result<bool> {
+    match output_format {
+        QueryOutputFormat::Default => Ok(false),
+        QueryOutputFormat::Json => Ok(true),
+        format => Err(QueryCommandError::ExplainPathsUnsupportedFormat(
+            format.as_str_name().to_lowercase(),
+        )
+        .into()),
+    }
+}
+
+pub(crate) fn print_explained_edges(
+    mut out: impl Write,
+    edges: &[ExplainedEdge],
+    json: bool,
+) -> buck2_error::Result<()> {
+    if json {
+        serde_json::to_writer_pretty(&mut out, edges)?;
+        writeln!(out)?;
+        return Ok(());
+    }
+
+    for edge in edges {
+        match &edge.attribute {
+            Some(attribute) => writeln!(
+                out,
+                "{} -> {} ({} dep from attribute `{}`)",
+                edge.from, edge.to, edge.kind, attribute
+            )?,
+            None => writeln!(
+                out,
+                "{} -> {} ({} dep, not from an attribute)",
+                edge.from, edge.to, edge.kind
+            )?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use buck2_core::bzl::ImportPath;
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::configuration::pair::ConfigurationNoExec;
+    use buck2_core::execution_types::execution::ExecutionPlatformResolution;
+    use buck2_core::plugins::PluginKindSet;
+    use buck2_core::plugins::PluginLists;
+    use buck2_core::provider::label::ProvidersName;
+    use buck2_core::target::label::label::TargetLabel;
+    use buck2_node::attrs::attr::Attribute;
+    use buck2_node::attrs::attr_type::list::ListLiteral;
+    use buck2_node::attrs::attr_type::AttrType;
+    use buck2_node::attrs::coerced_attr::CoercedAttr;
+    use buck2_node::bzl_or_bxl_path::BzlOrBxlPath;
+    use buck2_node::configuration::resolved::ConfigurationSettingKey;
+    use buck2_node::configuration::resolved::ResolvedConfiguration;
+    use buck2_node::configuration::resolved::ResolvedConfigurationSettings;
+    use buck2_node::nodes::unconfigured::testing::TargetNodeExt;
+    use buck2_node::nodes::unconfigured::TargetNode;
+    use buck2_node::provider_id_set::ProviderIdSet;
+    use buck2_node::rule_type::RuleType;
+    use buck2_node::rule_type::StarlarkRuleType;
+    use buck2_util::arc_str::ArcSlice;
+    use starlark_map::ordered_map::OrderedMap;
+
+    use super::*;
+
+    fn label(name: &str) -> ConfiguredTargetLabel {
+        ConfiguredTargetLabel::testing_parse(name, ConfigurationData::testing_new())
+    }
+
+    fn deps_attr(name: &'static str, deps: &[&str]) -> (&'static str, Attribute, CoercedAttr) {
+        (
+            name,
+            Attribute::new(
+                None,
+                "",
+                AttrType::list(AttrType::dep(ProviderIdSet::EMPTY, PluginKindSet::EMPTY)),
+            ),
+            CoercedAttr::List(ListLiteral(ArcSlice::from_iter(deps.iter().map(|dep| {
+                CoercedAttr::Dep(ProvidersLabel::new(
+                    TargetLabel::testing_parse(dep),
+                    ProvidersName::Default,
+                ))
+            })))),
+        )
+    }
+
+    fn node(
+        name: &str,
+        attrs: Vec<(&str, Attribute, CoercedAttr)>,
+        exec_deps: Vec<ConfiguredTargetNode>,
+    ) -> ConfiguredTargetNode {
+        let label = label(name);
+        let rule_type = RuleType::Starlark(Arc::new(StarlarkRuleType {
+            path: BzlOrBxlPath::Bzl(ImportPath::testing_new("root//:rules.bzl")),
+            name: "some_rule".to_owned(),
+        }));
+        ConfiguredTargetNode::new(
+            label.dupe(),
+            TargetNode::testing_new(label.unconfigured().dupe(), rule_type, attrs, vec![], None),
+            ResolvedConfiguration::new(
+                ConfigurationNoExec::new(label.cfg().dupe()),
+                ResolvedConfigurationSettings::empty(),
+            ),
+            OrderedMap::new(),
+            ExecutionPlatformResolution::new(None, Vec::new()),
+            Vec::new(),
+            exec_deps,
+            OrderedMap::new(),
+            PluginLists::new(),
+        )
+    }
+
+    #[test]
+    fn test_explain_edges() -> buck2_error::Result<()> {
+        let tool = node("root//:tool", vec![], vec![]);
+        let setting = node("root//:setting", vec![], vec![]);
+        let lib = node("root//:lib", vec![], vec![tool.dupe()]);
+        let bin = node(
+            "root//:bin",
+            vec![
+                // The same dependency twice in an attribute is one edge, and targets outside the
+                // result have no edges.
+                deps_attr("deps", &["root//:lib", "root//:lib", "root//:other"]),
+                deps_attr("runtime_deps", &["root//:lib"]),
+                (
+                    "setting",
+                    Attribute::new(None, "", AttrType::configuration_dep()),
+                    CoercedAttr::ConfigurationDep(ConfigurationSettingKey::testing_parse(
+                        "root//:setting",
+                    )),
+                ),
+            ],
+            vec![],
+        );
+        let targets = TargetSet::from_iter([bin, lib, tool, setting]);
+
+        let edge = |from: &str, to: &str, attribute: Option<&str>, kind: DepKind| ExplainedEdge {
+            from: label(from),
+            to: label(to),
+            attribute: attribute.map(str::to_owned),
+            kind,
+        };
+        assert_eq!(
+            vec![
+                edge("root//:bin", "root//:lib", Some("deps"), DepKind::Target),
+                edge(
+                    "root//:bin",
+                    "root//:lib",
+                    Some("runtime_deps"),
+                    DepKind::Target
+                ),
+                edge(
+                    "root//:bin",
+                    "root//:setting",
+                    Some("setting"),
+                    DepKind::Configuration
+                ),
+                edge("root//:lib", "root//:tool", None, DepKind::Exec),
+            ],
+            explain_edges(&targets)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_explained_edges_json() {
+        assert!(!explained_edges_json(QueryOutputFormat::Default).unwrap());
+        assert!(explained_edges_json(QueryOutputFormat::Json).unwrap());
+        assert_eq!(
+            "--explain-paths only supports the default and json output formats, not dot_compact",
+            explained_edges_json(QueryOutputFormat::DotCompact)
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_print_text() {
+        let edges = vec![
+            ExplainedEdge {
+                from: label("root//:bin"),
+                to: label("root//:lib"),
+                attribute: Some("deps".to_owned()),
+                kind: DepKind::Target,
+            },
+            ExplainedEdge {
+                from: label("root//:lib"),
+                to: label("root//:tool"),
+                attribute: None,
+                kind: DepKind::Exec,
+            },
+        ];
+        let mut out = Vec::new();
+        print_explained_edges(&mut out, &edges, false).unwrap();
+        let cfg = ConfigurationData::testing_new();
+        assert_eq!(
+            format!(
+                "root//:bin ({cfg}) -> root//:lib ({cfg}) (target dep from attribute `deps`)\n\
+                 root//:lib ({cfg}) -> root//:tool ({cfg}) (exec dep, not from an attribute)\n"
+            ),
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_print_json() {
+        let edges = vec![ExplainedEdge {
+            from: label("root//:bin"),
+            to: label("root//:toolchain"),
+            attribute: Some("_toolchain".to_owned()),
+            kind: DepKind::Toolchain,
+        }];
+        let mut out = Vec::new();
+        print_explained_edges(&mut out, &edges, true).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!("_toolchain", json[0]["attribute"]);
+        assert_eq!("toolchain", json[0]["kind"]);
+    }
+}
@@ -43,6 +43,9 @@ use serde::Serializer;
 
 use crate::commands::query::query_target_ext::QueryCommandTarget;
 use crate::commands::query::QueryCommandError;
+use crate::dot::graphml::GraphMl;
+use crate::dot::json_graph::JsonGraph;
+use crate::dot::mermaid::Mermaid;
 use crate::dot::targets::DotTargetGraph;
 use crate::dot::Dot;
 use crate::dot::DotCompact;
@@ -410,6 +413,33 @@ impl<'a> QueryResultPrinter<'a> {
                         &mut output,
                     )?;
                 }
+                QueryOutputFormat::Graphml => {
+                    GraphMl::render(
+                        &DotTargetGraph {
+                            targets,
+                            attributes: self.attributes.clone(),
+                        },
+                        &mut output,
+                    )?;
+                }
+                QueryOutputFormat::Mermaid => {
+                    Mermaid::render(
+                        &DotTargetGraph {
+                            targets,
+                            attributes: self.attributes.clone(),
+                        },
+                        &mut output,
+                    )?;
+                }
+                QueryOutputFormat::JsonGraph => {
+                    JsonGraph::render(
+                        &DotTargetGraph {
+                            targets,
+                            attributes: self.attributes.clone(),
+                        },
+                        &mut output,
+                    )?;
+                }
             },
             QueryEvaluationValue::FileSet(files) => {
                 if self.attributes.is_some() {
@@ -442,6 +472,15 @@ impl<'a> QueryResultPrinter<'a> {
                     QueryOutputFormat::DotCompact => {
                         unimplemented!("dot_compact output for files not implemented yet")
                     }
+                    QueryOutputFormat::Graphml => {
+                        return Err(QueryCommandError::FileSetHasNoGraph("graphml").into());
+                    }
+                    QueryOutputFormat::Mermaid => {
+                        return Err(QueryCommandError::FileSetHasNoGraph("mermaid").into());
+                    }
+                    QueryOutputFormat::JsonGraph => {
+                        return Err(QueryCommandError::FileSetHasNoGraph("json_graph").into());
+                    }
                 }
             }
         }
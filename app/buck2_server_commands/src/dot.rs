@@ -27,6 +27,9 @@ use once_cell::sync::Lazy;
 use regex::Regex;
 use starlark_map::small_map::SmallMap;
 
+pub mod graphml;
+pub mod json_graph;
+pub mod mermaid;
 pub mod targets;
 
 #[derive(Default, Debug)]
@@ -180,3 +183,129 @@ impl DotCompact {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dot::graphml::GraphMl;
+    use crate::dot::json_graph::JsonGraph;
+    use crate::dot::mermaid::Mermaid;
+
+    struct TestNode {
+        id: &'static str,
+        deps: Vec<&'static str>,
+    }
+
+    impl DotNode for TestNode {
+        fn attrs(&self) -> buck2_error::Result<DotNodeAttrs> {
+            let mut extra = SmallMap::new();
+            extra.insert("buck_type".to_owned(), "\"genrule\" & co".to_owned());
+            Ok(DotNodeAttrs {
+                extra,
+                ..DotNodeAttrs::default()
+            })
+        }
+
+        fn id(&self) -> String {
+            self.id.to_owned()
+        }
+    }
+
+    struct TestGraph(Vec<TestNode>);
+
+    impl<'a> DotDigraph<'a> for TestGraph {
+        type Node = TestNode;
+
+        fn name(&self) -> &str {
+            "result_graph"
+        }
+
+        fn for_each_node<F: FnMut(&Self::Node) -> buck2_error::Result<()>>(
+            &'a self,
+            mut f: F,
+        ) -> buck2_error::Result<()> {
+            self.0.iter().try_for_each(|node| f(node))
+        }
+
+        fn for_each_edge<F: FnMut(&DotEdge) -> buck2_error::Result<()>>(
+            &'a self,
+            node: &Self::Node,
+            mut f: F,
+        ) -> buck2_error::Result<()> {
+            node.deps.iter().try_for_each(|dep| {
+                f(&DotEdge {
+                    from: node.id,
+                    to: dep,
+                })
+            })
+        }
+    }
+
+    fn graph() -> TestGraph {
+        TestGraph(vec![
+            TestNode {
+                id: "root//:a",
+                deps: vec!["root//:b"],
+            },
+            TestNode {
+                id: "root//:b",
+                deps: vec![],
+            },
+        ])
+    }
+
+    fn render(f: impl FnOnce(&TestGraph, &mut Vec<u8>) -> buck2_error::Result<()>) -> String {
+        let mut out = Vec::new();
+        f(&graph(), &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_graphml() {
+        assert_eq!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <key id="label" for="node" attr.name="label" attr.type="string"/>
+  <key id="d0" for="node" attr.name="buck_type" attr.type="string"/>
+  <graph id="result_graph" edgedefault="directed">
+    <node id="root//:a">
+      <data key="label">root//:a</data>
+      <data key="d0">&quot;genrule&quot; &amp; co</data>
+    </node>
+    <node id="root//:b">
+      <data key="label">root//:b</data>
+      <data key="d0">&quot;genrule&quot; &amp; co</data>
+    </node>
+    <edge source="root//:a" target="root//:b"/>
+  </graph>
+</graphml>
+"#,
+            render(|g, w| GraphMl::render(g, w))
+        );
+    }
+
+    #[test]
+    fn test_mermaid() {
+        assert_eq!(
+            "flowchart LR\n  n1[\"root//:a\"]\n  n1 --> n2\n  n2[\"root//:b\"]\n",
+            render(|g, w| Mermaid::render(g, w))
+        );
+    }
+
+    #[test]
+    fn test_json_graph() {
+        let output: serde_json::Value =
+            serde_json::from_str(&render(|g, w| JsonGraph::render(g, w))).unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "name": "result_graph",
+                "nodes": [
+                    { "id": "root//:a", "attrs": { "buck_type": "\"genrule\" & co" } },
+                    { "id": "root//:b", "attrs": { "buck_type": "\"genrule\" & co" } },
+                ],
+                "edges": [{ "source": "root//:a", "target": "root//:b" }],
+            }),
+            output
+        );
+    }
+}
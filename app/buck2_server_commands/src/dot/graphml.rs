@@ -0,0 +1,113 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! GraphML output (see <http://graphml.graphdrawing.org/specification.html>), for tools like yEd
+//! and Gephi.
+
+use std::io::Write;
+
+use starlark_map::small_set::SmallSet;
+
+use crate::dot::DotDigraph;
+use crate::dot::DotNode;
+use crate::dot::DotNodeAttrs;
+
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub struct GraphMl {}
+
+impl GraphMl {
+    pub(crate) fn render<'a, T: DotDigraph<'a>, W: Write>(
+        graph: &'a T,
+        mut w: W,
+    ) -> buck2_error::Result<()> {
+        // GraphML declares all attributes before the graph, so collect the graph first.
+        let mut nodes: Vec<(String, DotNodeAttrs)> = Vec::new();
+        let mut edges: Vec<(String, String)> = Vec::new();
+        let mut keys: SmallSet<String> = SmallSet::new();
+        graph.for_each_node(|node| {
+            let attrs = node.attrs()?;
+            for key in attrs.extra.keys() {
+                keys.insert(key.clone());
+            }
+            nodes.push((node.id(), attrs));
+            graph.for_each_edge(node, |edge| {
+                edges.push((edge.from.to_owned(), edge.to.to_owned()));
+                Ok(())
+            })
+        })?;
+
+        writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            w,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        writeln!(
+            w,
+            r#"  <key id="label" for="node" attr.name="label" attr.type="string"/>"#
+        )?;
+        for (i, key) in keys.iter().enumerate() {
+            writeln!(
+                w,
+                r#"  <key id="d{}" for="node" attr.name="{}" attr.type="string"/>"#,
+                i,
+                escape_xml(key)
+            )?;
+        }
+        writeln!(
+            w,
+            r#"  <graph id="{}" edgedefault="directed">"#,
+            escape_xml(graph.name())
+        )?;
+        for (id, attrs) in &nodes {
+            writeln!(w, r#"    <node id="{}">"#, escape_xml(id))?;
+            writeln!(
+                w,
+                r#"      <data key="label">{}</data>"#,
+                escape_xml(attrs.label.as_deref().unwrap_or(id))
+            )?;
+            for (key, value) in &attrs.extra {
+                let i = keys
+                    .get_index_of(key)
+                    .expect("keys of all nodes were collected");
+                writeln!(
+                    w,
+                    r#"      <data key="d{}">{}</data>"#,
+                    i,
+                    escape_xml(value)
+                )?;
+            }
+            writeln!(w, "    </node>")?;
+        }
+        for (from, to) in &edges {
+            writeln!(
+                w,
+                r#"    <edge source="{}" target="{}"/>"#,
+                escape_xml(from),
+                escape_xml(to)
+            )?;
+        }
+        writeln!(w, "  </graph>")?;
+        writeln!(w, "</graphml>")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A JSON object with a list of nodes, with their attributes, and a list of edges. Unlike the
+//! `--json` output, edges are explicit, so it can be loaded into graph tools without knowing
+//! which attributes hold dependencies.
+
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::dot::DotDigraph;
+use crate::dot::DotNode;
+
+#[derive(Serialize)]
+struct JsonGraphNode {
+    id: String,
+    attrs: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Serialize)]
+struct JsonGraphEdge {
+    source: String,
+    target: String,
+}
+
+#[derive(Serialize)]
+struct JsonGraphOutput<'a> {
+    name: &'a str,
+    nodes: Vec<JsonGraphNode>,
+    edges: Vec<JsonGraphEdge>,
+}
+
+pub struct JsonGraph {}
+
+impl JsonGraph {
+    pub(crate) fn render<'a, T: DotDigraph<'a>, W: Write>(
+        graph: &'a T,
+        mut w: W,
+    ) -> buck2_error::Result<()> {
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        graph.for_each_node(|node| {
+            let attrs = node.attrs()?;
+            nodes.push(JsonGraphNode {
+                id: node.id(),
+                attrs: attrs
+                    .label
+                    .map(|label| ("label".to_owned(), label))
+                    .into_iter()
+                    .chain(attrs.extra)
+                    .map(|(key, value)| (key, serde_json::Value::String(value)))
+                    .collect(),
+            });
+            graph.for_each_edge(node, |edge| {
+                edges.push(JsonGraphEdge {
+                    source: edge.from.to_owned(),
+                    target: edge.to.to_owned(),
+                });
+                Ok(())
+            })
+        })?;
+
+        serde_json::to_writer_pretty(
+            &mut w,
+            &JsonGraphOutput {
+                name: graph.name(),
+                nodes,
+                edges,
+            },
+        )?;
+        // need to add a newline to flush the output.
+        writeln!(w)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Mermaid flowchart output (see <https://mermaid.js.org/syntax/flowchart.html>), which renders
+//! in markdown on GitHub and in most documentation tools.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::io::Write;
+
+use crate::dot::DotDigraph;
+use crate::dot::DotNode;
+
+/// Mermaid ids can't contain most of the characters in target labels, so nodes are numbered and
+/// labelled instead, like in `DotCompact`. Quotes are the only characters that need escaping in a
+/// quoted label.
+fn escape_label(value: &str) -> String {
+    value.replace('"', "#quot;")
+}
+
+pub struct Mermaid {}
+
+impl Mermaid {
+    pub(crate) fn render<'a, T: DotDigraph<'a>, W: Write>(
+        graph: &'a T,
+        mut w: W,
+    ) -> buck2_error::Result<()> {
+        writeln!(w, "flowchart LR")?;
+
+        let mut next_id: u32 = 0;
+        let mut lookup_numeric_id: HashMap<String, u32> = HashMap::new();
+
+        let mut name_to_number = |node_name: &str| -> u32 {
+            match lookup_numeric_id.entry(node_name.to_owned()) {
+                Entry::Vacant(entry) => {
+                    next_id += 1;
+                    entry.insert(next_id);
+                    next_id
+                }
+                Entry::Occupied(entry) => *entry.get(),
+            }
+        };
+
+        graph.for_each_node(|node| {
+            let attrs = node.attrs()?;
+            let id = node.id();
+            writeln!(
+                w,
+                "  n{}[\"{}\"]",
+                name_to_number(&id),
+                escape_label(attrs.label.as_deref().unwrap_or(&id))
+            )?;
+            graph.for_each_edge(node, |edge| {
+                writeln!(
+                    w,
+                    "  n{} --> n{}",
+                    name_to_number(edge.from),
+                    name_to_number(edge.to)
+                )?;
+                Ok(())
+            })?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+}
@@ -16,6 +16,8 @@ use buck2_cli_proto::new_generic::ExpandExternalCellsRequest;
 use buck2_cli_proto::new_generic::ExpandExternalCellsResponse;
 use buck2_cli_proto::new_generic::ExplainRequest;
 use buck2_cli_proto::new_generic::ExplainResponse;
+use buck2_cli_proto::new_generic::OfflineBundleRequest;
+use buck2_cli_proto::new_generic::OfflineBundleResponse;
 use buck2_util::late_binding::LateBinding;
 
 use crate::ctx::ServerCommandContextTrait;
@@ -95,6 +97,12 @@ pub trait OtherServerCommands: Send + Sync + 'static {
         partial_result_dispatcher: PartialResultDispatcher<NoPartialResult>,
         req: ExpandExternalCellsRequest,
     ) -> buck2_error::Result<ExpandExternalCellsResponse>;
+    async fn offline_bundle(
+        &self,
+        ctx: &dyn ServerCommandContextTrait,
+        partial_result_dispatcher: PartialResultDispatcher<NoPartialResult>,
+        req: OfflineBundleRequest,
+    ) -> buck2_error::Result<OfflineBundleResponse>;
 }
 
 pub static OTHER_SERVER_COMMANDS: LateBinding<&'static dyn OtherServerCommands> =
@@ -9,6 +9,7 @@
 
 use std::collections::HashSet;
 use std::ops::ControlFlow;
+use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
@@ -31,6 +32,7 @@ use buck2_build_api::materialize::MaterializationContext;
 use buck2_cli_proto::HasClientContext;
 use buck2_cli_proto::TestRequest;
 use buck2_cli_proto::TestResponse;
+use buck2_cli_proto::TestSharding;
 use buck2_common::dice::cells::HasCellResolver;
 use buck2_common::events::HasEvents;
 use buck2_common::legacy_configs::dice::HasLegacyConfigs;
@@ -76,6 +78,7 @@ use buck2_server_ctx::test_command::TEST_COMMAND;
 use buck2_test_api::data::TestResult;
 use buck2_test_api::data::TestStatus;
 use buck2_test_api::protocol::TestExecutor;
+use buck2_test_api::sharding::TestShard;
 use dice::DiceTransaction;
 use dice::LinearRecomputeDiceComputations;
 use dupe::Dupe;
@@ -183,6 +186,7 @@ struct TestStatuses {
     fatals: CounterWithExamples,
     listing_success: CounterWithExamples,
     listing_failed: CounterWithExamples,
+    flaky: CounterWithExamples,
 }
 impl TestStatuses {
     fn ingest(&mut self, result: &TestResult) {
@@ -197,6 +201,7 @@ impl TestStatuses {
             TestStatus::RERUN => {}
             TestStatus::LISTING_SUCCESS => self.listing_success.add(&result.name),
             TestStatus::LISTING_FAILED => self.listing_failed.add(&result.name),
+            TestStatus::FLAKY => self.flaky.add(&result.name),
         }
     }
 }
@@ -349,11 +354,31 @@ async fn test(
         .transpose()
         .context("Invalid `duration`")?;
 
+    // Targets are sharded here, so that targets in other shards aren't built. Test cases are
+    // only known to the test executor, so it shards those itself.
+    let mut test_executor_args = Vec::new();
+    let shard = match &request.sharding {
+        Some(sharding) if sharding.by_test_case => {
+            test_executor_args.extend(test_executor_shard_args(sharding));
+            None
+        }
+        Some(sharding) => {
+            let mut shard = TestShard::new(sharding.index, sharding.count)?;
+            if let Some(path) = &sharding.timings_path {
+                shard = shard.with_timings(TestShard::load_timings(Path::new(path))?);
+            }
+            Some(shard)
+        }
+        None => None,
+    };
+    test_executor_args.extend(request.test_executor_args.iter().cloned());
+
     let test_outcome = test_targets(
         ctx.dupe(),
         resolved_pattern,
         global_cfg_options,
-        request.test_executor_args.clone(),
+        test_executor_args,
+        shard,
         Arc::new(TestLabelFiltering::new(
             request.included_labels.clone(),
             request.excluded_labels.clone(),
@@ -423,6 +448,13 @@ async fn test(
                 .listing_failed
                 .to_cli_proto_counter(),
         ),
+        flaky: Some(
+            test_outcome
+                .executor_report
+                .statuses
+                .flaky
+                .to_cli_proto_counter(),
+        ),
     };
 
     let serialized_build_report = if build_opts.unstable_print_build_report {
@@ -454,11 +486,26 @@ async fn test(
     })
 }
 
+fn test_executor_shard_args(sharding: &TestSharding) -> Vec<String> {
+    let mut args = vec![
+        "--shard-index".to_owned(),
+        sharding.index.to_string(),
+        "--shard-count".to_owned(),
+        sharding.count.to_string(),
+    ];
+    if let Some(path) = &sharding.timings_path {
+        args.push("--shard-timings".to_owned());
+        args.push(path.clone());
+    }
+    args
+}
+
 async fn test_targets(
     ctx: DiceTransaction,
     pattern: ResolvedPattern<ConfiguredProvidersPatternExtra>,
     global_cfg_options: GlobalCfgOptions,
     external_runner_args: Vec<String>,
+    shard: Option<TestShard>,
     label_filtering: Arc<TestLabelFiltering>,
     launcher: &dyn ExecutorLauncher,
     session: TestSession,
@@ -548,6 +595,7 @@ async fn test_targets(
                     working_dir_cell,
                     missing_target_behavior,
                     ignore_tests_attribute,
+                    shard: shard.as_ref(),
                 });
 
                 driver.push_pattern(
@@ -687,6 +735,7 @@ struct TestDriverState<'a, 'e> {
     working_dir_cell: CellName,
     missing_target_behavior: MissingTargetBehavior,
     ignore_tests_attribute: bool,
+    shard: Option<&'a TestShard>,
 }
 
 /// Maintains the state of an ongoing test execution.
@@ -942,6 +991,12 @@ impl<'a, 'e> TestDriver<'a, 'e> {
             return;
         }
 
+        if let Some(shard) = self.state.shard {
+            if !shard.contains(&label.target().unconfigured().to_string()) {
+                return;
+            }
+        }
+
         let state = self.state;
         let build_label = label.dupe();
         let fut = async move {
@@ -1714,6 +1714,7 @@ impl<'a> Execute2RequestExpander<'a> {
                     exe: worker_rendered,
                     id: WorkerId(worker.id),
                     concurrency: worker.concurrency(),
+                    protocol: worker.protocol(),
                 })
             }
             _ => None,
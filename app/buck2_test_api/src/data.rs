@@ -128,6 +128,8 @@ pub enum TestStatus {
     RERUN,
     LISTING_SUCCESS,
     LISTING_FAILED,
+    // Failed, then passed when retried
+    FLAKY,
 }
 
 /// The set of information about a test rule that is passed to the test executor
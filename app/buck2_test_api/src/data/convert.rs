@@ -223,6 +223,7 @@ impl TryFrom<i32> for TestStatus {
             buck2_test_proto::TestStatus::Rerun => TestStatus::RERUN,
             buck2_test_proto::TestStatus::ListingSuccess => TestStatus::LISTING_SUCCESS,
             buck2_test_proto::TestStatus::ListingFailed => TestStatus::LISTING_FAILED,
+            buck2_test_proto::TestStatus::Flaky => TestStatus::FLAKY,
         })
     }
 }
@@ -242,6 +243,7 @@ impl TryInto<i32> for TestStatus {
             TestStatus::RERUN => buck2_test_proto::TestStatus::Rerun,
             TestStatus::LISTING_SUCCESS => buck2_test_proto::TestStatus::ListingSuccess,
             TestStatus::LISTING_FAILED => buck2_test_proto::TestStatus::ListingFailed,
+            TestStatus::FLAKY => buck2_test_proto::TestStatus::Flaky,
         } as i32)
     }
 }
@@ -0,0 +1,168 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Splitting tests across several test runs ("shards"), e.g. on different CI machines.
+//!
+//! Every shard computes the same partition independently, so the assignment of a test to a shard
+//! must only depend on the test's name and on the inputs all shards share. Tests with a known
+//! duration (from a timing file) are assigned so that shards take about the same time. All other
+//! tests are assigned by a stable hash of their name.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::Context;
+
+#[derive(Debug, buck2_error::Error)]
+pub enum TestShardError {
+    #[error("Shard count must be at least 1")]
+    ZeroShards,
+    #[error("Shard index `{0}` is out of range for `{1}` shards")]
+    IndexOutOfRange(u32, u32),
+}
+
+#[derive(Debug, Clone)]
+pub struct TestShard {
+    index: u32,
+    count: u32,
+    /// Shards of the tests that have a known duration.
+    balanced: HashMap<String, u32>,
+}
+
+impl TestShard {
+    pub fn new(index: u32, count: u32) -> anyhow::Result<Self> {
+        if count == 0 {
+            return Err(TestShardError::ZeroShards.into());
+        }
+        if index >= count {
+            return Err(TestShardError::IndexOutOfRange(index, count).into());
+        }
+        Ok(Self {
+            index,
+            count,
+            balanced: HashMap::new(),
+        })
+    }
+
+    /// Balance the tests in `timings` (test name to duration in seconds) across shards, by
+    /// assigning the longest tests first, each to the shard with the least total duration so far.
+    pub fn with_timings(mut self, timings: HashMap<String, f64>) -> Self {
+        let mut timings: Vec<_> = timings.into_iter().collect();
+        // Break ties by name, so that every shard sees the same order.
+        timings.sort_by(|(a_name, a), (b_name, b)| b.total_cmp(a).then_with(|| a_name.cmp(b_name)));
+
+        let mut totals = vec![0.0f64; self.count as usize];
+        for (name, duration) in timings {
+            let (shard, total) = totals
+                .iter_mut()
+                .enumerate()
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .expect("there is at least one shard");
+            *total += duration.max(0.0);
+            self.balanced.insert(name, shard as u32);
+        }
+        self
+    }
+
+    /// Read a timing file: a JSON object mapping test names to their duration in seconds.
+    pub fn load_timings(path: &Path) -> anyhow::Result<HashMap<String, f64>> {
+        let contents = std::fs::read(path)
+            .with_context(|| format!("Error reading test timings from `{}`", path.display()))?;
+        serde_json::from_slice(&contents)
+            .with_context(|| format!("Invalid test timings in `{}`", path.display()))
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Whether the test called `name` runs in this shard.
+    pub fn contains(&self, name: &str) -> bool {
+        let shard = match self.balanced.get(name) {
+            Some(shard) => *shard,
+            None => (stable_hash(name) % u64::from(self.count)) as u32,
+        };
+        shard == self.index
+    }
+}
+
+/// 64-bit FNV-1a, which, unlike the hashers in std, is guaranteed not to change between builds.
+fn stable_hash(s: &str) -> u64 {
+    s.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shards(count: u32, timings: &HashMap<String, f64>) -> Vec<TestShard> {
+        (0..count)
+            .map(|i| {
+                TestShard::new(i, count)
+                    .unwrap()
+                    .with_timings(timings.clone())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_stable_hash() {
+        // Shard assignments must not change between versions of Buck2.
+        assert_eq!(0xcbf29ce484222325, stable_hash(""));
+        assert_eq!(0xaf63dc4c8601ec8c, stable_hash("a"));
+    }
+
+    #[test]
+    fn test_every_test_is_in_one_shard() {
+        let timings = HashMap::from([("root//:slow".to_owned(), 30.0)]);
+        let shards = shards(4, &timings);
+        for i in 0..100 {
+            let name = format!("root//:test_{}", i);
+            assert_eq!(1, shards.iter().filter(|s| s.contains(&name)).count());
+        }
+        assert_eq!(
+            1,
+            shards.iter().filter(|s| s.contains("root//:slow")).count()
+        );
+    }
+
+    #[test]
+    fn test_balanced_by_timings() {
+        let timings = HashMap::from([
+            ("a".to_owned(), 10.0),
+            ("b".to_owned(), 6.0),
+            ("c".to_owned(), 5.0),
+            ("d".to_owned(), 4.0),
+        ]);
+        let shards = shards(2, &timings);
+        let tests_in = |shard: &TestShard| {
+            let mut tests: Vec<_> = timings
+                .keys()
+                .filter(|t| shard.contains(t))
+                .cloned()
+                .collect();
+            tests.sort();
+            tests
+        };
+        assert_eq!(vec!["a", "d"], tests_in(&shards[0]));
+        assert_eq!(vec!["b", "c"], tests_in(&shards[1]));
+    }
+
+    #[test]
+    fn test_invalid_shard() {
+        assert!(TestShard::new(0, 0).is_err());
+        assert!(TestShard::new(2, 2).is_err());
+    }
+}
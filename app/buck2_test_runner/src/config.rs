@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::time::Duration;
 
@@ -31,6 +32,46 @@ pub struct Config {
     /// Available as a workaround for when test features are available.
     #[clap(long, num_args=1.., allow_hyphen_values = true)]
     pub test_arg: Vec<String>,
+
+    /// Number of times to retry a failing test. Targets can override this with a `retries=N`
+    /// label.
+    #[clap(long, default_value = "0")]
+    pub retries: u32,
+
+    /// Seconds to wait before retrying a failed test. The wait doubles with every retry.
+    #[clap(long, default_value = "1", value_parser = try_parse_backoff_from_str)]
+    pub retry_backoff: Duration,
+
+    /// Write a JUnit XML report of the results to this path.
+    #[clap(long)]
+    pub junit_xml: Option<PathBuf>,
+
+    /// Write a JSON summary of the results to this path.
+    #[clap(long)]
+    pub json_summary: Option<PathBuf>,
+
+    /// Argument that makes a test binary print its test cases, one per line, instead of running
+    /// them. When set, every test case is run and reported on its own, using `--filter-arg`.
+    #[clap(long, allow_hyphen_values = true, requires = "filter_arg")]
+    pub list_arg: Option<String>,
+
+    /// Arguments that make a test binary run a single test case, with `{}` replaced by the name
+    /// of the test case, e.g. `--filter-arg --exact {}`.
+    #[clap(long, num_args=1.., allow_hyphen_values = true, requires = "list_arg")]
+    pub filter_arg: Vec<String>,
+
+    /// Only run the tests in this shard, out of `--shard-count` shards. Test cases are sharded if
+    /// `--list-arg` is set, test targets otherwise.
+    #[clap(long, requires = "shard_count")]
+    pub shard_index: Option<u32>,
+
+    /// Number of shards to split the tests into.
+    #[clap(long, requires = "shard_index")]
+    pub shard_count: Option<u32>,
+
+    /// JSON file mapping test names to their duration in seconds, used to balance shards.
+    #[clap(long, requires = "shard_index")]
+    pub shard_timings: Option<PathBuf>,
 }
 
 /// Uiltity that can be used to parse Env values from CLI arguments.
@@ -70,3 +111,10 @@ fn try_parse_timeout_from_str(input: &str) -> anyhow::Result<Duration> {
     let seconds = input.parse().context("Could not parse provided timeout")?;
     Ok(Duration::from_secs(seconds))
 }
+
+fn try_parse_backoff_from_str(input: &str) -> anyhow::Result<Duration> {
+    let seconds = input
+        .parse()
+        .context("Could not parse provided retry backoff")?;
+    Duration::try_from_secs_f64(seconds).context("Invalid retry backoff")
+}
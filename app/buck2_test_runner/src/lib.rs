@@ -11,6 +11,7 @@
 
 mod config;
 mod executor;
+mod report;
 mod runner;
 mod service;
 pub mod tcp;
@@ -0,0 +1,411 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Reports of a test run, written once all tests have finished: a JUnit XML file, which most CI
+//! systems can display, and a JSON summary for everything else.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Context;
+use buck2_test_api::data::TestStatus;
+use parking_lot::Mutex;
+use serde::Serialize;
+
+/// The result of a single test case, or of a whole target if its test cases weren't listed.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct TestCaseReport {
+    /// The target the test belongs to.
+    pub(crate) target: String,
+    /// The test case, if the target's test cases were listed.
+    pub(crate) test_case: Option<String>,
+    #[serde(serialize_with = "serialize_status")]
+    pub(crate) status: TestStatus,
+    #[serde(rename = "duration_secs", serialize_with = "serialize_duration")]
+    pub(crate) duration: Option<Duration>,
+    pub(crate) stdout: String,
+    pub(crate) stderr: String,
+    /// The directory the test could write additional artifacts to.
+    pub(crate) artifacts_dir: Option<String>,
+    /// The failed attempts before the last one, if the test was retried.
+    pub(crate) reruns: Vec<AttemptReport>,
+}
+
+/// A failed attempt at running a test that was then retried.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct AttemptReport {
+    #[serde(serialize_with = "serialize_status")]
+    pub(crate) status: TestStatus,
+    #[serde(rename = "duration_secs", serialize_with = "serialize_duration")]
+    pub(crate) duration: Option<Duration>,
+    pub(crate) stdout: String,
+    pub(crate) stderr: String,
+}
+
+#[derive(Serialize)]
+struct Summary<'a> {
+    total: usize,
+    counts: BTreeMap<&'static str, usize>,
+    /// The test cases listed for each target whose test cases were listed.
+    listed_test_cases: &'a BTreeMap<String, Vec<String>>,
+    results: &'a [TestCaseReport],
+}
+
+#[derive(Default)]
+pub(crate) struct TestReporter {
+    results: Mutex<Vec<TestCaseReport>>,
+    listed_test_cases: Mutex<BTreeMap<String, Vec<String>>>,
+}
+
+impl TestReporter {
+    pub(crate) fn record(&self, report: TestCaseReport) {
+        self.results.lock().push(report);
+    }
+
+    pub(crate) fn record_listing(&self, target: &str, test_cases: &[String]) {
+        self.listed_test_cases
+            .lock()
+            .insert(target.to_owned(), test_cases.to_vec());
+    }
+
+    /// Results ordered by target and test case, since tests finish in any order.
+    fn sorted_results(&self) -> Vec<TestCaseReport> {
+        let mut results = self.results.lock().clone();
+        results.sort_by(|a, b| (&a.target, &a.test_case).cmp(&(&b.target, &b.test_case)));
+        results
+    }
+
+    pub(crate) fn write_junit_xml(&self, path: &Path) -> anyhow::Result<()> {
+        let xml = render_junit_xml(&self.sorted_results());
+        std::fs::write(path, xml)
+            .with_context(|| format!("Error writing JUnit XML report to `{}`", path.display()))
+    }
+
+    pub(crate) fn write_json_summary(&self, path: &Path) -> anyhow::Result<()> {
+        let results = self.sorted_results();
+        let listed_test_cases = self.listed_test_cases.lock();
+        let mut counts = BTreeMap::new();
+        for result in &results {
+            *counts.entry(status_name(&result.status)).or_insert(0) += 1;
+        }
+        let summary = Summary {
+            total: results.len(),
+            counts,
+            listed_test_cases: &listed_test_cases,
+            results: &results,
+        };
+        let json = serde_json::to_vec_pretty(&summary)?;
+        std::fs::write(path, json)
+            .with_context(|| format!("Error writing JSON summary to `{}`", path.display()))
+    }
+}
+
+fn status_name(status: &TestStatus) -> &'static str {
+    match status {
+        TestStatus::PASS => "pass",
+        TestStatus::FAIL => "fail",
+        TestStatus::SKIP => "skip",
+        TestStatus::OMITTED => "omitted",
+        TestStatus::FATAL => "fatal",
+        TestStatus::TIMEOUT => "timeout",
+        TestStatus::UNKNOWN => "unknown",
+        TestStatus::RERUN => "rerun",
+        TestStatus::LISTING_SUCCESS => "listing_success",
+        TestStatus::LISTING_FAILED => "listing_failed",
+        TestStatus::FLAKY => "flaky",
+    }
+}
+
+fn serialize_status<S: serde::Serializer>(
+    status: &TestStatus,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(status_name(status))
+}
+
+fn serialize_duration<S: serde::Serializer>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match duration {
+        Some(duration) => serializer.serialize_some(&duration.as_secs_f64()),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// How a status is represented in JUnit, which only distinguishes failures (the test ran and
+/// failed), errors (the test could not run properly) and skipped tests.
+enum JUnitOutcome {
+    Success,
+    Failure,
+    Error,
+    Skipped,
+}
+
+impl JUnitOutcome {
+    fn of(status: &TestStatus) -> Self {
+        match status {
+            TestStatus::PASS | TestStatus::LISTING_SUCCESS | TestStatus::FLAKY => Self::Success,
+            TestStatus::FAIL | TestStatus::RERUN => Self::Failure,
+            TestStatus::FATAL
+            | TestStatus::TIMEOUT
+            | TestStatus::UNKNOWN
+            | TestStatus::LISTING_FAILED => Self::Error,
+            TestStatus::SKIP | TestStatus::OMITTED => Self::Skipped,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Counts {
+    tests: usize,
+    failures: usize,
+    errors: usize,
+    skipped: usize,
+    time: Duration,
+}
+
+impl Counts {
+    fn add(&mut self, result: &TestCaseReport) {
+        self.tests += 1;
+        match JUnitOutcome::of(&result.status) {
+            JUnitOutcome::Success => {}
+            JUnitOutcome::Failure => self.failures += 1,
+            JUnitOutcome::Error => self.errors += 1,
+            JUnitOutcome::Skipped => self.skipped += 1,
+        }
+        self.time += result.duration.unwrap_or_default();
+    }
+
+    fn attributes(&self) -> String {
+        format!(
+            r#"tests="{}" failures="{}" errors="{}" skipped="{}" time="{:.3}""#,
+            self.tests,
+            self.failures,
+            self.errors,
+            self.skipped,
+            self.time.as_secs_f64()
+        )
+    }
+}
+
+/// Render results as JUnit XML, with one `testsuite` per target.
+fn render_junit_xml(results: &[TestCaseReport]) -> String {
+    let mut suites: BTreeMap<&str, Vec<&TestCaseReport>> = BTreeMap::new();
+    let mut total = Counts::default();
+    for result in results {
+        suites.entry(&result.target).or_default().push(result);
+        total.add(result);
+    }
+
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    writeln!(xml, "<testsuites {}>", total.attributes()).unwrap();
+    for (target, results) in suites {
+        let mut counts = Counts::default();
+        for result in &results {
+            counts.add(result);
+        }
+        writeln!(
+            xml,
+            r#"  <testsuite name="{}" {}>"#,
+            escape(target),
+            counts.attributes()
+        )
+        .unwrap();
+        for result in results {
+            render_test_case(&mut xml, result);
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+fn render_test_case(xml: &mut String, result: &TestCaseReport) {
+    writeln!(
+        xml,
+        r#"    <testcase name="{}" classname="{}" time="{:.3}">"#,
+        escape(result.test_case.as_deref().unwrap_or(&result.target)),
+        escape(&result.target),
+        result.duration.unwrap_or_default().as_secs_f64()
+    )
+    .unwrap();
+
+    let status = status_name(&result.status);
+    match JUnitOutcome::of(&result.status) {
+        JUnitOutcome::Success => {}
+        JUnitOutcome::Failure => {
+            writeln!(xml, r#"      <failure message="{}"/>"#, status).unwrap();
+        }
+        JUnitOutcome::Error => {
+            writeln!(xml, r#"      <error message="{}"/>"#, status).unwrap();
+        }
+        JUnitOutcome::Skipped => {
+            writeln!(xml, r#"      <skipped message="{}"/>"#, status).unwrap();
+        }
+    }
+
+    let mut properties = Vec::new();
+    if let Some(artifacts_dir) = &result.artifacts_dir {
+        properties.push(("artifacts_dir", artifacts_dir.clone()));
+    }
+    if !result.reruns.is_empty() {
+        properties.push(("attempts", (result.reruns.len() + 1).to_string()));
+    }
+    if result.status == TestStatus::FLAKY {
+        properties.push(("flaky", "true".to_owned()));
+    }
+    if !properties.is_empty() {
+        xml.push_str("      <properties>\n");
+        for (name, value) in properties {
+            writeln!(
+                xml,
+                r#"        <property name="{}" value="{}"/>"#,
+                name,
+                escape(&value)
+            )
+            .unwrap();
+        }
+        xml.push_str("      </properties>\n");
+    }
+    // Failed attempts use Surefire's elements, which most JUnit consumers understand: flaky
+    // failures for tests that passed in the end, and rerun failures otherwise.
+    let rerun_element = if result.status == TestStatus::FLAKY {
+        "flakyFailure"
+    } else {
+        "rerunFailure"
+    };
+    for rerun in &result.reruns {
+        writeln!(
+            xml,
+            r#"      <{} message="{}" time="{:.3}">"#,
+            rerun_element,
+            status_name(&rerun.status),
+            rerun.duration.unwrap_or_default().as_secs_f64()
+        )
+        .unwrap();
+        render_output(xml, "        ", &rerun.stdout, &rerun.stderr);
+        writeln!(xml, "      </{}>", rerun_element).unwrap();
+    }
+    render_output(xml, "      ", &result.stdout, &result.stderr);
+    xml.push_str("    </testcase>\n");
+}
+
+fn render_output(xml: &mut String, indent: &str, stdout: &str, stderr: &str) {
+    if !stdout.is_empty() {
+        writeln!(xml, "{}<system-out>{}</system-out>", indent, escape(stdout)).unwrap();
+    }
+    if !stderr.is_empty() {
+        writeln!(xml, "{}<system-err>{}</system-err>", indent, escape(stderr)).unwrap();
+    }
+}
+
+/// Escape text for use in XML attributes and text. Control characters other than whitespace are
+/// not allowed in XML at all, so they are dropped.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(target: &str, test_case: Option<&str>, status: TestStatus) -> TestCaseReport {
+        TestCaseReport {
+            target: target.to_owned(),
+            test_case: test_case.map(|c| c.to_owned()),
+            status,
+            duration: Some(Duration::from_millis(1500)),
+            stdout: String::new(),
+            stderr: String::new(),
+            artifacts_dir: None,
+            reruns: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            "a &lt;b&gt; &amp; &quot;c&quot;\n",
+            escape("a <b> & \"c\"\x1b\n")
+        );
+    }
+
+    #[test]
+    fn test_render_junit_xml() {
+        let mut failed = report("root//:a", Some("case<1>"), TestStatus::FAIL);
+        failed.stderr = "assertion failed".to_owned();
+        failed.artifacts_dir = Some("/tmp/artifacts".to_owned());
+        let results = vec![
+            failed,
+            report("root//:a", Some("case2"), TestStatus::PASS),
+            report("root//:b", None, TestStatus::TIMEOUT),
+            TestCaseReport {
+                reruns: vec![AttemptReport {
+                    status: TestStatus::FAIL,
+                    duration: Some(Duration::from_millis(500)),
+                    stdout: "first attempt".to_owned(),
+                    stderr: String::new(),
+                }],
+                ..report("root//:c", None, TestStatus::FLAKY)
+            },
+        ];
+
+        assert_eq!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites tests="4" failures="1" errors="1" skipped="0" time="6.000">
+  <testsuite name="root//:a" tests="2" failures="1" errors="0" skipped="0" time="3.000">
+    <testcase name="case&lt;1&gt;" classname="root//:a" time="1.500">
+      <failure message="fail"/>
+      <properties>
+        <property name="artifacts_dir" value="/tmp/artifacts"/>
+      </properties>
+      <system-err>assertion failed</system-err>
+    </testcase>
+    <testcase name="case2" classname="root//:a" time="1.500">
+    </testcase>
+  </testsuite>
+  <testsuite name="root//:b" tests="1" failures="0" errors="1" skipped="0" time="1.500">
+    <testcase name="root//:b" classname="root//:b" time="1.500">
+      <error message="timeout"/>
+    </testcase>
+  </testsuite>
+  <testsuite name="root//:c" tests="1" failures="0" errors="0" skipped="0" time="1.500">
+    <testcase name="root//:c" classname="root//:c" time="1.500">
+      <properties>
+        <property name="attempts" value="2"/>
+        <property name="flaky" value="true"/>
+      </properties>
+      <flakyFailure message="fail" time="0.500">
+        <system-out>first attempt</system-out>
+      </flakyFailure>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#,
+            render_junit_xml(&results)
+        );
+    }
+}
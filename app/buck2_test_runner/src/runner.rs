@@ -11,16 +11,22 @@ use anyhow::Context;
 use buck2_test_api::data::ArgValue;
 use buck2_test_api::data::ArgValueContent;
 use buck2_test_api::data::ConfiguredTargetHandle;
+use buck2_test_api::data::DeclaredOutput;
 use buck2_test_api::data::ExecuteResponse;
 use buck2_test_api::data::ExecutionResult2;
 use buck2_test_api::data::ExecutionStatus;
+use buck2_test_api::data::ExecutionStream;
 use buck2_test_api::data::ExternalRunnerSpec;
 use buck2_test_api::data::ExternalRunnerSpecValue;
+use buck2_test_api::data::Output;
+use buck2_test_api::data::OutputName;
+use buck2_test_api::data::RemoteStorageConfig;
 use buck2_test_api::data::RequiredLocalResources;
 use buck2_test_api::data::TestResult;
 use buck2_test_api::data::TestStage;
 use buck2_test_api::data::TestStatus;
 use buck2_test_api::grpc::TestOrchestratorClient;
+use buck2_test_api::sharding::TestShard;
 use clap::Parser;
 use futures::channel::mpsc::UnboundedReceiver;
 use futures::StreamExt;
@@ -29,6 +35,16 @@ use parking_lot::Mutex;
 
 use crate::config::Config;
 use crate::config::EnvValue;
+use crate::report::AttemptReport;
+use crate::report::TestCaseReport;
+use crate::report::TestReporter;
+
+/// The declared output tests can write additional artifacts to, e.g. logs or screenshots.
+const ARTIFACTS_DIR_OUTPUT: &str = "test_artifacts";
+/// The environment variable that points tests at their artifacts directory.
+const ARTIFACTS_DIR_ENV: &str = "TEST_RESULT_ARTIFACTS_DIR";
+/// Labels that override `--retries` for a target, e.g. `retries=3`.
+const RETRIES_LABEL_PREFIX: &str = "retries=";
 
 pub type SpecReceiver = UnboundedReceiver<ExternalRunnerSpec>;
 
@@ -43,6 +59,9 @@ pub struct Buck2TestRunner {
     orchestrator_client: TestOrchestratorClient,
     spec_receiver: Mutex<Option<SpecReceiver>>,
     config: Config,
+    /// Only set if a report was requested.
+    reporter: Option<TestReporter>,
+    shard: Option<TestShard>,
 }
 
 impl Buck2TestRunner {
@@ -52,10 +71,24 @@ impl Buck2TestRunner {
         args: Vec<String>,
     ) -> anyhow::Result<Self> {
         let config = Config::try_parse_from(args).context("Error parsing test runner arguments")?;
+        let reporter = (config.junit_xml.is_some() || config.json_summary.is_some())
+            .then(TestReporter::default);
+        let shard = match config.shard_index.zip(config.shard_count) {
+            Some((index, count)) => {
+                let mut shard = TestShard::new(index, count)?;
+                if let Some(path) = &config.shard_timings {
+                    shard = shard.with_timings(TestShard::load_timings(path)?);
+                }
+                Some(shard)
+            }
+            None => None,
+        };
         Ok(Self {
             orchestrator_client,
             spec_receiver: Mutex::new(Some(spec_receiver)),
             config,
+            reporter,
+            shard,
         })
     }
 
@@ -74,26 +107,12 @@ impl Buck2TestRunner {
                     "{}//{}:{}",
                     spec.target.cell, spec.target.package, spec.target.target
                 );
-                let target_handle = spec.target.handle.to_owned();
-
-                let execution_response = self
-                    .execute_test_from_spec(spec)
-                    .await
-                    .expect("Test execution request failed");
-
-                let execution_result = match execution_response {
-                    ExecuteResponse::Result(r) => r,
-                    ExecuteResponse::Cancelled => return TestStatus::OMITTED,
-                };
-
-                let test_result = get_test_result(name, target_handle, execution_result);
-                let test_status = test_result.status.clone();
-
-                self.report_test_result(test_result)
-                    .await
-                    .expect("Test result reporting failed");
 
-                test_status
+                match &self.config.list_arg {
+                    Some(list_arg) => self.run_listed_test_cases(&name, &spec, list_arg).await,
+                    None if !self.in_shard(&name) => Vec::new(),
+                    None => vec![self.run_test(&name, &spec, None).await],
+                }
             })
             // Use an arbitrarily large buffer -- execution throttling will be handled by the Buck2
             // executor, so no need to hold back on requests here.
@@ -101,8 +120,11 @@ impl Buck2TestRunner {
             // If any individual test failed, consider the entire run to have failed.
             .fold(
                 RunVerdict::Pass,
-                |mut run_verdict, test_status| async move {
-                    if test_status != TestStatus::PASS {
+                |mut run_verdict, test_statuses| async move {
+                    if test_statuses
+                        .iter()
+                        .any(|s| !matches!(s, TestStatus::PASS | TestStatus::FLAKY))
+                    {
                         run_verdict = RunVerdict::Fail;
                     }
                     run_verdict
@@ -110,19 +132,225 @@ impl Buck2TestRunner {
             )
             .await;
 
+        if let Some(reporter) = &self.reporter {
+            if let Some(path) = &self.config.junit_xml {
+                reporter.write_junit_xml(path)?;
+            }
+            if let Some(path) = &self.config.json_summary {
+                reporter.write_json_summary(path)?;
+            }
+        }
+
         self.orchestrator_client
             .end_of_test_results(run_verdict.exit_code())
             .await
     }
 
+    /// Run the test binary once to list its test cases, then run each test case on its own.
+    async fn run_listed_test_cases(
+        &self,
+        name: &str,
+        spec: &ExternalRunnerSpec,
+        list_arg: &str,
+    ) -> Vec<TestStatus> {
+        let stage = TestStage::Listing(spec.target.target.clone());
+        let execution_response = self
+            .execute_test_from_spec(spec, stage, vec![list_arg.to_owned()])
+            .await
+            .expect("Test execution request failed");
+
+        let execution_result = match execution_response {
+            ExecuteResponse::Result(r) => r,
+            ExecuteResponse::Cancelled => return vec![TestStatus::OMITTED],
+        };
+
+        let test_cases = match execution_result.status {
+            ExecutionStatus::Finished { exitcode: 0 } => stream_to_string(&execution_result.stdout)
+                .lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty())
+                .map(|line| line.to_owned())
+                .collect::<Vec<_>>(),
+            _ => {
+                let mut test_result = get_test_result(
+                    name.to_owned(),
+                    spec.target.handle.to_owned(),
+                    &execution_result,
+                );
+                test_result.status = TestStatus::LISTING_FAILED;
+                self.record(name, None, &test_result, &execution_result, Vec::new());
+                self.report_test_result(test_result)
+                    .await
+                    .expect("Test result reporting failed");
+                return vec![TestStatus::LISTING_FAILED];
+            }
+        };
+
+        if let Some(reporter) = &self.reporter {
+            reporter.record_listing(name, &test_cases);
+        }
+
+        futures::future::join_all(
+            test_cases
+                .iter()
+                .filter(|test_case| self.in_shard(&format!("{} - {}", name, test_case)))
+                .map(|test_case| self.run_test(name, spec, Some(test_case))),
+        )
+        .await
+    }
+
+    /// Whether the test called `name` runs in this shard. Everything runs without sharding.
+    fn in_shard(&self, name: &str) -> bool {
+        self.shard
+            .as_ref()
+            .map_or(true, |shard| shard.contains(name))
+    }
+
+    /// Run a test target, or a single one of its test cases, retrying it if it fails. Failed
+    /// attempts are reported as reruns, and a test that passes after failing is flaky.
+    async fn run_test(
+        &self,
+        name: &str,
+        spec: &ExternalRunnerSpec,
+        test_case: Option<&str>,
+    ) -> TestStatus {
+        let (stage, extra_args) = match test_case {
+            Some(test_case) => (
+                TestStage::Testing {
+                    suite: spec.target.target.clone(),
+                    testcases: vec![test_case.to_owned()],
+                },
+                self.config
+                    .filter_arg
+                    .iter()
+                    .map(|arg| arg.replace("{}", test_case))
+                    .collect(),
+            ),
+            None => (
+                TestStage::Testing {
+                    suite: spec.target.target.clone(),
+                    testcases: Vec::new(),
+                },
+                Vec::new(),
+            ),
+        };
+
+        let test_name = match test_case {
+            Some(test_case) => format!("{} - {}", name, test_case),
+            None => name.to_owned(),
+        };
+        let attempts = self.retries(spec) + 1;
+        let mut backoff = self.config.retry_backoff;
+        let mut attempt = 0;
+        let mut reruns = Vec::new();
+
+        loop {
+            attempt += 1;
+            let execution_response = self
+                .execute_test_from_spec(spec, stage.clone(), extra_args.clone())
+                .await
+                .expect("Test execution request failed");
+
+            let execution_result = match execution_response {
+                ExecuteResponse::Result(r) => r,
+                ExecuteResponse::Cancelled => return TestStatus::OMITTED,
+            };
+
+            let mut test_result = get_test_result(
+                test_name.clone(),
+                spec.target.handle.to_owned(),
+                &execution_result,
+            );
+            let failed = matches!(test_result.status, TestStatus::FAIL | TestStatus::TIMEOUT);
+
+            if failed && attempt < attempts {
+                if self.reporter.is_some() {
+                    reruns.push(AttemptReport {
+                        status: test_result.status.clone(),
+                        duration: test_result.duration,
+                        stdout: stream_to_string(&execution_result.stdout),
+                        stderr: stream_to_string(&execution_result.stderr),
+                    });
+                }
+                test_result.status = TestStatus::RERUN;
+                test_result.msg = Some(format!(
+                    "Attempt {} of {} failed, retrying",
+                    attempt, attempts
+                ));
+                self.report_test_result(test_result)
+                    .await
+                    .expect("Test result reporting failed");
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                continue;
+            }
+
+            if attempt > 1 && test_result.status == TestStatus::PASS {
+                test_result.status = TestStatus::FLAKY;
+                test_result.msg = Some(format!("Passed on attempt {} of {}", attempt, attempts));
+            }
+
+            let test_status = test_result.status.clone();
+            self.record(name, test_case, &test_result, &execution_result, reruns);
+
+            self.report_test_result(test_result)
+                .await
+                .expect("Test result reporting failed");
+
+            return test_status;
+        }
+    }
+
+    /// How many times to retry the target if it fails.
+    fn retries(&self, spec: &ExternalRunnerSpec) -> u32 {
+        spec.labels
+            .iter()
+            .find_map(|label| label.strip_prefix(RETRIES_LABEL_PREFIX)?.parse().ok())
+            .unwrap_or(self.config.retries)
+    }
+
+    fn record(
+        &self,
+        target: &str,
+        test_case: Option<&str>,
+        test_result: &TestResult,
+        execution_result: &ExecutionResult2,
+        reruns: Vec<AttemptReport>,
+    ) {
+        let Some(reporter) = &self.reporter else {
+            return;
+        };
+        let artifacts_dir = match execution_result
+            .outputs
+            .get(&OutputName::unchecked_new(ARTIFACTS_DIR_OUTPUT.to_owned()))
+        {
+            Some(Output::LocalPath(path)) => Some(path.to_string()),
+            Some(Output::RemoteObject(_)) | None => None,
+        };
+        reporter.record(TestCaseReport {
+            target: target.to_owned(),
+            test_case: test_case.map(|c| c.to_owned()),
+            status: test_result.status.clone(),
+            duration: test_result.duration,
+            stdout: stream_to_string(&execution_result.stdout),
+            stderr: stream_to_string(&execution_result.stderr),
+            artifacts_dir,
+            reruns,
+        });
+    }
+
     async fn execute_test_from_spec(
         &self,
-        spec: ExternalRunnerSpec,
+        spec: &ExternalRunnerSpec,
+        stage: TestStage,
+        extra_args: Vec<String>,
     ) -> anyhow::Result<ExecuteResponse> {
-        let stage = TestStage::Testing {
-            suite: spec.target.target,
-            testcases: Vec::new(),
-        };
+        let extra_args = extra_args.into_iter().map(|arg| ArgValue {
+            content: ArgValueContent::ExternalRunnerSpecValue(ExternalRunnerSpecValue::Verbatim(
+                arg,
+            )),
+            format: None,
+        });
 
         let config_args = self.config.test_arg.iter().map(|arg| ArgValue {
             content: ArgValueContent::ExternalRunnerSpecValue(ExternalRunnerSpecValue::Verbatim(
@@ -133,11 +361,12 @@ impl Buck2TestRunner {
 
         let command = spec
             .command
-            .into_iter()
+            .iter()
             .map(|spec_value| ArgValue {
-                content: ArgValueContent::ExternalRunnerSpecValue(spec_value),
+                content: ArgValueContent::ExternalRunnerSpecValue(spec_value.clone()),
                 format: None,
             })
+            .chain(extra_args)
             .chain(config_args)
             .collect();
 
@@ -153,24 +382,48 @@ impl Buck2TestRunner {
             )
         });
 
+        // The artifacts directory is only declared when a report points at it, since it changes
+        // the command of every test, and with it the action cache key.
+        let artifacts_dir = self
+            .reporter
+            .is_some()
+            .then(|| OutputName::unchecked_new(ARTIFACTS_DIR_OUTPUT.to_owned()));
+        let artifacts_dir_env = artifacts_dir.iter().map(|artifacts_dir| {
+            (
+                ARTIFACTS_DIR_ENV.to_owned(),
+                ArgValue {
+                    content: ArgValueContent::DeclaredOutput(artifacts_dir.clone()),
+                    format: None,
+                },
+            )
+        });
+
         let env = spec
             .env
-            .into_iter()
+            .iter()
             .map(|(key, value)| {
                 (
-                    key,
+                    key.clone(),
                     ArgValue {
-                        content: ArgValueContent::ExternalRunnerSpecValue(value),
+                        content: ArgValueContent::ExternalRunnerSpecValue(value.clone()),
                         format: None,
                     },
                 )
             })
+            .chain(artifacts_dir_env)
             .chain(config_env)
             .collect();
 
-        let target_handle = spec.target.handle;
+        let target_handle = spec.target.handle.to_owned();
         let host_sharing_requirements = HostSharingRequirements::default();
-        let pre_create_dirs = Vec::new();
+        // Keep artifacts local, so that the reports can point at them.
+        let pre_create_dirs = artifacts_dir
+            .into_iter()
+            .map(|name| DeclaredOutput {
+                name,
+                remote_storage_config: RemoteStorageConfig::new(false),
+            })
+            .collect();
         let executor_override = None;
 
         self.orchestrator_client
@@ -198,7 +451,7 @@ impl Buck2TestRunner {
 fn get_test_result(
     name: String,
     target: ConfiguredTargetHandle,
-    execution_result: ExecutionResult2,
+    execution_result: &ExecutionResult2,
 ) -> TestResult {
     let status = match execution_result.status {
         ExecutionStatus::Finished { exitcode } => match exitcode {
@@ -220,6 +473,12 @@ fn get_test_result(
     }
 }
 
+fn stream_to_string(stream: &ExecutionStream) -> String {
+    match stream {
+        ExecutionStream::Inline(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
 #[derive(Debug)]
 enum RunVerdict {
     Pass,
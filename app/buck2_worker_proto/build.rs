@@ -10,9 +10,13 @@
 use std::io;
 
 fn main() -> io::Result<()> {
-    let proto_files = &["worker.proto"];
+    let proto_files = &["worker.proto", "bazel_worker_protocol.proto"];
 
     buck2_protoc_dev::configure()
         .setup_protoc()
+        .type_attribute(
+            ".blaze.worker",
+            "#[derive(::serde::Serialize, ::serde::Deserialize)] #[serde(rename_all = \"camelCase\", default)]",
+        )
         .compile(proto_files, &["."])
 }
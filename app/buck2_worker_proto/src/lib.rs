@@ -10,3 +10,8 @@
 #![feature(error_generic_member_access)]
 
 tonic::include_proto!("worker");
+
+/// The messages of Bazel's persistent worker protocol.
+pub mod bazel {
+    tonic::include_proto!("blaze.worker");
+}
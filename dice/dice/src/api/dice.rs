@@ -171,6 +171,8 @@ use futures::future::Future;
 use serde::Serializer;
 
 use crate::api::cycles::DetectCycles;
+use crate::api::dyn_key::DynKey;
+use crate::api::invalidation_tracking::DiceRecomputeExplanations;
 use crate::api::transaction::DiceTransactionUpdater;
 use crate::api::user_data::UserComputationData;
 use crate::metrics::Metrics;
@@ -249,6 +251,19 @@ impl Dice {
     pub async fn is_idle(&self) -> bool {
         self.implementation.is_idle().await
     }
+
+    /// Explain why the keys matching `matches` were recomputed after the latest committed
+    /// transaction: for each of them whose value changed, find the chains of deps that lead to the
+    /// keys the transaction changed. At most `max_keys` keys are explained.
+    pub async fn explain_recompute(
+        &self,
+        matches: impl Fn(&DynKey) -> bool,
+        max_keys: usize,
+    ) -> DiceRecomputeExplanations {
+        self.implementation
+            .explain_recompute(matches, max_keys)
+            .await
+    }
 }
 
 pub struct DiceDataBuilder(DiceDataBuilderImpl);
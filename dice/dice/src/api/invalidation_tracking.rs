@@ -27,6 +27,10 @@
 //! and high priority source. If those versions were reversed (A at v2, B at v1), we'd
 //! record A for the normal priority invalidation path (because its invalidation is more recent) and would
 //! record B for the high priority invalidation path (because A is normal priority).
+//!
+//! Invalidation paths only record the most recent invalidation source of a node. To find all the
+//! changes that caused a node to be recomputed, [`crate::Dice::explain_recompute`] walks the deps
+//! of the node back to every key changed by the latest committed transaction.
 
 use std::sync::Arc;
 
@@ -107,6 +111,25 @@ impl DiceInvalidationPath {
     }
 }
 
+/// How a key whose value changed depends on the keys changed by the latest committed transaction.
+/// This is returned by [`crate::Dice::explain_recompute`].
+pub struct DiceRecomputeExplanation {
+    pub key: DynKey,
+    /// One chain of deps per changed key that `key` transitively depends on, only through deps
+    /// whose value changed too. The first entry of a chain is the changed key, and the last one is
+    /// `key`.
+    pub chains: Vec<Vec<DynKey>>,
+}
+
+/// The explanations for the keys matched by [`crate::Dice::explain_recompute`] whose value changed.
+pub struct DiceRecomputeExplanations {
+    /// The version of the latest committed transaction.
+    pub version: VersionNumber,
+    pub explanations: Vec<DiceRecomputeExplanation>,
+    /// How many more matching keys changed, beyond the `max_keys` that were explained.
+    pub omitted: usize,
+}
+
 impl DiceKeyTrackedInvalidationPaths {
     pub(crate) fn new(
         dice: Arc<DiceModern>,
@@ -580,6 +580,17 @@ impl OccupiedGraphNode {
     pub(crate) fn is_verified_at(&self, version: VersionNumber) -> bool {
         self.metadata.verified_ranges.contains(version)
     }
+
+    /// Whether the value was computed at `version` and wasn't valid at any earlier version.
+    pub(crate) fn changed_at(&self, version: VersionNumber) -> bool {
+        self.is_verified_at(version)
+            && (version == VersionNumber::ZERO
+                || self
+                    .metadata
+                    .verified_ranges
+                    .find_value_upper_bound(VersionNumber::new(version.0 - 1))
+                    .is_none())
+    }
 }
 
 /// An entry in the graph that has no computation value associated. This is used to store the
@@ -696,6 +707,11 @@ impl InjectedGraphNode {
         }
     }
 
+    /// Whether a new value was injected at `version`.
+    pub(crate) fn changed_at(&self, version: VersionNumber) -> bool {
+        self.data_at(version).is_some_and(|(v, _)| *v == version)
+    }
+
     pub(crate) fn latest(&self) -> &InjectedNodeData {
         // We don't ever create an empty values map
         self.values.values().next_back().unwrap()
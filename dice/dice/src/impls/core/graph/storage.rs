@@ -166,6 +166,9 @@
 //! A: This could also be interesting to explore. It's possible that this could resolve all the issues with doing
 //! value-based dep checks.
 
+use std::collections::hash_map::Entry;
+use std::collections::VecDeque;
+
 use allocative::Allocative;
 
 use crate::api::key::InvalidationSourcePriority;
@@ -339,6 +342,60 @@ impl VersionedGraph {
         true
     }
 
+    /// Whether the value of `key` at `v` is a new one, rather than one that was already valid at an
+    /// earlier version.
+    pub(crate) fn changed_at(&self, key: DiceKey, v: VersionNumber) -> bool {
+        match self.nodes.get(&key) {
+            Some(VersionedGraphNode::Occupied(occ)) => occ.changed_at(v),
+            Some(VersionedGraphNode::Injected(inj)) => inj.changed_at(v),
+            _ => false,
+        }
+    }
+
+    /// The shortest chains of deps from `key` to each of the `sources` it transitively depends on.
+    /// Every chain starts with its source and ends with `key`.
+    ///
+    /// Chains only go through deps whose value changed at `v`: a dep that was recomputed to the
+    /// same value doesn't make its dependents recompute.
+    pub(crate) fn dep_chains(
+        &self,
+        key: DiceKey,
+        v: VersionNumber,
+        sources: &HashSet<DiceKey>,
+    ) -> Vec<Vec<DiceKey>> {
+        // The dependent through which every visited key was reached.
+        let mut reached_from: HashMap<DiceKey, Option<DiceKey>> = HashMap::default();
+        reached_from.insert(key, None);
+        let mut queue = VecDeque::from([key]);
+        let mut chains = Vec::new();
+
+        // Breadth-first, so that the chains are the shortest ones.
+        while let Some(k) = queue.pop_front() {
+            if sources.contains(&k) {
+                let mut chain = vec![k];
+                let mut next = reached_from[&k];
+                while let Some(dependent) = next {
+                    chain.push(dependent);
+                    next = reached_from[&dependent];
+                }
+                chains.push(chain);
+            }
+            if let Some(VersionedGraphNode::Occupied(occ)) = self.nodes.get(&k) {
+                for dep in occ.deps().iter_keys() {
+                    if !self.changed_at(dep, v) {
+                        continue;
+                    }
+                    if let Entry::Vacant(e) = reached_from.entry(dep) {
+                        e.insert(Some(k));
+                        queue.push_back(dep);
+                    }
+                }
+            }
+        }
+
+        chains
+    }
+
     // -----------------------------------------------------------------------------
     // ------------------------- Implementation functions below --------------------
     // -----------------------------------------------------------------------------
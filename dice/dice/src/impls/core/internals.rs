@@ -37,6 +37,17 @@ use crate::metrics::Metrics;
 use crate::result::CancellableResult;
 use crate::result::CancellationReason;
 use crate::versions::VersionNumber;
+use crate::HashSet;
+
+/// The keys whose value changed at the version of the latest committed changes, with their chains
+/// of deps from a key changed by that transaction to them.
+#[derive(Debug)]
+pub(crate) struct RecomputeExplanations {
+    pub(crate) version: VersionNumber,
+    pub(crate) explanations: Vec<(DiceKey, Vec<Vec<DiceKey>>)>,
+    /// How many more of the given keys changed, beyond the ones that were explained.
+    pub(crate) omitted: usize,
+}
 
 /// Core state of DICE, holding the actual graph and version information
 #[derive(allocative::Allocative)]
@@ -44,6 +55,8 @@ pub(super) struct CoreState {
     version_tracker: VersionTracker,
     graph: VersionedGraph,
     pending_termination_tasks: Vec<DiceTask>,
+    /// The keys changed by the latest committed transaction, and its version.
+    latest_changes: (VersionNumber, Vec<DiceKey>),
 }
 
 impl CoreState {
@@ -52,6 +65,7 @@ impl CoreState {
             version_tracker: VersionTracker::new(),
             graph: VersionedGraph::new(),
             pending_termination_tasks: Vec::new(),
+            latest_changes: (VersionNumber::ZERO, Vec::new()),
         }
     }
 
@@ -62,9 +76,9 @@ impl CoreState {
         let version_update = self.version_tracker.write();
         let v = version_update.version();
 
-        let mut changes_recorded = false;
+        let mut changed = Vec::new();
         for (key, change, invalidation_priority) in updates {
-            changes_recorded |= self.graph.invalidate(
+            if self.graph.invalidate(
                 VersionedGraphKey::new(v, key),
                 match change {
                     ChangeType::Invalidate => InvalidateKind::ForceDirty,
@@ -73,10 +87,14 @@ impl CoreState {
                     ChangeType::TestingSoftDirty => InvalidateKind::Invalidate,
                 },
                 invalidation_priority,
-            );
+            ) {
+                changed.push(key);
+            }
         }
-        if changes_recorded {
-            version_update.commit()
+        if !changed.is_empty() {
+            let v = version_update.commit();
+            self.latest_changes = (v, changed);
+            v
         } else {
             version_update.undo()
         }
@@ -129,6 +147,31 @@ impl CoreState {
         }
     }
 
+    /// For each of `keys` whose value changed at the version of the latest committed transaction,
+    /// the chains of deps through which it depends on the keys changed by that transaction. This
+    /// runs on the state thread, so only the first `max_keys` of them are explained.
+    pub(super) fn explain_recompute(
+        &self,
+        keys: Vec<DiceKey>,
+        max_keys: usize,
+    ) -> RecomputeExplanations {
+        let (v, changed) = &self.latest_changes;
+        let changed: HashSet<DiceKey> = changed.iter().copied().collect();
+        let mut recomputed = keys
+            .into_iter()
+            .filter(|key| self.graph.changed_at(*key, *v));
+        let explanations = recomputed
+            .by_ref()
+            .take(max_keys)
+            .map(|key| (key, self.graph.dep_chains(key, *v, &changed)))
+            .collect();
+        RecomputeExplanations {
+            version: *v,
+            explanations,
+            omitted: recomputed.count(),
+        }
+    }
+
     pub(super) fn get_tasks_pending_cancellation(&mut self) -> Vec<TerminationObserver> {
         self.pending_termination_tasks
             .retain(|task| task.is_pending());
@@ -143,6 +186,7 @@ impl CoreState {
         // Do the actual drop on a different thread because we may have to drop a lot of stuff
         // here.
         let map = std::mem::take(&mut self.graph.nodes);
+        self.latest_changes.1.clear();
         thread::Builder::new()
             .name("dice-drop-everything".to_owned())
             .spawn(move || drop(map))
@@ -112,6 +112,13 @@ impl StateProcessor {
                     invalidation_paths,
                 )));
             }
+            StateRequest::ExplainRecompute {
+                keys,
+                max_keys,
+                resp,
+            } => {
+                let _ignored = resp.send(self.state.explain_recompute(keys, max_keys));
+            }
             StateRequest::GetTasksPendingCancellation { resp } => {
                 let _ignored = resp.send(self.state.get_tasks_pending_cancellation());
             }
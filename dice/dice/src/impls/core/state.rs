@@ -25,6 +25,7 @@ use crate::impls::core::graph::types::VersionedGraphKey;
 use crate::impls::core::graph::types::VersionedGraphResult;
 use crate::impls::core::graph::types::VersionedGraphResultMismatch;
 use crate::impls::core::internals::CoreState;
+use crate::impls::core::internals::RecomputeExplanations;
 use crate::impls::core::processor::StateProcessor;
 use crate::impls::core::versions::introspection::VersionIntrospectable;
 use crate::impls::core::versions::VersionEpoch;
@@ -156,6 +157,23 @@ impl CoreStateHandle {
         )
     }
 
+    /// Explains how the given keys depend on the keys changed by the latest committed transaction
+    pub(crate) fn explain_recompute(
+        &self,
+        keys: Vec<DiceKey>,
+        max_keys: usize,
+    ) -> impl Future<Output = RecomputeExplanations> {
+        let (resp, recv) = oneshot::channel();
+        self.call(
+            StateRequest::ExplainRecompute {
+                keys,
+                max_keys,
+                resp,
+            },
+            recv,
+        )
+    }
+
     /// Get all the tasks pending cancellation
     pub(crate) fn get_tasks_pending_cancellation(
         &self,
@@ -269,6 +287,12 @@ pub(super) enum StateRequest {
         /// given computed value if the state already stores an instance of value that is equal.
         resp: Sender<CancellableResult<DiceComputedValue>>,
     },
+    /// Explains how the given keys depend on the keys changed by the latest committed transaction
+    ExplainRecompute {
+        keys: Vec<DiceKey>,
+        max_keys: usize,
+        resp: Sender<RecomputeExplanations>,
+    },
     /// Get all the tasks pending cancellation
     GetTasksPendingCancellation {
         #[derivative(Debug = "ignore")]
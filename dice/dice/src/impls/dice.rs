@@ -16,7 +16,11 @@ use dupe::Dupe;
 
 use crate::api::cycles::DetectCycles;
 use crate::api::data::DiceData;
+use crate::api::dyn_key::DynKey;
+use crate::api::invalidation_tracking::DiceRecomputeExplanation;
+use crate::api::invalidation_tracking::DiceRecomputeExplanations;
 use crate::api::user_data::UserComputationData;
+use crate::impls::core::internals::RecomputeExplanations;
 use crate::impls::core::state::init_state;
 use crate::impls::core::state::CoreStateHandle;
 use crate::impls::key_index::DiceKeyIndex;
@@ -120,6 +124,38 @@ impl DiceModern {
 
         tasks.iter().all(|task| task.is_terminated())
     }
+
+    pub async fn explain_recompute(
+        &self,
+        matches: impl Fn(&DynKey) -> bool,
+        max_keys: usize,
+    ) -> DiceRecomputeExplanations {
+        // Match the keys here rather than on the state thread, since this looks at every key.
+        let keys = self.key_index.find(|key| matches(DynKey::ref_cast(key)));
+        let RecomputeExplanations {
+            version,
+            explanations,
+            omitted,
+        } = self.state_handle.explain_recompute(keys, max_keys).await;
+
+        let to_dyn_key = |key| DynKey {
+            erased: self.key_index.get(key).dupe(),
+        };
+        DiceRecomputeExplanations {
+            version,
+            explanations: explanations
+                .into_iter()
+                .map(|(key, chains)| DiceRecomputeExplanation {
+                    key: to_dyn_key(key),
+                    chains: chains
+                        .into_iter()
+                        .map(|chain| chain.into_iter().map(to_dyn_key).collect())
+                        .collect(),
+                })
+                .collect(),
+            omitted,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -150,6 +150,25 @@ impl DiceKeyIndex {
         self.index(CowDiceKeyHashed::key(key))
     }
 
+    /// All the keys matching `f`.
+    pub(crate) fn find(&self, f: impl Fn(&DiceKeyErased) -> bool) -> Vec<DiceKey> {
+        let mut keys = Vec::new();
+        for (shard_index, shard) in self.shards.iter().enumerate() {
+            for (index_in_shard, key) in shard.key_by_index.iter().enumerate() {
+                if f(key) {
+                    keys.push(
+                        DiceKeyUnpacked {
+                            shard_index: shard_index as u32,
+                            index_in_shard: index_in_shard as u32,
+                        }
+                        .pack(),
+                    );
+                }
+            }
+        }
+        keys
+    }
+
     pub(crate) fn get(&self, key: DiceKey) -> &DiceKeyErased {
         let unpack = DiceKeyUnpacked::unpack(key);
         self.shards[unpack.shard_index as usize]
@@ -10,6 +10,7 @@
 mod activation_tracker;
 mod demo;
 mod events;
+mod explain_recompute;
 mod general;
 mod keys;
 mod spawner;
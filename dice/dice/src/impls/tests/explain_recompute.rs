@@ -0,0 +1,155 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use allocative::Allocative;
+use async_trait::async_trait;
+use buck2_futures::cancellation::CancellationContext;
+use derive_more::Display;
+use dupe::Dupe;
+
+use crate::api::computations::DiceComputations;
+use crate::api::cycles::DetectCycles;
+use crate::api::injected::InjectedKey;
+use crate::api::key::Key;
+use crate::versions::VersionNumber;
+use crate::Dice;
+use crate::DynKey;
+
+#[derive(Clone, Dupe, Debug, Display, Eq, Hash, PartialEq, Allocative)]
+#[display("Input({})", _0)]
+struct Input(u32);
+
+impl InjectedKey for Input {
+    type Value = u32;
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
+#[derive(Clone, Dupe, Debug, Display, Eq, Hash, PartialEq, Allocative)]
+#[display("Doubled({})", _0)]
+struct Doubled(u32);
+
+#[async_trait]
+impl Key for Doubled {
+    type Value = u32;
+
+    async fn compute(
+        &self,
+        ctx: &mut DiceComputations,
+        _cancellations: &CancellationContext,
+    ) -> Self::Value {
+        ctx.compute(&Input(self.0)).await.unwrap() * 2
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
+#[derive(Clone, Dupe, Debug, Display, Eq, Hash, PartialEq, Allocative)]
+#[display("Positive({})", _0)]
+struct Positive(u32);
+
+#[async_trait]
+impl Key for Positive {
+    type Value = bool;
+
+    async fn compute(
+        &self,
+        ctx: &mut DiceComputations,
+        _cancellations: &CancellationContext,
+    ) -> Self::Value {
+        ctx.compute(&Input(self.0)).await.unwrap() > 0
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
+#[derive(Clone, Dupe, Debug, Display, Eq, Hash, PartialEq, Allocative)]
+#[display("Sum")]
+struct Sum;
+
+#[async_trait]
+impl Key for Sum {
+    type Value = u32;
+
+    async fn compute(
+        &self,
+        ctx: &mut DiceComputations,
+        _cancellations: &CancellationContext,
+    ) -> Self::Value {
+        ctx.compute(&Doubled(1)).await.unwrap() + ctx.compute(&Doubled(2)).await.unwrap()
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
+/// The chains of deps that explain `key`, or `None` if its value didn't change.
+async fn chains(dice: &Dice, key: &str) -> Option<Vec<Vec<String>>> {
+    let explanations = dice
+        .explain_recompute(|k: &DynKey| k.to_string() == key, 10)
+        .await;
+    assert_eq!(0, explanations.omitted);
+    assert!(explanations.explanations.len() <= 1);
+    let explanation = explanations.explanations.first()?;
+    Some(
+        explanation
+            .chains
+            .iter()
+            .map(|chain| chain.iter().map(|k| k.to_string()).collect())
+            .collect(),
+    )
+}
+
+#[tokio::test]
+async fn test_explain_recompute() -> anyhow::Result<()> {
+    let dice = Dice::builder().build(DetectCycles::Disabled);
+
+    let mut updater = dice.updater();
+    updater.changed_to(vec![(Input(1), 1), (Input(2), 2)])?;
+    let mut ctx = updater.commit().await;
+    assert_eq!(6, ctx.compute(&Sum).await?);
+    assert!(ctx.compute(&Positive(2)).await?);
+    drop(ctx);
+
+    let mut updater = dice.updater();
+    updater.changed_to(vec![(Input(2), 3)])?;
+    let mut ctx = updater.commit().await;
+    assert_eq!(8, ctx.compute(&Sum).await?);
+    assert!(ctx.compute(&Positive(2)).await?);
+    drop(ctx);
+
+    let explanations = dice.explain_recompute(|_| false, 10).await;
+    assert_eq!(VersionNumber::new(2), explanations.version);
+    assert_eq!(
+        Some(vec![vec!["Input(2)", "Doubled(2)", "Sum"]]),
+        chains(&dice, "Sum").await
+    );
+    assert_eq!(
+        Some(vec![vec!["Input(2)"]]),
+        chains(&dice, "Input(2)").await
+    );
+    // Not affected by the change.
+    assert_eq!(None, chains(&dice, "Doubled(1)").await);
+    // Recomputed, but to the same value.
+    assert_eq!(None, chains(&dice, "Positive(2)").await);
+
+    // Input(2), Doubled(2) and Sum changed.
+    let explanations = dice.explain_recompute(|_| true, 1).await;
+    assert_eq!(1, explanations.explanations.len());
+    assert_eq!(2, explanations.omitted);
+
+    Ok(())
+}
@@ -224,6 +224,8 @@ pub use crate::api::events::DiceEventListener;
 pub use crate::api::injected::InjectedKey;
 pub use crate::api::invalidation_tracking::DiceInvalidationPath;
 pub use crate::api::invalidation_tracking::DiceKeyTrackedInvalidationPaths;
+pub use crate::api::invalidation_tracking::DiceRecomputeExplanation;
+pub use crate::api::invalidation_tracking::DiceRecomputeExplanations;
 pub use crate::api::invalidation_tracking::DiceTrackedInvalidationPath;
 pub use crate::api::invalidation_tracking::InvalidationPathEntry;
 pub use crate::api::key::InvalidationSourcePriority;
@@ -320,6 +322,16 @@ impl DiceImplementation {
             DiceImplementation::Modern(dice) => dice.is_idle().await,
         }
     }
+
+    pub async fn explain_recompute(
+        &self,
+        matches: impl Fn(&DynKey) -> bool,
+        max_keys: usize,
+    ) -> DiceRecomputeExplanations {
+        match self {
+            DiceImplementation::Modern(dice) => dice.explain_recompute(matches, max_keys).await,
+        }
+    }
 }
 
 pub(crate) enum DiceDataBuilderImpl {
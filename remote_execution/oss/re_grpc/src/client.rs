@@ -83,6 +83,13 @@ use crate::response::*;
 
 const DEFAULT_MAX_TOTAL_BATCH_SIZE: usize = 4 * 1000 * 1000;
 
+/// REv2 does not tell us how long blobs live, so unless configured otherwise, assume they only
+/// live for an hour after we last referenced them.
+const DEFAULT_CAS_TTL_SECS: u64 = 60 * 60;
+
+/// How many digests to send in a single `FindMissingBlobs` request.
+const FIND_MISSING_CHUNK_SIZE: usize = 100;
+
 fn tdigest_to(tdigest: TDigest) -> Digest {
     Digest {
         hash: tdigest.hash,
@@ -216,6 +223,8 @@ pub struct RERuntimeOpts {
     use_fbcode_metadata: bool,
     /// Maximum number of concurrent upload requests.
     max_concurrent_uploads_per_action: Option<usize>,
+    /// How long the CAS keeps a blob after it was last referenced.
+    cas_ttl: Duration,
 }
 
 struct InstanceName(Option<String>);
@@ -342,6 +351,7 @@ impl REClientBuilder {
             RERuntimeOpts {
                 use_fbcode_metadata: opts.use_fbcode_metadata,
                 max_concurrent_uploads_per_action: opts.max_concurrent_uploads_per_action,
+                cas_ttl: Duration::from_secs(opts.cas_ttl_secs.unwrap_or(DEFAULT_CAS_TTL_SECS)),
             },
             grpc_clients,
             capabilities,
@@ -449,7 +459,8 @@ pub struct GRPCClients {
 }
 
 enum DigestRemoteState {
-    ExistsOnRemote,
+    /// The CAS last reported this digest as present at the given time.
+    ExistsOnRemote(Instant),
     Missing,
 }
 
@@ -459,6 +470,10 @@ struct FindMissingCache {
     /// we clear our local cache once every `ttl`.
     ttl: Duration,
     last_check: Instant,
+    /// How long the CAS keeps a blob after it was last referenced. REv2 servers extend the lifetime
+    /// of the blobs `FindMissingBlobs` reports as present, so that is how long we assume a digest
+    /// lives after we last saw it.
+    cas_ttl: Duration,
 }
 
 impl FindMissingCache {
@@ -477,6 +492,28 @@ impl FindMissingCache {
         self.clear_if_ttl_expires();
         self.cache.get(digest)
     }
+
+    /// How much longer a digest is known to stay in the CAS, if we know it is there at all.
+    fn remaining_ttl(&mut self, digest: &TDigest) -> Option<Duration> {
+        let cas_ttl = self.cas_ttl;
+        match self.get(digest) {
+            Some(DigestRemoteState::ExistsOnRemote(checked)) => {
+                cas_ttl.checked_sub(checked.elapsed())
+            }
+            Some(DigestRemoteState::Missing) | None => None,
+        }
+    }
+
+    /// Record the result of a `FindMissingBlobs` call for `checked`.
+    fn record_find_missing(&mut self, checked: &[TDigest], missing: &[TDigest]) {
+        let now = Instant::now();
+        for digest in checked {
+            self.put(digest.clone(), DigestRemoteState::ExistsOnRemote(now));
+        }
+        for digest in missing {
+            self.put(digest.clone(), DigestRemoteState::Missing);
+        }
+    }
 }
 
 pub struct REClient {
@@ -550,6 +587,7 @@ impl REClient {
         capabilities: RECapabilities,
         instance_name: InstanceName,
     ) -> Self {
+        let cas_ttl = runtime_opts.cas_ttl;
         REClient {
             runtime_opts,
             grpc_clients,
@@ -559,6 +597,7 @@ impl REClient {
                 cache: LruCache::new(NonZeroUsize::new(50 << 20).unwrap()), // 50Mb
                 ttl: Duration::from_secs(12 * 60 * 60), // 12 hours TODO: Tune this parameter
                 last_check: Instant::now(),
+                cas_ttl,
             }),
         }
     }
@@ -814,80 +853,49 @@ impl REClient {
         metadata: RemoteExecutionMetadata,
         request: GetDigestsTtlRequest,
     ) -> anyhow::Result<GetDigestsTtlResponse> {
-        let mut cas_client = self.grpc_clients.cas_client.clone();
-        let mut remote_ttl: HashMap<TDigest, DigestWithTtl> = HashMap::new();
-
-        for digest_chunk in request.digests.chunks(100) {
-            let mut digest_to_check: Vec<TDigest> = Vec::new();
-            {
-                let mut find_missing_cache = self.find_missing_cache.lock().unwrap();
-                for digest in digest_chunk {
-                    // Assume that all digests are present on the remote because the API
-                    // returns what is *not* present.
-                    remote_ttl.insert(
-                        digest.clone(),
-                        DigestWithTtl {
-                            digest: digest.clone(),
-                            // NOTE: This is an arbitrary number because RBE does not return information
-                            // on the TTL of the remote blob.
-                            ttl: 60,
-                        },
-                    );
-                    match find_missing_cache.get(digest) {
-                        Some(DigestRemoteState::Missing) | None => {
-                            digest_to_check.push(digest.clone());
-                        }
-                        _ => {}
-                    }
-                }
-            }
-
-            if digest_to_check.is_empty() {
-                continue;
-            }
+        let digests_with_ttl =
+            get_digests_ttl_impl(&self.find_missing_cache, &request.digests, |digests| {
+                self.find_missing_blobs(metadata.clone(), digests)
+            })
+            .await?;
 
-            let missing_blobs = cas_client
-                .find_missing_blobs(with_re_metadata(
-                    FindMissingBlobsRequest {
-                        instance_name: self.instance_name.as_str().to_owned(),
-                        blob_digests: digest_to_check.map(|b| tdigest_to(b.clone())),
-                    },
-                    metadata.clone(),
-                    self.runtime_opts.use_fbcode_metadata,
-                ))
-                .await
-                .context("Failed to request what blobs are not present on remote")?;
-            let resp: FindMissingBlobsResponse = missing_blobs.into_inner();
-            let mut find_missing_cache = self.find_missing_cache.lock().unwrap();
-            for digest in &digest_to_check {
-                find_missing_cache.put(digest.clone(), DigestRemoteState::ExistsOnRemote);
-            }
-            for digest in &resp.missing_blob_digests.map(|d| tdigest_from(d.clone())) {
-                // If it's present in the MissingBlobsResponse, it's expired on the remote and
-                // needs to be refetched.
-                remote_ttl.insert(
-                    digest.clone(),
-                    DigestWithTtl {
-                        digest: digest.clone(),
-                        ttl: 0,
-                    },
-                );
-                find_missing_cache.put(digest.clone(), DigestRemoteState::Missing);
-            }
-        }
+        Ok(GetDigestsTtlResponse { digests_with_ttl })
+    }
 
-        Ok(GetDigestsTtlResponse {
-            digests_with_ttl: remote_ttl.values().cloned().collect::<Vec<DigestWithTtl>>(),
+    pub async fn extend_digest_ttl(
+        &self,
+        metadata: RemoteExecutionMetadata,
+        request: ExtendDigestsTtlRequest,
+    ) -> anyhow::Result<()> {
+        // REv2 has no way to ask for a specific TTL, so `request.ttl` is ignored: the CAS decides
+        // how long referenced blobs live for.
+        extend_digest_ttl_impl(&self.find_missing_cache, &request.digests, |digests| {
+            self.find_missing_blobs(metadata.clone(), digests)
         })
+        .await
     }
 
-    pub async fn extend_digest_ttl(
+    /// Ask the CAS which of `digests` it does not have, which also extends the lifetime of those
+    /// it does have.
+    async fn find_missing_blobs(
         &self,
-        _metadata: RemoteExecutionMetadata,
-        _request: ExtendDigestsTtlRequest,
-    ) -> anyhow::Result<TDigest> {
-        // TODO(arr)
-        Err(anyhow::anyhow!("Not implemented (RE extend_digest_ttl)"))
+        metadata: RemoteExecutionMetadata,
+        digests: Vec<TDigest>,
+    ) -> anyhow::Result<Vec<TDigest>> {
+        let mut cas_client = self.grpc_clients.cas_client.clone();
+        let missing_blobs = cas_client
+            .find_missing_blobs(with_re_metadata(
+                FindMissingBlobsRequest {
+                    instance_name: self.instance_name.as_str().to_owned(),
+                    blob_digests: digests.into_map(tdigest_to),
+                },
+                metadata,
+                self.runtime_opts.use_fbcode_metadata,
+            ))
+            .await
+            .context("Failed to request what blobs are not present on remote")?;
+        let resp: FindMissingBlobsResponse = missing_blobs.into_inner();
+        Ok(resp.missing_blob_digests.into_map(tdigest_from))
     }
 
     pub fn get_execution_client(&self) -> &Self {
@@ -916,6 +924,85 @@ impl REClient {
     }
 }
 
+/// Report how long each of `digests` will stay in the CAS, only asking the CAS about those we have
+/// not seen present recently. Digests that are missing get a TTL of zero.
+async fn get_digests_ttl_impl<Fut>(
+    find_missing_cache: &Mutex<FindMissingCache>,
+    digests: &[TDigest],
+    find_missing: impl Fn(Vec<TDigest>) -> Fut,
+) -> anyhow::Result<Vec<DigestWithTtl>>
+where
+    Fut: Future<Output = anyhow::Result<Vec<TDigest>>>,
+{
+    let mut remote_ttl: HashMap<TDigest, Duration> = HashMap::new();
+
+    for digest_chunk in digests.chunks(FIND_MISSING_CHUNK_SIZE) {
+        let mut digest_to_check: Vec<TDigest> = Vec::new();
+        {
+            let mut find_missing_cache = find_missing_cache.lock().unwrap();
+            for digest in digest_chunk {
+                match find_missing_cache.remaining_ttl(digest) {
+                    Some(ttl) => {
+                        remote_ttl.insert(digest.clone(), ttl);
+                    }
+                    None => digest_to_check.push(digest.clone()),
+                }
+            }
+        }
+
+        if digest_to_check.is_empty() {
+            continue;
+        }
+
+        let missing = find_missing(digest_to_check.clone()).await?;
+        let mut find_missing_cache = find_missing_cache.lock().unwrap();
+        find_missing_cache.record_find_missing(&digest_to_check, &missing);
+        for digest in digest_to_check {
+            remote_ttl.insert(digest, find_missing_cache.cas_ttl);
+        }
+        // If it's present in the MissingBlobsResponse, it's expired on the remote and
+        // needs to be refetched.
+        for digest in missing {
+            remote_ttl.insert(digest, Duration::ZERO);
+        }
+    }
+
+    Ok(remote_ttl
+        .into_iter()
+        .map(|(digest, ttl)| DigestWithTtl {
+            digest,
+            ttl: ttl.as_secs() as i64,
+        })
+        .collect())
+}
+
+/// Reference all of `digests` in the CAS so it keeps them around for another full TTL. Digests
+/// that are already gone cannot be extended: they are recorded as missing, so they are reported
+/// with a TTL of zero, and the rest are still extended.
+async fn extend_digest_ttl_impl<Fut>(
+    find_missing_cache: &Mutex<FindMissingCache>,
+    digests: &[TDigest],
+    find_missing: impl Fn(Vec<TDigest>) -> Fut,
+) -> anyhow::Result<()>
+where
+    Fut: Future<Output = anyhow::Result<Vec<TDigest>>>,
+{
+    for digest_chunk in digests.chunks(FIND_MISSING_CHUNK_SIZE) {
+        let missing = find_missing(digest_chunk.to_vec()).await?;
+        find_missing_cache
+            .lock()
+            .unwrap()
+            .record_find_missing(digest_chunk, &missing);
+        if !missing.is_empty() {
+            tracing::debug!(
+                "Cannot extend the TTL of {} digests missing from the CAS",
+                missing.len()
+            );
+        }
+    }
+    Ok(())
+}
+
 fn convert_action_result(action_result: ActionResult) -> anyhow::Result<TActionResult2> {
     let execution_metadata = action_result
         .execution_metadata
@@ -2456,6 +2543,141 @@ mod tests {
         Ok(())
     }
 
+    /// A CAS that keeps blobs for `ttl` after they were last referenced, like a REv2 server.
+    struct FakeCas {
+        ttl: Duration,
+        blobs: Mutex<HashMap<TDigest, Instant>>,
+        find_missing_calls: AtomicU16,
+    }
+
+    impl FakeCas {
+        fn new(ttl: Duration, digests: &[TDigest]) -> Self {
+            let expires = Instant::now() + ttl;
+            Self {
+                ttl,
+                blobs: Mutex::new(digests.iter().map(|d| (d.clone(), expires)).collect()),
+                find_missing_calls: AtomicU16::new(0),
+            }
+        }
+
+        fn evict(&self, digest: &TDigest) {
+            self.blobs.lock().unwrap().remove(digest);
+        }
+
+        fn calls(&self) -> u16 {
+            self.find_missing_calls.load(Ordering::Relaxed)
+        }
+
+        async fn find_missing(&self, digests: Vec<TDigest>) -> anyhow::Result<Vec<TDigest>> {
+            self.find_missing_calls.fetch_add(1, Ordering::Relaxed);
+            let now = Instant::now();
+            let mut blobs = self.blobs.lock().unwrap();
+            Ok(digests
+                .into_iter()
+                .filter(|digest| match blobs.get_mut(digest) {
+                    Some(expires) if *expires > now => {
+                        *expires = now + self.ttl;
+                        false
+                    }
+                    _ => true,
+                })
+                .collect())
+        }
+    }
+
+    fn find_missing_cache(cas_ttl: Duration) -> Mutex<FindMissingCache> {
+        Mutex::new(FindMissingCache {
+            cache: LruCache::new(NonZeroUsize::new(100).unwrap()),
+            ttl: Duration::from_secs(12 * 60 * 60),
+            last_check: Instant::now(),
+            cas_ttl,
+        })
+    }
+
+    fn digest(hash: &str) -> TDigest {
+        TDigest {
+            hash: hash.to_owned(),
+            size_in_bytes: 3,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_digests_ttl() -> anyhow::Result<()> {
+        let present = digest("aa");
+        let missing = digest("bb");
+        let cas = FakeCas::new(Duration::from_secs(3600), &[present.clone()]);
+        let cache = find_missing_cache(Duration::from_secs(3600));
+
+        let ttls: HashMap<_, _> =
+            get_digests_ttl_impl(&cache, &[present.clone(), missing.clone()], |digests| {
+                cas.find_missing(digests)
+            })
+            .await?
+            .into_iter()
+            .map(|d| (d.digest, d.ttl))
+            .collect();
+        assert_eq!(ttls[&present], 3600);
+        assert_eq!(ttls[&missing], 0);
+        assert_eq!(cas.calls(), 1);
+
+        // Digests we saw present are served from the cache, missing ones are checked again.
+        let ttls = get_digests_ttl_impl(&cache, &[present.clone()], |digests| {
+            cas.find_missing(digests)
+        })
+        .await?;
+        assert!(ttls[0].ttl <= 3600);
+        assert_eq!(cas.calls(), 1);
+
+        get_digests_ttl_impl(&cache, &[missing.clone()], |digests| {
+            cas.find_missing(digests)
+        })
+        .await?;
+        assert_eq!(cas.calls(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_extend_digest_ttl() -> anyhow::Result<()> {
+        let present = digest("aa");
+        let other = digest("bb");
+        let cas = FakeCas::new(Duration::from_secs(3600), &[present.clone(), other.clone()]);
+        let cache = find_missing_cache(Duration::from_secs(3600));
+
+        get_digests_ttl_impl(&cache, &[present.clone()], |digests| {
+            cas.find_missing(digests)
+        })
+        .await?;
+        assert_eq!(cas.calls(), 1);
+
+        // Extending always references the digests in the CAS, even when they are cached.
+        extend_digest_ttl_impl(&cache, &[present.clone()], |digests| {
+            cas.find_missing(digests)
+        })
+        .await?;
+        assert_eq!(cas.calls(), 2);
+
+        // Once the CAS dropped a digest, it cannot be extended and is reported missing, but the
+        // other digests in the batch are still extended.
+        cas.evict(&present);
+        extend_digest_ttl_impl(&cache, &[present.clone(), other.clone()], |digests| {
+            cas.find_missing(digests)
+        })
+        .await?;
+        assert_eq!(cas.calls(), 3);
+        let ttls = get_digests_ttl_impl(&cache, &[present.clone(), other.clone()], |digests| {
+            cas.find_missing(digests)
+        })
+        .await?;
+        assert_eq!(cas.calls(), 4);
+        let ttl = |digest: &TDigest| ttls.iter().find(|d| &d.digest == digest).unwrap().ttl;
+        assert_eq!(ttl(&present), 0);
+        assert!(ttl(&other) > 0);
+
+        Ok(())
+    }
+
     #[test]
     fn test_substitute_env_vars() {
         let getter = |s: &str| match s {
@@ -23,6 +23,7 @@ pub use lint_message::LintMessage;
 pub use types::EvalMessage;
 pub use types::EvalSeverity;
 pub use types::Lint;
+pub use types::LintFix;
 pub use unused_loads::remove::remove_unused_loads;
 
 use crate::analysis::types::LintT;
@@ -71,6 +72,31 @@ mod tests {
         AstModule::parse("X", x.to_owned(), &Dialect::AllOptionsInternal).unwrap()
     }
 
+    #[test]
+    fn test_lint_fixes() {
+        let m = module(
+            r#"
+load("a", "unused")
+def f(x):
+    if type(x) == list:
+        return dict(**x)
+    return x
+"#,
+        );
+        let res = m.lint(None);
+        let fixed = LintFix::apply_all(res.iter().filter_map(|x| x.fix.as_ref())).unwrap();
+        assert_eq!(
+            r#"
+def f(x):
+    if type(x) == type([]):
+        return dict(x)
+    return x
+"#,
+            fixed
+        );
+        assert!(module(&fixed).lint(None).iter().all(|x| x.fix.is_none()));
+    }
+
     #[test]
     fn test_lint_suppressions_keyword_matching() {
         let m = module(
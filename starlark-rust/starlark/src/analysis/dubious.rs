@@ -25,6 +25,7 @@ use starlark_syntax::syntax::ast::Stmt;
 use starlark_syntax::syntax::module::AstModuleFields;
 use thiserror::Error;
 
+use crate::analysis::types::statement_line_span;
 use crate::analysis::types::LintT;
 use crate::analysis::types::LintWarning;
 use crate::analysis::EvalSeverity;
@@ -96,14 +97,29 @@ fn duplicate_dictionary_key(module: &AstModule, res: &mut Vec<LintT<Dubious>>) {
         match &**x {
             Expr::Dict(args) => {
                 let mut seen = HashMap::new();
-                for (key, _) in args {
+                for (i, (key, _)) in args.iter().enumerate() {
                     if let Some((key_id, pos)) = to_key(key) {
-                        if let Some(old) = seen.insert(key_id, pos) {
-                            res.push(LintT::new(
+                        if let Some((old, old_i)) = seen.insert(key_id, (pos, i)) {
+                            let lint = LintT::new(
                                 codemap,
                                 old,
                                 Dubious::DuplicateKey(key.to_string(), codemap.file_span(pos)),
-                            ))
+                            );
+                            // The earlier entry is overwritten, so it can go, along with
+                            // everything up to the next entry, as long as evaluating its
+                            // value does nothing.
+                            let (old_key, old_value) = &args[old_i];
+                            res.push(
+                                if matches!(&**old_value, Expr::Literal(_) | Expr::Identifier(_)) {
+                                    let next_key = &args[old_i + 1].0;
+                                    lint.with_fix(
+                                        Span::new(old_key.span.begin(), next_key.span.begin()),
+                                        String::new(),
+                                    )
+                                } else {
+                                    lint
+                                },
+                            )
                         }
                     }
                 }
@@ -119,21 +135,35 @@ fn duplicate_dictionary_key(module: &AstModule, res: &mut Vec<LintT<Dubious>>) {
 }
 
 fn identifier_as_statement(module: &AstModule, res: &mut Vec<LintT<Dubious>>) {
-    fn stmt<'a>(x: &'a AstStmt, codemap: &CodeMap, res: &mut Vec<LintT<Dubious>>) {
+    // `alone` is whether the statement is the only one in its block.
+    fn stmt<'a>(x: &'a AstStmt, codemap: &CodeMap, alone: bool, res: &mut Vec<LintT<Dubious>>) {
         match &**x {
-            Stmt::Expression(x) => match &**x {
-                Expr::Identifier(x) => res.push(LintT::new(
-                    codemap,
-                    x.span,
-                    Dubious::IdentifierAsStatement(x.node.ident.clone()),
-                )),
+            Stmt::Expression(e) => match &**e {
+                Expr::Identifier(ident) => {
+                    let lint = LintT::new(
+                        codemap,
+                        ident.span,
+                        Dubious::IdentifierAsStatement(ident.node.ident.clone()),
+                    );
+                    // Removing the only statement in a block would leave it empty.
+                    res.push(if alone {
+                        lint.with_fix(x.span, "pass".to_owned())
+                    } else {
+                        lint.with_fix(statement_line_span(codemap, x.span), String::new())
+                    })
+                }
                 _ => {}
             },
-            _ => x.visit_stmt(|x| stmt(x, codemap, res)),
+            Stmt::Statements(xs) => {
+                for x in xs {
+                    stmt(x, codemap, xs.len() == 1, res)
+                }
+            }
+            _ => x.visit_stmt(|x| stmt(x, codemap, true, res)),
         }
     }
 
-    stmt(module.statement(), module.codemap(), res)
+    stmt(module.statement(), module.codemap(), true, res)
 }
 
 pub(crate) fn lint(module: &AstModule) -> Vec<LintT<Dubious>> {
@@ -148,6 +178,7 @@ mod tests {
     use starlark_syntax::slice_vec_ext::SliceExt;
 
     use super::*;
+    use crate::analysis::LintFix;
     use crate::syntax::Dialect;
 
     fn module(x: &str) -> AstModule {
@@ -182,12 +213,9 @@ mod tests {
         );
         let mut res = Vec::new();
         duplicate_dictionary_key(&m, &mut res);
-        assert_eq!(
-            res.map(|x| x.problem.about()),
-            &[
-                "\"no1\"", "42", "\"no2\"", "123", "0.25", "no3", "no3", "no4"
-            ]
-        );
+        assert_eq!(res.map(|x| x.problem.about()), &[
+            "\"no1\"", "42", "\"no2\"", "123", "0.25", "no3", "no3", "no4"
+        ]);
     }
 
     #[test]
@@ -204,4 +232,34 @@ def foo():
         identifier_as_statement(&m, &mut res);
         assert_eq!(res.map(|x| x.problem.about()), &["no1", "no2"]);
     }
+
+    #[test]
+    fn test_lint_dubious_fixes() {
+        let m = module(
+            r#"
+x = {'a': 1, 'b': 2, 'a': 3}
+y = {'a': f(), 'a': 3}
+def foo():
+    no1
+def bar():
+    no2  # Comment.
+    return 1
+"#,
+        );
+        let res = lint(&m);
+        let fixed = LintFix::apply_all(res.iter().filter_map(|x| x.fix.as_ref()));
+        assert_eq!(
+            fixed.as_deref(),
+            Some(
+                r#"
+x = {'b': 2, 'a': 3}
+y = {'a': f(), 'a': 3}
+def foo():
+    pass
+def bar():
+    return 1
+"#
+            )
+        );
+    }
 }
@@ -383,10 +383,9 @@ def yes4() -> str:
         );
         let mut res = Vec::new();
         stmt(m.codemap(), m.statement(), &mut res);
-        assert_eq!(
-            res.map(|x| x.problem.about()),
-            &["no1", "no2", "no3", "no4"]
-        );
+        assert_eq!(res.map(|x| x.problem.about()), &[
+            "no1", "no2", "no3", "no4"
+        ]);
     }
 
     #[test]
@@ -430,10 +429,9 @@ def f():
         );
         let mut res = Vec::new();
         reachable(m.codemap(), m.statement(), &mut res);
-        assert_eq!(
-            res.map(|x| x.problem.about()),
-            &["no1", "no2", "no3", "no4"]
-        );
+        assert_eq!(res.map(|x| x.problem.about()), &[
+            "no1", "no2", "no3", "no4"
+        ]);
     }
 
     #[test]
@@ -473,10 +471,9 @@ def test7():
         );
         let mut res = Vec::new();
         redundant(m.codemap(), m.statement(), &mut res);
-        assert_eq!(
-            res.map(|x| x.location.resolve_span().begin.line),
-            &[3, 9, 19]
-        );
+        assert_eq!(res.map(|x| x.location.resolve_span().begin.line), &[
+            3, 9, 19
+        ]);
     }
 
     #[test]
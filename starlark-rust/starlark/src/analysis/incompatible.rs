@@ -101,14 +101,15 @@ fn match_bad_type_equality(
             if (*op == BinOp::Equal || *op == BinOp::NotEqual) && is_type_call(lhs) =>
         {
             if let Some(replacement) = lookup_type(rhs, types) {
-                res.push(LintT::new(
-                    codemap,
-                    x.span,
-                    Incompatibility::IncompatibleTypeCheck(
-                        x.to_string(),
-                        format!("{}{}type({})", lhs.node, op, replacement),
-                    ),
-                ))
+                let replacement = format!("{}{}type({})", lhs.node, op, replacement);
+                res.push(
+                    LintT::new(
+                        codemap,
+                        x.span,
+                        Incompatibility::IncompatibleTypeCheck(x.to_string(), replacement.clone()),
+                    )
+                    .with_fix(x.span, replacement),
+                )
             }
         }
         _ => {}
@@ -232,12 +233,9 @@ def foo():
             ),
             &mut res,
         );
-        assert_eq!(
-            res.map(|x| x.to_string()),
-            &[
-                "bad.py:3:8-22: Type check `(type(x) == str)` should be written `type(x) == type(\"\")`"
-            ]
-        );
+        assert_eq!(res.map(|x| x.to_string()), &[
+            "bad.py:3:8-22: Type check `(type(x) == str)` should be written `type(x) == type(\"\")`"
+        ]);
     }
 
     #[test]
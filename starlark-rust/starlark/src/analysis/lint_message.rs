@@ -35,11 +35,32 @@ pub struct LintMessage {
     description: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     original: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fix: Option<LintMessageFix>,
+}
+
+/// A patch that fixes a [`LintMessage`], which may cover more code than the problem itself.
+#[derive(Debug, Clone, Serialize)]
+struct LintMessageFix {
+    line: usize,
+    char: usize,
+    /// The code to replace, starting at `line` and `char`.
+    original: String,
+    replacement: String,
 }
 
 impl LintMessage {
     /// Construct from an [`EvalMessage`].
     pub fn new(x: EvalMessage) -> Self {
+        let fix = x.fix.map(|fix| {
+            let span = fix.location.resolve_span();
+            LintMessageFix {
+                line: span.begin.line + 1,
+                char: span.begin.column + 1,
+                original: fix.location.source_span().to_owned(),
+                replacement: fix.replacement,
+            }
+        });
         Self {
             path: x.path,
             line: x.span.map(|x| x.begin.line + 1),
@@ -49,6 +70,7 @@ impl LintMessage {
             name: x.name,
             description: Some(x.description),
             original: x.original,
+            fix,
         }
     }
 }
@@ -44,8 +44,10 @@ use starlark_syntax::syntax::ast::ForP;
 use starlark_syntax::syntax::ast::LoadArgP;
 use starlark_syntax::syntax::ast::Stmt;
 use starlark_syntax::syntax::module::AstModuleFields;
+use starlark_syntax::syntax::top_level_stmts::top_level_stmts;
 use thiserror::Error;
 
+use crate::analysis::types::statement_line_span;
 use crate::analysis::types::LintT;
 use crate::analysis::types::LintWarning;
 use crate::analysis::EvalSeverity;
@@ -555,7 +557,83 @@ pub(crate) fn lint(
         loop_depth: 0,
     };
     state.module(module);
-    state.warnings
+    fix_unused_loads(module, state.warnings)
+}
+
+/// Fix unused loads by removing them, or the whole `load` if none of its symbols are used.
+///
+/// All the unused symbols of a `load` share one fix that rewrites the whole statement, so the
+/// fixes never overlap and the separators between the remaining symbols stay tidy.
+fn fix_unused_loads(
+    module: &AstModule,
+    warnings: Vec<LintT<NameWarning>>,
+) -> Vec<LintT<NameWarning>> {
+    let codemap = module.codemap();
+    let unused: HashSet<Span> = warnings
+        .iter()
+        .filter(|x| matches!(x.problem, NameWarning::UnusedLoad(_)))
+        .map(|x| x.location.span)
+        .collect();
+    let mut fixes = HashMap::new();
+    for stmt in top_level_stmts(module.statement()) {
+        let Stmt::Load(load) = &**stmt else {
+            continue;
+        };
+        let is_unused = |arg: &LoadArgP<_>| unused.contains(&arg.local.span);
+        let fix = match load.args.iter().rposition(|arg| !is_unused(arg)) {
+            None => (statement_line_span(codemap, stmt.span), String::new()),
+            Some(last_used) => {
+                // Each unused symbol before the last used one goes along with everything up to
+                // the next symbol, and any after it go along with everything from its end.
+                let mut cuts = Vec::new();
+                for (i, arg) in load.args[..last_used].iter().enumerate() {
+                    if is_unused(arg) {
+                        cuts.push(Span::new(
+                            arg.span().begin(),
+                            load.args[i + 1].span().begin(),
+                        ));
+                    }
+                }
+                if let Some(last) = load.args[last_used + 1..].last() {
+                    let kept = &load.args[last_used];
+                    // Keep a trailing comma if the load had one.
+                    let begin = match last.comma {
+                        Some(_) => kept.span_with_trailing_comma().end(),
+                        None => kept.span().end(),
+                    };
+                    // Take a comment after the last symbol along with it.
+                    let mut end = last.span_with_trailing_comma().end();
+                    let line_end = codemap.line_span_trim_newline(codemap.find_line(end)).end();
+                    let rest = codemap.source_span(Span::new(end, line_end)).trim_start();
+                    if rest.starts_with('#') {
+                        end = line_end;
+                    }
+                    cuts.push(Span::new(begin, end));
+                }
+
+                let mut replacement = String::new();
+                let mut pos = stmt.span.begin();
+                for cut in cuts {
+                    replacement.push_str(codemap.source_span(Span::new(pos, cut.begin())));
+                    pos = cut.end();
+                }
+                replacement.push_str(codemap.source_span(Span::new(pos, stmt.span.end())));
+                (stmt.span, replacement)
+            }
+        };
+        for arg in load.args.iter().filter(|arg| is_unused(arg)) {
+            fixes.insert(arg.local.span, fix.clone());
+        }
+    }
+    warnings
+        .into_iter()
+        .map(|x| match (&x.problem, fixes.remove(&x.location.span)) {
+            (NameWarning::UnusedLoad(_), Some((span, replacement))) => {
+                x.with_fix(span, replacement)
+            }
+            _ => x,
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -563,6 +641,7 @@ mod tests {
     use starlark_syntax::slice_vec_ext::SliceExt;
 
     use super::*;
+    use crate::analysis::LintFix;
     use crate::syntax::Dialect;
 
     impl NameWarning {
@@ -630,6 +709,54 @@ def foo():
         assert_eq!(res, &["_no2", "_no4", "_no6", "no1", "no3", "no5"]);
     }
 
+    #[test]
+    fn test_lint_unused_load_fixes() {
+        let m = module(
+            r#"
+load("a", "no1", "b")
+load("c", "no2", no3 = "d")
+load("e", "no4")  # Comment.
+print(b)
+"#,
+        );
+        let res = lint(&m, None);
+        let fixed = LintFix::apply_all(res.iter().filter_map(|x| x.fix.as_ref()));
+        assert_eq!(fixed.as_deref(), Some("\nload(\"a\", \"b\")\nprint(b)\n"));
+    }
+
+    #[test]
+    fn test_lint_unused_load_fixes_multiline() {
+        let m = module(
+            r#"
+load(
+    "a",
+    "b",  # Why b.
+    "no1",
+    "c",
+    "no2",  # Why no2.
+)
+load("d", "e", "no3", "no4")
+print(b, c, e)
+"#,
+        );
+        let res = lint(&m, None);
+        let fixed = LintFix::apply_all(res.iter().filter_map(|x| x.fix.as_ref()));
+        assert_eq!(
+            fixed.as_deref(),
+            Some(
+                r#"
+load(
+    "a",
+    "b",  # Why b.
+    "c",
+)
+load("d", "e")
+print(b, c, e)
+"#
+            )
+        );
+    }
+
     #[test]
     fn test_lint_duplicate_assign() {
         let m = module(
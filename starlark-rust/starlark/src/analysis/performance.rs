@@ -60,11 +60,15 @@ fn match_dict_copy(codemap: &CodeMap, x: &AstExpr, res: &mut Vec<LintT<Performan
     match &**x {
         Expr::Call(fun, args) if args.args.len() == 1 => match (&***fun, &*args.args[0]) {
             (Expr::Identifier(f), Argument::KwArgs(arg)) if f.node.ident == "dict" => {
-                res.push(LintT::new(
-                    codemap,
-                    x.span,
-                    Performance::DictWithoutStarStar(x.to_string(), format!("dict({})", arg.node)),
-                ))
+                let replacement = format!("dict({})", arg.node);
+                res.push(
+                    LintT::new(
+                        codemap,
+                        x.span,
+                        Performance::DictWithoutStarStar(x.to_string(), replacement.clone()),
+                    )
+                    .with_fix(x.span, replacement),
+                )
             }
             _ => {}
         },
@@ -153,10 +157,9 @@ def foo(extra, **kwargs):
             ),
             &mut res,
         );
-        assert_eq!(
-            res.map(|x| x.to_string()),
-            &["bad.bzl:3:9-23: Dict copy `dict(**kwargs)` is more efficient as `dict(kwargs)`"]
-        );
+        assert_eq!(res.map(|x| x.to_string()), &[
+            "bad.bzl:3:9-23: Dict copy `dict(**kwargs)` is more efficient as `dict(kwargs)`"
+        ]);
     }
 
     #[test]
@@ -177,15 +180,12 @@ def foo(items):
             ),
             &mut res,
         );
-        assert_eq!(
-            res.map(|x| x.to_string()),
-            &[
-                "bad.bzl:4:9-38: `all` eagerly evaluates all items in the iterable, and allocates an array for the results. Prefer using a for-loop.",
-                "bad.bzl:5:9-38: `any` eagerly evaluates all items in the iterable, and allocates an array for the results. Prefer using a for-loop.",
-                "bad.bzl:6:9-34: `all` eagerly evaluates all items in the iterable, and allocates an array for the results. Prefer using a for-loop.",
-                "bad.bzl:7:9-22: `any(list({}))` allocates a new list for the results. Prefer using a for-loop.",
-                "bad.bzl:8:9-22: `all(dict([]))` allocates a new dict for the results. Prefer using a for-loop."
-            ]
-        );
+        assert_eq!(res.map(|x| x.to_string()), &[
+            "bad.bzl:4:9-38: `all` eagerly evaluates all items in the iterable, and allocates an array for the results. Prefer using a for-loop.",
+            "bad.bzl:5:9-38: `any` eagerly evaluates all items in the iterable, and allocates an array for the results. Prefer using a for-loop.",
+            "bad.bzl:6:9-34: `all` eagerly evaluates all items in the iterable, and allocates an array for the results. Prefer using a for-loop.",
+            "bad.bzl:7:9-22: `any(list({}))` allocates a new list for the results. Prefer using a for-loop.",
+            "bad.bzl:8:9-22: `all(dict([]))` allocates a new dict for the results. Prefer using a for-loop."
+        ]);
     }
 }
@@ -24,6 +24,7 @@ use serde::Serialize;
 
 use crate::codemap::CodeMap;
 use crate::codemap::FileSpan;
+use crate::codemap::Pos;
 use crate::codemap::ResolvedSpan;
 use crate::codemap::Span;
 
@@ -39,6 +40,7 @@ pub(crate) struct LintT<T> {
     pub location: FileSpan,
     pub original: String,
     pub problem: T,
+    pub fix: Option<LintFix>,
 }
 
 /// A lint produced by `AstModule::lint`.
@@ -55,6 +57,59 @@ pub struct Lint {
     pub problem: String,
     /// The source code at [`location`](Lint::location).
     pub original: String,
+    /// An edit that fixes the problem, if one can be made mechanically.
+    pub fix: Option<LintFix>,
+}
+
+/// A machine-applicable edit that fixes a [`Lint`].
+#[derive(Debug, Clone)]
+pub struct LintFix {
+    /// The code to replace, which is always in the same file as the lint.
+    pub location: FileSpan,
+    /// The code to replace it with.
+    pub replacement: String,
+}
+
+impl LintFix {
+    /// Apply fixes to the source of the file they are in, which must be the same for all of them.
+    /// A fix that overlaps one earlier in the file is skipped, so running the linter again may
+    /// find more to fix. Returns `None` if there are no fixes.
+    pub fn apply_all<'a>(fixes: impl IntoIterator<Item = &'a LintFix>) -> Option<String> {
+        let mut fixes: Vec<&LintFix> = fixes.into_iter().collect();
+        let file = &fixes.first()?.location.file;
+        fixes.sort_by_key(|fix| (fix.location.span.begin(), fix.location.span.end()));
+
+        let mut out = String::new();
+        let mut pos = Pos::new(0);
+        for fix in fixes {
+            let span = fix.location.span;
+            if span.begin() < pos {
+                continue;
+            }
+            out.push_str(file.source_span(Span::new(pos, span.begin())));
+            out.push_str(&fix.replacement);
+            pos = span.end();
+        }
+        out.push_str(file.source_span(Span::new(pos, file.full_span().end())));
+        Some(out)
+    }
+}
+
+/// The span of a statement, extended to its whole lines if nothing but whitespace and a trailing
+/// comment is on them, so that removing it leaves no blank line behind.
+pub(crate) fn statement_line_span(codemap: &CodeMap, span: Span) -> Span {
+    let lines = codemap
+        .line_span(codemap.find_line(span.begin()))
+        .merge(codemap.line_span(codemap.find_line(span.end())));
+    let before = codemap.source_span(Span::new(lines.begin(), span.begin()));
+    let after = codemap
+        .source_span(Span::new(span.end(), lines.end()))
+        .trim_start();
+    if before.trim().is_empty() && (after.is_empty() || after.starts_with('#')) {
+        lines
+    } else {
+        span
+    }
 }
 
 impl Display for Lint {
@@ -76,9 +131,19 @@ impl<T: LintWarning> LintT<T> {
             original: location.file.source_span(span).to_owned(),
             location,
             problem,
+            fix: None,
         }
     }
 
+    /// Fix the problem by replacing the code at `span` with `replacement`.
+    pub(crate) fn with_fix(mut self, span: Span, replacement: String) -> Self {
+        self.fix = Some(LintFix {
+            location: self.location.file.file_span(span),
+            replacement,
+        });
+        self
+    }
+
     pub(crate) fn erase(self) -> Lint {
         Lint {
             location: self.location,
@@ -86,6 +151,7 @@ impl<T: LintWarning> LintT<T> {
             severity: self.problem.severity(),
             problem: self.problem.to_string(),
             original: self.original,
+            fix: self.fix,
         }
     }
 }
@@ -132,6 +198,8 @@ pub struct EvalMessage {
     pub full_error_with_span: Option<String>,
     /// The text referred to by `.span`
     pub original: Option<String>,
+    /// An edit that fixes the problem, if one can be made mechanically.
+    pub fix: Option<LintFix>,
 }
 
 impl Display for EvalMessage {
@@ -165,6 +233,7 @@ impl EvalMessage {
             description: format!("{:#}", x),
             full_error_with_span: None,
             original: None,
+            fix: None,
         }
     }
 
@@ -183,6 +252,7 @@ impl EvalMessage {
             description: format!("{:#}", message),
             full_error_with_span: Some(full_error.to_string()),
             original: Some(original),
+            fix: None,
         }
     }
 }
@@ -197,6 +267,7 @@ impl From<Lint> for EvalMessage {
             description: x.problem,
             full_error_with_span: None,
             original: Some(x.original),
+            fix: x.fix,
         }
     }
 }
@@ -19,19 +19,28 @@ use std::collections::HashSet;
 
 use starlark_syntax::syntax::ast::AssignP;
 use starlark_syntax::syntax::ast::AssignTarget;
+use starlark_syntax::syntax::ast::AstAssignIdent;
 use starlark_syntax::syntax::ast::AstExpr;
+use starlark_syntax::syntax::ast::AstNoPayload;
+use starlark_syntax::syntax::ast::AstParameter;
 use starlark_syntax::syntax::ast::AstStmt;
+use starlark_syntax::syntax::ast::ClauseP;
 use starlark_syntax::syntax::ast::DefP;
 use starlark_syntax::syntax::ast::Expr;
+use starlark_syntax::syntax::ast::ForP;
 use starlark_syntax::syntax::ast::Stmt;
 use starlark_syntax::syntax::module::AstModuleFields;
+use starlark_syntax::syntax::top_level_stmts::top_level_stmts;
+use starlark_syntax::syntax::uniplate::Visit;
 use thiserror::Error;
 
 use crate::analysis::types::LintT;
 use crate::analysis::types::LintWarning;
 use crate::analysis::EvalSeverity;
 use crate::codemap::CodeMap;
+use crate::codemap::Span;
 use crate::syntax::AstModule;
+use crate::syntax::Dialect;
 
 #[derive(Error, Debug)]
 pub(crate) enum UnderscoreWarning {
@@ -54,11 +63,120 @@ impl LintWarning for UnderscoreWarning {
     }
 }
 
+impl UnderscoreWarning {
+    fn name(&self) -> &str {
+        match self {
+            UnderscoreWarning::UnderscoreDefinition(x) => x,
+            UnderscoreWarning::UsingIgnored(x) => x,
+        }
+    }
+}
+
 pub(crate) fn lint(module: &AstModule) -> Vec<LintT<UnderscoreWarning>> {
     let mut res = Vec::new();
     inappropriate_underscore(module.codemap(), module.statement(), true, &mut res);
     use_ignored(module.codemap(), module.statement(), &mut res);
-    res
+    res.into_iter().map(|x| fix_rename(module, x)).collect()
+}
+
+/// Fix a warning by dropping the leading underscores from the name everywhere in the top level
+/// statement it is in. Nested names are local to that statement, so this is safe as long as
+/// the new name is not used anywhere else in the module, and the old one is not defined at the
+/// top level.
+fn fix_rename(module: &AstModule, x: LintT<UnderscoreWarning>) -> LintT<UnderscoreWarning> {
+    let name = x.problem.name();
+    let new_name = name.trim_start_matches('_');
+    let mut roots = HashSet::new();
+    root_definitions(module.statement(), &mut roots);
+    let mut clashes = Vec::new();
+    name_spans(Visit::Stmt(module.statement()), new_name, &mut clashes);
+    if roots.contains(name) || !clashes.is_empty() || !is_identifier(new_name) {
+        return x;
+    }
+
+    let Some(stmt) = top_level_stmts(module.statement())
+        .into_iter()
+        .find(|stmt| stmt.span.contains(x.location.span.begin()))
+    else {
+        return x;
+    };
+    let mut spans = Vec::new();
+    name_spans(Visit::Stmt(stmt), name, &mut spans);
+    spans.sort();
+
+    let codemap = module.codemap();
+    let mut replacement = String::new();
+    let mut pos = stmt.span.begin();
+    for span in spans {
+        replacement.push_str(codemap.source_span(Span::new(pos, span.begin())));
+        replacement.push_str(new_name);
+        pos = span.end();
+    }
+    replacement.push_str(codemap.source_span(Span::new(pos, stmt.span.end())));
+    x.with_fix(stmt.span, replacement)
+}
+
+/// Whether `name` parses as an identifier, so is not empty or a keyword.
+fn is_identifier(name: &str) -> bool {
+    let Ok(module) = AstModule::parse("", name.to_owned(), &Dialect::AllOptionsInternal) else {
+        return false;
+    };
+    match top_level_stmts(module.statement()).as_slice() {
+        [stmt] => matches!(&***stmt, Stmt::Expression(x) if matches!(&**x, Expr::Identifier(_))),
+        _ => false,
+    }
+}
+
+/// The spans of every definition and use of `name`.
+fn name_spans(x: Visit<AstNoPayload>, name: &str, res: &mut Vec<Span>) {
+    fn ident(x: &AstAssignIdent, name: &str, res: &mut Vec<Span>) {
+        if x.ident == name {
+            res.push(x.span);
+        }
+    }
+
+    fn params(xs: &[AstParameter], name: &str, res: &mut Vec<Span>) {
+        for x in xs.iter().filter_map(|x| x.ident()) {
+            ident(x, name, res);
+        }
+    }
+
+    match x {
+        Visit::Stmt(x) => match &**x {
+            Stmt::Def(def) => {
+                ident(&def.name, name, res);
+                params(&def.params, name, res);
+            }
+            Stmt::Assign(AssignP { lhs, .. })
+            | Stmt::AssignModify(lhs, _, _)
+            | Stmt::For(ForP { var: lhs, .. }) => lhs.visit_lvalue(|x| ident(x, name, res)),
+            Stmt::Load(load) => {
+                for arg in &load.args {
+                    ident(&arg.local, name, res);
+                }
+            }
+            _ => {}
+        },
+        Visit::Expr(x) => match &**x {
+            Expr::Identifier(x) => {
+                if x.ident == name {
+                    res.push(x.span);
+                }
+            }
+            Expr::Lambda(lambda) => params(&lambda.params, name, res),
+            Expr::ListComprehension(_, for_, clauses)
+            | Expr::DictComprehension(_, for_, clauses) => {
+                for_.var.visit_lvalue(|x| ident(x, name, res));
+                for clause in clauses {
+                    if let ClauseP::For(for_) = clause {
+                        for_.var.visit_lvalue(|x| ident(x, name, res));
+                    }
+                }
+            }
+            _ => {}
+        },
+    }
+    x.visit_children(|x| name_spans(x, name, res));
 }
 
 // There's no reason to make a def or lambda and give it an underscore name not at the top level
@@ -105,28 +223,29 @@ fn inappropriate_underscore(
     }
 }
 
-// Don't want to use a variable that has been defined to be ignored
-fn use_ignored(codemap: &CodeMap, x: &AstStmt, res: &mut Vec<LintT<UnderscoreWarning>>) {
-    // we are ok with using things that were defined at the top level, but not nested
-    fn root_definitions<'a>(x: &'a AstStmt, res: &mut HashSet<&'a str>) {
-        match &**x {
-            Stmt::Assign(AssignP { lhs: x, .. }) | Stmt::AssignModify(x, _, _) => {
-                x.visit_lvalue(|x| {
-                    res.insert(x.ident.as_str());
-                });
-            }
-            Stmt::Def(x) => {
-                res.insert(x.name.ident.as_str());
-            }
-            Stmt::Load(xs) => {
-                for x in &xs.args {
-                    res.insert(x.local.ident.as_str());
-                }
+// The names defined at the top level of a module
+fn root_definitions<'a>(x: &'a AstStmt, res: &mut HashSet<&'a str>) {
+    match &**x {
+        Stmt::Assign(AssignP { lhs: x, .. }) | Stmt::AssignModify(x, _, _) => {
+            x.visit_lvalue(|x| {
+                res.insert(x.ident.as_str());
+            });
+        }
+        Stmt::Def(x) => {
+            res.insert(x.name.ident.as_str());
+        }
+        Stmt::Load(xs) => {
+            for x in &xs.args {
+                res.insert(x.local.ident.as_str());
             }
-            _ => x.visit_stmt(|x| root_definitions(x, res)),
         }
+        _ => x.visit_stmt(|x| root_definitions(x, res)),
     }
+}
 
+// Don't want to use a variable that has been defined to be ignored
+fn use_ignored(codemap: &CodeMap, x: &AstStmt, res: &mut Vec<LintT<UnderscoreWarning>>) {
+    // we are ok with using things that were defined at the top level, but not nested
     fn is_ignored(x: &str) -> bool {
         // we want things like __internal__ for builtin things to expose themselves quietly
         x.starts_with('_') && !(x.starts_with("__") && x.ends_with("__"))
@@ -162,16 +281,7 @@ mod tests {
     use starlark_syntax::slice_vec_ext::SliceExt;
 
     use super::*;
-    use crate::syntax::Dialect;
-
-    impl UnderscoreWarning {
-        fn about(&self) -> &String {
-            match self {
-                UnderscoreWarning::UnderscoreDefinition(x) => x,
-                UnderscoreWarning::UsingIgnored(x) => x,
-            }
-        }
-    }
+    use crate::analysis::LintFix;
 
     fn module(x: &str) -> AstModule {
         AstModule::parse("X", x.to_owned(), &Dialect::AllOptionsInternal).unwrap()
@@ -190,11 +300,56 @@ def _ok():
         );
         let mut res = Vec::new();
         inappropriate_underscore(m.codemap(), m.statement(), true, &mut res);
-        let mut res = res.map(|x| x.problem.about());
+        let mut res = res.map(|x| x.problem.name());
         res.sort();
         assert_eq!(res, &["_no1", "_no2", "_no3"])
     }
 
+    #[test]
+    fn test_lint_underscore_fixes() {
+        let m = module(
+            r#"
+_top = 1
+def f():
+    _x = [y for y in []]
+    print(_x, "_x")
+    def _top(): pass
+    def _g(_a): return _a
+    _g(_x)
+"#,
+        );
+        let res = lint(&m);
+        let fixed = LintFix::apply_all(res.iter().filter_map(|x| x.fix.as_ref()));
+        assert_eq!(
+            fixed.as_deref(),
+            Some(
+                r#"
+_top = 1
+def f():
+    x = [y for y in []]
+    print(x, "_x")
+    def _top(): pass
+    def _g(_a): return _a
+    _g(x)
+"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_lint_underscore_fix_clash() {
+        let m = module(
+            r#"
+def f():
+    _x = [1]
+    _def = [2]
+    return _x + _def
+x = 1
+"#,
+        );
+        assert!(lint(&m).iter().all(|x| x.fix.is_none()));
+    }
+
     #[test]
     fn test_lint_use_ignored() {
         let m = module(
@@ -220,7 +375,7 @@ def bar():
         );
         let mut res = Vec::new();
         use_ignored(m.codemap(), m.statement(), &mut res);
-        let mut res = res.map(|x| x.problem.about());
+        let mut res = res.map(|x| x.problem.name());
         res.sort();
         assert_eq!(res, &["_no1", "_no2", "_no3"])
     }
@@ -22,5 +22,6 @@ pub use starlark_syntax::frame::Frame;
 pub use crate::analysis::EvalMessage;
 pub use crate::analysis::EvalSeverity;
 pub use crate::analysis::Lint;
+pub use crate::analysis::LintFix;
 
 pub(crate) mod did_you_mean;
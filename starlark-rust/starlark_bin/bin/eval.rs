@@ -23,9 +23,12 @@ use std::iter;
 use std::path::Path;
 use std::path::PathBuf;
 
+use dupe::Dupe;
 use itertools::Either;
 use lsp_types::Url;
 use starlark::analysis::AstModuleLint;
+use starlark::analysis::Lint;
+use starlark::analysis::LintFix;
 use starlark::docs::DocModule;
 use starlark::environment::FrozenModule;
 use starlark::environment::Globals;
@@ -41,6 +44,7 @@ use starlark_lsp::server::LspContext;
 use starlark_lsp::server::LspEvalResult;
 use starlark_lsp::server::LspUrl;
 use starlark_lsp::server::StringLiteralResult;
+use walkdir::WalkDir;
 
 use crate::suppression::GlobLintSuppression;
 
@@ -223,6 +227,35 @@ impl Context {
         )
     }
 
+    /// Rewrite `file` in canonical format. Returns whether its contents changed.
+    pub(crate) fn format_file(&self, file: &Path) -> starlark::Result<bool> {
+        let content = fs::read_to_string(file).map_err(anyhow::Error::from)?;
+        let ast = AstModule::parse(&file.to_string_lossy(), content.clone(), &self.dialect)?;
+        let formatted = ast.format();
+        if formatted == content {
+            return Ok(false);
+        }
+        fs::write(file, formatted).map_err(anyhow::Error::from)?;
+        Ok(true)
+    }
+
+    /// Rewrite `file` with the fixes for its lints. Returns whether its contents changed.
+    ///
+    /// Fixes that overlap are skipped, so some problems may need another run to fix.
+    pub(crate) fn fix_file(&self, file: &Path) -> starlark::Result<bool> {
+        let filename = file.to_string_lossy();
+        let content = fs::read_to_string(file).map_err(anyhow::Error::from)?;
+        let ast = AstModule::parse(&filename, content, &self.dialect)?;
+        let lints = self.lints(&filename, &ast);
+        match LintFix::apply_all(lints.iter().filter_map(|lint| lint.fix.as_ref())) {
+            Some(fixed) => {
+                fs::write(file, fixed).map_err(anyhow::Error::from)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
     pub(crate) fn file_with_contents(
         &self,
         filename: &str,
@@ -271,6 +304,10 @@ impl Context {
     }
 
     fn check(&self, file: &str, module: &AstModule) -> impl Iterator<Item = EvalMessage> {
+        self.lints(file, module).into_iter().map(EvalMessage::from)
+    }
+
+    fn lints(&self, file: &str, module: &AstModule) -> Vec<Lint> {
         let globals = if self.prelude.is_empty() {
             None
         } else {
@@ -290,7 +327,7 @@ impl Context {
 
         let mut lints = module.lint(globals.as_ref());
         lints.retain(|issue| !self.is_suppressed(file, &issue.short_name));
-        lints.into_iter().map(EvalMessage::from)
+        lints
     }
 }
 
@@ -382,4 +419,30 @@ impl LspContext for Context {
     fn get_environment(&self, _uri: &LspUrl) -> DocModule {
         DocModule::default()
     }
+
+    fn get_workspace_files(&self, workspace_root: Option<&Path>) -> anyhow::Result<Vec<LspUrl>> {
+        let Some(workspace_root) = workspace_root else {
+            return Ok(Vec::new());
+        };
+        let mut files = Vec::new();
+        for entry in WalkDir::new(workspace_root)
+            .into_iter()
+            // Skip things like `.git`, which can be large and never contain starlark.
+            .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+        {
+            let entry = entry?;
+            let is_starlark = matches!(
+                entry.path().extension().and_then(|x| x.to_str()),
+                Some("bzl" | "star" | "sky")
+            );
+            if entry.file_type().is_file() && is_starlark {
+                files.push(LspUrl::File(entry.into_path()));
+            }
+        }
+        Ok(files)
+    }
+
+    fn get_typecheck_globals(&self, _uri: &LspUrl) -> Option<Globals> {
+        Some(self.globals.dupe())
+    }
 }
@@ -22,6 +22,7 @@
 use std::ffi::OsStr;
 use std::fmt;
 use std::fmt::Display;
+use std::iter;
 use std::path::PathBuf;
 use std::sync::Arc;
 
@@ -63,6 +64,7 @@ struct Args {
             "docs",
             "evaluate",
             "files",
+            "format",
         ],
     )]
     lsp: bool,
@@ -80,6 +82,7 @@ struct Args {
             "prelude",
             "evaluate",
             "files",
+            "format",
         ],
     )]
     dap: bool,
@@ -91,6 +94,13 @@ struct Args {
     )]
     check: bool,
 
+    #[arg(
+        long = "apply-fixes",
+        help = "Rewrite files with the fixes that lints suggest, then check them.",
+        requires = "check"
+    )]
+    apply_fixes: bool,
+
     #[arg(
         long = "json",
         help = "Show output as JSON lines.",
@@ -105,6 +115,13 @@ struct Args {
     )]
     docs: Option<ArgsDoc>,
 
+    #[arg(
+        long = "format",
+        help = "Rewrite files in canonical format, preserving comments.",
+        conflicts_with_all = &["lsp", "dap", "check", "docs", "evaluate"],
+    )]
+    format: bool,
+
     #[arg(
         long = "extension",
         help = "File extension when searching directories."
@@ -343,6 +360,27 @@ fn main() -> anyhow::Result<()> {
                 }
                 ArgsDoc::Code => println!("{}", global_module.render_as_code("globals")),
             };
+        } else if args.format {
+            let mut stats = Stats::default();
+            for file in expand_dirs(ext, args.files.clone()) {
+                stats.increment_file();
+                match ctx.format_file(&file) {
+                    Ok(true) => println!("Reformatted {}", file.display()),
+                    Ok(false) => {}
+                    Err(e) => drain(
+                        iter::once(EvalMessage::from_error(&file, &e)),
+                        args.json,
+                        &mut stats,
+                    )?,
+                }
+            }
+
+            if !args.json {
+                println!("{}", stats);
+                if stats.error > 0 {
+                    return Err(anyhow::anyhow!("Failed with {} errors", stats.error));
+                }
+            }
         } else if is_interactive {
             interactive(&ctx)?;
         } else {
@@ -354,6 +392,14 @@ fn main() -> anyhow::Result<()> {
 
             for file in expand_dirs(ext, args.files.clone()) {
                 stats.increment_file();
+                if args.apply_fixes {
+                    // Any error is reported when the file is checked.
+                    if let Ok(true) = ctx.fix_file(&file) {
+                        if !args.json {
+                            println!("Fixed {}", file.display());
+                        }
+                    }
+                }
                 drain(ctx.file(&file).messages, args.json, &mut stats)?;
             }
 
@@ -15,8 +15,16 @@
  * limitations under the License.
  */
 
+use std::collections::HashMap;
+
+use lsp_types::CodeAction;
+use lsp_types::CodeActionKind;
+use lsp_types::Diagnostic;
 use lsp_types::NumberOrString;
 use lsp_types::Range;
+use lsp_types::TextEdit;
+use lsp_types::Url;
+use lsp_types::WorkspaceEdit;
 use starlark::analysis::EvalMessage;
 use starlark::analysis::EvalSeverity;
 
@@ -25,7 +33,7 @@ pub fn eval_message_to_lsp_diagnostic(eval_message: EvalMessage) -> lsp_types::D
         Some(s) => s.into(),
         _ => Range::default(),
     };
-    lsp_types::Diagnostic::new(
+    let mut diagnostic = lsp_types::Diagnostic::new(
         range,
         Some(eval_severity_to_lsp_diagnostic_severity(
             eval_message.severity,
@@ -35,7 +43,32 @@ pub fn eval_message_to_lsp_diagnostic(eval_message: EvalMessage) -> lsp_types::D
         eval_message.description,
         None,
         None,
-    )
+    );
+    // Clients send diagnostics back when asking for code actions, so keep the fix with it.
+    diagnostic.data = eval_message.fix.and_then(|fix| {
+        serde_json::to_value(TextEdit::new(
+            fix.location.resolve_span().into(),
+            fix.replacement,
+        ))
+        .ok()
+    });
+    diagnostic
+}
+
+/// The quick fix for a diagnostic from [`eval_message_to_lsp_diagnostic`], if it has one.
+pub(crate) fn diagnostic_quick_fix(uri: &Url, diagnostic: &Diagnostic) -> Option<CodeAction> {
+    let edit: TextEdit = serde_json::from_value(diagnostic.data.clone()?).ok()?;
+    Some(CodeAction {
+        title: format!("Fix: {}", diagnostic.message),
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diagnostic.clone()]),
+        edit: Some(WorkspaceEdit::new(HashMap::from([(
+            uri.clone(),
+            vec![edit],
+        )]))),
+        is_preferred: Some(true),
+        ..CodeAction::default()
+    })
 }
 
 fn eval_severity_to_lsp_diagnostic_severity(
@@ -29,7 +29,9 @@ pub mod error;
 mod exported;
 pub(crate) mod inspect;
 pub(crate) mod loaded;
+mod references;
 pub mod server;
+mod signature;
 mod symbols;
 #[cfg(test)]
 mod test;
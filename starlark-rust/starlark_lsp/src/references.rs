@@ -0,0 +1,721 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Implementations of find references and rename, and related types.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+
+use lsp_types::Location;
+use lsp_types::MessageType;
+use lsp_types::PrepareRenameResponse;
+use lsp_types::TextEdit;
+use lsp_types::Url;
+use lsp_types::WorkspaceEdit;
+use starlark::codemap::CodeMap;
+use starlark::codemap::Pos;
+use starlark::codemap::Span;
+use starlark_syntax::lexer::lex_exactly_one_identifier;
+use starlark_syntax::syntax::ast::StmtP;
+use starlark_syntax::syntax::module::AstModuleFields;
+use starlark_syntax::syntax::top_level_stmts::top_level_stmts;
+
+use crate::bind::scope;
+use crate::bind::Assigner;
+use crate::bind::Bind;
+use crate::bind::Scope;
+use crate::definition::LspModule;
+use crate::server::Backend;
+use crate::server::LspContext;
+use crate::server::LspUrl;
+
+/// Errors when renaming a symbol.
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum RenameError {
+    /// The new name is not a valid Starlark identifier.
+    #[error("`{0}` is not a valid identifier")]
+    InvalidName(String),
+    /// The symbol is not defined in any Starlark file, so cannot be renamed.
+    #[error("`{0}` is a global symbol and cannot be renamed")]
+    GlobalSymbol(String),
+    /// There was no symbol at the requested position.
+    #[error("No symbol to rename at the given position")]
+    NoSymbol,
+}
+
+/// How a reference to a symbol is spelled in the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ReferenceKind {
+    /// A plain identifier, e.g. `foo` in `foo()`.
+    Identifier,
+    /// The symbol name inside a string in a `load()` statement, e.g. `"foo"` in
+    /// `load(":a.bzl", "foo")`. The span includes the quotes.
+    LoadString,
+    /// A usage through a local alias, e.g. `bar` after `load(":a.bzl", bar = "foo")`.
+    /// These refer to the symbol, but are not changed when the symbol is renamed.
+    Alias,
+}
+
+/// A single place in a module that refers to a symbol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Reference {
+    pub(crate) span: Span,
+    pub(crate) kind: ReferenceKind,
+    /// Whether this is the place where the symbol is defined.
+    pub(crate) declaration: bool,
+}
+
+/// The symbol found at a position in a module. Used as the starting point when
+/// looking for references.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ReferencedSymbol {
+    /// A variable bound in the current module. `binding` is the first assignment to
+    /// that name in its scope, which uniquely identifies the variable.
+    Local {
+        name: String,
+        binding: Span,
+        top_level: bool,
+    },
+    /// A symbol loaded from another module. `path` is the unresolved path in the
+    /// `load()` statement, and `name` the name of the symbol in that module.
+    Loaded { path: String, name: String },
+    /// A symbol that is not bound anywhere in the current module, most likely a global.
+    Global { name: String },
+}
+
+/// The references to a symbol within a single module.
+pub(crate) type ModuleReferences = (LspUrl, Arc<LspModule>, Vec<Reference>);
+
+/// A single use of an identifier, along with the binding it resolves to, if any.
+struct Occurrence<'a> {
+    name: &'a str,
+    span: Span,
+    binding: Option<&'a (Assigner, Span)>,
+    /// Whether the binding was found in the top level scope of the module.
+    top_level: bool,
+}
+
+/// Walk all scopes in `scope`, resolving every identifier against the innermost scope
+/// that binds it.
+fn collect_occurrences<'a>(
+    scope: &'a Scope,
+    parents: &mut Vec<&'a Scope>,
+    res: &mut Vec<Occurrence<'a>>,
+) {
+    fn resolve<'a>(
+        scope: &'a Scope,
+        parents: &[&'a Scope],
+        name: &str,
+    ) -> (Option<&'a (Assigner, Span)>, bool) {
+        iter_scopes(scope, parents)
+            .enumerate()
+            .find_map(|(depth, s)| {
+                s.bound
+                    .get(name)
+                    .map(|binding| (Some(binding), depth == parents.len()))
+            })
+            .unwrap_or((None, false))
+    }
+
+    fn iter_scopes<'a, 'b>(
+        scope: &'a Scope,
+        parents: &'b [&'a Scope],
+    ) -> impl Iterator<Item = &'a Scope> + 'b {
+        std::iter::once(scope).chain(parents.iter().rev().copied())
+    }
+
+    for bind in &scope.inner {
+        let (name, span) = match bind {
+            Bind::Set(_, x) => (x.ident.as_str(), x.span),
+            Bind::Get(x) => (x.ident.as_str(), x.span),
+            Bind::GetDotted(x) => (x.variable.ident.as_str(), x.variable.span),
+            Bind::Scope(inner) => {
+                parents.push(scope);
+                collect_occurrences(inner, parents, res);
+                parents.pop();
+                continue;
+            }
+            Bind::Flow => continue,
+        };
+        let (binding, top_level) = resolve(scope, parents, name);
+        res.push(Occurrence {
+            name,
+            span,
+            binding,
+            top_level,
+        });
+    }
+}
+
+/// For a string literal span (including its quotes), get the span of its contents.
+pub(crate) fn string_contents_span(codemap: &CodeMap, span: Span) -> Span {
+    let text = codemap.source_span(span);
+    let quote_len = if text.starts_with("\"\"\"") || text.starts_with("'''") {
+        3
+    } else {
+        1
+    };
+    if text.len() < 2 * quote_len {
+        return span;
+    }
+    let quote_len = quote_len as u32;
+    Span::new(span.begin() + quote_len, span.end() - quote_len)
+}
+
+impl LspModule {
+    /// Convert a zero based line and column into a position in this module.
+    pub(crate) fn position(&self, line: u32, col: u32) -> Option<Pos> {
+        let line_span = self.ast.codemap().line_span_opt(line as usize)?;
+        Some(std::cmp::min(line_span.begin() + col, line_span.end()))
+    }
+
+    /// Find the symbol at the given zero based line and column, if there is one.
+    ///
+    /// Unlike [`LspModule::find_definition_at_location`], this also finds symbols at the
+    /// place they are assigned, e.g. the name of a `def`.
+    pub(crate) fn find_symbol_at_location(
+        &self,
+        line: u32,
+        col: u32,
+    ) -> Option<(ReferencedSymbol, Span)> {
+        let pos = self.position(line, col)?;
+        let scope = scope(&self.ast);
+        let mut occurrences = Vec::new();
+        collect_occurrences(&scope, &mut Vec::new(), &mut occurrences);
+
+        if let Some(occurrence) = occurrences.iter().find(|o| o.span.contains(pos)) {
+            let symbol = match occurrence.binding {
+                None => ReferencedSymbol::Global {
+                    name: occurrence.name.to_owned(),
+                },
+                // `load("foo.star", "x")` binds `x` at the same span as the loaded name,
+                // so references are to the symbol in the other module.
+                Some((Assigner::Load { path, name }, span)) if name.span == *span => {
+                    ReferencedSymbol::Loaded {
+                        path: path.node.clone(),
+                        name: name.node.clone(),
+                    }
+                }
+                Some((_, span)) => ReferencedSymbol::Local {
+                    name: occurrence.name.to_owned(),
+                    binding: *span,
+                    top_level: occurrence.top_level,
+                },
+            };
+            return Some((symbol, occurrence.span));
+        }
+
+        // The loaded name in `load("foo.star", y = "x")` is not a binding, so check for it
+        // separately.
+        top_level_stmts(self.ast.statement())
+            .into_iter()
+            .filter_map(|x| match &x.node {
+                StmtP::Load(load) => Some(load),
+                _ => None,
+            })
+            .find_map(|load| {
+                load.args
+                    .iter()
+                    .find(|arg| arg.their.span.contains(pos))
+                    .map(|arg| {
+                        (
+                            ReferencedSymbol::Loaded {
+                                path: load.module.node.clone(),
+                                name: arg.their.node.clone(),
+                            },
+                            arg.their.span,
+                        )
+                    })
+            })
+    }
+
+    /// Find all references to the variable first bound at `binding`.
+    pub(crate) fn find_local_references(&self, binding: Span) -> Vec<Reference> {
+        let scope = scope(&self.ast);
+        let mut occurrences = Vec::new();
+        collect_occurrences(&scope, &mut Vec::new(), &mut occurrences);
+        // `x += 1` both reads and assigns `x` at the same span, only report it once.
+        let mut seen = HashSet::new();
+        occurrences
+            .into_iter()
+            .filter(|o| seen.insert(o.span))
+            .filter_map(|o| match o.binding {
+                Some((assigner, span)) if *span == binding => {
+                    let kind = match assigner {
+                        Assigner::Load { name, .. } if name.span == o.span => {
+                            ReferenceKind::LoadString
+                        }
+                        _ => ReferenceKind::Identifier,
+                    };
+                    Some(Reference {
+                        span: o.span,
+                        kind,
+                        declaration: o.span == binding,
+                    })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Find all references to a top level symbol in this module, e.g. so that
+    /// uses of an exported function can be found alongside the uses in other modules.
+    pub(crate) fn find_top_level_references(&self, name: &str) -> Vec<Reference> {
+        match scope(&self.ast).bound.get(name) {
+            Some((_, binding)) => self.find_local_references(*binding),
+            None => Vec::new(),
+        }
+    }
+
+    /// Find all references to `name` which is loaded from a module that `is_module`
+    /// returns `true` for. `is_module` is given the unresolved path from the `load()`.
+    pub(crate) fn find_loaded_references(
+        &self,
+        name: &str,
+        is_module: impl Fn(&str) -> bool,
+    ) -> Vec<Reference> {
+        let mut res = Vec::new();
+        for load in top_level_stmts(self.ast.statement())
+            .into_iter()
+            .filter_map(|x| match &x.node {
+                StmtP::Load(load) => Some(load),
+                _ => None,
+            })
+        {
+            let args: Vec<_> = load
+                .args
+                .iter()
+                .filter(|arg| arg.their.node == name)
+                .collect();
+            if args.is_empty() || !is_module(&load.module.node) {
+                continue;
+            }
+            for arg in args {
+                let local_references = self.find_local_references(arg.local.span);
+                if arg.local.span == arg.their.span {
+                    res.extend(local_references.into_iter().map(|r| Reference {
+                        declaration: false,
+                        ..r
+                    }));
+                } else {
+                    res.push(Reference {
+                        span: arg.their.span,
+                        kind: ReferenceKind::LoadString,
+                        declaration: false,
+                    });
+                    res.extend(local_references.into_iter().map(|r| Reference {
+                        span: r.span,
+                        kind: ReferenceKind::Alias,
+                        declaration: false,
+                    }));
+                }
+            }
+        }
+        res
+    }
+
+    /// Find all references to a symbol that is not bound in this module.
+    pub(crate) fn find_global_references(&self, name: &str) -> Vec<Reference> {
+        let scope = scope(&self.ast);
+        let mut occurrences = Vec::new();
+        collect_occurrences(&scope, &mut Vec::new(), &mut occurrences);
+        occurrences
+            .into_iter()
+            .filter(|o| o.binding.is_none() && o.name == name)
+            .map(|o| Reference {
+                span: o.span,
+                kind: ReferenceKind::Identifier,
+                declaration: false,
+            })
+            .collect()
+    }
+}
+
+impl<T: LspContext> Backend<T> {
+    /// Find every reference to the symbol at the given position, grouped by the module
+    /// they are in.
+    ///
+    /// Symbols that are visible to other modules are looked for in all open documents,
+    /// and in whatever files [`LspContext::get_workspace_files`] returns.
+    pub(crate) fn find_references(
+        &self,
+        uri: &LspUrl,
+        line: u32,
+        character: u32,
+        workspace_root: Option<&Path>,
+    ) -> anyhow::Result<Option<(ReferencedSymbol, Vec<ModuleReferences>)>> {
+        let Some(module) = self.get_ast_or_load_from_disk(uri)? else {
+            return Ok(None);
+        };
+        let Some((symbol, _)) = module.find_symbol_at_location(line, character) else {
+            return Ok(None);
+        };
+        let references = match &symbol {
+            ReferencedSymbol::Local {
+                name,
+                binding,
+                top_level,
+            } => {
+                if *top_level && !name.starts_with('_') {
+                    self.find_exported_symbol_references(uri, name, workspace_root)?
+                } else {
+                    let references = module.find_local_references(*binding);
+                    vec![(uri.clone(), module, references)]
+                }
+            }
+            ReferencedSymbol::Loaded { path, name } => {
+                let loaded_uri = self.resolve_load_path(path, uri, workspace_root)?;
+                self.find_exported_symbol_references(&loaded_uri, name, workspace_root)?
+            }
+            ReferencedSymbol::Global { name } => {
+                let references = module.find_global_references(name);
+                vec![(uri.clone(), module, references)]
+            }
+        };
+        Ok(Some((symbol, references)))
+    }
+
+    /// Find the references to a symbol named `name` defined at the top level of the
+    /// module at `defining_uri`, both in that module and in every module that loads it.
+    fn find_exported_symbol_references(
+        &self,
+        defining_uri: &LspUrl,
+        name: &str,
+        workspace_root: Option<&Path>,
+    ) -> anyhow::Result<Vec<ModuleReferences>> {
+        let mut res = Vec::new();
+        if let Some(module) = self.get_ast_or_load_from_disk(defining_uri)? {
+            let references = module.find_top_level_references(name);
+            if !references.is_empty() {
+                res.push((defining_uri.clone(), module, references));
+            }
+        }
+
+        for candidate in self.workspace_documents(workspace_root) {
+            if &candidate == defining_uri {
+                continue;
+            }
+            // Files elsewhere in the workspace may well not parse, that shouldn't stop
+            // us finding references in all of the others.
+            let Ok(Some(module)) = self.get_ast_or_load_from_disk(&candidate) else {
+                continue;
+            };
+            let references = module.find_loaded_references(name, |path| {
+                self.context
+                    .resolve_load(path, &candidate, workspace_root)
+                    .is_ok_and(|loaded| &loaded == defining_uri)
+            });
+            if !references.is_empty() {
+                res.push((candidate, module, references));
+            }
+        }
+        Ok(res)
+    }
+
+    /// All of the documents that might refer to symbols in other documents: those open
+    /// in the editor, and those the context knows about in the workspace.
+    fn workspace_documents(&self, workspace_root: Option<&Path>) -> Vec<LspUrl> {
+        let mut seen = HashSet::new();
+        let open: Vec<_> = self
+            .last_valid_parse
+            .read()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        let workspace = match self.context.get_workspace_files(workspace_root) {
+            Ok(files) => files,
+            Err(e) => {
+                // The open documents are still worth searching.
+                self.log_message(
+                    MessageType::WARNING,
+                    &format!("Error listing workspace files: {:#}", e),
+                );
+                Vec::new()
+            }
+        };
+        open.into_iter()
+            .chain(workspace)
+            .filter(|uri| seen.insert(uri.clone()))
+            .collect()
+    }
+
+    /// Get the locations of every reference to the symbol at the given position.
+    pub(crate) fn reference_locations(
+        &self,
+        uri: &LspUrl,
+        line: u32,
+        character: u32,
+        include_declaration: bool,
+        workspace_root: Option<&Path>,
+    ) -> anyhow::Result<Vec<Location>> {
+        let Some((_, references)) = self.find_references(uri, line, character, workspace_root)?
+        else {
+            return Ok(Vec::new());
+        };
+        let mut locations = Vec::new();
+        for (uri, module, references) in references {
+            let url = Url::try_from(&uri)?;
+            for reference in references {
+                if reference.declaration && !include_declaration {
+                    continue;
+                }
+                locations.push(Location::new(
+                    url.clone(),
+                    module.ast.codemap().resolve_span(reference.span).into(),
+                ));
+            }
+        }
+        Ok(locations)
+    }
+
+    /// Check whether the symbol at the given position can be renamed, and if so, the
+    /// range of text that will be replaced.
+    pub(crate) fn prepare_rename_range(
+        &self,
+        uri: &LspUrl,
+        line: u32,
+        character: u32,
+    ) -> anyhow::Result<Option<PrepareRenameResponse>> {
+        let Some(module) = self.get_ast_or_load_from_disk(uri)? else {
+            return Ok(None);
+        };
+        let codemap = module.ast.codemap();
+        Ok(match module.find_symbol_at_location(line, character) {
+            None | Some((ReferencedSymbol::Global { .. }, _)) => None,
+            Some((_, span)) => {
+                // Only the name inside of a string in a `load()` statement is replaced.
+                let span = if codemap.source_span(span).starts_with(['"', '\'']) {
+                    string_contents_span(codemap, span)
+                } else {
+                    span
+                };
+                Some(PrepareRenameResponse::Range(
+                    codemap.resolve_span(span).into(),
+                ))
+            }
+        })
+    }
+
+    /// Compute the edits needed to rename the symbol at the given position to `new_name`
+    /// in every module that refers to it.
+    pub(crate) fn rename_edits(
+        &self,
+        uri: &LspUrl,
+        line: u32,
+        character: u32,
+        new_name: &str,
+        workspace_root: Option<&Path>,
+    ) -> anyhow::Result<WorkspaceEdit> {
+        if lex_exactly_one_identifier(new_name).as_deref() != Some(new_name) {
+            return Err(RenameError::InvalidName(new_name.to_owned()).into());
+        }
+        let (symbol, references) = self
+            .find_references(uri, line, character, workspace_root)?
+            .ok_or(RenameError::NoSymbol)?;
+        if let ReferencedSymbol::Global { name } = symbol {
+            return Err(RenameError::GlobalSymbol(name).into());
+        }
+
+        let mut changes = HashMap::new();
+        for (uri, module, references) in references {
+            let codemap = module.ast.codemap();
+            let edits: Vec<_> = references
+                .into_iter()
+                .filter_map(|reference| {
+                    let span = match reference.kind {
+                        ReferenceKind::Identifier => reference.span,
+                        ReferenceKind::LoadString => string_contents_span(codemap, reference.span),
+                        ReferenceKind::Alias => return None,
+                    };
+                    Some(TextEdit::new(
+                        codemap.resolve_span(span).into(),
+                        new_name.to_owned(),
+                    ))
+                })
+                .collect();
+            if !edits.is_empty() {
+                changes.insert(Url::try_from(&uri)?, edits);
+            }
+        }
+        Ok(WorkspaceEdit::new(changes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use textwrap::dedent;
+
+    use super::*;
+    use crate::definition::helpers::FixtureWithRanges;
+
+    fn resolved_references(
+        module: &LspModule,
+        references: Vec<Reference>,
+    ) -> Vec<(String, ReferenceKind)> {
+        references
+            .into_iter()
+            .map(|r| (module.ast.codemap().source_span(r.span).to_owned(), r.kind))
+            .collect()
+    }
+
+    #[test]
+    fn finds_symbol_at_assignment_and_use() -> starlark::Result<()> {
+        let contents = dedent(
+            r#"
+            load("bar.star", "<loaded>loaded</loaded>", alias = "<their>their</their>")
+
+            <x>x</x> = 1
+
+            def <f>f</f>(<p>p</p>):
+                return <x_use>x</x_use> + <p_use>p</p_use> + <global>len</global>(<alias>alias</alias>)
+            "#,
+        )
+        .trim()
+        .to_owned();
+        let parsed = FixtureWithRanges::from_fixture("foo.star", &contents)?;
+        let module = parsed.module()?;
+
+        let symbol = |id: &str| {
+            module
+                .find_symbol_at_location(parsed.begin_line(id), parsed.begin_column(id))
+                .map(|(symbol, _)| symbol)
+        };
+
+        let x = symbol("x").unwrap();
+        assert!(matches!(&x, ReferencedSymbol::Local { name, top_level: true, .. } if name == "x"));
+        assert_eq!(Some(x), symbol("x_use"));
+        assert!(matches!(
+            symbol("f"),
+            Some(ReferencedSymbol::Local {
+                top_level: true,
+                ..
+            })
+        ));
+        let p = symbol("p").unwrap();
+        assert!(matches!(
+            &p,
+            ReferencedSymbol::Local {
+                top_level: false,
+                ..
+            }
+        ));
+        assert_eq!(Some(p), symbol("p_use"));
+        assert_eq!(
+            Some(ReferencedSymbol::Global {
+                name: "len".to_owned()
+            }),
+            symbol("global")
+        );
+        assert_eq!(
+            Some(ReferencedSymbol::Loaded {
+                path: "bar.star".to_owned(),
+                name: "loaded".to_owned()
+            }),
+            symbol("loaded")
+        );
+        assert_eq!(
+            Some(ReferencedSymbol::Loaded {
+                path: "bar.star".to_owned(),
+                name: "their".to_owned()
+            }),
+            symbol("their")
+        );
+        assert!(matches!(
+            symbol("alias"),
+            Some(ReferencedSymbol::Local { name, .. }) if name == "alias"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn finds_local_references_respecting_scopes() -> starlark::Result<()> {
+        let contents = dedent(
+            r#"
+            <x>x</x> = 1
+
+            def f(x):
+                return x
+
+            def g():
+                return <x_use>x</x_use> + [x for x in []][0]
+
+            <x_set>x</x_set> += 1
+            "#,
+        )
+        .trim()
+        .to_owned();
+        let parsed = FixtureWithRanges::from_fixture("foo.star", &contents)?;
+        let module = parsed.module()?;
+
+        let Some((ReferencedSymbol::Local { binding, .. }, _)) =
+            module.find_symbol_at_location(parsed.begin_line("x"), parsed.begin_column("x"))
+        else {
+            panic!("expected a local symbol");
+        };
+        let references = module.find_local_references(binding);
+        let spans: Vec<_> = references
+            .iter()
+            .map(|r| module.ast.codemap().resolve_span(r.span))
+            .collect();
+        assert_eq!(
+            vec![
+                parsed.resolved_span("x"),
+                parsed.resolved_span("x_use"),
+                parsed.resolved_span("x_set"),
+            ],
+            spans
+        );
+        assert!(references[0].declaration);
+        assert!(!references[1].declaration);
+        Ok(())
+    }
+
+    #[test]
+    fn finds_loaded_references() -> starlark::Result<()> {
+        let contents = dedent(
+            r#"
+            load("bar.star", "foo")
+            load("baz.star", other = "foo")
+            load("bar.star", aliased = "foo")
+
+            foo()
+            aliased()
+            other()
+            "#,
+        )
+        .trim()
+        .to_owned();
+        let parsed = FixtureWithRanges::from_fixture("foo.star", &contents)?;
+        let module = parsed.module()?;
+
+        let references = module.find_loaded_references("foo", |path| path == "bar.star");
+        assert_eq!(
+            vec![
+                ("\"foo\"".to_owned(), ReferenceKind::LoadString),
+                ("foo".to_owned(), ReferenceKind::Identifier),
+                ("\"foo\"".to_owned(), ReferenceKind::LoadString),
+                ("aliased".to_owned(), ReferenceKind::Alias),
+                ("aliased".to_owned(), ReferenceKind::Alias),
+            ],
+            resolved_references(&module, references)
+        );
+        Ok(())
+    }
+}
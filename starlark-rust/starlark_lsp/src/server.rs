@@ -42,9 +42,22 @@ use lsp_types::notification::DidCloseTextDocument;
 use lsp_types::notification::DidOpenTextDocument;
 use lsp_types::notification::LogMessage;
 use lsp_types::notification::PublishDiagnostics;
+use lsp_types::request::CodeActionRequest;
 use lsp_types::request::Completion;
+use lsp_types::request::DocumentSymbolRequest;
+use lsp_types::request::Formatting;
 use lsp_types::request::GotoDefinition;
 use lsp_types::request::HoverRequest;
+use lsp_types::request::PrepareRenameRequest;
+use lsp_types::request::References;
+use lsp_types::request::Rename;
+use lsp_types::request::SignatureHelpRequest;
+use lsp_types::CodeActionKind;
+use lsp_types::CodeActionOptions;
+use lsp_types::CodeActionOrCommand;
+use lsp_types::CodeActionParams;
+use lsp_types::CodeActionProviderCapability;
+use lsp_types::CodeActionResponse;
 use lsp_types::CompletionItem;
 use lsp_types::CompletionItemKind;
 use lsp_types::CompletionOptions;
@@ -55,6 +68,9 @@ use lsp_types::Diagnostic;
 use lsp_types::DidChangeTextDocumentParams;
 use lsp_types::DidCloseTextDocumentParams;
 use lsp_types::DidOpenTextDocumentParams;
+use lsp_types::DocumentFormattingParams;
+use lsp_types::DocumentSymbolParams;
+use lsp_types::DocumentSymbolResponse;
 use lsp_types::Documentation;
 use lsp_types::GotoDefinitionParams;
 use lsp_types::GotoDefinitionResponse;
@@ -64,6 +80,7 @@ use lsp_types::HoverParams;
 use lsp_types::HoverProviderCapability;
 use lsp_types::InitializeParams;
 use lsp_types::LanguageString;
+use lsp_types::Location;
 use lsp_types::LocationLink;
 use lsp_types::LogMessageParams;
 use lsp_types::MarkedString;
@@ -72,14 +89,23 @@ use lsp_types::MarkupKind;
 use lsp_types::MessageType;
 use lsp_types::OneOf;
 use lsp_types::Position;
+use lsp_types::PrepareRenameResponse;
 use lsp_types::PublishDiagnosticsParams;
 use lsp_types::Range;
+use lsp_types::ReferenceParams;
+use lsp_types::RenameOptions;
+use lsp_types::RenameParams;
 use lsp_types::ServerCapabilities;
+use lsp_types::SignatureHelp;
+use lsp_types::SignatureHelpOptions;
+use lsp_types::SignatureHelpParams;
+use lsp_types::TextDocumentPositionParams;
 use lsp_types::TextDocumentSyncCapability;
 use lsp_types::TextDocumentSyncKind;
 use lsp_types::TextEdit;
 use lsp_types::Url;
 use lsp_types::WorkDoneProgressOptions;
+use lsp_types::WorkspaceEdit;
 use lsp_types::WorkspaceFolder;
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
@@ -93,7 +119,12 @@ use starlark::docs::markdown::render_doc_param;
 use starlark::docs::DocItem;
 use starlark::docs::DocMember;
 use starlark::docs::DocModule;
+use starlark::environment::Globals;
+use starlark::errors::EvalMessage;
 use starlark::syntax::AstModule;
+use starlark::typing::AstModuleTypecheck;
+use starlark::typing::Interface;
+use starlark::ErrorKind;
 use starlark_syntax::codemap::ResolvedPos;
 use starlark_syntax::syntax::ast::AstPayload;
 use starlark_syntax::syntax::ast::LoadArgP;
@@ -105,8 +136,11 @@ use crate::definition::Definition;
 use crate::definition::DottedDefinition;
 use crate::definition::IdentifierDefinition;
 use crate::definition::LspModule;
+use crate::error::diagnostic_quick_fix;
+use crate::error::eval_message_to_lsp_diagnostic;
 use crate::inspect::AstModuleInspect;
 use crate::inspect::AutocompleteType;
+use crate::symbols::find_document_symbols;
 use crate::symbols::find_symbols_at_location;
 
 /// The request to get the file contents for a starlark: URI
@@ -372,6 +406,30 @@ pub trait LspContext {
         let _unused = (document_uri, kind, current_value, workspace_root);
         Ok(Vec::new())
     }
+
+    /// Get the starlark files in the workspace, whether or not they are open.
+    ///
+    /// These are searched when finding references to, or renaming, a symbol that
+    /// other files may load. By default no files are returned, which limits those
+    /// searches to the files that are currently open.
+    fn get_workspace_files(&self, workspace_root: Option<&Path>) -> anyhow::Result<Vec<LspUrl>> {
+        let _unused = workspace_root;
+        Ok(Vec::new())
+    }
+
+    /// Get the globals that a file should be typechecked against.
+    ///
+    /// If this returns `Some`, the file is typechecked every time it changes, and type
+    /// errors are published along with the diagnostics from
+    /// [`LspContext::parse_file_with_contents`]. Symbols from `load()` statements are
+    /// typed from the loaded module, which is typechecked against its own globals but
+    /// without following its loads. Undefined names are not reported, as those should
+    /// come from lints that know every name in scope. By default files are not
+    /// typechecked.
+    fn get_typecheck_globals(&self, uri: &LspUrl) -> Option<Globals> {
+        let _unused = uri;
+        None
+    }
 }
 
 /// Errors when [`LspContext::resolve_load()`] cannot resolve a given path.
@@ -390,12 +448,23 @@ pub(crate) enum LoadContentsError {
     WrongScheme(String, LspUrl),
 }
 
+/// Errors when formatting a document.
+#[derive(thiserror::Error, Debug)]
+enum FormattingError {
+    /// The current contents of the document do not parse, so there is no AST to format.
+    #[error("Cannot format `{}` as it does not parse", .0)]
+    DoesNotParse(LspUrl),
+}
+
 pub(crate) struct Backend<T: LspContext> {
     connection: Connection,
     pub(crate) context: T,
     /// The `AstModule` from the last time that a file was opened / changed and parsed successfully.
     /// Entries are evicted when the file is closed.
     pub(crate) last_valid_parse: RwLock<HashMap<LspUrl, Arc<LspModule>>>,
+    /// The latest contents of each open file, whether or not they parsed.
+    /// Entries are evicted when the file is closed.
+    open_documents: RwLock<HashMap<LspUrl, String>>,
 }
 
 /// The logic implementations of stuff
@@ -413,6 +482,26 @@ impl<T: LspContext> Backend<T> {
             definition_provider,
             completion_provider: Some(CompletionOptions::default()),
             hover_provider: Some(HoverProviderCapability::Simple(true)),
+            references_provider: Some(OneOf::Left(true)),
+            rename_provider: Some(OneOf::Right(RenameOptions {
+                prepare_provider: Some(true),
+                work_done_progress_options: WorkDoneProgressOptions {
+                    work_done_progress: None,
+                },
+            })),
+            signature_help_provider: Some(SignatureHelpOptions {
+                trigger_characters: Some(vec!["(".to_owned(), ",".to_owned()]),
+                retrigger_characters: None,
+                work_done_progress_options: WorkDoneProgressOptions {
+                    work_done_progress: None,
+                },
+            }),
+            document_symbol_provider: Some(OneOf::Left(true)),
+            document_formatting_provider: Some(OneOf::Left(true)),
+            code_action_provider: Some(CodeActionProviderCapability::Options(CodeActionOptions {
+                code_action_kinds: Some(vec![CodeActionKind::QUICKFIX]),
+                ..CodeActionOptions::default()
+            })),
             ..ServerCapabilities::default()
         }
     }
@@ -436,40 +525,111 @@ impl<T: LspContext> Backend<T> {
         Ok(module)
     }
 
-    fn validate(&self, uri: Url, version: Option<i64>, text: String) -> anyhow::Result<()> {
-        let lsp_url = uri.clone().try_into()?;
+    fn validate(
+        &self,
+        uri: Url,
+        version: Option<i64>,
+        text: String,
+        initialize_params: &InitializeParams,
+    ) -> anyhow::Result<()> {
+        let lsp_url: LspUrl = uri.clone().try_into()?;
+        self.open_documents
+            .write()
+            .unwrap()
+            .insert(lsp_url.clone(), text.clone());
         let eval_result = self.context.parse_file_with_contents(&lsp_url, text);
+        let mut diagnostics = eval_result.diagnostics;
         if let Some(ast) = eval_result.ast {
+            let workspace_root =
+                Self::get_workspace_root(initialize_params.workspace_folders.as_ref(), &lsp_url);
+            diagnostics.extend(self.typecheck(&lsp_url, &ast, workspace_root.as_deref()));
             let module = Arc::new(LspModule::new(ast));
             let mut last_valid_parse = self.last_valid_parse.write().unwrap();
             last_valid_parse.insert(lsp_url, module);
         }
-        self.publish_diagnostics(uri, eval_result.diagnostics, version);
+        self.publish_diagnostics(uri, diagnostics, version);
         Ok(())
     }
 
-    fn did_open(&self, params: DidOpenTextDocumentParams) -> anyhow::Result<()> {
+    /// Typecheck a module against the globals from the context, if it provides any.
+    fn typecheck(
+        &self,
+        uri: &LspUrl,
+        ast: &AstModule,
+        workspace_root: Option<&Path>,
+    ) -> Vec<Diagnostic> {
+        let Some(globals) = self.context.get_typecheck_globals(uri) else {
+            return Vec::new();
+        };
+        let loads = self.loaded_interfaces(uri, ast, workspace_root);
+        let (errors, _, _, _) = ast.clone().typecheck(&globals, &loads);
+        errors
+            .iter()
+            // Unresolved names may well be defined by the context in ways that are not
+            // visible in `globals`, e.g. by an implicitly loaded prelude.
+            .filter(|e| !matches!(e.kind(), ErrorKind::Scope(_)))
+            .map(|e| eval_message_to_lsp_diagnostic(EvalMessage::from_error(uri.path(), e)))
+            .collect()
+    }
+
+    /// The types exported by the modules that `ast` loads, keyed by load path.
+    ///
+    /// Loaded modules are typechecked without their own loads, so anything they
+    /// re-export from a `load()` is untyped. Modules that cannot be resolved, parsed or
+    /// typechecked are left out, and their symbols are untyped as well.
+    fn loaded_interfaces(
+        &self,
+        uri: &LspUrl,
+        ast: &AstModule,
+        workspace_root: Option<&Path>,
+    ) -> HashMap<String, Interface> {
+        ast.loads()
+            .into_iter()
+            .filter_map(|load| {
+                let load_uri = self
+                    .resolve_load_path(load.module_id, uri, workspace_root)
+                    .ok()?;
+                let globals = self.context.get_typecheck_globals(&load_uri)?;
+                let module = self.get_ast_or_load_from_disk(&load_uri).ok()??;
+                let (_, _, interface, _) = module.ast.clone().typecheck(&globals, &HashMap::new());
+                Some((load.module_id.to_owned(), interface))
+            })
+            .collect()
+    }
+
+    fn did_open(
+        &self,
+        params: DidOpenTextDocumentParams,
+        initialize_params: &InitializeParams,
+    ) -> anyhow::Result<()> {
         self.validate(
             params.text_document.uri,
             Some(params.text_document.version as i64),
             params.text_document.text,
+            initialize_params,
         )
     }
 
-    fn did_change(&self, params: DidChangeTextDocumentParams) -> anyhow::Result<()> {
+    fn did_change(
+        &self,
+        params: DidChangeTextDocumentParams,
+        initialize_params: &InitializeParams,
+    ) -> anyhow::Result<()> {
         // We asked for Sync full, so can just grab all the text from params
         let change = params.content_changes.into_iter().next().unwrap();
         self.validate(
             params.text_document.uri,
             Some(params.text_document.version as i64),
             change.text,
+            initialize_params,
         )
     }
 
     fn did_close(&self, params: DidCloseTextDocumentParams) -> anyhow::Result<()> {
         {
-            let mut last_valid_parse = self.last_valid_parse.write().unwrap();
-            last_valid_parse.remove(&params.text_document.uri.clone().try_into()?);
+            let uri = params.text_document.uri.clone().try_into()?;
+            self.last_valid_parse.write().unwrap().remove(&uri);
+            self.open_documents.write().unwrap().remove(&uri);
         }
         self.publish_diagnostics(params.text_document.uri, Vec::new(), None);
         Ok(())
@@ -511,6 +671,60 @@ impl<T: LspContext> Backend<T> {
         self.send_response(new_response(id, self.hover_info(params, initialize_params)));
     }
 
+    /// Finds all references to the symbol at the current cursor, including in other files.
+    fn references(
+        &self,
+        id: RequestId,
+        params: ReferenceParams,
+        initialize_params: &InitializeParams,
+    ) {
+        self.send_response(new_response(
+            id,
+            self.find_reference_locations(params, initialize_params),
+        ));
+    }
+
+    /// Checks whether the symbol at the current cursor can be renamed.
+    fn prepare_rename(&self, id: RequestId, params: TextDocumentPositionParams) {
+        self.send_response(new_response(id, self.prepare_rename_info(params)));
+    }
+
+    /// Renames the symbol at the current cursor, including in other files that load it.
+    fn rename(&self, id: RequestId, params: RenameParams, initialize_params: &InitializeParams) {
+        self.send_response(new_response(
+            id,
+            self.rename_symbol(params, initialize_params),
+        ));
+    }
+
+    /// Offers the signature of the function being called at the current cursor.
+    fn signature_help(
+        &self,
+        id: RequestId,
+        params: SignatureHelpParams,
+        initialize_params: &InitializeParams,
+    ) {
+        self.send_response(new_response(
+            id,
+            self.signature_help_info(params, initialize_params),
+        ));
+    }
+
+    /// Lists the functions and variables defined in a document.
+    fn document_symbols(&self, id: RequestId, params: DocumentSymbolParams) {
+        self.send_response(new_response(id, self.document_symbols_info(params)));
+    }
+
+    /// Rewrites a document in canonical format.
+    fn formatting(&self, id: RequestId, params: DocumentFormattingParams) {
+        self.send_response(new_response(id, self.format_document(params)));
+    }
+
+    /// Offers the fixes that lints come with as quick fixes.
+    fn code_action(&self, id: RequestId, params: CodeActionParams) {
+        self.send_response(new_response(id, Ok(Some(Self::quick_fixes(params)))));
+    }
+
     /// Get the file contents of a starlark: URI.
     fn get_starlark_file_contents(&self, id: RequestId, params: StarlarkFileContentsParams) {
         let response: anyhow::Result<_> = match params.uri {
@@ -1003,6 +1217,141 @@ impl<T: LspContext> Backend<T> {
         })
     }
 
+    fn find_reference_locations(
+        &self,
+        params: ReferenceParams,
+        initialize_params: &InitializeParams,
+    ) -> anyhow::Result<Vec<Location>> {
+        let uri = params.text_document_position.text_document.uri.try_into()?;
+        let line = params.text_document_position.position.line;
+        let character = params.text_document_position.position.character;
+        let workspace_root =
+            Self::get_workspace_root(initialize_params.workspace_folders.as_ref(), &uri);
+
+        self.reference_locations(
+            &uri,
+            line,
+            character,
+            params.context.include_declaration,
+            workspace_root.as_deref(),
+        )
+    }
+
+    fn prepare_rename_info(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> anyhow::Result<Option<PrepareRenameResponse>> {
+        let uri = params.text_document.uri.try_into()?;
+        self.prepare_rename_range(&uri, params.position.line, params.position.character)
+    }
+
+    fn rename_symbol(
+        &self,
+        params: RenameParams,
+        initialize_params: &InitializeParams,
+    ) -> anyhow::Result<WorkspaceEdit> {
+        let uri = params.text_document_position.text_document.uri.try_into()?;
+        let line = params.text_document_position.position.line;
+        let character = params.text_document_position.position.character;
+        let workspace_root =
+            Self::get_workspace_root(initialize_params.workspace_folders.as_ref(), &uri);
+
+        self.rename_edits(
+            &uri,
+            line,
+            character,
+            &params.new_name,
+            workspace_root.as_deref(),
+        )
+    }
+
+    fn signature_help_info(
+        &self,
+        params: SignatureHelpParams,
+        initialize_params: &InitializeParams,
+    ) -> anyhow::Result<Option<SignatureHelp>> {
+        let uri = params
+            .text_document_position_params
+            .text_document
+            .uri
+            .try_into()?;
+        let position = params.text_document_position_params.position;
+        let workspace_root =
+            Self::get_workspace_root(initialize_params.workspace_folders.as_ref(), &uri);
+
+        self.signature_help_at(
+            &uri,
+            position.line,
+            position.character,
+            workspace_root.as_deref(),
+        )
+    }
+
+    fn document_symbols_info(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> anyhow::Result<Option<DocumentSymbolResponse>> {
+        let uri = params.text_document.uri.try_into()?;
+        Ok(self.get_ast_or_load_from_disk(&uri)?.map(|module| {
+            DocumentSymbolResponse::Nested(find_document_symbols(
+                module.ast.codemap(),
+                module.ast.statement(),
+            ))
+        }))
+    }
+
+    /// Quick fixes for the diagnostics in the request, unless it only wants other kinds of action.
+    fn quick_fixes(params: CodeActionParams) -> CodeActionResponse {
+        let wants_quick_fixes = params.context.only.map_or(true, |kinds| {
+            kinds
+                .iter()
+                .any(|kind| CodeActionKind::QUICKFIX.as_str().starts_with(kind.as_str()))
+        });
+        if !wants_quick_fixes {
+            return Vec::new();
+        }
+        params
+            .context
+            .diagnostics
+            .iter()
+            .filter_map(|diagnostic| diagnostic_quick_fix(&params.text_document.uri, diagnostic))
+            .map(CodeActionOrCommand::CodeAction)
+            .collect()
+    }
+
+    /// Replace the whole document with its canonical formatting,
+    /// or return no edits if it is already formatted.
+    fn format_document(
+        &self,
+        params: DocumentFormattingParams,
+    ) -> anyhow::Result<Option<Vec<TextEdit>>> {
+        let uri: LspUrl = params.text_document.uri.try_into()?;
+        let Some(module) = self.get_ast_or_load_from_disk(&uri)? else {
+            return Ok(None);
+        };
+        let source = module.ast.codemap().source();
+        // The last valid parse is stale if the document has been edited into something
+        // that does not parse, and formatting it would discard those edits.
+        if let Some(text) = self.open_documents.read().unwrap().get(&uri) {
+            if text != source {
+                return Err(FormattingError::DoesNotParse(uri).into());
+            }
+        }
+        let formatted = module.ast.format();
+        if formatted == source {
+            return Ok(Some(Vec::new()));
+        }
+        let last_line = source.rsplit('\n').next().unwrap_or_default();
+        let end = Position::new(
+            source.matches('\n').count() as u32,
+            last_line.encode_utf16().count() as u32,
+        );
+        Ok(Some(vec![TextEdit::new(
+            Range::new(Position::new(0, 0), end),
+            formatted,
+        )]))
+    }
+
     /// Get hover information for a given position in a document.
     fn hover_info(
         &self,
@@ -1200,7 +1549,7 @@ impl<T: LspContext> Backend<T> {
         self.connection.sender.send(Message::Response(x)).unwrap()
     }
 
-    fn log_message(&self, typ: MessageType, message: &str) {
+    pub(crate) fn log_message(&self, typ: MessageType, message: &str) {
         self.send_notification(new_notification::<LogMessage>(LogMessageParams {
             typ,
             message: message.to_owned(),
@@ -1218,8 +1567,6 @@ impl<T: LspContext> Backend<T> {
         for msg in &self.connection.receiver {
             match msg {
                 Message::Request(req) => {
-                    // TODO(nmj): Also implement DocumentSymbols so that some logic can
-                    //            be handled client side.
                     if let Some(params) = as_request::<GotoDefinition>(&req) {
                         self.goto_definition(req.id, params, &initialize_params);
                     } else if let Some(params) = as_request::<StarlarkFileContentsRequest>(&req) {
@@ -1228,6 +1575,20 @@ impl<T: LspContext> Backend<T> {
                         self.completion(req.id, params, &initialize_params);
                     } else if let Some(params) = as_request::<HoverRequest>(&req) {
                         self.hover(req.id, params, &initialize_params);
+                    } else if let Some(params) = as_request::<References>(&req) {
+                        self.references(req.id, params, &initialize_params);
+                    } else if let Some(params) = as_request::<PrepareRenameRequest>(&req) {
+                        self.prepare_rename(req.id, params);
+                    } else if let Some(params) = as_request::<Rename>(&req) {
+                        self.rename(req.id, params, &initialize_params);
+                    } else if let Some(params) = as_request::<SignatureHelpRequest>(&req) {
+                        self.signature_help(req.id, params, &initialize_params);
+                    } else if let Some(params) = as_request::<DocumentSymbolRequest>(&req) {
+                        self.document_symbols(req.id, params);
+                    } else if let Some(params) = as_request::<Formatting>(&req) {
+                        self.formatting(req.id, params);
+                    } else if let Some(params) = as_request::<CodeActionRequest>(&req) {
+                        self.code_action(req.id, params);
                     } else if self.connection.handle_shutdown(&req)? {
                         return Ok(());
                     }
@@ -1235,9 +1596,9 @@ impl<T: LspContext> Backend<T> {
                 }
                 Message::Notification(x) => {
                     if let Some(params) = as_notification::<DidOpenTextDocument>(&x) {
-                        self.did_open(params)?;
+                        self.did_open(params, &initialize_params)?;
                     } else if let Some(params) = as_notification::<DidChangeTextDocument>(&x) {
-                        self.did_change(params)?;
+                        self.did_change(params, &initialize_params)?;
                     } else if let Some(params) = as_notification::<DidCloseTextDocument>(&x) {
                         self.did_close(params)?;
                     }
@@ -1291,6 +1652,7 @@ pub fn server_with_connection<T: LspContext>(
         connection,
         context,
         last_valid_parse: RwLock::default(),
+        open_documents: RwLock::default(),
     }
     .main_loop(initialization_params)?;
 
@@ -1374,15 +1736,44 @@ mod tests {
     use anyhow::Context;
     use lsp_server::Request;
     use lsp_server::RequestId;
+    use lsp_types::notification::PublishDiagnostics;
+    use lsp_types::request::CodeActionRequest;
+    use lsp_types::request::DocumentSymbolRequest;
+    use lsp_types::request::Formatting;
     use lsp_types::request::GotoDefinition;
+    use lsp_types::request::PrepareRenameRequest;
+    use lsp_types::request::References;
+    use lsp_types::request::Rename;
+    use lsp_types::request::SignatureHelpRequest;
+    use lsp_types::CodeAction;
+    use lsp_types::CodeActionContext;
+    use lsp_types::CodeActionKind;
+    use lsp_types::CodeActionOrCommand;
+    use lsp_types::CodeActionParams;
+    use lsp_types::CodeActionResponse;
+    use lsp_types::DiagnosticSeverity;
+    use lsp_types::DocumentFormattingParams;
+    use lsp_types::DocumentSymbolParams;
+    use lsp_types::DocumentSymbolResponse;
+    use lsp_types::Documentation;
     use lsp_types::GotoDefinitionParams;
     use lsp_types::GotoDefinitionResponse;
+    use lsp_types::Location;
     use lsp_types::LocationLink;
     use lsp_types::Position;
+    use lsp_types::PrepareRenameResponse;
     use lsp_types::Range;
+    use lsp_types::ReferenceContext;
+    use lsp_types::ReferenceParams;
+    use lsp_types::RenameParams;
+    use lsp_types::SignatureHelp;
+    use lsp_types::SignatureHelpParams;
+    use lsp_types::SymbolKind;
     use lsp_types::TextDocumentIdentifier;
     use lsp_types::TextDocumentPositionParams;
+    use lsp_types::TextEdit;
     use lsp_types::Url;
+    use lsp_types::WorkspaceEdit;
     use starlark::codemap::ResolvedSpan;
     use starlark::wasm::is_wasm;
     use textwrap::dedent;
@@ -2508,4 +2899,459 @@ mod tests {
         }
         Ok(())
     }
+
+    fn sorted_locations(mut locations: Vec<Location>) -> Vec<Location> {
+        locations.sort_by_key(|l| (l.uri.to_string(), l.range.start, l.range.end));
+        locations
+    }
+
+    #[test]
+    fn finds_references_in_other_files() -> anyhow::Result<()> {
+        if is_wasm() {
+            return Ok(());
+        }
+
+        let foo_uri = temp_file_uri("foo.star");
+        let bar_uri = temp_file_uri("bar.star");
+        let baz_uri = temp_file_uri("baz.star");
+
+        let foo_contents = dedent(
+            r#"
+            load("{load}", <load_string>"quz"</load_string>)
+            <call>quz</call>()
+            "#,
+        )
+        .replace("{load}", &uri_to_load_string(&bar_uri))
+        .trim()
+        .to_owned();
+        let bar_contents = dedent(
+            r#"
+            def <def><click>q</click>uz</def>():
+                pass
+            x = <use>quz</use>
+            "#,
+        )
+        .trim()
+        .to_owned();
+        let baz_contents = dedent(
+            r#"
+            load("{load}", <alias_decl>renamed</alias_decl> = <alias_string>"quz"</alias_string>)
+            <alias_call>renamed</alias_call>()
+            "#,
+        )
+        .replace("{load}", &uri_to_load_string(&bar_uri))
+        .trim()
+        .to_owned();
+        let foo = FixtureWithRanges::from_fixture(foo_uri.path(), &foo_contents)?;
+        let bar = FixtureWithRanges::from_fixture(bar_uri.path(), &bar_contents)?;
+        let baz = FixtureWithRanges::from_fixture(baz_uri.path(), &baz_contents)?;
+
+        let mut server = TestServer::new()?;
+        server.open_file(foo_uri.clone(), foo.program())?;
+        server.open_file(bar_uri.clone(), bar.program())?;
+        // Not opened, so has to be found through the workspace files.
+        server.set_file_contents(&baz_uri, baz.program())?;
+
+        let request = server.new_request::<References>(ReferenceParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: bar_uri.clone(),
+                },
+                position: Position::new(bar.begin_line("click"), bar.begin_column("click")),
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+            context: ReferenceContext {
+                include_declaration: true,
+            },
+        });
+        let request_id = server.send_request(request)?;
+        let response = server.get_response::<Vec<Location>>(request_id)?;
+
+        let location = |uri: &Url, fixture: &FixtureWithRanges, id: &str| {
+            Location::new(uri.clone(), fixture.resolved_span(id).into())
+        };
+        let expected = vec![
+            location(&foo_uri, &foo, "load_string"),
+            location(&foo_uri, &foo, "call"),
+            location(&bar_uri, &bar, "def"),
+            location(&bar_uri, &bar, "use"),
+            location(&baz_uri, &baz, "alias_decl"),
+            location(&baz_uri, &baz, "alias_string"),
+            location(&baz_uri, &baz, "alias_call"),
+        ];
+        assert_eq!(sorted_locations(expected), sorted_locations(response));
+        Ok(())
+    }
+
+    #[test]
+    fn renames_symbols_in_other_files() -> anyhow::Result<()> {
+        if is_wasm() {
+            return Ok(());
+        }
+
+        let foo_uri = temp_file_uri("foo.star");
+        let bar_uri = temp_file_uri("bar.star");
+
+        let foo_contents = dedent(
+            r#"
+            load("{load}", "<load_string>quz</load_string>", other = "x")
+            <call><click>q</click>uz</call>()
+            other()
+            "#,
+        )
+        .replace("{load}", &uri_to_load_string(&bar_uri))
+        .trim()
+        .to_owned();
+        let bar_contents = dedent(
+            r#"
+            def <def>quz</def>():
+                pass
+            x = <use>quz</use>
+            "#,
+        )
+        .trim()
+        .to_owned();
+        let foo = FixtureWithRanges::from_fixture(foo_uri.path(), &foo_contents)?;
+        let bar = FixtureWithRanges::from_fixture(bar_uri.path(), &bar_contents)?;
+
+        let mut server = TestServer::new()?;
+        server.open_file(foo_uri.clone(), foo.program())?;
+        server.set_file_contents(&bar_uri, bar.program())?;
+
+        let position = TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier {
+                uri: foo_uri.clone(),
+            },
+            position: Position::new(foo.begin_line("click"), foo.begin_column("click")),
+        };
+
+        let request = server.new_request::<PrepareRenameRequest>(position.clone());
+        let request_id = server.send_request(request)?;
+        let response = server.get_response::<Option<PrepareRenameResponse>>(request_id)?;
+        assert_eq!(
+            Some(PrepareRenameResponse::Range(
+                foo.resolved_span("call").into()
+            )),
+            response
+        );
+
+        let request = server.new_request::<Rename>(RenameParams {
+            text_document_position: position.clone(),
+            new_name: "new_quz".to_owned(),
+            work_done_progress_params: Default::default(),
+        });
+        let request_id = server.send_request(request)?;
+        let mut response = server.get_response::<WorkspaceEdit>(request_id)?;
+
+        let edit = |fixture: &FixtureWithRanges, id: &str| {
+            TextEdit::new(fixture.resolved_span(id).into(), "new_quz".to_owned())
+        };
+        let mut changes = response.changes.take().unwrap_or_default();
+        assert_eq!(2, changes.len());
+        assert_eq!(
+            Some(vec![edit(&foo, "load_string"), edit(&foo, "call")]),
+            changes.remove(&foo_uri)
+        );
+        assert_eq!(
+            Some(vec![edit(&bar, "def"), edit(&bar, "use")]),
+            changes.remove(&bar_uri)
+        );
+
+        let request = server.new_request::<Rename>(RenameParams {
+            text_document_position: position,
+            new_name: "not valid".to_owned(),
+            work_done_progress_params: Default::default(),
+        });
+        let request_id = server.send_request(request)?;
+        assert!(server.get_response::<WorkspaceEdit>(request_id).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn signature_help_for_loaded_function() -> anyhow::Result<()> {
+        if is_wasm() {
+            return Ok(());
+        }
+
+        let foo_uri = temp_file_uri("foo.star");
+        let bar_uri = temp_file_uri("bar.star");
+
+        let foo_contents = dedent(
+            r#"
+            load("{load}", "quz")
+            quz(1, <click>c</click> = 2)
+            "#,
+        )
+        .replace("{load}", &uri_to_load_string(&bar_uri))
+        .trim()
+        .to_owned();
+        let bar_contents = dedent(
+            r#"
+            def quz(a, b = None, *, c):
+                """Does the quz.
+
+                Args:
+                    c: The c to quz.
+                """
+                pass
+            "#,
+        )
+        .trim()
+        .to_owned();
+        let foo = FixtureWithRanges::from_fixture(foo_uri.path(), &foo_contents)?;
+
+        let mut server = TestServer::new()?;
+        server.open_file(foo_uri.clone(), foo.program())?;
+        server.set_file_contents(&bar_uri, bar_contents)?;
+
+        let request = server.new_request::<SignatureHelpRequest>(SignatureHelpParams {
+            context: None,
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri: foo_uri },
+                position: Position::new(foo.begin_line("click"), foo.begin_column("click")),
+            },
+            work_done_progress_params: Default::default(),
+        });
+        let request_id = server.send_request(request)?;
+        let response = server
+            .get_response::<Option<SignatureHelp>>(request_id)?
+            .context("no signature help")?;
+
+        assert_eq!(Some(2), response.active_parameter);
+        let signature = &response.signatures[0];
+        assert_eq!("quz(a, b = None, *, c)", signature.label);
+        let docs = |documentation: &Option<Documentation>| match documentation {
+            Some(Documentation::MarkupContent(content)) => content.value.clone(),
+            _ => String::new(),
+        };
+        assert_eq!("Does the quz.", docs(&signature.documentation));
+        let parameters = signature.parameters.as_ref().unwrap();
+        assert_eq!(3, parameters.len());
+        assert_eq!("The c to quz.", docs(&parameters[2].documentation));
+        Ok(())
+    }
+
+    #[test]
+    fn document_symbols_outline() -> anyhow::Result<()> {
+        if is_wasm() {
+            return Ok(());
+        }
+
+        let foo_uri = temp_file_uri("foo.star");
+        let foo_contents = dedent(
+            r#"
+            <def>def <name>quz</name>(a):
+                inner = a
+                return inner
+            </def><x>x</x> = 1
+            "#,
+        )
+        .trim()
+        .to_owned();
+        let foo = FixtureWithRanges::from_fixture(foo_uri.path(), &foo_contents)?;
+
+        let mut server = TestServer::new()?;
+        server.open_file(foo_uri.clone(), foo.program())?;
+
+        let request = server.new_request::<DocumentSymbolRequest>(DocumentSymbolParams {
+            text_document: TextDocumentIdentifier { uri: foo_uri },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        });
+        let request_id = server.send_request(request)?;
+        let response = server.get_response::<Option<DocumentSymbolResponse>>(request_id)?;
+
+        let Some(DocumentSymbolResponse::Nested(symbols)) = response else {
+            panic!("Expected nested document symbols, got {:?}", response);
+        };
+        assert_eq!(2, symbols.len());
+        assert_eq!("quz", symbols[0].name);
+        assert_eq!(SymbolKind::FUNCTION, symbols[0].kind);
+        assert_eq!(Range::from(foo.resolved_span("def")), symbols[0].range);
+        assert_eq!(
+            Range::from(foo.resolved_span("name")),
+            symbols[0].selection_range
+        );
+        assert_eq!(
+            vec!["inner"],
+            symbols[0]
+                .children
+                .iter()
+                .flatten()
+                .map(|s| s.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("x", symbols[1].name);
+        assert_eq!(SymbolKind::VARIABLE, symbols[1].kind);
+        assert_eq!(
+            Range::from(foo.resolved_span("x")),
+            symbols[1].selection_range
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn typechecks_on_change() -> anyhow::Result<()> {
+        if is_wasm() {
+            return Ok(());
+        }
+
+        let foo_uri = temp_file_uri("foo.star");
+        let mut server = TestServer::new()?;
+        server.open_file(foo_uri.clone(), "def f(x: int):\n    return x\n".to_owned())?;
+
+        server.change_file(
+            foo_uri.clone(),
+            "def f(x: int):\n    return x\n\ndef g():\n    f(\"x\")\n    undefined()\n".to_owned(),
+        )?;
+        let notification = server.get_notification::<PublishDiagnostics>()?;
+        assert_eq!(foo_uri, notification.uri);
+        // Only the bad argument is reported, the unknown `undefined` is left to lints.
+        assert_eq!(1, notification.diagnostics.len());
+        let diagnostic = &notification.diagnostics[0];
+        assert_eq!(Some(DiagnosticSeverity::ERROR), diagnostic.severity);
+        assert_eq!(
+            Range::new(Position::new(4, 6), Position::new(4, 9)),
+            diagnostic.range
+        );
+        assert!(
+            diagnostic
+                .message
+                .contains("Expected type `int` but got `str`"),
+            "{}",
+            diagnostic.message
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn typechecks_loaded_symbols() -> anyhow::Result<()> {
+        if is_wasm() {
+            return Ok(());
+        }
+
+        let foo_uri = temp_file_uri("foo.star");
+        let bar_uri = temp_file_uri("bar.star");
+        let foo_contents = format!(
+            "load(\"{}\", \"quz\")\n\ndef g():\n    quz(\"x\")\n",
+            uri_to_load_string(&bar_uri)
+        );
+
+        let mut server = TestServer::new()?;
+        server.set_file_contents(&bar_uri, "def quz(x: int):\n    return x\n".to_owned())?;
+        server.open_file(foo_uri.clone(), "x = 1\n".to_owned())?;
+        server.change_file(foo_uri.clone(), foo_contents)?;
+        let notification = server.get_notification::<PublishDiagnostics>()?;
+        assert_eq!(foo_uri, notification.uri);
+        assert_eq!(1, notification.diagnostics.len());
+        assert_eq!(
+            Range::new(Position::new(3, 8), Position::new(3, 11)),
+            notification.diagnostics[0].range
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn quick_fixes_from_lints() -> anyhow::Result<()> {
+        if is_wasm() {
+            return Ok(());
+        }
+
+        let foo_uri = temp_file_uri("foo.star");
+        let mut server = TestServer::new()?;
+        server.open_file(foo_uri.clone(), "x = 1\n".to_owned())?;
+        server.change_file(
+            foo_uri.clone(),
+            "load(\"a.star\", \"a\")\nx = dict(**{})\n".to_owned(),
+        )?;
+        let diagnostics = server.get_notification::<PublishDiagnostics>()?.diagnostics;
+        assert_eq!(2, diagnostics.len());
+
+        let request = server.new_request::<CodeActionRequest>(CodeActionParams {
+            text_document: TextDocumentIdentifier {
+                uri: foo_uri.clone(),
+            },
+            range: Range::new(Position::new(0, 0), Position::new(2, 0)),
+            context: CodeActionContext {
+                diagnostics,
+                only: None,
+                trigger_kind: None,
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        });
+        let request_id = server.send_request(request)?;
+        let actions = server.get_response::<CodeActionResponse>(request_id)?;
+
+        let edits = actions
+            .iter()
+            .map(|action| match action {
+                CodeActionOrCommand::CodeAction(CodeAction {
+                    kind: Some(kind),
+                    edit: Some(edit),
+                    ..
+                }) if *kind == CodeActionKind::QUICKFIX => {
+                    Ok(edit.changes.as_ref().unwrap()[&foo_uri].clone())
+                }
+                _ => Err(anyhow::anyhow!("Expected a quick fix, got {:?}", action)),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        assert_eq!(
+            vec![
+                vec![TextEdit::new(
+                    Range::new(Position::new(0, 0), Position::new(1, 0)),
+                    String::new(),
+                )],
+                vec![TextEdit::new(
+                    Range::new(Position::new(1, 4), Position::new(1, 14)),
+                    "dict({})".to_owned(),
+                )],
+            ],
+            edits
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn formatting_replaces_document() -> anyhow::Result<()> {
+        if is_wasm() {
+            return Ok(());
+        }
+
+        let foo_uri = temp_file_uri("foo.star");
+        let mut server = TestServer::new()?;
+        server.open_file(foo_uri.clone(), "x=[1,\n  2]  # Two.\n".to_owned())?;
+
+        let formatting_request = |server: &mut TestServer| {
+            server.new_request::<Formatting>(DocumentFormattingParams {
+                text_document: TextDocumentIdentifier {
+                    uri: foo_uri.clone(),
+                },
+                options: Default::default(),
+                work_done_progress_params: Default::default(),
+            })
+        };
+        let request = formatting_request(&mut server);
+        let request_id = server.send_request(request)?;
+        let edits = server.get_response::<Option<Vec<TextEdit>>>(request_id)?;
+        assert_eq!(
+            Some(vec![TextEdit::new(
+                Range::new(Position::new(0, 0), Position::new(2, 0)),
+                "x = [1, 2]  # Two.\n".to_owned(),
+            )]),
+            edits
+        );
+
+        // Edits that do not parse must not be thrown away by formatting the last valid parse.
+        server.change_file(foo_uri.clone(), "x = [1,\n".to_owned())?;
+        let request = formatting_request(&mut server);
+        let request_id = server.send_request(request)?;
+        assert!(
+            server
+                .get_response::<Option<Vec<TextEdit>>>(request_id)
+                .is_err()
+        );
+        Ok(())
+    }
 }
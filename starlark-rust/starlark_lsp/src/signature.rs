@@ -0,0 +1,529 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Implementation of signature help for function calls.
+
+use std::path::Path;
+
+use lsp_types::Documentation;
+use lsp_types::MarkupContent;
+use lsp_types::MarkupKind;
+use lsp_types::ParameterInformation;
+use lsp_types::ParameterLabel;
+use lsp_types::SignatureHelp;
+use lsp_types::SignatureInformation;
+use starlark::codemap::CodeMap;
+use starlark::codemap::Pos;
+use starlark::codemap::ResolvedSpan;
+use starlark::codemap::Span;
+use starlark::docs::DocFunction;
+use starlark::docs::DocItem;
+use starlark::docs::DocMember;
+use starlark::docs::DocParam;
+use starlark::docs::DocString;
+use starlark::docs::FmtParam;
+use starlark::typing::Ty;
+use starlark_syntax::syntax::ast::ArgumentP;
+use starlark_syntax::syntax::ast::AstArgument;
+use starlark_syntax::syntax::ast::AstExpr;
+use starlark_syntax::syntax::ast::AstNoPayload;
+use starlark_syntax::syntax::ast::AstStmt;
+use starlark_syntax::syntax::ast::ExprP;
+use starlark_syntax::syntax::ast::StmtP;
+use starlark_syntax::syntax::module::AstModuleFields;
+use starlark_syntax::syntax::top_level_stmts::top_level_stmts;
+use starlark_syntax::syntax::uniplate::Visit;
+
+use crate::definition::Definition;
+use crate::definition::DottedDefinition;
+use crate::definition::IdentifierDefinition;
+use crate::definition::LspModule;
+use crate::docs::get_signature_for_def;
+use crate::server::Backend;
+use crate::server::LspContext;
+use crate::server::LspUrl;
+
+/// Which argument of a call the cursor is in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ActiveArgument {
+    /// The n-th positional argument.
+    Positional(usize),
+    /// A named argument, e.g. `x = 1`.
+    Named(String),
+    /// A `*args` argument.
+    Args,
+    /// A `**kwargs` argument.
+    Kwargs,
+    /// A new argument after named arguments, so we can't tell which parameter it is for.
+    Unknown,
+}
+
+impl ActiveArgument {
+    fn new(args: &[AstArgument], position: Pos) -> Self {
+        let current = args
+            .iter()
+            .position(|arg| arg.span.contains(position))
+            .unwrap_or_else(|| args.iter().filter(|arg| arg.span.end() < position).count());
+        match args.get(current).map(|arg| &arg.node) {
+            Some(ArgumentP::Named(name, _)) => ActiveArgument::Named(name.node.clone()),
+            Some(ArgumentP::Args(_)) => ActiveArgument::Args,
+            Some(ArgumentP::KwArgs(_)) => ActiveArgument::Kwargs,
+            Some(ArgumentP::Positional(_)) | None => {
+                // Positional arguments can't follow named ones, so we can't
+                // tell what the user is typing yet.
+                if args[..current]
+                    .iter()
+                    .all(|arg| matches!(arg.node, ArgumentP::Positional(_)))
+                {
+                    ActiveArgument::Positional(current)
+                } else {
+                    ActiveArgument::Unknown
+                }
+            }
+        }
+    }
+}
+
+/// The innermost function call around a position in a module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CallAtPosition {
+    /// The span of the left most identifier of the function expression,
+    /// e.g. `foo` in `foo.bar(x)`.
+    pub(crate) root_span: Span,
+    /// The identifiers making up the function expression, e.g. `["foo", "bar"]`.
+    pub(crate) segments: Vec<String>,
+    /// The argument the position is in.
+    pub(crate) argument: ActiveArgument,
+}
+
+impl CallAtPosition {
+    fn function_segments(function: &AstExpr) -> Option<(Span, Vec<String>)> {
+        match &function.node {
+            ExprP::Identifier(ident) => Some((ident.span, vec![ident.node.ident.clone()])),
+            ExprP::Dot(object, attribute) => {
+                let (span, mut segments) = Self::function_segments(object)?;
+                segments.push(attribute.node.clone());
+                Some((span, segments))
+            }
+            _ => None,
+        }
+    }
+
+    fn find(position: Pos, node: Visit<AstNoPayload>, result: &mut Option<CallAtPosition>) {
+        match node {
+            Visit::Stmt(stmt) => {
+                if stmt.span.contains(position) {
+                    stmt.visit_children(|x| Self::find(position, x, result));
+                }
+            }
+            Visit::Expr(expr) => {
+                if !expr.span.contains(position) {
+                    return;
+                }
+                if let ExprP::Call(function, args) = &expr.node {
+                    // Only inside the brackets, not on the function name or after the call.
+                    if function.span.end() < position && position < expr.span.end() {
+                        if let Some((root_span, segments)) = Self::function_segments(function) {
+                            *result = Some(CallAtPosition {
+                                root_span,
+                                segments,
+                                argument: ActiveArgument::new(&args.args, position),
+                            });
+                        }
+                    }
+                }
+                // Keep going, so that nested calls take precedence.
+                expr.visit_expr(|x| Self::find(position, Visit::Expr(x), result));
+            }
+        }
+    }
+}
+
+impl LspModule {
+    /// Find the innermost function call that the given zero based line and column is in
+    /// the arguments of.
+    pub(crate) fn find_call_at_location(&self, line: u32, col: u32) -> Option<CallAtPosition> {
+        let position = self.position(line, col)?;
+        let mut result = None;
+        CallAtPosition::find(position, Visit::Stmt(self.ast.statement()), &mut result);
+        result
+    }
+
+    /// Find the signature of the `def` whose name is at `destination`.
+    fn find_def_signature_at(&self, destination: ResolvedSpan) -> Option<DocFunction> {
+        fn walk(
+            stmt: &AstStmt,
+            destination: ResolvedSpan,
+            codemap: &CodeMap,
+        ) -> Option<DocFunction> {
+            if let StmtP::Def(def) = &stmt.node {
+                if codemap.resolve_span(def.name.span) == destination {
+                    // Invalid parameters get no signature help.
+                    return get_signature_for_def(def, codemap);
+                }
+            }
+            let mut result = None;
+            stmt.visit_stmt(|x| {
+                if result.is_none() {
+                    result = walk(x, destination, codemap);
+                }
+            });
+            result
+        }
+
+        walk(self.ast.statement(), destination, self.ast.codemap())
+    }
+
+    /// Find the signature of an exported function called `name`.
+    fn find_exported_signature(&self, name: &str) -> Option<DocFunction> {
+        top_level_stmts(self.ast.statement())
+            .into_iter()
+            .find_map(|stmt| match &stmt.node {
+                StmtP::Def(def) if def.name.ident == name => {
+                    get_signature_for_def(def, self.ast.codemap())
+                }
+                _ => None,
+            })
+            .or_else(|| match self.find_exported_symbol(name)?.docs? {
+                DocItem::Member(DocMember::Function(function)) => Some(function),
+                _ => None,
+            })
+    }
+}
+
+/// Get the signature of a callable documented item, e.g. a function or the
+/// constructor of a type.
+fn doc_item_signature(item: DocItem) -> Option<DocFunction> {
+    match item {
+        DocItem::Member(DocMember::Function(function)) => Some(function),
+        DocItem::Type(ty) => ty.constructor,
+        DocItem::Module(_) | DocItem::Member(DocMember::Property(_)) => None,
+    }
+}
+
+/// Look up a member of a documented item, e.g. a function in a namespace.
+fn doc_item_member(item: DocItem, name: &str) -> Option<DocItem> {
+    match item {
+        DocItem::Module(mut module) => module.members.shift_remove(name),
+        DocItem::Type(mut ty) => ty.members.shift_remove(name).map(DocItem::Member),
+        DocItem::Member(_) => None,
+    }
+}
+
+fn render_doc_string(doc: &DocString) -> Documentation {
+    let value = match &doc.details {
+        Some(details) => format!("{}\n\n{}", doc.summary, details),
+        None => doc.summary.clone(),
+    };
+    Documentation::MarkupContent(MarkupContent {
+        kind: MarkupKind::Markdown,
+        value,
+    })
+}
+
+fn render_param(prefix: &str, param: &DocParam) -> String {
+    let mut res = format!("{}{}", prefix, param.name);
+    if param.typ != Ty::any() {
+        res.push_str(&format!(": {}", param.typ));
+    }
+    if let Some(default_value) = &param.default_value {
+        res.push_str(&format!(" = {}", default_value));
+    }
+    res
+}
+
+/// Work out the index of the parameter that an argument is passed to, in the order
+/// the parameters are listed in the signature.
+fn active_parameter(function: &DocFunction, argument: &ActiveArgument) -> Option<usize> {
+    let params = &function.params;
+    let positional = params.pos_only.len() + params.pos_or_named.len();
+    let named_only_start = positional + params.args.iter().len();
+    let kwargs = params
+        .kwargs
+        .as_ref()
+        .map(|_| named_only_start + params.named_only.len());
+    match argument {
+        ActiveArgument::Positional(i) if *i < positional => Some(*i),
+        ActiveArgument::Positional(_) | ActiveArgument::Args => {
+            params.args.as_ref().map(|_| positional)
+        }
+        ActiveArgument::Named(name) => params
+            .pos_or_named
+            .iter()
+            .position(|p| &p.name == name)
+            .map(|i| params.pos_only.len() + i)
+            .or_else(|| {
+                params
+                    .named_only
+                    .iter()
+                    .position(|p| &p.name == name)
+                    .map(|i| named_only_start + i)
+            })
+            .or(kwargs),
+        ActiveArgument::Kwargs => kwargs,
+        ActiveArgument::Unknown => None,
+    }
+}
+
+/// Build the signature help for a call to the function `name`.
+pub(crate) fn signature_help(
+    name: &str,
+    function: &DocFunction,
+    argument: &ActiveArgument,
+) -> SignatureHelp {
+    // Offsets into the label are measured in UTF-16 code units.
+    fn offset(label: &str) -> u32 {
+        label.encode_utf16().count() as u32
+    }
+
+    let mut label = format!("{}(", name);
+    let mut parameters = Vec::new();
+    for (i, param) in function.params.fmt_params().enumerate() {
+        if i != 0 {
+            label.push_str(", ");
+        }
+        let (text, param) = match param {
+            FmtParam::Regular(p) => (render_param("", p), Some(p)),
+            FmtParam::Args(p) => (render_param("*", p), Some(p)),
+            FmtParam::Kwargs(p) => (render_param("**", p), Some(p)),
+            FmtParam::Slash => ("/".to_owned(), None),
+            FmtParam::Star => ("*".to_owned(), None),
+        };
+        let start = offset(&label);
+        label.push_str(&text);
+        if let Some(param) = param {
+            parameters.push(ParameterInformation {
+                label: ParameterLabel::LabelOffsets([start, offset(&label)]),
+                documentation: param.docs.as_ref().map(render_doc_string),
+            });
+        }
+    }
+    label.push(')');
+    if function.ret.typ != Ty::any() {
+        label.push_str(&format!(" -> {}", function.ret.typ));
+    }
+
+    let active_parameter = active_parameter(function, argument).map(|i| i as u32);
+    SignatureHelp {
+        signatures: vec![SignatureInformation {
+            label,
+            documentation: function.docs.as_ref().map(render_doc_string),
+            parameters: Some(parameters),
+            active_parameter,
+        }],
+        active_signature: Some(0),
+        active_parameter,
+    }
+}
+
+impl<T: LspContext> Backend<T> {
+    /// Find the signature of the function being called at the given position, along
+    /// with which parameter the cursor is at.
+    pub(crate) fn signature_help_at(
+        &self,
+        document_uri: &LspUrl,
+        line: u32,
+        character: u32,
+        workspace_root: Option<&Path>,
+    ) -> anyhow::Result<Option<SignatureHelp>> {
+        let Some(document) = self.get_ast_or_load_from_disk(document_uri)? else {
+            return Ok(None);
+        };
+        let Some(call) = document.find_call_at_location(line, character) else {
+            return Ok(None);
+        };
+        let name = call.segments.last().expect("always at least one segment");
+        Ok(self
+            .resolve_call_signature(&call, &document, document_uri, workspace_root)?
+            .map(|function| signature_help(name, &function, &call.argument)))
+    }
+
+    fn resolve_call_signature(
+        &self,
+        call: &CallAtPosition,
+        document: &LspModule,
+        document_uri: &LspUrl,
+        workspace_root: Option<&Path>,
+    ) -> anyhow::Result<Option<DocFunction>> {
+        let root = document.ast.codemap().resolve_span(call.root_span);
+        let root_definition = match document
+            .find_definition_at_location(root.begin.line as u32, root.begin.column as u32)
+        {
+            Definition::Identifier(definition) => definition,
+            Definition::Dotted(DottedDefinition {
+                root_definition_location,
+                ..
+            }) => root_definition_location,
+        };
+        let members = &call.segments[1..];
+
+        Ok(match root_definition {
+            IdentifierDefinition::Location { destination, .. } if members.is_empty() => {
+                document.find_def_signature_at(destination)
+            }
+            IdentifierDefinition::LoadedLocation { path, name, .. } if members.is_empty() => {
+                let load_uri = self.resolve_load_path(&path, document_uri, workspace_root)?;
+                self.get_ast_or_load_from_disk(&load_uri)?
+                    .and_then(|module| module.find_exported_signature(&name))
+            }
+            IdentifierDefinition::Unresolved { name, .. } => {
+                // Maybe it's a global symbol, or a member of one, e.g. `native.genrule`.
+                let mut item = self
+                    .context
+                    .get_environment(document_uri)
+                    .members
+                    .shift_remove(&name);
+                for member in members {
+                    item = item.and_then(|item| doc_item_member(item, member));
+                }
+                item.and_then(doc_item_signature)
+            }
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starlark::docs::DocParams;
+    use starlark::syntax::AstModule;
+    use starlark::syntax::Dialect;
+    use textwrap::dedent;
+
+    use super::*;
+    use crate::definition::helpers::FixtureWithRanges;
+
+    fn param(name: &str) -> DocParam {
+        DocParam {
+            name: name.to_owned(),
+            docs: None,
+            typ: Ty::any(),
+            default_value: None,
+        }
+    }
+
+    #[test]
+    fn finds_innermost_call() -> starlark::Result<()> {
+        let contents = dedent(
+            r#"
+            foo.bar(<a>1</a>, baz(x = <b>2</b>), <c></c>)
+            qux(y = 1, <d></d>)
+            "#,
+        )
+        .trim()
+        .to_owned();
+        let parsed = FixtureWithRanges::from_fixture("foo.star", &contents)?;
+        let module = parsed.module()?;
+        let call = |id: &str| {
+            module
+                .find_call_at_location(parsed.begin_line(id), parsed.begin_column(id))
+                .map(|call| (call.segments, call.argument))
+        };
+
+        assert_eq!(
+            Some((
+                vec!["foo".to_owned(), "bar".to_owned()],
+                ActiveArgument::Positional(0)
+            )),
+            call("a")
+        );
+        assert_eq!(
+            Some((
+                vec!["baz".to_owned()],
+                ActiveArgument::Named("x".to_owned())
+            )),
+            call("b")
+        );
+        assert_eq!(
+            Some((
+                vec!["foo".to_owned(), "bar".to_owned()],
+                ActiveArgument::Positional(2)
+            )),
+            call("c")
+        );
+        assert_eq!(
+            Some((vec!["qux".to_owned()], ActiveArgument::Unknown)),
+            call("d")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn renders_signature() {
+        let function = DocFunction {
+            params: DocParams {
+                pos_only: vec![param("a")],
+                pos_or_named: vec![DocParam {
+                    default_value: Some("1".to_owned()),
+                    ..param("b")
+                }],
+                args: None,
+                named_only: vec![param("c")],
+                kwargs: Some(param("kwargs")),
+            },
+            ..DocFunction::default()
+        };
+
+        let help = signature_help("f", &function, &ActiveArgument::Named("c".to_owned()));
+        let signature = &help.signatures[0];
+        assert_eq!("f(a, /, b = 1, *, c, **kwargs)", signature.label);
+        let labels: Vec<_> = signature
+            .parameters
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|p| match p.label {
+                ParameterLabel::LabelOffsets([start, end]) => {
+                    &signature.label[start as usize..end as usize]
+                }
+                ParameterLabel::Simple(_) => unreachable!(),
+            })
+            .collect();
+        assert_eq!(vec!["a", "b = 1", "c", "**kwargs"], labels);
+        assert_eq!(Some(2), help.active_parameter);
+
+        let active = |argument| signature_help("f", &function, &argument).active_parameter;
+        assert_eq!(Some(1), active(ActiveArgument::Positional(1)));
+        assert_eq!(None, active(ActiveArgument::Positional(2)));
+        assert_eq!(Some(3), active(ActiveArgument::Named("d".to_owned())));
+        // Positional-only parameters can't be passed by name.
+        assert_eq!(Some(3), active(ActiveArgument::Named("a".to_owned())));
+    }
+
+    #[test]
+    fn no_signature_for_invalid_params() -> starlark::Result<()> {
+        let module = AstModule::parse(
+            "foo.star",
+            "def f(b, a = 1):\n    pass\n".to_owned(),
+            &Dialect::Extended,
+        )?;
+        let def = top_level_stmts(module.statement())
+            .into_iter()
+            .find_map(|stmt| match &stmt.node {
+                StmtP::Def(def) => Some(def.clone()),
+                _ => None,
+            })
+            .unwrap();
+        assert!(get_signature_for_def(&def, module.codemap()).is_some());
+
+        // The parser rejects these parameters, but the LSP must not panic on them either.
+        let mut invalid = def;
+        invalid.params.reverse();
+        assert!(get_signature_for_def(&invalid, module.codemap()).is_none());
+        Ok(())
+    }
+}
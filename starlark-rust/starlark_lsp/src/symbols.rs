@@ -15,16 +15,19 @@
  * limitations under the License.
  */
 
-//! Find which symbols are in scope at a particular point.
+//! Find which symbols are in scope at a particular point, and the outline of a module.
 
 use std::collections::HashMap;
+use std::collections::HashSet;
 
+use lsp_types::DocumentSymbol;
 use starlark::codemap::CodeMap;
 use starlark::docs::DocItem;
 use starlark::docs::DocMember;
 use starlark::docs::DocParam;
 use starlark_syntax::codemap::ResolvedPos;
 use starlark_syntax::syntax::ast::AssignP;
+use starlark_syntax::syntax::ast::AstAssignIdentP;
 use starlark_syntax::syntax::ast::AstPayload;
 use starlark_syntax::syntax::ast::AstStmtP;
 use starlark_syntax::syntax::ast::ExprP;
@@ -162,6 +165,76 @@ pub(crate) fn find_symbols_at_location<P: AstPayload>(
     symbols
 }
 
+/// Walk the AST and build an outline of the module: the functions and variables it
+/// defines, with those defined inside a function nested under it. Only the first
+/// assignment to each name at each level is included.
+pub(crate) fn find_document_symbols<P: AstPayload>(
+    codemap: &CodeMap,
+    ast: &AstStmtP<P>,
+) -> Vec<DocumentSymbol> {
+    fn document_symbol<P: AstPayload>(
+        codemap: &CodeMap,
+        name: &AstAssignIdentP<P>,
+        stmt: &AstStmtP<P>,
+        kind: lsp_types::SymbolKind,
+        detail: Option<String>,
+        children: Vec<DocumentSymbol>,
+    ) -> DocumentSymbol {
+        #[allow(deprecated)] // `deprecated` is deprecated, but has to be filled in.
+        DocumentSymbol {
+            name: name.ident.clone(),
+            detail,
+            kind,
+            tags: None,
+            deprecated: None,
+            range: codemap.resolve_span(stmt.span).into(),
+            selection_range: codemap.resolve_span(name.span).into(),
+            children: (!children.is_empty()).then_some(children),
+        }
+    }
+
+    fn walk<P: AstPayload>(
+        codemap: &CodeMap,
+        ast: &AstStmtP<P>,
+        seen: &mut HashSet<String>,
+        symbols: &mut Vec<DocumentSymbol>,
+    ) {
+        match &ast.node {
+            StmtP::Assign(AssignP { lhs, ty: _, rhs }) => lhs.visit_lvalue(|x| {
+                if seen.insert(x.ident.clone()) {
+                    let kind = match rhs.node {
+                        ExprP::Lambda(_) => lsp_types::SymbolKind::FUNCTION,
+                        _ => lsp_types::SymbolKind::VARIABLE,
+                    };
+                    symbols.push(document_symbol(codemap, x, ast, kind, None, Vec::new()));
+                }
+            }),
+            StmtP::Def(def) => {
+                let mut children = Vec::new();
+                walk(codemap, &def.body, &mut HashSet::new(), &mut children);
+                if seen.insert(def.name.ident.clone()) {
+                    let detail = get_doc_item_for_def(def, codemap)
+                        .and_then(|doc| doc.docs)
+                        .map(|docs| docs.summary);
+                    symbols.push(document_symbol(
+                        codemap,
+                        &def.name,
+                        ast,
+                        lsp_types::SymbolKind::FUNCTION,
+                        detail,
+                        children,
+                    ));
+                }
+            }
+            stmt => stmt.visit_stmt(|x| walk(codemap, x, seen, symbols)),
+        }
+    }
+
+    let mut symbols = Vec::new();
+    walk(codemap, ast, &mut HashSet::new(), &mut symbols);
+    symbols
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
@@ -171,6 +244,7 @@ mod tests {
     use starlark_syntax::codemap::ResolvedPos;
     use starlark_syntax::syntax::module::AstModuleFields;
 
+    use super::find_document_symbols;
     use super::find_symbols_at_location;
     use super::Symbol;
     use super::SymbolKind;
@@ -318,4 +392,53 @@ my_var = True
             ])
         );
     }
+
+    #[test]
+    fn document_symbols() {
+        let ast_module = AstModule::parse(
+            "t.star",
+            r#"load("foo.star", "exported_a")
+
+def method(param):
+    """Does a thing."""
+    inner = 1
+    def nested():
+        pass
+
+my_var = True
+my_var = False
+other = lambda x: x
+        "#
+            .to_owned(),
+            &Dialect::Standard,
+        )
+        .unwrap();
+
+        let symbols = find_document_symbols(ast_module.codemap(), ast_module.statement());
+        let outline: Vec<_> = symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.kind, s.detail.as_deref()))
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    "method",
+                    lsp_types::SymbolKind::FUNCTION,
+                    Some("Does a thing.")
+                ),
+                ("my_var", lsp_types::SymbolKind::VARIABLE, None),
+                ("other", lsp_types::SymbolKind::FUNCTION, None),
+            ],
+            outline
+        );
+        let children: Vec<_> = symbols[0]
+            .children
+            .iter()
+            .flatten()
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(vec!["inner", "nested"], children);
+        assert_eq!(2, symbols[0].range.start.line);
+        assert_eq!(4, symbols[0].selection_range.start.character);
+    }
 }
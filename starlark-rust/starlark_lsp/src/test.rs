@@ -61,6 +61,7 @@ use starlark::docs::DocFunction;
 use starlark::docs::DocItem;
 use starlark::docs::DocMember;
 use starlark::docs::DocModule;
+use starlark::environment::Globals;
 use starlark::errors::EvalMessage;
 use starlark::syntax::AstModule;
 use starlark::syntax::Dialect;
@@ -297,6 +298,20 @@ impl LspContext for TestServerContext {
                 .collect(),
         }
     }
+
+    fn get_workspace_files(&self, _workspace_root: Option<&Path>) -> anyhow::Result<Vec<LspUrl>> {
+        Ok(self
+            .file_contents
+            .read()
+            .unwrap()
+            .keys()
+            .map(|path| LspUrl::File(path.clone()))
+            .collect())
+    }
+
+    fn get_typecheck_globals(&self, _uri: &LspUrl) -> Option<Globals> {
+        Some(Globals::standard())
+    }
 }
 
 /// A server for use in testing that provides helpers for sending requests, correlating
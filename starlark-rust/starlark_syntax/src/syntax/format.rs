@@ -0,0 +1,1066 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Canonical formatting of Starlark source code.
+//!
+//! The formatter prints the AST rather than patching the original text,
+//! so the output does not depend on how the input was laid out, except for:
+//!
+//! * comments, which are recovered from the lexer and reattached
+//!   to the closest statement or collection item;
+//! * blank lines between statements, which are kept (but collapsed to one);
+//! * grouping parentheses, which are kept so operator precedence
+//!   never has to be reconstructed;
+//! * whether a collection, call or parameter list is split over several lines,
+//!   which follows the source (a line break after the opening bracket),
+//!   unless it contains comments or does not fit in [`MAX_WIDTH`] columns.
+//!
+//! Literals are printed as written, except that single-quoted strings
+//! which need no escaping are converted to double quotes.
+
+use std::mem;
+use std::ptr;
+
+use dupe::Dupe;
+
+use crate::codemap::CodeMap;
+use crate::codemap::Pos;
+use crate::codemap::Span;
+use crate::lexer::Lexer;
+use crate::lexer::Token;
+use crate::slice_vec_ext::SliceExt;
+use crate::syntax::ast::Argument;
+use crate::syntax::ast::AssignP;
+use crate::syntax::ast::AssignTarget;
+use crate::syntax::ast::AstArgument;
+use crate::syntax::ast::AstAssignTarget;
+use crate::syntax::ast::AstExpr;
+use crate::syntax::ast::AstLiteral;
+use crate::syntax::ast::AstParameter;
+use crate::syntax::ast::AstStmt;
+use crate::syntax::ast::Clause;
+use crate::syntax::ast::DefP;
+use crate::syntax::ast::Expr;
+use crate::syntax::ast::ForClause;
+use crate::syntax::ast::ForP;
+use crate::syntax::ast::LambdaP;
+use crate::syntax::ast::Load;
+use crate::syntax::ast::Parameter;
+use crate::syntax::ast::Stmt;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+
+/// Lines longer than this are split at the outermost bracket that allows it.
+const MAX_WIDTH: usize = 100;
+
+/// One level of indentation.
+const INDENT: &str = "    ";
+
+impl AstModule {
+    /// Print the module in canonical form, preserving comments.
+    ///
+    /// Formatting is idempotent: formatting the output again yields the same text.
+    pub fn format(&self) -> String {
+        Formatter::new(&self.codemap, &self.dialect).module(&self.statement)
+    }
+}
+
+struct Comment<'a> {
+    begin: usize,
+    end: usize,
+    /// Text including the leading `#`, without trailing whitespace.
+    text: &'a str,
+}
+
+struct Formatter<'a> {
+    codemap: &'a CodeMap,
+    source: &'a str,
+    /// Tokens which are not comments, newlines or indentation.
+    tokens: Vec<(usize, Token, usize)>,
+    /// Comments in source order, consumed from `next_comment` onwards.
+    comments: Vec<Comment<'a>>,
+    next_comment: usize,
+    /// Set while trying to fit a bracketed construct on one line.
+    in_flat_attempt: bool,
+    out: String,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(codemap: &'a CodeMap, dialect: &Dialect) -> Self {
+        let source = codemap.source();
+        let mut tokens = Vec::new();
+        let mut comments = Vec::new();
+        // The module parsed, so lexing it again cannot fail.
+        for (begin, token, end) in Lexer::new(source, dialect, codemap.dupe()).flatten() {
+            match token {
+                Token::Comment(_) => comments.push(Comment {
+                    begin,
+                    end,
+                    text: source[begin..end].trim_end(),
+                }),
+                Token::Newline | Token::Indent | Token::Dedent | Token::Tabs => {}
+                token => tokens.push((begin, token, end)),
+            }
+        }
+        Formatter {
+            codemap,
+            source,
+            tokens,
+            comments,
+            next_comment: 0,
+            in_flat_attempt: false,
+            out: String::new(),
+        }
+    }
+
+    fn module(mut self, stmt: &AstStmt) -> String {
+        self.block(stmt, "", self.source.len());
+        while let Some(comment) = self.take_comment_before(usize::MAX) {
+            self.out.push_str(comment);
+            self.newline();
+        }
+        let len = self.out.trim_end().len();
+        self.out.truncate(len);
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        self.out
+    }
+
+    // Source positions.
+
+    fn line(&self, pos: usize) -> usize {
+        self.codemap.find_line(Pos::new(pos as u32))
+    }
+
+    fn column(&self, pos: usize) -> usize {
+        pos - self.codemap.line_span(self.line(pos)).begin().get() as usize
+    }
+
+    /// Is there an empty line between `after` and the line containing `pos`.
+    fn blank_line_before(&self, pos: usize, after: usize) -> bool {
+        let line = self.line(pos);
+        line > self.line(after) + 1 && self.codemap.source_line(line - 1).trim().is_empty()
+    }
+
+    /// Index of the first token starting at or after `pos`.
+    fn token_after(&self, pos: usize) -> usize {
+        self.tokens.partition_point(|t| t.0 < pos)
+    }
+
+    /// End of the last token in `span`.
+    /// Block spans also cover the blank lines after the last statement.
+    fn code_end(&self, span: Span) -> usize {
+        let after = self.token_after(span.end().get() as usize);
+        after
+            .checked_sub(1)
+            .map_or(span.begin().get() as usize, |i| self.tokens[i].2)
+    }
+
+    /// Start of the first token at or after `pos`, or the end of the file.
+    fn next_token_begin(&self, pos: usize) -> usize {
+        self.tokens
+            .get(self.token_after(pos))
+            .map_or(self.source.len(), |t| t.0)
+    }
+
+    /// Index of the token closing the bracket opened by the token at `open`.
+    fn matching_close(&self, open: usize) -> usize {
+        let mut depth = 0;
+        for (i, (_, token, _)) in self.tokens.iter().enumerate().skip(open) {
+            match token {
+                Token::OpeningRound | Token::OpeningSquare | Token::OpeningCurly => depth += 1,
+                Token::ClosingRound | Token::ClosingSquare | Token::ClosingCurly => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return i;
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.tokens.len() - 1
+    }
+
+    /// If `span` is wrapped in parentheses which only group it,
+    /// return the indices of the `(` and `)` tokens.
+    fn grouping_parens(&self, span: Span) -> Option<(usize, usize)> {
+        let begin = self
+            .tokens
+            .binary_search_by_key(&(span.begin().get() as usize), |t| t.0)
+            .ok()?;
+        let end = self
+            .tokens
+            .binary_search_by_key(&(span.end().get() as usize), |t| t.2)
+            .ok()?;
+        let open = begin.checked_sub(1)?;
+        let close = end + 1;
+        if !matches!(self.tokens[open].1, Token::OpeningRound)
+            || !matches!(self.tokens.get(close), Some((_, Token::ClosingRound, _)))
+        {
+            return None;
+        }
+        // Parentheses after something callable belong to a call, a `def` or a `load`.
+        let call = open.checked_sub(1).map_or(false, |i| {
+            matches!(
+                self.tokens[i].1,
+                Token::Identifier(_)
+                    | Token::String(_)
+                    | Token::FString(_)
+                    | Token::Int(_)
+                    | Token::Float(_)
+                    | Token::Ellipsis
+                    | Token::ClosingRound
+                    | Token::ClosingSquare
+                    | Token::ClosingCurly
+                    | Token::Load
+            )
+        });
+        if call {
+            None
+        } else {
+            Some((open, close))
+        }
+    }
+
+    // Output.
+
+    fn push(&mut self, s: &str) {
+        self.out.push_str(s);
+    }
+
+    fn newline(&mut self) {
+        let len = self.out.trim_end_matches(' ').len();
+        self.out.truncate(len);
+        self.out.push('\n');
+    }
+
+    fn blank_line(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    /// Width of the last output line.
+    fn out_width(&self) -> usize {
+        let line_start = self.out.rfind('\n').map_or(0, |i| i + 1);
+        self.out[line_start..].chars().count()
+    }
+
+    // Comments.
+
+    fn peek_comment(&self) -> Option<&Comment<'a>> {
+        self.comments.get(self.next_comment)
+    }
+
+    fn take_comment_before(&mut self, pos: usize) -> Option<&'a str> {
+        let comment = self.comments.get(self.next_comment)?;
+        if comment.begin < pos {
+            self.next_comment += 1;
+            Some(comment.text)
+        } else {
+            None
+        }
+    }
+
+    /// Emit comments starting before `pos` on their own lines.
+    /// `prev_end` is the end of the previously emitted code,
+    /// or `None` at the start of a block, where no blank line is kept.
+    fn own_line_comments(&mut self, pos: usize, indent: &str, prev_end: &mut Option<usize>) {
+        while let Some(comment) = self.peek_comment() {
+            if comment.begin >= pos {
+                break;
+            }
+            let (begin, end, text) = (comment.begin, comment.end, comment.text);
+            if prev_end.map_or(false, |prev| self.blank_line_before(begin, prev)) {
+                self.blank_line();
+            }
+            self.next_comment += 1;
+            self.push(indent);
+            self.push(text);
+            self.newline();
+            *prev_end = Some(end);
+        }
+    }
+
+    /// Finish the line for code ending at `end`.
+    ///
+    /// Comments inside the code which were not attached elsewhere are emitted
+    /// after it, together with the comment on the same line as `end` if it starts before `limit`.
+    fn finish_line(&mut self, end: usize, limit: usize, indent: &str) {
+        let mut pending = Vec::new();
+        while let Some(text) = self.take_comment_before(end) {
+            pending.push(text);
+        }
+        if let Some(comment) = self.peek_comment() {
+            if comment.begin < limit && self.line(comment.begin) == self.line(end) {
+                pending.push(comment.text);
+                self.next_comment += 1;
+            }
+        }
+        for (i, text) in pending.into_iter().enumerate() {
+            if i == 0 {
+                self.push("  ");
+            } else {
+                self.newline();
+                self.push(indent);
+            }
+            self.push(text);
+        }
+        self.newline();
+    }
+
+    // Statements.
+
+    fn flatten<'s>(stmt: &'s AstStmt, res: &mut Vec<&'s AstStmt>) {
+        match &stmt.node {
+            Stmt::Statements(stmts) => {
+                for stmt in stmts {
+                    Self::flatten(stmt, res);
+                }
+            }
+            _ => res.push(stmt),
+        }
+    }
+
+    /// Emit a block of statements.
+    /// Trailing comments up to `limit` indented at least as deep as the block belong to it.
+    fn block(&mut self, stmt: &AstStmt, indent: &str, limit: usize) {
+        let mut stmts = Vec::new();
+        Self::flatten(stmt, &mut stmts);
+        let mut prev_end = None;
+        for (i, stmt) in stmts.iter().enumerate() {
+            let begin = stmt.span.begin().get() as usize;
+            self.own_line_comments(begin, indent, &mut prev_end);
+            if prev_end.map_or(false, |prev| self.blank_line_before(begin, prev)) {
+                self.blank_line();
+            }
+            let next = stmts
+                .get(i + 1)
+                .map_or(usize::MAX, |s| s.span.begin().get() as usize);
+            self.stmt(stmt, indent, next);
+            prev_end = Some(self.code_end(stmt.span));
+        }
+        let column = stmts
+            .first()
+            .map_or(0, |s| self.column(s.span.begin().get() as usize));
+        while let Some(comment) = self.peek_comment() {
+            if comment.begin >= limit || self.column(comment.begin) < column {
+                break;
+            }
+            let begin = comment.begin;
+            self.own_line_comments(begin + 1, indent, &mut prev_end);
+        }
+    }
+
+    /// Emit the `:` ending a compound statement header, and then its body.
+    fn header_and_body(&mut self, body: &AstStmt, indent: &str) {
+        let body_begin = body.span.begin().get() as usize;
+        let colon = self.tokens[self.token_after(body_begin) - 1].2;
+        let body_indent = format!("{indent}{INDENT}");
+        self.push(":");
+        self.finish_line(colon, body_begin, &body_indent);
+        let body_end = body.span.end().get() as usize;
+        let limit = self.next_token_begin(body_end);
+        self.block(body, &body_indent, limit);
+    }
+
+    fn stmt(&mut self, stmt: &AstStmt, indent: &str, next: usize) {
+        let end = stmt.span.end().get() as usize;
+        match &stmt.node {
+            Stmt::Statements(_) => {
+                // Flattened by `block`.
+                self.block(stmt, indent, end);
+                return;
+            }
+            Stmt::If(cond, body) => return self.if_stmt("if", cond, body, None, indent),
+            Stmt::IfElse(cond, bodies) => {
+                return self.if_stmt("if", cond, &bodies.0, Some(&bodies.1), indent);
+            }
+            Stmt::For(ForP { var, over, body }) => {
+                self.push(indent);
+                self.push("for ");
+                self.target(var, indent);
+                self.push(" in ");
+                self.expr(over, indent);
+                return self.header_and_body(body, indent);
+            }
+            Stmt::Def(DefP {
+                name,
+                params,
+                return_type,
+                body,
+                payload: _,
+            }) => {
+                self.push(indent);
+                self.push("def ");
+                self.push(&name.ident);
+                let open = self.token_after(name.span.end().get() as usize);
+                let close = self.matching_close(open);
+                self.seq(
+                    "(",
+                    ")",
+                    &params.map(|p| p.span),
+                    self.tokens[open].2,
+                    self.tokens[close].0,
+                    false,
+                    indent,
+                    |this, i, indent| this.param(&params[i], indent),
+                );
+                if let Some(return_type) = return_type {
+                    self.push(" -> ");
+                    self.expr(&return_type.expr, indent);
+                }
+                return self.header_and_body(body, indent);
+            }
+            _ => {}
+        }
+        self.push(indent);
+        match &stmt.node {
+            Stmt::Break => self.push("break"),
+            Stmt::Continue => self.push("continue"),
+            Stmt::Pass => self.push("pass"),
+            Stmt::Return(None) => self.push("return"),
+            Stmt::Return(Some(e)) => {
+                self.push("return ");
+                self.expr(e, indent);
+            }
+            Stmt::Expression(e) => self.expr(e, indent),
+            Stmt::Assign(AssignP { lhs, ty, rhs }) => {
+                self.target(lhs, indent);
+                if let Some(ty) = ty {
+                    self.push(": ");
+                    self.expr(&ty.expr, indent);
+                }
+                self.push(" = ");
+                self.expr(rhs, indent);
+            }
+            Stmt::AssignModify(lhs, op, rhs) => {
+                self.target(lhs, indent);
+                self.push(&op.to_string());
+                self.expr(rhs, indent);
+            }
+            Stmt::Load(load) => self.load(stmt.span, load, indent),
+            Stmt::Statements(_) | Stmt::If(..) | Stmt::IfElse(..) | Stmt::For(_) | Stmt::Def(_) => {
+                unreachable!("handled above")
+            }
+        }
+        self.finish_line(end, next, indent);
+    }
+
+    fn if_stmt(
+        &mut self,
+        keyword: &str,
+        cond: &AstExpr,
+        then: &AstStmt,
+        els: Option<&AstStmt>,
+        indent: &str,
+    ) {
+        self.push(indent);
+        self.push(keyword);
+        self.push(" ");
+        self.expr(cond, indent);
+        self.header_and_body(then, indent);
+        let Some(els) = els else {
+            return;
+        };
+        let else_begin = els.span.begin().get() as usize;
+        let before = self.token_after(else_begin) - 1;
+        let (keyword, keyword_begin) = match &self.tokens[before] {
+            (begin, Token::Elif, _) => ("elif", *begin),
+            _ => ("else", self.tokens[before - 1].0),
+        };
+        self.own_line_comments(keyword_begin, indent, &mut None);
+        match (&els.node, keyword) {
+            (Stmt::If(cond, body), "elif") => self.if_stmt("elif", cond, body, None, indent),
+            (Stmt::IfElse(cond, bodies), "elif") => {
+                self.if_stmt("elif", cond, &bodies.0, Some(&bodies.1), indent)
+            }
+            _ => {
+                self.push(indent);
+                self.push("else");
+                self.header_and_body(els, indent);
+            }
+        }
+    }
+
+    fn load(&mut self, span: Span, load: &Load, indent: &str) {
+        self.push("load");
+        let open = self.token_after(span.begin().get() as usize) + 1;
+        let mut spans = vec![load.module.span];
+        spans.extend(load.args.iter().map(|arg| arg.span()));
+        self.seq(
+            "(",
+            ")",
+            &spans,
+            self.tokens[open].2,
+            span.end().get() as usize - 1,
+            false,
+            indent,
+            |this, i, _| match i {
+                0 => this.string(load.module.span),
+                _ => {
+                    let arg = &load.args[i - 1];
+                    if arg.local.ident != arg.their.node {
+                        this.push(&arg.local.ident);
+                        this.push(" = ");
+                    }
+                    this.string(arg.their.span);
+                }
+            },
+        );
+    }
+
+    // Collections.
+
+    /// Does the source have a line break right after `pos`.
+    fn line_break_after(&self, pos: usize) -> bool {
+        self.source[pos..self.next_token_begin(pos)].contains('\n')
+    }
+
+    /// Is there an unconsumed comment before `pos`.
+    fn comment_before(&self, pos: usize) -> bool {
+        self.peek_comment().map_or(false, |c| c.begin < pos)
+    }
+
+    /// Emit code with `flat`, and undo it if the line got too long.
+    ///
+    /// Constructs nested inside a flat attempt are always kept flat,
+    /// so that the outermost bracket is the one which gets split.
+    fn try_flat(&mut self, flat: impl FnOnce(&mut Self)) -> bool {
+        let mark = (self.out.len(), self.next_comment);
+        let nested = mem::replace(&mut self.in_flat_attempt, true);
+        flat(self);
+        self.in_flat_attempt = nested;
+        if nested || self.out[mark.0..].contains('\n') || self.out_width() <= MAX_WIDTH {
+            return true;
+        }
+        self.out.truncate(mark.0);
+        self.next_comment = mark.1;
+        false
+    }
+
+    /// Emit a bracketed, comma-separated sequence of items.
+    ///
+    /// `open_pos` is just after the opening bracket and `close_pos` is at the closing one.
+    /// The items are written flat unless the source had a line break after the opening bracket,
+    /// there are comments inside, or the line gets too long.
+    #[allow(clippy::too_many_arguments)]
+    fn seq(
+        &mut self,
+        open: &str,
+        close: &str,
+        spans: &[Span],
+        open_pos: usize,
+        close_pos: usize,
+        tuple: bool,
+        indent: &str,
+        item: impl Fn(&mut Self, usize, &str),
+    ) {
+        if !self.comment_before(close_pos) && (spans.is_empty() || !self.line_break_after(open_pos))
+        {
+            let flat = |this: &mut Self| {
+                this.push(open);
+                for i in 0..spans.len() {
+                    if i != 0 {
+                        this.push(", ");
+                    }
+                    item(this, i, indent);
+                }
+                if tuple && spans.len() == 1 {
+                    this.push(",");
+                }
+                this.push(close);
+            };
+            if spans.is_empty() {
+                return flat(self);
+            }
+            if self.try_flat(flat) {
+                return;
+            }
+        }
+
+        let inner = format!("{indent}{INDENT}");
+        self.push(open);
+        self.newline();
+        let mut prev_end = None;
+        for (i, span) in spans.iter().enumerate() {
+            let begin = span.begin().get() as usize;
+            self.own_line_comments(begin, &inner, &mut prev_end);
+            if prev_end.map_or(false, |prev| self.blank_line_before(begin, prev)) {
+                self.blank_line();
+            }
+            self.push(&inner);
+            item(self, i, &inner);
+            self.push(",");
+            let end = span.end().get() as usize;
+            let next = spans
+                .get(i + 1)
+                .map_or(close_pos, |s| s.begin().get() as usize);
+            self.finish_line(end, next, &inner);
+            prev_end = Some(end);
+        }
+        self.own_line_comments(close_pos, &inner, &mut prev_end);
+        self.push(indent);
+        self.push(close);
+    }
+
+    /// Emit a list or dict comprehension, one clause per line if the source split it.
+    #[allow(clippy::too_many_arguments)]
+    fn comprehension(
+        &mut self,
+        open: &str,
+        close: &str,
+        span: Span,
+        item: impl Fn(&mut Self, &str),
+        item_span: Span,
+        for_: &ForClause,
+        clauses: &[Clause],
+        indent: &str,
+    ) {
+        let open_pos = span.begin().get() as usize + 1;
+        let close_pos = span.end().get() as usize - 1;
+        let item_begin = item_span.begin().get() as usize;
+        let flat = |this: &mut Self| {
+            this.push(open);
+            item(this, indent);
+            this.for_clause(for_, " ", indent);
+            for clause in clauses {
+                this.clause(clause, " ", indent);
+            }
+            this.push(close);
+        };
+        if !self.comment_before(close_pos)
+            && !self.line_break_after(open_pos)
+            && self.try_flat(flat)
+        {
+            return;
+        }
+
+        let inner = format!("{indent}{INDENT}");
+        self.push(open);
+        self.newline();
+        self.own_line_comments(item_begin, &inner, &mut None);
+        self.push(&inner);
+        item(self, &inner);
+        let mut parts = vec![(for_.var.span, None)];
+        parts.extend(clauses.iter().map(|c| match c {
+            Clause::For(f) => (f.var.span, Some(c)),
+            Clause::If(e) => (e.span, Some(c)),
+        }));
+        for (span, clause) in parts {
+            // Each clause starts a new line at its `for` or `if` keyword.
+            let before = self.token_after(span.begin().get() as usize);
+            let keyword = self.tokens[..before]
+                .iter()
+                .rposition(|t| matches!(t.1, Token::For | Token::If))
+                .unwrap_or(before - 1);
+            let keyword_begin = self.tokens[keyword].0;
+            self.finish_line(self.tokens[keyword - 1].2, keyword_begin, &inner);
+            self.own_line_comments(keyword_begin, &inner, &mut None);
+            self.push(&inner);
+            match clause {
+                None => self.for_clause(for_, "", &inner),
+                Some(clause) => self.clause(clause, "", &inner),
+            }
+        }
+        let last = self.tokens[self.token_after(close_pos) - 1].2;
+        self.finish_line(last, close_pos, &inner);
+        self.own_line_comments(close_pos, &inner, &mut None);
+        self.push(indent);
+        self.push(close);
+    }
+
+    fn for_clause(&mut self, for_: &ForClause, sep: &str, indent: &str) {
+        self.push(sep);
+        self.push("for ");
+        self.target(&for_.var, indent);
+        self.push(" in ");
+        self.expr(&for_.over, indent);
+    }
+
+    fn clause(&mut self, clause: &Clause, sep: &str, indent: &str) {
+        match clause {
+            Clause::For(for_) => self.for_clause(for_, sep, indent),
+            Clause::If(cond) => {
+                self.push(sep);
+                self.push("if ");
+                self.expr(cond, indent);
+            }
+        }
+    }
+
+    // Expressions.
+
+    /// Emit a string literal, preferring double quotes.
+    fn string(&mut self, span: Span) {
+        let codemap = self.codemap;
+        let text = codemap.source_span(span);
+        if text.len() >= 2 && text.starts_with('\'') && !text.starts_with("'''") {
+            let inner = &text[1..text.len() - 1];
+            if !inner.contains(['"', '\\']) {
+                self.push("\"");
+                self.push(inner);
+                self.push("\"");
+                return;
+            }
+        }
+        self.push(text);
+    }
+
+    fn expr(&mut self, expr: &AstExpr, indent: &str) {
+        let parens = self.grouping_parens(expr.span);
+        match (&expr.node, parens) {
+            (Expr::Tuple(xs), Some((open, close))) if !xs.is_empty() => {
+                return self.seq(
+                    "(",
+                    ")",
+                    &xs.map(|x| x.span),
+                    self.tokens[open].2,
+                    self.tokens[close].0,
+                    true,
+                    indent,
+                    |this, i, indent| this.expr(&xs[i], indent),
+                );
+            }
+            (Expr::Op(..), Some((open, close)))
+                if self.comment_before(self.tokens[close].0)
+                    || self.line_break_after(self.tokens[open].2) =>
+            {
+                return self.operands(expr, self.tokens[close].0, indent);
+            }
+            (_, Some(_)) => self.push("("),
+            (_, None) => {}
+        }
+        let begin = expr.span.begin().get() as usize;
+        let end = expr.span.end().get() as usize;
+        match &expr.node {
+            Expr::Tuple(xs) if xs.is_empty() => self.push("()"),
+            Expr::Tuple(xs) => {
+                for (i, x) in xs.iter().enumerate() {
+                    if i != 0 {
+                        self.push(", ");
+                    }
+                    self.expr(x, indent);
+                }
+                if xs.len() == 1 {
+                    self.push(",");
+                }
+            }
+            Expr::Dot(e, name) => {
+                self.expr(e, indent);
+                self.push(".");
+                self.push(&name.node);
+            }
+            Expr::Call(f, args) => {
+                self.expr(f, indent);
+                let open = self.token_after(f.span.end().get() as usize);
+                let open = open
+                    + self.tokens[open..]
+                        .iter()
+                        .position(|t| matches!(t.1, Token::OpeningRound))
+                        .unwrap_or(0);
+                self.seq(
+                    "(",
+                    ")",
+                    &args.args.map(|a| a.span),
+                    self.tokens[open].2,
+                    end - 1,
+                    false,
+                    indent,
+                    |this, i, indent| this.arg(&args.args[i], indent),
+                );
+            }
+            Expr::Index(e_i) => {
+                self.expr(&e_i.0, indent);
+                self.push("[");
+                self.expr(&e_i.1, indent);
+                self.push("]");
+            }
+            Expr::Index2(e_i) => {
+                self.expr(&e_i.0, indent);
+                self.push("[");
+                self.expr(&e_i.1, indent);
+                self.push(", ");
+                self.expr(&e_i.2, indent);
+                self.push("]");
+            }
+            Expr::Slice(e, start, stop, step) => {
+                self.expr(e, indent);
+                self.push("[");
+                if let Some(start) = start {
+                    self.expr(start, indent);
+                }
+                self.push(":");
+                if let Some(stop) = stop {
+                    self.expr(stop, indent);
+                }
+                if let Some(step) = step {
+                    self.push(":");
+                    self.expr(step, indent);
+                }
+                self.push("]");
+            }
+            Expr::Identifier(ident) => self.push(&ident.node.ident),
+            Expr::Lambda(LambdaP {
+                params,
+                body,
+                payload: _,
+            }) => {
+                self.push("lambda");
+                for (i, param) in params.iter().enumerate() {
+                    self.push(if i == 0 { " " } else { ", " });
+                    self.param(param, indent);
+                }
+                self.push(": ");
+                self.expr(body, indent);
+            }
+            Expr::Literal(AstLiteral::String(s)) => self.string(s.span),
+            Expr::Literal(AstLiteral::Int(_) | AstLiteral::Float(_)) | Expr::FString(_) => {
+                let source = self.source;
+                self.push(&source[begin..end]);
+            }
+            Expr::Literal(AstLiteral::Ellipsis) => self.push("..."),
+            Expr::Not(e) => {
+                self.push("not ");
+                self.expr(e, indent);
+            }
+            Expr::Minus(e) => {
+                self.push("-");
+                self.expr(e, indent);
+            }
+            Expr::Plus(e) => {
+                self.push("+");
+                self.expr(e, indent);
+            }
+            Expr::BitNot(e) => {
+                self.push("~");
+                self.expr(e, indent);
+            }
+            Expr::Op(l, op, r) => {
+                self.expr(l, indent);
+                self.push(&op.to_string());
+                self.expr(r, indent);
+            }
+            Expr::If(cond_then_else) => {
+                let (cond, then, els) = &**cond_then_else;
+                self.expr(then, indent);
+                self.push(" if ");
+                self.expr(cond, indent);
+                self.push(" else ");
+                self.expr(els, indent);
+            }
+            Expr::List(xs) => self.seq(
+                "[",
+                "]",
+                &xs.map(|x| x.span),
+                begin + 1,
+                end - 1,
+                false,
+                indent,
+                |this, i, indent| this.expr(&xs[i], indent),
+            ),
+            Expr::Dict(xs) => self.seq(
+                "{",
+                "}",
+                &xs.map(|(k, v)| k.span.merge(v.span)),
+                begin + 1,
+                end - 1,
+                false,
+                indent,
+                |this, i, indent| {
+                    this.expr(&xs[i].0, indent);
+                    this.push(": ");
+                    this.expr(&xs[i].1, indent);
+                },
+            ),
+            Expr::ListComprehension(x, for_, clauses) => self.comprehension(
+                "[",
+                "]",
+                expr.span,
+                |this, indent| this.expr(x, indent),
+                x.span,
+                for_,
+                clauses,
+                indent,
+            ),
+            Expr::DictComprehension(k_v, for_, clauses) => self.comprehension(
+                "{",
+                "}",
+                expr.span,
+                |this, indent| {
+                    this.expr(&k_v.0, indent);
+                    this.push(": ");
+                    this.expr(&k_v.1, indent);
+                },
+                k_v.0.span.merge(k_v.1.span),
+                for_,
+                clauses,
+                indent,
+            ),
+        }
+        if parens.is_some() {
+            self.push(")");
+        }
+    }
+
+    /// Emit a parenthesized chain of binary operators one operand per line,
+    /// so the comments between the operands have a place to go.
+    fn operands(&mut self, expr: &AstExpr, close_pos: usize, indent: &str) {
+        let Expr::Op(_, op, _) = &expr.node else {
+            unreachable!("only called for binary operators")
+        };
+        let mut operands = Vec::new();
+        let mut e = expr;
+        loop {
+            match &e.node {
+                Expr::Op(l, o, r)
+                    if o == op && (ptr::eq(e, expr) || self.grouping_parens(e.span).is_none()) =>
+                {
+                    operands.push(&**r);
+                    e = l;
+                }
+                _ => break operands.push(e),
+            }
+        }
+        operands.reverse();
+
+        let inner = format!("{indent}{INDENT}");
+        let op = op.to_string();
+        self.push("(");
+        self.newline();
+        let mut prev_end = None;
+        for (i, operand) in operands.iter().enumerate() {
+            let begin = operand.span.begin().get() as usize;
+            self.own_line_comments(begin, &inner, &mut prev_end);
+            self.push(&inner);
+            self.expr(operand, &inner);
+            let next = match operands.get(i + 1) {
+                Some(next) => {
+                    self.push(op.trim_end());
+                    next.span.begin().get() as usize
+                }
+                None => close_pos,
+            };
+            let end = self.code_end(operand.span);
+            self.finish_line(end, next, &inner);
+            prev_end = Some(end);
+        }
+        self.own_line_comments(close_pos, &inner, &mut prev_end);
+        self.push(indent);
+        self.push(")");
+    }
+
+    fn target(&mut self, target: &AstAssignTarget, indent: &str) {
+        let parens = self.grouping_parens(target.span);
+        match &target.node {
+            AssignTarget::Tuple(xs) => {
+                let begin = target.span.begin().get() as usize;
+                let end = target.span.end().get() as usize;
+                let brackets = if self.source[begin..].starts_with('[') {
+                    Some(("[", "]", begin + 1, end - 1))
+                } else {
+                    parens
+                        .map(|(open, close)| ("(", ")", self.tokens[open].2, self.tokens[close].0))
+                };
+                match brackets {
+                    Some((open, close, open_pos, close_pos)) => self.seq(
+                        open,
+                        close,
+                        &xs.map(|x| x.span),
+                        open_pos,
+                        close_pos,
+                        open == "(",
+                        indent,
+                        |this, i, indent| this.target(&xs[i], indent),
+                    ),
+                    None => {
+                        for (i, x) in xs.iter().enumerate() {
+                            if i != 0 {
+                                self.push(", ");
+                            }
+                            self.target(x, indent);
+                        }
+                        if xs.len() == 1 {
+                            self.push(",");
+                        }
+                    }
+                }
+                return;
+            }
+            _ if parens.is_some() => self.push("("),
+            _ => {}
+        }
+        match &target.node {
+            AssignTarget::Tuple(_) => unreachable!("handled above"),
+            AssignTarget::Index(e_i) => {
+                self.expr(&e_i.0, indent);
+                self.push("[");
+                self.expr(&e_i.1, indent);
+                self.push("]");
+            }
+            AssignTarget::Dot(e, name) => {
+                self.expr(e, indent);
+                self.push(".");
+                self.push(&name.node);
+            }
+            AssignTarget::Identifier(ident) => self.push(&ident.node.ident),
+        }
+        if parens.is_some() {
+            self.push(")");
+        }
+    }
+
+    fn arg(&mut self, arg: &AstArgument, indent: &str) {
+        match &arg.node {
+            Argument::Positional(e) => self.expr(e, indent),
+            Argument::Named(name, e) => {
+                self.push(&name.node);
+                self.push(" = ");
+                self.expr(e, indent);
+            }
+            Argument::Args(e) => {
+                self.push("*");
+                self.expr(e, indent);
+            }
+            Argument::KwArgs(e) => {
+                self.push("**");
+                self.expr(e, indent);
+            }
+        }
+    }
+
+    fn param(&mut self, param: &AstParameter, indent: &str) {
+        let (prefix, name, ty, default) = match &param.node {
+            Parameter::Slash => return self.push("/"),
+            Parameter::NoArgs => return self.push("*"),
+            Parameter::Normal(name, ty, default) => ("", name, ty, default.as_deref()),
+            Parameter::Args(name, ty) => ("*", name, ty, None),
+            Parameter::KwArgs(name, ty) => ("**", name, ty, None),
+        };
+        self.push(prefix);
+        self.push(&name.ident);
+        if let Some(ty) = ty {
+            self.push(": ");
+            self.expr(&ty.expr, indent);
+        }
+        if let Some(default) = default {
+            self.push(" = ");
+            self.expr(default, indent);
+        }
+    }
+}
@@ -0,0 +1,196 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use dupe::Dupe;
+
+use crate::codemap::CodeMap;
+use crate::golden_test_template::golden_test_template;
+use crate::lexer::Lexer;
+use crate::lexer::Token;
+use crate::syntax::grammar_tests;
+use crate::syntax::Dialect;
+
+fn comments(program: &str) -> Vec<String> {
+    let codemap = CodeMap::new("x.star".to_owned(), program.to_owned());
+    Lexer::new(program, &Dialect::AllOptionsInternal, codemap.dupe())
+        .filter_map(|t| match t.unwrap().1 {
+            Token::Comment(c) => Some(c.trim_end().to_owned()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Format `program` and check the result parses to the same AST,
+/// keeps all the comments in order, and is a fixed point of formatting.
+pub(crate) fn format(program: &str) -> String {
+    let formatted = grammar_tests::parse_ast(program).format();
+    assert_eq!(
+        grammar_tests::parse(program),
+        grammar_tests::parse(&formatted),
+        "formatting changed the meaning of:\n{program}\nformatted:\n{formatted}"
+    );
+    assert_eq!(
+        comments(program),
+        comments(&formatted),
+        "formatting lost comments of:\n{program}\nformatted:\n{formatted}"
+    );
+    assert_eq!(
+        formatted,
+        grammar_tests::parse_ast(&formatted).format(),
+        "formatting is not idempotent for:\n{program}"
+    );
+    formatted
+}
+
+fn format_golden(name: &str, program: &str) {
+    let program = program.trim();
+    let formatted = format(program);
+    golden_test_template(
+        &format!("src/syntax/format_tests/{name}.golden"),
+        &format!("Program:\n{program}\n\nFormatted:\n{formatted}"),
+    );
+}
+
+#[test]
+fn test_spacing() {
+    assert_eq!(format("x=1+2*y"), "x = 1 + 2 * y\n");
+    assert_eq!(format("f(a,b=1,*c,**d)"), "f(a, b = 1, *c, **d)\n");
+    assert_eq!(
+        format("x[1:2]\nx[::2]\nx[a,b]"),
+        "x[1:2]\nx[::2]\nx[a, b]\n"
+    );
+    assert_eq!(format("x = lambda a,b=1: a"), "x = lambda a, b = 1: a\n");
+    assert_eq!(format("x = {1:2}"), "x = {1: 2}\n");
+    assert_eq!(format("x = 1,"), "x = 1,\n");
+    assert_eq!(format("a, b = c"), "a, b = c\n");
+    assert_eq!(format("[a, b] = c"), "[a, b] = c\n");
+}
+
+#[test]
+fn test_empty() {
+    assert_eq!(format(""), "");
+    assert_eq!(format("\n\n"), "");
+    assert_eq!(format("# only\n\n"), "# only\n");
+}
+
+#[test]
+fn test_strings() {
+    assert_eq!(format("x = 'a'"), "x = \"a\"\n");
+    assert_eq!(format("x = 'a\"b'"), "x = 'a\"b'\n");
+    assert_eq!(format("x = 'a\\n'"), "x = 'a\\n'\n");
+    assert_eq!(format("x = r'a'"), "x = r'a'\n");
+    assert_eq!(format("x = '''a'''"), "x = '''a'''\n");
+    assert_eq!(format("x = 0x10 + 1.50"), "x = 0x10 + 1.50\n");
+}
+
+#[test]
+fn test_grouping_parens() {
+    assert_eq!(format("x = (a+b)*c"), "x = (a + b) * c\n");
+    assert_eq!(format("x = ((a))"), "x = (a)\n");
+    assert_eq!(format("x = (1, 2)"), "x = (1, 2)\n");
+    assert_eq!(format("x = (1,)"), "x = (1,)\n");
+    assert_eq!(format("x = ()"), "x = ()\n");
+    assert_eq!(format("f((a, b))"), "f((a, b))\n");
+    assert_eq!(
+        format("for (k, v) in x: pass"),
+        "for (k, v) in x:\n    pass\n"
+    );
+}
+
+#[test]
+fn test_statements() {
+    assert_eq!(format("x = 1; y = 2"), "x = 1\ny = 2\n");
+    assert_eq!(
+        format("if x: pass\nelif y: pass\nelse: pass"),
+        "if x:\n    pass\nelif y:\n    pass\nelse:\n    pass\n"
+    );
+    assert_eq!(
+        format("if x: pass\nelse:\n  if y: pass"),
+        "if x:\n    pass\nelse:\n    if y:\n        pass\n"
+    );
+}
+
+#[test]
+fn test_long_line_is_split() {
+    let program = format!("x = [{}]", ["\"aaaaaaaaaa\""; 10].join(", "));
+    let formatted = format(&program);
+    assert_eq!(formatted.lines().count(), 12);
+    assert!(formatted.starts_with("x = [\n    \"aaaaaaaaaa\",\n"));
+}
+
+#[test]
+fn test_format_comments() {
+    format_golden(
+        "comments",
+        r#"
+# Header.
+
+load("//a.bzl", "a", c = "b")  # Trailing load comment.
+
+# Leading comment.
+def f(x, y = 1):  # Header comment.
+  # First in body.
+  if x:
+    return y # Return.
+    # End of if.
+  # Before else.
+  else:
+    pass
+
+
+  # Last in body.
+
+# Between.
+X = [
+  1,  # One.
+
+  # Before two.
+  2,
+  # Dangling.
+]
+Y = {"a": 1, # A.
+  "b": 2}
+Z = [
+    x
+    # Before clause.
+    for x in X  # Clause.
+    if x
+]
+if (X or
+    # Either.
+    Y or Z):  # Condition.
+    pass
+"#,
+    );
+}
+
+#[test]
+fn test_format_build_file() {
+    format_golden(
+        "build_file",
+        r#"
+load('@prelude//:rules.bzl','cc_library')
+cc_library(name='foo',srcs=glob(['*.c']),
+    deps=[':bar'])
+cc_library(
+  name = 'bar',
+  visibility = ['PUBLIC'],
+  srcs = ['bar.c'] + select({'DEFAULT': [], 'ovr_config//os:linux': ['linux.c']}),
+)
+"#,
+    );
+}
@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 
+use crate::syntax::format_tests;
 use crate::syntax::grammar_tests;
 
 macro_rules! testcases_parse {
@@ -214,3 +215,10 @@ fn parsing_testcases() {
         grammar_tests::parse(content);
     }
 }
+
+#[test]
+fn formatting_testcases() {
+    for (_, content) in TESTCASE_FILES {
+        format_tests::format(content);
+    }
+}
@@ -16,7 +16,9 @@ pub use alignment::Aligned;
 pub use blank::Blank;
 pub use bordering::Bordered;
 pub use bounding::Bounded;
+pub use detail_pane::DetailPane;
 pub use padding::Padded;
+pub use scrolling::ScrollableList;
 pub use splitting::Split;
 
 pub use crate::components::draw_horizontal::DrawHorizontal;
@@ -28,10 +30,12 @@ pub mod alignment;
 mod blank;
 pub mod bordering;
 mod bounding;
+mod detail_pane;
 mod draw_horizontal;
 mod draw_vertical;
 pub(crate) mod echo;
 pub mod padding;
+mod scrolling;
 pub mod splitting;
 
 /// Used to mark whether a draw is final.
@@ -0,0 +1,112 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use crossterm::style::Attribute;
+
+use crate::Component;
+use crate::Dimensions;
+use crate::DrawMode;
+use crate::Line;
+use crate::Lines;
+use crate::Span;
+
+/// The `DetailPane` [`Component`] draws a bold title, a separator and a body below it.
+/// If the body does not fit in the render space, its last visible line says how many lines were cut.
+#[derive(Debug)]
+pub struct DetailPane {
+    title: Line,
+    body: Lines,
+}
+
+impl DetailPane {
+    pub fn new(title: Line, body: Lines) -> Self {
+        Self { title, body }
+    }
+}
+
+impl Component for DetailPane {
+    fn draw_unchecked(&self, dimensions: Dimensions, _mode: DrawMode) -> anyhow::Result<Lines> {
+        let title = self
+            .title
+            .iter()
+            .map(|span| {
+                let mut span = span.clone();
+                span.style.attributes.set(Attribute::Bold);
+                span
+            })
+            .collect::<Line>();
+        let separator = "-".repeat(self.title.len().clamp(1, dimensions.width.max(1)));
+        let mut lines = Lines(vec![
+            title,
+            Line::from_iter([Span::new_unstyled_lossy(separator)]),
+        ]);
+
+        let height = dimensions.height.saturating_sub(lines.len());
+        if self.body.len() <= height {
+            lines.extend(self.body.iter().cloned());
+        } else if height > 0 {
+            lines.extend(self.body.iter().take(height - 1).cloned());
+            lines.push(Line::from_iter([Span::new_unstyled_lossy(format!(
+                "... {} more lines",
+                self.body.len() - (height - 1)
+            ))]));
+        }
+        Ok(lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pane(body_lines: usize) -> DetailPane {
+        DetailPane::new(
+            Line::unstyled("title").unwrap(),
+            (0..body_lines)
+                .map(|i| Line::unstyled(&format!("line {}", i)).unwrap())
+                .collect(),
+        )
+    }
+
+    fn draw(pane: &DetailPane, height: usize) -> anyhow::Result<Vec<String>> {
+        Ok(pane
+            .draw(Dimensions::new(20, height), DrawMode::Normal)?
+            .iter()
+            .map(|line| line.to_unstyled())
+            .collect())
+    }
+
+    #[test]
+    fn test_fits() -> anyhow::Result<()> {
+        assert_eq!(
+            vec!["title", "-----", "line 0", "line 1"],
+            draw(&pane(2), 10)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncated() -> anyhow::Result<()> {
+        assert_eq!(
+            vec!["title", "-----", "line 0", "... 4 more lines"],
+            draw(&pane(5), 4)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_title_is_bold() -> anyhow::Result<()> {
+        let output = pane(0).draw(Dimensions::new(20, 10), DrawMode::Normal)?;
+        let title = output.iter().next().unwrap();
+        assert!(title
+            .iter()
+            .all(|span| span.style.attributes.has(Attribute::Bold)));
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use crossterm::style::Attribute;
+
+use crate::Component;
+use crate::Dimensions;
+use crate::DrawMode;
+use crate::Line;
+use crate::Lines;
+use crate::Span;
+
+/// The `ScrollableList` [`Component`] draws a list of single line items, one of which may be selected.
+/// When there are more items than fit in the render space, only a window of them is drawn, scrolled so that the selected item is visible.
+/// The selected item is drawn in reverse video.
+#[derive(Debug)]
+pub struct ScrollableList {
+    items: Vec<Line>,
+    selected: Option<usize>,
+}
+
+impl ScrollableList {
+    /// `selected` is clamped to the last item.
+    pub fn new(items: Vec<Line>, selected: Option<usize>) -> Self {
+        let selected = selected.map(|selected| selected.min(items.len().saturating_sub(1)));
+        Self { items, selected }
+    }
+
+    /// The range of items that fit in `height` lines.
+    fn window(&self, height: usize) -> std::ops::Range<usize> {
+        if self.items.len() <= height {
+            return 0..self.items.len();
+        }
+        // Keep the selected item in the middle of the window when possible.
+        let start = match self.selected {
+            Some(selected) => selected
+                .saturating_sub(height / 2)
+                .min(self.items.len() - height),
+            None => 0,
+        };
+        start..start + height
+    }
+}
+
+fn highlight(line: &Line) -> Line {
+    line.iter()
+        .map(|span| {
+            let mut span = span.clone();
+            span.style.attributes.set(Attribute::Reverse);
+            span
+        })
+        .collect()
+}
+
+impl Component for ScrollableList {
+    fn draw_unchecked(&self, dimensions: Dimensions, _mode: DrawMode) -> anyhow::Result<Lines> {
+        let window = self.window(dimensions.height);
+        let mut lines = Lines::new();
+        for (i, item) in self.items[window.clone()].iter().enumerate() {
+            if Some(window.start + i) == self.selected {
+                let mut item = item.clone();
+                // Highlight the whole row, not just the text.
+                item.pad_right(dimensions.width.saturating_sub(item.len()));
+                lines.push(highlight(&item));
+            } else {
+                lines.push(item.clone());
+            }
+        }
+        if lines.is_empty() {
+            lines.push(Line::from_iter([Span::new_unstyled_lossy("<empty>")]));
+        }
+        Ok(lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(count: usize) -> Vec<Line> {
+        (0..count)
+            .map(|i| Line::unstyled(&format!("item {}", i)).unwrap())
+            .collect()
+    }
+
+    fn draw(list: &ScrollableList, height: usize) -> anyhow::Result<Vec<String>> {
+        Ok(list
+            .draw(Dimensions::new(10, height), DrawMode::Normal)?
+            .iter()
+            .map(|line| line.to_unstyled().trim_end().to_owned())
+            .collect())
+    }
+
+    #[test]
+    fn test_fits() -> anyhow::Result<()> {
+        let list = ScrollableList::new(items(3), None);
+        assert_eq!(vec!["item 0", "item 1", "item 2"], draw(&list, 5)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scrolls_to_selection() -> anyhow::Result<()> {
+        let list = ScrollableList::new(items(10), Some(0));
+        assert_eq!(vec!["item 0", "item 1", "item 2"], draw(&list, 3)?);
+
+        let list = ScrollableList::new(items(10), Some(5));
+        assert_eq!(vec!["item 4", "item 5", "item 6"], draw(&list, 3)?);
+
+        let list = ScrollableList::new(items(10), Some(20));
+        assert_eq!(vec!["item 7", "item 8", "item 9"], draw(&list, 3)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_highlights_selection() -> anyhow::Result<()> {
+        let list = ScrollableList::new(items(2), Some(1));
+        let output = list.draw(Dimensions::new(10, 5), DrawMode::Normal)?;
+        let styles: Vec<bool> = output
+            .iter()
+            .map(|line| {
+                line.iter()
+                    .all(|span| span.style.attributes.has(Attribute::Reverse))
+            })
+            .collect();
+        assert_eq!(vec![false, true], styles);
+        assert_eq!(10, output.iter().nth(1).unwrap().len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty() -> anyhow::Result<()> {
+        let list = ScrollableList::new(Vec::new(), Some(0));
+        assert_eq!(vec!["<empty>"], draw(&list, 3)?);
+        Ok(())
+    }
+}
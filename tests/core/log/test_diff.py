@@ -152,3 +152,50 @@ async def test_config_diff_command_project_relative(buck: Buck) -> None:
     # We only store the path of the modefile
     assert diff[0]["FirstOnly"]["key"] == "my_mode_a.bcfg"
     assert diff[1]["SecondOnly"]["key"] == "my_mode_b.bcfg"
+
+
+@buck_test()
+async def test_no_analysis_divergence_command(buck: Buck) -> None:
+    await buck.build("//:multi", *with_buck2_key_value("outputs", "a.txt"))
+    await buck.build("//:multi", *with_buck2_key_value("outputs", "a.txt"))
+    out = await buck.log(
+        "diff", "analysis-divergence", "--recent1", "1", "--recent2", "0"
+    )
+
+    assert "No divergent analyses found." in out.stdout
+
+
+@buck_test()
+async def test_analysis_divergence_command(buck: Buck) -> None:
+    await buck.build("//:multi", *with_buck2_key_value("outputs", "a.txt"))
+    await buck.build("//:multi", *with_buck2_key_value("outputs", "a.txt,b.txt"))
+    out = await buck.log(
+        "diff", "analysis-divergence", "--recent1", "1", "--recent2", "0"
+    )
+
+    assert "prelude//:multi (<unspecified>)" in out.stdout
+    assert "declared actions: first: 1 \t second: 2" in out.stdout
+    assert "second only: write b.txt" in out.stdout
+
+
+@buck_test()
+async def test_no_configuration_divergence_command(buck: Buck) -> None:
+    await buck.build("//:selected", "--target-platforms", "//:linux_platform")
+    await buck.build("//:selected", "--target-platforms", "//:linux_platform")
+    out = await buck.log(
+        "diff", "configuration-divergence", "--recent1", "1", "--recent2", "0"
+    )
+
+    assert "No divergent configurations found." in out.stdout
+
+
+@buck_test()
+async def test_configuration_divergence_command(buck: Buck) -> None:
+    await buck.build("//:selected", "--target-platforms", "//:linux_platform")
+    await buck.build("//:selected", "--target-platforms", "//:macos_platform")
+    out = await buck.log(
+        "diff", "configuration-divergence", "--recent1", "1", "--recent2", "0"
+    )
+
+    assert "prelude//:selected\nfirst: prelude//:linux_platform#" in out.stdout
+    assert "prelude//:os: prelude//:linux | prelude//:macos" in out.stdout
@@ -24,3 +24,60 @@ trivial_build = rule(
     impl = _trivial_build,
     attrs = {},
 )
+
+# Rule that writes one output per entry of `outputs`, so its analysis depends on the attribute
+def _multi_output_build(ctx):
+    return [DefaultInfo(default_outputs = [ctx.actions.write(out, out) for out in ctx.attrs.outputs])]
+
+multi_output_build = rule(
+    impl = _multi_output_build,
+    attrs = {
+        "outputs": attrs.list(attrs.string()),
+    },
+)
+
+def _constraint_setting(ctx):
+    return [DefaultInfo(), ConstraintSettingInfo(label = ctx.label.raw_target())]
+
+constraint_setting = rule(
+    impl = _constraint_setting,
+    is_configuration_rule = True,
+    attrs = {},
+)
+
+def _constraint_value(ctx):
+    value = ConstraintValueInfo(
+        setting = ctx.attrs.constraint_setting[ConstraintSettingInfo],
+        label = ctx.label.raw_target(),
+    )
+    return [
+        DefaultInfo(),
+        value,
+        ConfigurationInfo(constraints = {value.setting.label: value}, values = {}),
+    ]
+
+constraint_value = rule(
+    impl = _constraint_value,
+    is_configuration_rule = True,
+    attrs = {
+        "constraint_setting": attrs.dep(providers = [ConstraintSettingInfo]),
+    },
+)
+
+def _platform(ctx):
+    value = ctx.attrs.constraint_value[ConstraintValueInfo]
+    return [
+        DefaultInfo(),
+        PlatformInfo(
+            label = str(ctx.label.raw_target()),
+            configuration = ConfigurationInfo(constraints = {value.setting.label: value}, values = {}),
+        ),
+    ]
+
+platform = rule(
+    impl = _platform,
+    is_configuration_rule = True,
+    attrs = {
+        "constraint_value": attrs.dep(providers = [ConstraintValueInfo]),
+    },
+)
@@ -81,6 +81,98 @@ async def test_deps(buck: Buck) -> None:
     )
 
 
+@buck_test()
+async def test_inputs_of(buck: Buck) -> None:
+    stdout = (
+        await buck.aquery("inputs_of(all_actions(//:test))", "-a", "identifier")
+    ).stdout
+
+    golden(
+        output=stdout,
+        rel_path="inputs_of.golden.json",
+    )
+
+
+@buck_test()
+async def test_consumers(buck: Buck) -> None:
+    stdout = (
+        await buck.aquery(
+            "consumers(all_actions(//:test), identifier('^dep$', all_actions(//:test)))",
+            "-a",
+            "identifier",
+        )
+    ).stdout
+
+    golden(
+        output=stdout,
+        rel_path="consumers.golden.json",
+    )
+
+
+@buck_test()
+async def test_producers(buck: Buck) -> None:
+    stdout = (
+        await buck.aquery(
+            "producers('/sub_', all_actions(//:test))",
+            "-a",
+            "identifier",
+        )
+    ).stdout
+
+    golden(
+        output=stdout,
+        rel_path="producers.golden.json",
+    )
+
+
+@buck_test()
+async def test_category(buck: Buck) -> None:
+    stdout = (
+        await buck.aquery(
+            "category('^copy$', all_actions(//:test))",
+            "-a",
+            "identifier",
+        )
+    ).stdout
+
+    golden(
+        output=stdout,
+        rel_path="category.golden.json",
+    )
+
+
+@buck_test()
+async def test_identifier(buck: Buck) -> None:
+    stdout = (
+        await buck.aquery(
+            "identifier('^sub_', all_actions(//:test))",
+            "-a",
+            "identifier",
+        )
+    ).stdout
+
+    golden(
+        output=stdout,
+        rel_path="identifier.golden.json",
+    )
+
+
+@buck_test()
+async def test_executor(buck: Buck) -> None:
+    stdout = (
+        await buck.aquery(
+            "executor('local|hybrid', identifier('^dep$', all_actions(//:test)))",
+            "-a",
+            "identifier",
+        )
+    ).stdout
+
+    golden(
+        output=stdout,
+        rel_path="executor.golden.json",
+    )
+
+
 @buck_test()
 async def test_bxl_aquery_target(buck: Buck) -> None:
     stdout = (await buck.bxl("//:aquery.bxl:target")).stdout
@@ -153,3 +245,63 @@ async def test_bxl_aquery_eval(buck: Buck) -> None:
 @buck_test()
 async def test_bxl_aquery_action_query_node(buck: Buck) -> None:
     await buck.bxl("//:aquery.bxl:action_query_node")
+
+
+@buck_test()
+async def test_bxl_aquery_inputs_of(buck: Buck) -> None:
+    stdout = (await buck.bxl("//:aquery.bxl:inputs_of")).stdout
+
+    golden(
+        output=stdout,
+        rel_path="bxl_inputs_of.golden.json",
+    )
+
+
+@buck_test()
+async def test_bxl_aquery_consumers(buck: Buck) -> None:
+    stdout = (await buck.bxl("//:aquery.bxl:consumers")).stdout
+
+    golden(
+        output=stdout,
+        rel_path="bxl_consumers.golden.json",
+    )
+
+
+@buck_test()
+async def test_bxl_aquery_producers(buck: Buck) -> None:
+    stdout = (await buck.bxl("//:aquery.bxl:producers")).stdout
+
+    golden(
+        output=stdout,
+        rel_path="bxl_producers.golden.json",
+    )
+
+
+@buck_test()
+async def test_bxl_aquery_category(buck: Buck) -> None:
+    stdout = (await buck.bxl("//:aquery.bxl:category")).stdout
+
+    golden(
+        output=stdout,
+        rel_path="bxl_category.golden.json",
+    )
+
+
+@buck_test()
+async def test_bxl_aquery_identifier(buck: Buck) -> None:
+    stdout = (await buck.bxl("//:aquery.bxl:identifier")).stdout
+
+    golden(
+        output=stdout,
+        rel_path="bxl_identifier.golden.json",
+    )
+
+
+@buck_test()
+async def test_bxl_aquery_executor(buck: Buck) -> None:
+    stdout = (await buck.bxl("//:aquery.bxl:executor")).stdout
+
+    golden(
+        output=stdout,
+        rel_path="bxl_executor.golden.json",
+    )
@@ -9,6 +9,10 @@
 # pyre-strict
 
 
+import json
+from pathlib import Path
+from typing import Any, Dict, List
+
 from buck2.tests.e2e_util.api.buck import Buck
 from buck2.tests.e2e_util.asserts import expect_failure
 from buck2.tests.e2e_util.buck_workspace import buck_test, env
@@ -52,3 +56,73 @@ async def test_internal_test_executor_timeout(buck: Buck) -> None:
         ),
         stderr_regex="Timeout: ",
     )
+
+
+def _retry_args(tmp_path: Path, *args: str) -> List[str]:
+    return [
+        "--",
+        "--retry-backoff",
+        "0",
+        "--env",
+        f"ATTEMPTS_FILE={tmp_path / 'attempts'}",
+        "--json-summary",
+        str(tmp_path / "summary.json"),
+        *args,
+    ]
+
+
+def _single_result(tmp_path: Path) -> Dict[str, Any]:
+    summary = json.loads((tmp_path / "summary.json").read_text())
+    assert summary["total"] == 1
+    return summary["results"][0]
+
+
+@buck_test()
+@env("BUCK2_ALLOW_INTERNAL_TEST_RUNNER_DO_NOT_USE", "1")
+async def test_internal_test_executor_retry_flaky(buck: Buck, tmp_path: Path) -> None:
+    result = await buck.test(
+        ":fail_once",
+        *_retry_args(tmp_path, "--retries", "1"),
+        test_executor=INTERNAL_TEST_EXECUTOR,
+    )
+    assert "TESTS FLAKY" in result.stderr
+
+    test_result = _single_result(tmp_path)
+    assert test_result["status"] == "flaky"
+    assert [rerun["status"] for rerun in test_result["reruns"]] == ["fail"]
+
+
+@buck_test()
+@env("BUCK2_ALLOW_INTERNAL_TEST_RUNNER_DO_NOT_USE", "1")
+async def test_internal_test_executor_retries_label(buck: Buck, tmp_path: Path) -> None:
+    # No `--retries`: the target's `retries=2` label allows it to fail twice.
+    result = await buck.test(
+        ":fail_twice_with_retries_label",
+        *_retry_args(tmp_path),
+        test_executor=INTERNAL_TEST_EXECUTOR,
+    )
+    assert "TESTS FLAKY" in result.stderr
+
+    test_result = _single_result(tmp_path)
+    assert test_result["status"] == "flaky"
+    assert [rerun["status"] for rerun in test_result["reruns"]] == ["fail", "fail"]
+    assert (tmp_path / "attempts").read_text() == "3"
+
+
+@buck_test()
+@env("BUCK2_ALLOW_INTERNAL_TEST_RUNNER_DO_NOT_USE", "1")
+async def test_internal_test_executor_retries_exhausted(
+    buck: Buck, tmp_path: Path
+) -> None:
+    await expect_failure(
+        buck.test(
+            ":always_fail",
+            *_retry_args(tmp_path, "--retries", "2"),
+            test_executor=INTERNAL_TEST_EXECUTOR,
+        ),
+        stderr_regex="TESTS FAILED",
+    )
+
+    test_result = _single_result(tmp_path)
+    assert test_result["status"] == "fail"
+    assert [rerun["status"] for rerun in test_result["reruns"]] == ["fail", "fail"]
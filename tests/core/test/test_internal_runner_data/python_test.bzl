@@ -11,12 +11,24 @@ def _impl(ctx):
         ExternalRunnerTestInfo(
             command = ["python3", "-c", ctx.attrs.script],
             type = "custom",
+            labels = ctx.attrs.labels,
         ),
     ]
 
 python_test = rule(
     impl = _impl,
     attrs = {
+        "labels": attrs.list(attrs.string(), default = []),
         "script": attrs.string(),
     },
 )
+
+def fail_first_attempts(count):
+    """A script that fails its first `count` runs, counting runs in `$ATTEMPTS_FILE`."""
+    return "\n".join([
+        "import os, sys",
+        "path = os.environ['ATTEMPTS_FILE']",
+        "attempts = int(open(path).read()) if os.path.exists(path) else 0",
+        "open(path, 'w').write(str(attempts + 1))",
+        "sys.exit(1 if attempts < {} else 0)".format(count),
+    ])
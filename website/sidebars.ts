@@ -125,6 +125,10 @@ export const sidebars: SidebarsConfig = {
             'users/advanced/deferred_materialization',
             'users/advanced/restarter',
             'users/advanced/in_memory_cache',
+            'users/advanced/disk_action_cache',
+            'users/advanced/local_sandbox',
+            'users/advanced/local_resource_limits',
+            'users/advanced/bazel_workers',
             'users/advanced/external_cells',
             isInternal() ? 'users/advanced/offline_build_archives' : null,
             isInternal() ? 'users/advanced/vpnless' : null,